@@ -0,0 +1,131 @@
+//! `tc bench`: tokenizer throughput on real or synthetic input
+
+use crate::sample::parse_size;
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use token_counter_lib::{count_tokens, read_document_text};
+
+/// Text the synthetic input repeats: prose, code, numbers, and non-ASCII
+/// text, so it exercises more of a vocabulary than one language would
+const SYNTHETIC_TEXT: &str = "\
+The quick brown fox jumps over the lazy dog. Tokenizers split text into \
+pieces that a model has a vocabulary for; common words are one token, rare \
+ones several.
+
+fn count(text: &str) -> Result<usize, Error> {
+    let ids = tokenizer.encode(text, false)?.get_ids().len();
+    Ok(ids)
+}
+
+Revenue grew 12.5% to $4,096,000 in Q3 2024 (see table 7, rows 3-19).
+Café, naïve, résumé; Grüße aus München. Привет, мир! 你好，世界。こんにちは。
+";
+
+/// Measure tokens per second and megabytes per second for a tokenizer
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Input file (synthetic mixed text if not provided)
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Size of the synthetic input (e.g., "512K", "4M")
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "1M", conflicts_with = "file")]
+    size: u64,
+
+    /// Keep counting for at least this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 3.0)]
+    duration: f64,
+
+    /// Time a full encode with token offsets, as line stats and
+    /// --show-tokens need, instead of the count-only fast path
+    #[arg(long)]
+    offsets: bool,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &BenchArgs) -> Result<ExitCode> {
+    if !args.duration.is_finite() || args.duration <= 0.0 {
+        anyhow::bail!("--duration must be positive");
+    }
+    let (text, input) = match &args.file {
+        Some(file) => (
+            read_document_text(file).with_context(|| format!("Failed to read {:?}", file))?,
+            file.display().to_string(),
+        ),
+        None => (synthetic_text(args.size as usize), "synthetic".to_string()),
+    };
+
+    let start = Instant::now();
+    let tokenizer = args.tokenizer.load()?;
+    let load_time = start.elapsed();
+
+    let count = |text: &str| -> Result<usize> {
+        if args.offsets {
+            let encoding = tokenizer
+                .encode(text, false)
+                .map_err(|e| anyhow::anyhow!("Failed to encode: {}", e))?;
+            Ok(encoding.len())
+        } else {
+            Ok(count_tokens(text, &tokenizer, false)?)
+        }
+    };
+
+    // One untimed pass warms caches and gives the token count
+    let tokens = count(&text)?;
+
+    let target = Duration::from_secs_f64(args.duration);
+    let mut runs = 0u32;
+    let start = Instant::now();
+    while runs == 0 || start.elapsed() < target {
+        black_box(count(black_box(&text))?);
+        runs += 1;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let bytes = text.len() as f64 * runs as f64;
+    println!("{:<11} {}", "tokenizer", args.tokenizer.label());
+    println!(
+        "{:<11} {}",
+        "encode",
+        if args.offsets {
+            "with offsets"
+        } else {
+            "count only"
+        }
+    );
+    println!(
+        "{:<11} {} ({} bytes, {} tokens)",
+        "input",
+        input,
+        text.len(),
+        tokens
+    );
+    println!("{:<11} {:.1?}", "load", load_time);
+    println!("{:<11} {} in {:.2}s", "runs", runs, elapsed);
+    println!(
+        "{:<11} {:.0}",
+        "tokens/s",
+        tokens as f64 * runs as f64 / elapsed
+    );
+    println!("{:<11} {:.2}", "MB/s", bytes / elapsed / 1e6);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// [`SYNTHETIC_TEXT`] repeated to `size` bytes, cut at a character boundary
+fn synthetic_text(size: usize) -> String {
+    let mut text = SYNTHETIC_TEXT.repeat(size / SYNTHETIC_TEXT.len() + 1);
+    let mut end = size.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text
+}
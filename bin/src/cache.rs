@@ -0,0 +1,234 @@
+//! Token counts of file contents, cached across runs
+//!
+//! Counts are keyed by a hash of the tokenizer, the counting options, and a
+//! file's contents, so re-counting a mostly unchanged tree only tokenizes
+//! the files that changed. The cache is a SQLite database in `cache-dir` or
+//! `TC_CACHE_DIR` if set, otherwise `$XDG_CACHE_HOME/tc` or `~/.cache/tc`.
+
+use crate::config;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Database file inside the cache directory
+const DATABASE: &str = "counts.sqlite";
+
+/// Manage the cache of token counts
+#[derive(Args, Debug)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove every cached count
+    Clear,
+}
+
+/// The directory the cache is kept in
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = &config::get().cache_dir {
+        return Some(dir.clone());
+    }
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("tc"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache/tc"))
+}
+
+pub fn run(args: &CacheArgs) -> Result<ExitCode> {
+    match args.command {
+        CacheCommand::Clear => {
+            let Some(dir) = dir() else {
+                anyhow::bail!("no cache directory (set HOME or TC_CACHE_DIR)");
+            };
+            let path = dir.join(DATABASE);
+            let removed = clear(&path)?;
+            println!(
+                "removed {} cached count{} from {}",
+                removed,
+                if removed == 1 { "" } else { "s" },
+                path.display()
+            );
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(feature = "cache")]
+mod store {
+    use super::{dir, DATABASE};
+    use anyhow::{Context, Result};
+    use rusqlite::{params, Connection, OptionalExtension};
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+    use token_counter_lib::TokenStats;
+    use tokenizers::Tokenizer;
+    use tracing::info;
+
+    /// Bumped when the meaning of a cached count changes, so old entries
+    /// stop matching
+    const VERSION: &str = "1";
+
+    /// Cached counts for one tokenizer and set of counting options
+    pub struct CountCache {
+        connection: Connection,
+        /// Hash of the tokenizer and options that every key starts from
+        hasher: blake3::Hasher,
+    }
+
+    impl CountCache {
+        /// Opens the cache for counts made with `tokenizer` and `options`
+        /// (anything else that changes a count, such as `--strip`)
+        ///
+        /// Returns `None`, after logging why, if the cache cannot be used;
+        /// counting then goes on without it.
+        pub fn open(tokenizer: &Tokenizer, options: &str) -> Option<Self> {
+            match Self::try_open(tokenizer, options) {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    info!("not caching counts: {:#}", e);
+                    None
+                }
+            }
+        }
+
+        fn try_open(tokenizer: &Tokenizer, options: &str) -> Result<Self> {
+            let dir = dir().context("no cache directory")?;
+            fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+            let connection = open_database(&dir.join(DATABASE))?;
+
+            let serialized = tokenizer
+                .to_string(false)
+                .map_err(|e| anyhow::anyhow!("Failed to fingerprint the tokenizer: {}", e))?;
+            let mut hasher = blake3::Hasher::new();
+            for part in [VERSION, &serialized, options] {
+                hasher.update(&(part.len() as u64).to_le_bytes());
+                hasher.update(part.as_bytes());
+            }
+            Ok(Self { connection, hasher })
+        }
+
+        /// The key of a file's contents; `kind` is whatever else about the
+        /// file affects its count, such as its extension
+        pub fn key(&self, kind: &str, contents: &[u8]) -> [u8; 32] {
+            let mut hasher = self.hasher.clone();
+            hasher.update(&(kind.len() as u64).to_le_bytes());
+            hasher.update(kind.as_bytes());
+            hasher.update(contents);
+            *hasher.finalize().as_bytes()
+        }
+
+        pub fn get(&self, key: &[u8; 32]) -> Option<TokenStats> {
+            self.connection
+                .query_row(
+                    "SELECT tokens, lines, bytes, max_line_tokens, words, chars
+                     FROM counts WHERE key = ?1",
+                    params![&key[..]],
+                    |row| {
+                        Ok(TokenStats {
+                            tokens: row.get(0)?,
+                            lines: row.get(1)?,
+                            bytes: row.get(2)?,
+                            max_line_tokens: row.get(3)?,
+                            words: row.get(4)?,
+                            chars: row.get(5)?,
+                            path: None,
+                        })
+                    },
+                )
+                .optional()
+                .unwrap_or_else(|e| {
+                    info!("cache lookup failed: {}", e);
+                    None
+                })
+        }
+
+        /// Stores a count; failures are logged and otherwise ignored
+        pub fn put(&self, key: &[u8; 32], stats: &TokenStats) {
+            let result = self.connection.execute(
+                "INSERT OR REPLACE INTO counts
+                 (key, tokens, lines, bytes, max_line_tokens, words, chars)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    &key[..],
+                    stats.tokens,
+                    stats.lines,
+                    stats.bytes,
+                    stats.max_line_tokens,
+                    stats.words,
+                    stats.chars
+                ],
+            );
+            if let Err(e) = result {
+                info!("caching a count failed: {}", e);
+            }
+        }
+    }
+
+    fn open_database(path: &Path) -> Result<Connection> {
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        // Other tc runs may share the cache
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS counts (
+                key BLOB PRIMARY KEY,
+                tokens INTEGER NOT NULL,
+                lines INTEGER NOT NULL,
+                bytes INTEGER NOT NULL,
+                max_line_tokens INTEGER NOT NULL,
+                words INTEGER NOT NULL,
+                chars INTEGER NOT NULL
+            ) WITHOUT ROWID",
+            [],
+        )?;
+        Ok(connection)
+    }
+
+    /// Empties the cache database, returning how many counts it held
+    pub fn clear(path: &Path) -> Result<usize> {
+        if !path.exists() {
+            return Ok(0);
+        }
+        let connection = open_database(path)?;
+        let removed = connection.execute("DELETE FROM counts", [])?;
+        connection.execute("VACUUM", [])?;
+        Ok(removed)
+    }
+}
+
+#[cfg(feature = "cache")]
+pub use store::{clear, CountCache};
+
+#[cfg(not(feature = "cache"))]
+pub fn clear(_path: &std::path::Path) -> Result<usize> {
+    anyhow::bail!("the count cache is not enabled (build with the `cache` feature)")
+}
+
+/// Stand-in when caching is not compiled in: never opens
+#[cfg(not(feature = "cache"))]
+pub struct CountCache;
+
+#[cfg(not(feature = "cache"))]
+impl CountCache {
+    pub fn open(_tokenizer: &tokenizers::Tokenizer, _options: &str) -> Option<Self> {
+        None
+    }
+
+    pub fn key(&self, _kind: &str, _contents: &[u8]) -> [u8; 32] {
+        [0; 32]
+    }
+
+    pub fn get(&self, _key: &[u8; 32]) -> Option<token_counter_lib::TokenStats> {
+        None
+    }
+
+    pub fn put(&self, _key: &[u8; 32], _stats: &token_counter_lib::TokenStats) {}
+}
@@ -0,0 +1,84 @@
+//! `--chat`: count chat payloads the way chat APIs bill them
+
+use crate::output::outln;
+use crate::tokenizer::TokenizerArgs;
+use anyhow::Result;
+use clap::ValueEnum;
+use token_counter_lib::{
+    count_anthropic_chat, count_openai_chat, count_stats, parse_anthropic_chat, parse_openai_chat,
+    TokenStats,
+};
+use tokenizers::Tokenizer;
+
+/// Named tokenizer preferred for OpenAI payloads when installed
+pub const OPENAI_TOKENIZER: &str = "gpt4";
+
+/// Named tokenizer preferred for Anthropic payloads when installed
+pub const CLAUDE_TOKENIZER: &str = "claude";
+
+/// Chat payload formats understood by `--chat`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChatFormat {
+    /// OpenAI Chat Completions messages
+    Openai,
+    /// Anthropic Messages API requests (estimated)
+    Anthropic,
+}
+
+/// Tokenizer to count a chat format with
+///
+/// When no tokenizer was chosen explicitly, OpenAI payloads default to
+/// cl100k (`gpt4`) and Anthropic payloads to a Claude-compatible tokenizer,
+/// if installed.
+pub fn tokenizer_args(format: Option<ChatFormat>, selected: &TokenizerArgs) -> TokenizerArgs {
+    match format {
+        Some(ChatFormat::Openai) => selected.or_preferred(OPENAI_TOKENIZER, "counting OpenAI"),
+        Some(ChatFormat::Anthropic) => selected.or_preferred(CLAUDE_TOKENIZER, "estimating Claude"),
+        None => selected.clone(),
+    }
+}
+
+/// Tokens billed for each message of a chat payload, by role
+pub struct MessageCounts(Vec<(String, usize)>);
+
+impl MessageCounts {
+    /// Prints a line per message, ahead of the payload's stats line
+    pub fn print(&self) {
+        for (index, (role, tokens)) in self.0.iter().enumerate() {
+            outln!("{:8} [{}] {}", tokens, index + 1, role);
+        }
+    }
+}
+
+/// Counts billed tokens in a chat payload, and those of each message
+///
+/// Lines, bytes, and density describe the raw payload text.
+pub fn count(
+    format: ChatFormat,
+    text: &str,
+    tokenizer: &Tokenizer,
+) -> Result<(TokenStats, MessageCounts)> {
+    let (messages, count) = match format {
+        ChatFormat::Openai => {
+            let messages = parse_openai_chat(text)?;
+            let count = count_openai_chat(&messages, tokenizer)?;
+            (messages, count)
+        }
+        ChatFormat::Anthropic => {
+            let messages = parse_anthropic_chat(text)?;
+            let count = count_anthropic_chat(&messages, tokenizer)?;
+            (messages, count)
+        }
+    };
+
+    let rows = messages
+        .into_iter()
+        .zip(&count.messages)
+        .map(|(message, tokens)| (message.role, *tokens))
+        .collect();
+    let stats = TokenStats {
+        tokens: count.total,
+        ..count_stats(text, tokenizer)?
+    };
+    Ok((stats, MessageCounts(rows)))
+}
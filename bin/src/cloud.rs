@@ -0,0 +1,106 @@
+//! `s3://` and `gs://` inputs: reading objects from cloud storage
+//!
+//! Credentials and regions come from the usual environment variables
+//! (`AWS_ACCESS_KEY_ID`, `AWS_REGION`, `GOOGLE_SERVICE_ACCOUNT`, ...), as
+//! the `object_store` crate reads them.
+
+use std::path::Path;
+
+/// URL schemes of the object stores `tc` reads from
+const SCHEMES: [&str; 2] = ["s3://", "gs://"];
+
+/// Whether an input names an object (or, with -r, a prefix) in cloud
+/// storage
+pub fn is_object_url(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|input| SCHEMES.iter().any(|scheme| input.starts_with(scheme)))
+}
+
+#[cfg(feature = "object-store")]
+mod store {
+    use anyhow::{Context, Result};
+    use futures::TryStreamExt;
+    use object_store::aws::AmazonS3Builder;
+    use object_store::gcp::GoogleCloudStorageBuilder;
+    use object_store::path::Path as ObjectPath;
+    use object_store::ObjectStore;
+    use std::sync::OnceLock;
+    use tokio::runtime::Runtime;
+
+    /// The runtime object store requests run on, started on first use
+    fn runtime() -> Result<&'static Runtime> {
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+        if let Some(runtime) = RUNTIME.get() {
+            return Ok(runtime);
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start the object store runtime")?;
+        Ok(RUNTIME.get_or_init(|| runtime))
+    }
+
+    /// The store holding a URL's bucket, the URL's key, and the URL up to
+    /// the key
+    fn open(url: &str) -> Result<(Box<dyn ObjectStore>, ObjectPath, &str)> {
+        let (scheme, rest) = url.split_once("://").context("Not an object URL")?;
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        let base = &url[..scheme.len() + 3 + bucket.len()];
+        let store: Box<dyn ObjectStore> = match scheme {
+            "s3" => Box::new(
+                AmazonS3Builder::from_env()
+                    .with_bucket_name(bucket)
+                    .build()?,
+            ),
+            _ => Box::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(bucket)
+                    .build()?,
+            ),
+        };
+        Ok((store, ObjectPath::from(key), base))
+    }
+
+    /// Fetches an object's contents
+    pub fn get(url: &str) -> Result<Vec<u8>> {
+        let (store, key, _) = open(url)?;
+        let bytes = runtime()?
+            .block_on(async { store.get(&key).await?.bytes().await })
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => anyhow::anyhow!("no such object"),
+                e => e.into(),
+            })?;
+        Ok(bytes.to_vec())
+    }
+
+    /// The URLs of the objects under a prefix, in key order
+    ///
+    /// For `-r`; the prefix is matched by path segment, so `s3://b/data`
+    /// lists `data/a.txt` but not `database.txt`.
+    pub fn list(url: &str) -> Result<Vec<String>> {
+        let (store, prefix, base) = open(url)?;
+        let prefix = (!prefix.as_ref().is_empty()).then_some(&prefix);
+        let objects: Vec<_> =
+            runtime()?.block_on(async { store.list(prefix).try_collect().await })?;
+        let mut urls: Vec<_> = objects
+            .into_iter()
+            .map(|object| format!("{}/{}", base, object.location))
+            .collect();
+        urls.sort();
+        Ok(urls)
+    }
+}
+
+#[cfg(feature = "object-store")]
+pub use store::{get, list};
+
+#[cfg(not(feature = "object-store"))]
+pub fn get(_url: &str) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("s3:// and gs:// inputs are not enabled (build with the `object-store` feature)")
+}
+
+#[cfg(not(feature = "object-store"))]
+pub fn list(url: &str) -> anyhow::Result<Vec<String>> {
+    get(url).map(|_| Vec::new())
+}
@@ -0,0 +1,56 @@
+//! `--color` and `--thresholds`: coloring token counts by how close they
+//! are to a limit
+
+use serde::Deserialize;
+use std::env;
+use std::io::{self, IsTerminal};
+use token_counter_lib::{Threshold, Thresholds};
+
+/// When to color output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorWhen {
+    /// When stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorWhen {
+    /// Whether to color stdout
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorWhen::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+        }
+    }
+}
+
+/// Parses "WARN,ALERT", each a token count (e.g. "2000") or a share of the
+/// context window (e.g. "75%")
+pub fn parse_thresholds(value: &str) -> Result<Thresholds, String> {
+    let parse = |part: &str| {
+        let part = part.trim();
+        match part.strip_suffix('%') {
+            Some(percent) => percent
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|percent| percent.is_finite() && *percent >= 0.0)
+                .map(Threshold::Percent),
+            None => part.parse::<usize>().ok().map(Threshold::Tokens),
+        }
+    };
+    let invalid = || {
+        format!(
+            "expected WARN,ALERT as token counts or percentages (e.g. 2000,8000 or 75%,100%), got '{}'",
+            value
+        )
+    };
+
+    let (warn, alert) = value.split_once(',').ok_or_else(invalid)?;
+    let (warn, alert) = parse(warn).zip(parse(alert)).ok_or_else(invalid)?;
+    Ok(Thresholds::new(warn, alert))
+}
@@ -0,0 +1,319 @@
+//! `--output-parquet` and `--output-arrow`: per-file or per-record stats in
+//! columnar formats, for loading into DuckDB, Spark, and the like without a
+//! lossy trip through CSV
+//!
+//! The rows are gathered into a [`Table`] of typed columns. Parquet gets
+//! the whole table as one Arrow record batch once counting is done, while
+//! an [`ArrowStream`] sends a batch every 1024 rows as they are counted.
+//! The tokenizer and other details of the run are kept in the schema's
+//! metadata.
+
+use token_counter_lib::TokenStats;
+
+/// A column of a [`Table`]
+#[cfg_attr(not(any(feature = "arrow", feature = "parquet")), allow(dead_code))]
+pub enum Column {
+    Text(Vec<String>),
+    Count(Vec<u64>),
+}
+
+/// Named columns of equal length, and metadata about how they were counted
+#[cfg_attr(not(any(feature = "arrow", feature = "parquet")), allow(dead_code))]
+pub struct Table {
+    columns: Vec<(&'static str, Column)>,
+    metadata: Vec<(&'static str, String)>,
+}
+
+impl Table {
+    /// An empty table with a row per input: its name and stats
+    pub fn for_files() -> Self {
+        let mut columns = vec![("path", Column::Text(Vec::new()))];
+        for name in [
+            "tokens",
+            "lines",
+            "bytes",
+            "max_line_tokens",
+            "words",
+            "chars",
+        ] {
+            columns.push((name, Column::Count(Vec::new())));
+        }
+        Self {
+            columns,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// An empty table with a row per record of a dataset: its line number,
+    /// tokens, and bytes
+    pub fn for_records() -> Self {
+        Self {
+            columns: ["line", "tokens", "bytes"]
+                .map(|name| (name, Column::Count(Vec::new())))
+                .into(),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// A table of `inputs`, as [`Table::for_files`]
+    pub fn files(inputs: &[(String, TokenStats)]) -> Self {
+        let mut table = Self::for_files();
+        for (name, stats) in inputs {
+            table.push_file(name, stats);
+        }
+        table
+    }
+
+    /// A table of `records`, each a line number, tokens, and bytes, as
+    /// [`Table::for_records`]
+    pub fn records(records: &[(usize, usize, usize)]) -> Self {
+        let mut table = Self::for_records();
+        for &(line, tokens, bytes) in records {
+            table.push_record(line, tokens, bytes);
+        }
+        table
+    }
+
+    /// Adds `value` to the metadata under `tc.{key}`
+    pub fn with_metadata(mut self, key: &'static str, value: String) -> Self {
+        self.metadata.push((key, value));
+        self
+    }
+
+    /// Adds a row to a [`Table::for_files`] table
+    pub fn push_file(&mut self, name: &str, stats: &TokenStats) {
+        let counts = [
+            stats.tokens,
+            stats.lines,
+            stats.bytes,
+            stats.max_line_tokens,
+            stats.words,
+            stats.chars,
+        ];
+        self.push(Some(name), &counts);
+    }
+
+    /// Adds a row to a [`Table::for_records`] table
+    pub fn push_record(&mut self, line: usize, tokens: usize, bytes: usize) {
+        self.push(None, &[line, tokens, bytes]);
+    }
+
+    /// Adds a row of `text` for the text column, if any, and `counts` for
+    /// the others in order
+    fn push(&mut self, text: Option<&str>, counts: &[usize]) {
+        let mut counts = counts.iter();
+        for (_, column) in &mut self.columns {
+            match column {
+                Column::Text(values) => values.push(text.unwrap_or_default().to_string()),
+                Column::Count(values) => values.push(counts.next().copied().unwrap_or(0) as u64),
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+mod batch {
+    use super::{Column, Table};
+    use anyhow::Result;
+    use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    impl Table {
+        /// The table's columns and metadata as an Arrow schema
+        pub(super) fn schema(&self) -> Schema {
+            let fields: Vec<Field> = self
+                .columns
+                .iter()
+                .map(|(name, column)| {
+                    let data_type = match column {
+                        Column::Text(_) => DataType::Utf8,
+                        Column::Count(_) => DataType::UInt64,
+                    };
+                    Field::new(*name, data_type, false)
+                })
+                .collect();
+            let metadata: HashMap<String, String> = self
+                .metadata
+                .iter()
+                .map(|(key, value)| (format!("tc.{}", key), value.clone()))
+                .collect();
+            Schema::new(fields).with_metadata(metadata)
+        }
+
+        /// Takes the table's rows as one Arrow record batch, leaving it
+        /// empty
+        pub(super) fn take_batch(&mut self) -> Result<RecordBatch> {
+            let schema = self.schema();
+            let arrays: Vec<ArrayRef> = self
+                .columns
+                .iter_mut()
+                .map(|(_, column)| -> ArrayRef {
+                    match column {
+                        Column::Text(values) => Arc::new(StringArray::from(std::mem::take(values))),
+                        Column::Count(values) => {
+                            Arc::new(UInt64Array::from(std::mem::take(values)))
+                        }
+                    }
+                })
+                .collect();
+            Ok(RecordBatch::try_new(Arc::new(schema), arrays)?)
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_file {
+    use super::Table;
+    use anyhow::{Context, Result};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    /// Writes `table` to `path` as Snappy-compressed Parquet; nothing is
+    /// left at `path` if writing fails
+    pub fn write_parquet(path: &Path, mut table: Table) -> Result<()> {
+        let batch = table.take_batch()?;
+        let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let written =
+            ArrowWriter::try_new(file, batch.schema(), Some(properties)).and_then(|mut writer| {
+                writer.write(&batch)?;
+                writer.close()
+            });
+        if let Err(e) = written {
+            let _ = fs::remove_file(path);
+            return Err(e).with_context(|| format!("Failed to write {:?}", path));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use parquet_file::write_parquet;
+
+#[cfg(not(feature = "parquet"))]
+pub fn write_parquet(_path: &std::path::Path, _table: Table) -> anyhow::Result<()> {
+    anyhow::bail!("Parquet output is not enabled (build with the `parquet` feature)")
+}
+
+#[cfg(feature = "arrow")]
+mod ipc {
+    use super::{Column, Table};
+    use anyhow::{Context, Result};
+    use arrow_ipc::writer::StreamWriter;
+    use std::fs::File;
+    use std::io::{self, BufWriter, Write};
+    use std::path::Path;
+    use token_counter_lib::TokenStats;
+
+    /// Rows an [`ArrowStream`] gathers before sending them as a batch
+    const BATCH_ROWS: usize = 1024;
+
+    impl Table {
+        /// Rows in the table
+        fn len(&self) -> usize {
+            match self.columns.first() {
+                Some((_, Column::Text(values))) => values.len(),
+                Some((_, Column::Count(values))) => values.len(),
+                None => 0,
+            }
+        }
+    }
+
+    /// Per-file or per-record stats sent as an Arrow IPC stream while they
+    /// are counted
+    pub struct ArrowStream {
+        table: Table,
+        writer: StreamWriter<BufWriter<Box<dyn Write + Send>>>,
+        /// The first write that failed, reported by [`ArrowStream::finish`]
+        error: Option<anyhow::Error>,
+    }
+
+    impl ArrowStream {
+        /// Starts a stream of the rows of `table`, which should be empty, to
+        /// `path` (stdout if "-")
+        pub fn create(path: &Path, table: Table) -> Result<Self> {
+            let output: Box<dyn Write + Send> = if path == Path::new("-") {
+                Box::new(io::stdout())
+            } else {
+                Box::new(
+                    File::create(path).with_context(|| format!("Failed to create {:?}", path))?,
+                )
+            };
+            let writer = StreamWriter::try_new_buffered(output, &table.schema())?;
+            Ok(Self {
+                table,
+                writer,
+                error: None,
+            })
+        }
+
+        /// Adds a row for an input
+        pub fn push_file(&mut self, name: &str, stats: &TokenStats) {
+            self.table.push_file(name, stats);
+            self.send_full_batch();
+        }
+
+        /// Adds a row for a dataset record
+        pub fn push_record(&mut self, line: usize, tokens: usize, bytes: usize) {
+            self.table.push_record(line, tokens, bytes);
+            self.send_full_batch();
+        }
+
+        fn send_full_batch(&mut self) {
+            if self.table.len() >= BATCH_ROWS && self.error.is_none() {
+                if let Err(e) = self.send_batch() {
+                    self.error = Some(e);
+                }
+            }
+        }
+
+        /// Sends the rows gathered so far as a batch
+        fn send_batch(&mut self) -> Result<()> {
+            let batch = self.table.take_batch()?;
+            self.writer.write(&batch)?;
+            self.writer.flush()?;
+            Ok(())
+        }
+
+        /// Sends the last rows and ends the stream
+        pub fn finish(mut self) -> Result<()> {
+            if let Some(e) = self.error.take() {
+                return Err(e.context("Failed to write the Arrow stream"));
+            }
+            if self.table.len() > 0 {
+                self.send_batch()?;
+            }
+            self.writer.finish()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+pub use ipc::ArrowStream;
+
+/// Stand-in when Arrow output is not compiled in: never created
+#[cfg(not(feature = "arrow"))]
+pub struct ArrowStream;
+
+#[cfg(not(feature = "arrow"))]
+impl ArrowStream {
+    pub fn create(_path: &std::path::Path, _table: Table) -> anyhow::Result<Self> {
+        anyhow::bail!("Arrow output is not enabled (build with the `arrow` feature)")
+    }
+
+    pub fn push_file(&mut self, _name: &str, _stats: &TokenStats) {}
+
+    pub fn push_record(&mut self, _line: usize, _tokens: usize, _bytes: usize) {}
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! `tc compare`: token counts of inputs under several tokenizers
+
+use crate::tokenizer::{name_completer, TokenizerArgs};
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{count_multi, csv_field, read_document_text, TokenStats};
+
+/// Count inputs with several tokenizers side by side
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Input files (reads from stdin if not provided)
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Named tokenizer to compare (repeatable, e.g. -n gpt2 -n gpt4)
+    #[arg(
+        short = 'n',
+        long = "tokenizer-name",
+        value_name = "NAME",
+        add = name_completer()
+    )]
+    names: Vec<String>,
+
+    /// Tokenizer JSON file or model directory to compare (repeatable)
+    #[arg(short = 't', long = "tokenizer-path", value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = CompareFormat::Table)]
+    format: CompareFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl CompareArgs {
+    /// The tokenizers to compare: names in the order given, then paths
+    fn tokenizers(&self) -> Vec<TokenizerArgs> {
+        let names = self.names.iter().map(|name| TokenizerArgs {
+            tokenizer_name: Some(name.clone()),
+            ..Default::default()
+        });
+        let paths = self.paths.iter().map(|path| TokenizerArgs {
+            tokenizer_path: Some(path.clone()),
+            ..Default::default()
+        });
+        names.chain(paths).collect()
+    }
+}
+
+/// Token counts of every input under every tokenizer
+#[derive(Serialize)]
+struct Matrix {
+    tokenizers: Vec<String>,
+    files: Vec<Row>,
+    /// Per-tokenizer totals across all inputs
+    totals: Vec<usize>,
+    /// Sum of `totals`
+    total: usize,
+}
+
+/// One input's counts, in tokenizer order
+#[derive(Serialize)]
+struct Row {
+    path: String,
+    bytes: usize,
+    tokens: Vec<usize>,
+    /// Sum across tokenizers
+    total: usize,
+}
+
+pub fn run(args: &CompareArgs) -> Result<ExitCode> {
+    let selections = args.tokenizers();
+    if selections.len() < 2 {
+        anyhow::bail!("compare needs at least two tokenizers (-n NAME or -t PATH)");
+    }
+    let tokenizers = selections
+        .iter()
+        .map(TokenizerArgs::load)
+        .collect::<Result<Vec<_>>>()?;
+    let tokenizers: Vec<_> = tokenizers.iter().collect();
+
+    let mut inputs = Vec::new();
+    if args.files.is_empty() {
+        let text = io::read_to_string(io::stdin().lock()).context("Failed to read stdin")?;
+        inputs.push(("-".to_string(), text));
+    }
+    for file in &args.files {
+        let text =
+            read_document_text(file).with_context(|| format!("Failed to read {:?}", file))?;
+        inputs.push((file.display().to_string(), text));
+    }
+
+    let mut matrix = Matrix {
+        tokenizers: selections.iter().map(TokenizerArgs::label).collect(),
+        files: Vec::new(),
+        totals: vec![0; tokenizers.len()],
+        total: 0,
+    };
+    for (path, text) in inputs {
+        // Each input is read once and encoded by all the tokenizers at once
+        let tokens = count_multi(&text, &tokenizers)
+            .with_context(|| format!("Failed to count tokens in {}", path))?;
+        for (total, count) in matrix.totals.iter_mut().zip(&tokens) {
+            *total += count;
+        }
+        let total = tokens.iter().sum();
+        matrix.total += total;
+        matrix.files.push(Row {
+            path,
+            bytes: text.len(),
+            tokens,
+            total,
+        });
+    }
+
+    match args.format {
+        CompareFormat::Json => println!("{}", serde_json::to_string_pretty(&matrix)?),
+        CompareFormat::Csv => print_csv(&matrix),
+        CompareFormat::Table if matrix.files.len() == 1 => print_side_by_side(&matrix),
+        CompareFormat::Table => print_table(&matrix),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A single input: one line per tokenizer, with its difference from the
+/// first tokenizer given and how many bytes each token covers
+fn print_side_by_side(matrix: &Matrix) {
+    let file = &matrix.files[0];
+    let counts = &file.tokens;
+    let width = label_width(&matrix.tokenizers, "tokenizer");
+    println!(
+        "{:<width$} {:>10} {:>9} {:>11}",
+        "tokenizer", "tokens", "diff", "bytes/token"
+    );
+    for (index, (label, tokens)) in matrix.tokenizers.iter().zip(counts).enumerate() {
+        let diff = if index == 0 {
+            "baseline".to_string()
+        } else {
+            percent_diff(*tokens, counts[0])
+        };
+        let stats = TokenStats {
+            tokens: *tokens,
+            bytes: file.bytes,
+            ..TokenStats::new()
+        };
+        println!(
+            "{:<width$} {:>10} {:>9} {:>11.2}",
+            label,
+            tokens,
+            diff,
+            stats.bytes_per_token()
+        );
+    }
+}
+
+/// Several inputs: a row per input and a column per tokenizer, then the
+/// column totals and their differences from the first tokenizer's total
+fn print_table(matrix: &Matrix) {
+    let paths: Vec<_> = matrix.files.iter().map(|row| row.path.clone()).collect();
+    let width = label_width(&paths, "total");
+    let columns: Vec<usize> = matrix
+        .tokenizers
+        .iter()
+        .map(|label| label.len().max(10))
+        .collect();
+
+    let row = |label: &str, cells: &[String], total: &str| {
+        let cells: Vec<_> = cells
+            .iter()
+            .zip(&columns)
+            .map(|(cell, &column)| format!("{:>column$}", cell))
+            .collect();
+        let line = format!("{:<width$} {} {:>10}", label, cells.join(" "), total);
+        println!("{}", line.trim_end());
+    };
+
+    row("", &matrix.tokenizers, "total");
+    for file in &matrix.files {
+        row(&file.path, &strings(&file.tokens), &file.total.to_string());
+    }
+    row("total", &strings(&matrix.totals), &matrix.total.to_string());
+    let diffs: Vec<_> = matrix
+        .totals
+        .iter()
+        .enumerate()
+        .map(|(index, &tokens)| match index {
+            0 => "baseline".to_string(),
+            _ => percent_diff(tokens, matrix.totals[0]),
+        })
+        .collect();
+    row("diff", &diffs, "");
+}
+
+/// A header of tokenizer labels, a line per input, and a `total` line
+fn print_csv(matrix: &Matrix) {
+    let header: Vec<_> = std::iter::once("file")
+        .chain(matrix.tokenizers.iter().map(String::as_str))
+        .chain(["total"])
+        .map(csv_field)
+        .collect();
+    println!("{}", header.join(","));
+    for file in &matrix.files {
+        println!(
+            "{},{},{}",
+            csv_field(&file.path),
+            strings(&file.tokens).join(","),
+            file.total
+        );
+    }
+    println!(
+        "total,{},{}",
+        strings(&matrix.totals).join(","),
+        matrix.total
+    );
+}
+
+fn strings(counts: &[usize]) -> Vec<String> {
+    counts.iter().map(usize::to_string).collect()
+}
+
+/// Width of a column holding `labels` under a `header`
+fn label_width(labels: &[String], header: &str) -> usize {
+    labels
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max(header.len())
+}
+
+/// `count` relative to `baseline`, e.g. "+12.5%"
+fn percent_diff(count: usize, baseline: usize) -> String {
+    if baseline == 0 {
+        return if count == 0 { "+0.0%" } else { "n/a" }.to_string();
+    }
+    let diff = (count as f64 - baseline as f64) * 100.0 / baseline as f64;
+    format!("{:+.1}%", diff)
+}
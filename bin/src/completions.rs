@@ -0,0 +1,55 @@
+//! `tc completions`: shell completion scripts
+//!
+//! The scripts call back into `tc` on each <TAB> (with `COMPLETE=<shell>`
+//! set), so completions follow the installed version and `-n` offers the
+//! tokenizers actually installed rather than a list fixed when the script
+//! was generated.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use clap_complete::env::Shells;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// Environment variable through which the scripts request completions
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Print a shell completion script
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to complete in
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Powershell => "powershell",
+        }
+    }
+}
+
+pub fn run(args: &CompletionsArgs) -> Result<ExitCode> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(args.shell.name())
+        .expect("clap_complete supports every listed shell");
+
+    let mut script = Vec::new();
+    completer.write_registration(COMPLETE_VAR, "tc", "tc", "tc", &mut script)?;
+    io::stdout().write_all(&script)?;
+
+    Ok(ExitCode::SUCCESS)
+}
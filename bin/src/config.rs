@@ -0,0 +1,314 @@
+//! Defaults from `~/.config/tc/config.toml` and a project's `.tc.toml`
+//!
+//! The config files give defaults for flags, so a team can share settings
+//! without wrapping `tc` in aliases:
+//!
+//! ```toml
+//! tokenizer = "gpt4"                  # or tokenizer-path = "models/llama"
+//! model = "gpt-4o"
+//! format = "json"
+//! color = "auto"
+//! thresholds = "2000,8000"
+//! exclude = ["*.lock", "node_modules"]
+//! pricing-file = "team-pricing.toml"
+//! threads = 4
+//! cache-dir = "/var/cache/tc"
+//!
+//! # .tc.toml only: limits for the files under a path pattern
+//! [[budgets]]
+//! path = "prompts/**"
+//! max-tokens = 2000
+//!
+//! # .tc.toml only: named groups for `--group-by glob`
+//! [[groups]]
+//! name = "docs"
+//! path = "docs/**"
+//! ```
+//!
+//! The nearest `.tc.toml` in the working directory or above it overrides
+//! the user config key by key. `TC_TOKENIZER`, `TC_TOKENIZER_PATH`,
+//! `TC_FORMAT`, `TC_THREADS`, and `TC_CACHE_DIR` override both, and flags
+//! given on the command line take precedence over everything. Relative
+//! paths are relative to the directory holding the file.
+
+use crate::color::ColorWhen;
+use crate::OutputFormat;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::info;
+
+/// Config file name looked up in the user config directory
+const CONFIG_FILE: &str = "config.toml";
+
+/// Project config file name, looked up from the working directory upward
+pub const PROJECT_FILE: &str = ".tc.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Named tokenizer used when neither -n nor -t is given
+    pub tokenizer: Option<String>,
+    /// Tokenizer file or model directory used when neither -n nor -t is given
+    pub tokenizer_path: Option<PathBuf>,
+    /// Model used when --model is not given
+    pub model: Option<String>,
+    /// Output format for the counts
+    pub format: Option<OutputFormat>,
+    /// When to color output
+    pub color: Option<ColorWhen>,
+    /// Token counts (or context shares) at which counts turn yellow and red,
+    /// as "WARN,ALERT"
+    pub thresholds: Option<String>,
+    /// Globs of files and directories that -r skips
+    pub exclude: Option<Vec<String>>,
+    /// Pricing file used when --pricing-file is not given
+    pub pricing_file: Option<PathBuf>,
+    /// Threads to encode with (default: one per CPU)
+    pub threads: Option<usize>,
+    /// Directory of the count cache (default: ~/.cache/tc)
+    pub cache_dir: Option<PathBuf>,
+    /// Token limits for the files under path patterns (`.tc.toml` only)
+    #[serde(default)]
+    pub budgets: Vec<PathBudget>,
+    /// Named groups of the files under path patterns, for `--group-by glob`
+    /// (`.tc.toml` only)
+    #[serde(default)]
+    pub groups: Vec<PathGroup>,
+    /// Directory of the `.tc.toml`, which budget and group patterns are
+    /// relative to
+    #[serde(skip)]
+    pub project_root: Option<PathBuf>,
+}
+
+/// Token limits for each file matching a path pattern
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PathBudget {
+    /// Glob matched against paths relative to the project root
+    pub path: String,
+    /// Largest token count allowed per file
+    pub max_tokens: Option<usize>,
+    /// Smallest token count allowed per file
+    pub fail_under: Option<usize>,
+    #[serde(skip)]
+    matcher: Option<GlobMatcher>,
+}
+
+impl PathBudget {
+    /// Whether the budget covers `path`, relative to the project root
+    pub fn matches(&self, path: &Path) -> bool {
+        self.matcher
+            .as_ref()
+            .is_some_and(|matcher| matcher.is_match(path))
+    }
+}
+
+/// A named group of the files matching a path pattern
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PathGroup {
+    /// Name the group is reported under
+    pub name: String,
+    /// Glob matched against paths relative to the project root (or, given
+    /// to `--group-by`, the working directory)
+    pub path: String,
+    #[serde(skip)]
+    matcher: Option<GlobMatcher>,
+}
+
+impl PathGroup {
+    pub fn new(name: &str, path: &str) -> Result<Self, globset::Error> {
+        Ok(Self {
+            name: name.to_string(),
+            path: path.to_string(),
+            matcher: Some(Glob::new(path)?.compile_matcher()),
+        })
+    }
+
+    /// Whether the group takes in `path`
+    pub fn matches(&self, path: &Path) -> bool {
+        self.matcher
+            .as_ref()
+            .is_some_and(|matcher| matcher.is_match(path))
+    }
+}
+
+impl Config {
+    /// `self` with every key that `other` sets replaced by its value
+    fn overlay(mut self, other: Config) -> Config {
+        // The tokenizer is chosen by name or by path, never both
+        if other.tokenizer.is_some() || other.tokenizer_path.is_some() {
+            self.tokenizer = other.tokenizer;
+            self.tokenizer_path = other.tokenizer_path;
+        }
+        Config {
+            tokenizer: self.tokenizer,
+            tokenizer_path: self.tokenizer_path,
+            model: other.model.or(self.model),
+            format: other.format.or(self.format),
+            color: other.color.or(self.color),
+            thresholds: other.thresholds.or(self.thresholds),
+            exclude: other.exclude.or(self.exclude),
+            pricing_file: other.pricing_file.or(self.pricing_file),
+            threads: other.threads.or(self.threads),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            budgets: if other.budgets.is_empty() {
+                self.budgets
+            } else {
+                other.budgets
+            },
+            groups: if other.groups.is_empty() {
+                self.groups
+            } else {
+                other.groups
+            },
+            project_root: other.project_root.or(self.project_root),
+        }
+    }
+
+    /// `path` relative to the project root, if it lies beneath it
+    pub fn project_path(&self, path: &Path) -> Option<PathBuf> {
+        let root = self.project_root.as_ref()?;
+        let path = path.canonicalize().ok()?;
+        path.strip_prefix(root).ok().map(Path::to_path_buf)
+    }
+}
+
+/// Loads the user and project config files, if there are any, and the
+/// environment variables, and applies the thread count, `threads` (from
+/// `--threads`) overriding the configured one
+///
+/// Call once at startup, before anything encodes.
+pub fn init(threads: Option<usize>) -> Result<()> {
+    let mut config = match crate::user_config_dir().map(|dir| dir.join(CONFIG_FILE)) {
+        Some(path) if path.is_file() => {
+            info!("loaded config {}", path.display());
+            read_config(&path)?
+        }
+        _ => Config::default(),
+    };
+    if !config.budgets.is_empty() || !config.groups.is_empty() {
+        anyhow::bail!(
+            "budgets and groups belong in a project's {}, not the user config",
+            PROJECT_FILE
+        );
+    }
+    if let Some(path) = find_project_config() {
+        info!("loaded project config {}", path.display());
+        let mut project = read_config(&path)?;
+        project.project_root = path.parent().and_then(|dir| dir.canonicalize().ok());
+        config = config.overlay(project);
+    }
+    config = config.overlay(from_env()?);
+
+    if let Some(threads) = threads.or(config.threads) {
+        info!(
+            "encoding with up to {} thread{}",
+            threads,
+            if threads == 1 { "" } else { "s" }
+        );
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .context("Failed to set up encoding threads")?;
+    }
+
+    // A second call keeps the first config
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// The loaded config, or the empty config before [`init`]
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// The nearest `.tc.toml` in the working directory or one of its parents
+fn find_project_config() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The settings given by environment variables, for CI scripts where
+/// editing flags is awkward
+fn from_env() -> Result<Config> {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+    let mut config = Config {
+        tokenizer: var("TC_TOKENIZER"),
+        tokenizer_path: var("TC_TOKENIZER_PATH").map(PathBuf::from),
+        cache_dir: var("TC_CACHE_DIR").map(PathBuf::from),
+        ..Config::default()
+    };
+    if config.tokenizer.is_some() && config.tokenizer_path.is_some() {
+        anyhow::bail!("TC_TOKENIZER and TC_TOKENIZER_PATH are both set; keep one");
+    }
+    if let Some(threads) = var("TC_THREADS") {
+        config.threads = Some(
+            threads
+                .parse()
+                .ok()
+                .filter(|&threads| threads > 0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("TC_THREADS must be a positive number, got '{}'", threads)
+                })?,
+        );
+    }
+    if let Some(format) = var("TC_FORMAT") {
+        config.format = Some(OutputFormat::from_str(&format, true).map_err(|_| {
+            anyhow::anyhow!("TC_FORMAT must be plain, json, or csv, got '{}'", format)
+        })?);
+    }
+    Ok(config)
+}
+
+/// Parses and checks a config file
+fn read_config(path: &Path) -> Result<Config> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read config {:?}", path))?;
+    let mut config: Config =
+        toml::from_str(&contents).with_context(|| format!("Failed to parse config {:?}", path))?;
+
+    if config.tokenizer.is_some() && config.tokenizer_path.is_some() {
+        anyhow::bail!(
+            "Config {:?} sets both tokenizer and tokenizer-path; keep one",
+            path
+        );
+    }
+    if config.threads == Some(0) {
+        anyhow::bail!("Config {:?} sets threads = 0; use at least 1", path);
+    }
+    for budget in &mut config.budgets {
+        let glob = Glob::new(&budget.path)
+            .with_context(|| format!("Invalid budget path '{}' in {:?}", budget.path, path))?;
+        budget.matcher = Some(glob.compile_matcher());
+    }
+    for group in &mut config.groups {
+        *group = PathGroup::new(&group.name, &group.path)
+            .with_context(|| format!("Invalid group path '{}' in {:?}", group.path, path))?;
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for relative in [
+        &mut config.tokenizer_path,
+        &mut config.pricing_file,
+        &mut config.cache_dir,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *relative = dir.join(&*relative);
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,158 @@
+//! `tc tokenizers convert`: turning tiktoken and SentencePiece tokenizers
+//! into Hugging Face `tokenizer.json` files, the format `tc` loads fastest
+//!
+//! The conversion itself is the lib's `convert` module; this reads the
+//! file, picks a tiktoken encoding's pattern and special tokens from the
+//! file name and flags, and writes out only what loads back.
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{
+    load_tokenizer_from_bytes, sentencepiece_to_json, tiktoken_encoding, tiktoken_to_json,
+    TIKTOKEN_ENCODINGS,
+};
+
+/// Convert a tokenizer to the Hugging Face format
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Tokenizer file to convert
+    #[arg(value_name = "FILE")]
+    input: PathBuf,
+
+    /// Format of the input (default: from its extension, .tiktoken or
+    /// .model)
+    #[arg(long, value_name = "FORMAT")]
+    from: Option<SourceFormat>,
+
+    /// Format to write
+    #[arg(long, value_name = "FORMAT", default_value = "hf")]
+    to: TargetFormat,
+
+    /// File to write the converted tokenizer to
+    #[arg(short = 'o', long, value_name = "PATH")]
+    output: PathBuf,
+
+    /// Regex a tiktoken encoding splits text with before merging (default:
+    /// that of the encoding the file is named after, e.g. cl100k_base)
+    #[arg(long, value_name = "REGEX")]
+    pattern: Option<String>,
+
+    /// Special token of a tiktoken encoding and its ID, added to (or
+    /// replacing) those of a known encoding (repeatable)
+    #[arg(long = "special-token", value_name = "TOKEN=ID", value_parser = parse_special_token)]
+    special_tokens: Vec<(String, u32)>,
+}
+
+/// Tokenizer formats `tc tokenizers convert` reads
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SourceFormat {
+    /// tiktoken BPE ranks (.tiktoken)
+    Tiktoken,
+    /// SentencePiece model protobuf (.model)
+    Sentencepiece,
+}
+
+/// Tokenizer formats `tc tokenizers convert` writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TargetFormat {
+    /// Hugging Face tokenizer.json
+    Hf,
+}
+
+/// Parses `--special-token`, such as "<|endoftext|>=100257"
+fn parse_special_token(value: &str) -> Result<(String, u32), String> {
+    value
+        .rsplit_once('=')
+        .filter(|(token, _)| !token.is_empty())
+        .and_then(|(token, id)| Some((token.to_string(), id.parse().ok()?)))
+        .ok_or_else(|| format!("expected TOKEN=ID, got '{}'", value))
+}
+
+pub fn run(args: &ConvertArgs) -> Result<ExitCode> {
+    let from = match args.from {
+        Some(from) => from,
+        None => format_for(&args.input).with_context(|| {
+            format!(
+                "Cannot tell the format of {:?} from its extension; give --from",
+                args.input
+            )
+        })?,
+    };
+    if from != SourceFormat::Tiktoken && (args.pattern.is_some() || !args.special_tokens.is_empty())
+    {
+        anyhow::bail!("--pattern and --special-token only apply to tiktoken files");
+    }
+    let bytes =
+        fs::read(&args.input).with_context(|| format!("Failed to read {:?}", args.input))?;
+    let converted = match from {
+        SourceFormat::Tiktoken => from_tiktoken(&bytes, args),
+        SourceFormat::Sentencepiece => sentencepiece_to_json(&bytes).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("Failed to convert {:?}", args.input))?;
+
+    // Only write what tc can load back
+    let text = match args.to {
+        TargetFormat::Hf => serde_json::to_string(&converted)?,
+    };
+    let tokenizer = load_tokenizer_from_bytes(text.as_bytes())
+        .context("The converted tokenizer does not load")?;
+    fs::write(&args.output, text).with_context(|| format!("Failed to write {:?}", args.output))?;
+    println!(
+        "wrote {} ({} model, {} tokens)",
+        args.output.display(),
+        converted["model"]["type"].as_str().unwrap_or_default(),
+        tokenizer.get_vocab_size(true)
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The format implied by a path's extension
+fn format_for(path: &Path) -> Option<SourceFormat> {
+    match path.extension()?.to_str()? {
+        "tiktoken" => Some(SourceFormat::Tiktoken),
+        "model" => Some(SourceFormat::Sentencepiece),
+        _ => None,
+    }
+}
+
+/// Converts a tiktoken file to a `tokenizer.json` with the split pattern
+/// and special tokens of the encoding it is named after, as `args` amend
+fn from_tiktoken(bytes: &[u8], args: &ConvertArgs) -> Result<Value> {
+    let stem = args
+        .input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let known = tiktoken_encoding(&stem);
+    let pattern = match (&args.pattern, known) {
+        (Some(pattern), _) => pattern.as_str(),
+        (None, Some(encoding)) => encoding.pattern,
+        (None, None) => anyhow::bail!(
+            "{:?} is not a known tiktoken encoding ({}); give its split pattern with --pattern",
+            stem,
+            TIKTOKEN_ENCODINGS
+                .iter()
+                .map(|encoding| encoding.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut specials: Vec<(String, u32)> = known
+        .map(|encoding| {
+            encoding
+                .special_tokens
+                .iter()
+                .map(|(token, id)| (token.to_string(), *id))
+                .collect()
+        })
+        .unwrap_or_default();
+    for (token, id) in &args.special_tokens {
+        specials.retain(|(known, _)| known != token);
+        specials.push((token.clone(), *id));
+    }
+    Ok(tiktoken_to_json(bytes, pattern, &specials)?)
+}
@@ -0,0 +1,134 @@
+//! `tc daemon`: serve count requests over a Unix domain socket
+//!
+//! The protocol is newline-delimited JSON. Each request line is an object
+//! with either `text` or `path`, and optionally `tokenizer` (a named
+//! tokenizer) or `tokenizer_path`:
+//!
+//! ```text
+//! {"text": "Hello, world!"}
+//! {"path": "/abs/path/prompt.md", "tokenizer": "gpt4"}
+//! ```
+//!
+//! Each request gets one response line, either
+//! `{"tokens":4,"lines":1,"bytes":13}` or `{"error":"..."}`. A connection may
+//! send any number of requests.
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Serve token counts over a Unix domain socket
+#[derive(Args, Debug)]
+pub struct DaemonArgs {
+    /// Socket path (default: $XDG_RUNTIME_DIR/tc.sock, or /tmp/tc-$USER.sock)
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// Also serve Prometheus metrics over HTTP at ADDR/metrics (e.g., "127.0.0.1:9090")
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<String>,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[cfg(unix)]
+pub fn run(args: &DaemonArgs) -> Result<ExitCode> {
+    unix::serve(args)
+}
+
+#[cfg(not(unix))]
+pub fn run(_args: &DaemonArgs) -> Result<ExitCode> {
+    anyhow::bail!("tc daemon requires Unix domain sockets, which this platform does not support")
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::DaemonArgs;
+    use crate::service::{CountRequest, CountResponse, CountService, ErrorResponse};
+    use anyhow::{Context, Result};
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::process::ExitCode;
+    use std::sync::Arc;
+    use std::thread;
+
+    pub fn serve(args: &DaemonArgs) -> Result<ExitCode> {
+        let socket = args.socket.clone().unwrap_or_else(default_socket_path);
+        let service = Arc::new(CountService::new(args.tokenizer.clone())?);
+        if let Some(address) = &args.metrics_addr {
+            crate::serve::spawn_metrics_server(address, Arc::clone(&service))?;
+        }
+
+        remove_stale_socket(&socket)?;
+        let listener = UnixListener::bind(&socket)
+            .with_context(|| format!("Failed to bind socket {:?}", socket))?;
+        // Requests can read files as this user, so keep the socket private
+        fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))?;
+        eprintln!("tc: listening on {}", socket.display());
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let service = Arc::clone(&service);
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &service) {
+                            eprintln!("tc: connection error: {}", e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("tc: failed to accept connection: {}", e),
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    fn handle_connection(stream: UnixStream, service: &CountService) -> Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = serde_json::from_str::<CountRequest>(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(|request| service.count(&request));
+            let json = match response {
+                Ok(stats) => serde_json::to_string(&CountResponse::from(stats))?,
+                Err(e) => serde_json::to_string(&ErrorResponse {
+                    error: format!("{:#}", e),
+                })?,
+            };
+            writeln!(writer, "{}", json)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a socket file left behind by a daemon that is no longer running
+    fn remove_stale_socket(socket: &Path) -> Result<()> {
+        if !socket.exists() {
+            return Ok(());
+        }
+        if UnixStream::connect(socket).is_ok() {
+            anyhow::bail!("a daemon is already listening on {:?}", socket);
+        }
+        fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove stale socket {:?}", socket))
+    }
+
+    fn default_socket_path() -> PathBuf {
+        if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
+            return PathBuf::from(runtime_dir).join("tc.sock");
+        }
+        let user = env::var("USER").unwrap_or_else(|_| "default".to_string());
+        env::temp_dir().join(format!("tc-{}.sock", user))
+    }
+}
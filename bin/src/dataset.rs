@@ -0,0 +1,210 @@
+//! `tc dataset`: token statistics across the records of a JSONL file
+
+use crate::columnar::{self, ArrowStream, Table};
+use crate::language::{self, Languages};
+use crate::output::{self, outln};
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{count_tokens_batch, record_field, TokenDistribution, TokenStats};
+use tokenizers::Tokenizer;
+
+/// Percentiles reported for the distribution
+const PERCENTILES: &[f64] = &[50.0, 90.0, 95.0, 99.0];
+
+/// Records encoded together in one parallel batch
+const BATCH_SIZE: usize = 1024;
+
+/// Count tokens per record of a JSONL dataset and summarize the distribution
+#[derive(Args, Debug)]
+pub struct DatasetArgs {
+    /// JSONL file (reads from stdin if not provided)
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Record field whose tokens to count
+    #[arg(long, value_name = "NAME", default_value = "text")]
+    field: String,
+
+    /// List records with more than N tokens
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Break the total down by the natural language detected in each
+    /// record
+    #[arg(long)]
+    detect_lang: bool,
+
+    /// Write each record's line number, tokens, and bytes to PATH as
+    /// Parquet
+    #[arg(long, value_name = "PATH")]
+    output_parquet: Option<PathBuf>,
+
+    /// Stream each record's line number, tokens, and bytes to PATH (stdout
+    /// if "-") as Arrow IPC batches while counting
+    #[arg(long, value_name = "PATH")]
+    output_arrow: Option<PathBuf>,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &DatasetArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let reader: Box<dyn BufRead> = match &args.file {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {:?}", path))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+
+    let mut counts = Counts {
+        languages: language::from_flag(args.detect_lang)?,
+        records: args.output_parquet.is_some().then(Vec::new),
+        arrow: args
+            .output_arrow
+            .as_deref()
+            .map(|path| {
+                if path == Path::new("-") {
+                    output::discard();
+                }
+                let table = Table::for_records()
+                    .with_metadata("tokenizer", args.tokenizer.label())
+                    .with_metadata("field", args.field.clone());
+                ArrowStream::create(path, table)
+            })
+            .transpose()?,
+        ..Counts::default()
+    };
+    let mut skipped = 0;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let text = match record_field(&line, &args.field) {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                eprintln!("tc: line {}: no field '{}'", number, args.field);
+                skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("tc: line {}: {}", number, e);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        batch.push((number, text));
+        if batch.len() == BATCH_SIZE {
+            counts.add_batch(&batch, &tokenizer, args.limit)?;
+            batch.clear();
+        }
+    }
+    counts.add_batch(&batch, &tokenizer, args.limit)?;
+    let Counts {
+        over_limit,
+        languages,
+        records,
+        arrow,
+        ..
+    } = counts;
+    if let Some(arrow) = arrow {
+        arrow.finish()?;
+    }
+
+    let Some(distribution) = TokenDistribution::new(counts.tokens) else {
+        anyhow::bail!("no records with a '{}' field", args.field);
+    };
+
+    outln!("{:<8} {:>10}", "records", distribution.count());
+    if skipped > 0 {
+        outln!("{:<8} {:>10}", "skipped", skipped);
+    }
+    outln!("{:<8} {:>10}", "total", distribution.total());
+    outln!("{:<8} {:>10}", "min", distribution.min());
+    outln!("{:<8} {:>10.1}", "mean", distribution.mean());
+    for percent in PERCENTILES {
+        let label = format!("p{}", percent);
+        outln!("{:<8} {:>10}", label, distribution.percentile(*percent));
+    }
+    outln!("{:<8} {:>10}", "max", distribution.max());
+
+    if let Some(limit) = args.limit {
+        outln!();
+        outln!("{} records over {} tokens", over_limit.len(), limit);
+        for (number, tokens) in over_limit {
+            outln!("{:8} line {}", tokens, number);
+        }
+    }
+    if let Some(languages) = &languages {
+        languages.print();
+    }
+    if let (Some(path), Some(records)) = (&args.output_parquet, &records) {
+        let table = Table::records(records)
+            .with_metadata("tokenizer", args.tokenizer.label())
+            .with_metadata("field", args.field.clone());
+        columnar::write_parquet(path, table)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Token counts of the records seen so far
+#[derive(Default)]
+struct Counts {
+    tokens: Vec<usize>,
+    /// Line numbers and counts of records over `--limit`
+    over_limit: Vec<(usize, usize)>,
+    /// Record stats by language, for `--detect-lang`
+    languages: Option<Languages>,
+    /// Line number, tokens, and bytes of each record, for
+    /// `--output-parquet`
+    records: Option<Vec<(usize, usize, usize)>>,
+    /// Where each record's stats are sent as they are counted, for
+    /// `--output-arrow`
+    arrow: Option<ArrowStream>,
+}
+
+impl Counts {
+    /// Counts a batch of (line number, text) records
+    fn add_batch(
+        &mut self,
+        batch: &[(usize, String)],
+        tokenizer: &Tokenizer,
+        limit: Option<usize>,
+    ) -> Result<()> {
+        let texts: Vec<&str> = batch.iter().map(|(_, text)| text.as_str()).collect();
+        let tokens = count_tokens_batch(&texts, tokenizer)?;
+        for ((number, text), &tokens) in batch.iter().zip(&tokens) {
+            if limit.is_some_and(|limit| tokens > limit) {
+                self.over_limit.push((*number, tokens));
+            }
+            if let Some(records) = &mut self.records {
+                records.push((*number, tokens, text.len()));
+            }
+            if let Some(arrow) = &mut self.arrow {
+                arrow.push_record(*number, tokens, text.len());
+            }
+            if let Some(languages) = &mut self.languages {
+                let stats = TokenStats {
+                    tokens,
+                    bytes: text.len(),
+                    ..TokenStats::new()
+                };
+                languages.add(text, &stats);
+            }
+        }
+        self.tokens.extend(tokens);
+        Ok(())
+    }
+}
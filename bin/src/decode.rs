@@ -0,0 +1,50 @@
+//! `tc decode`: token IDs back to text
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use token_counter_lib::{decode_ids, parse_ids};
+
+/// Convert token IDs back to text
+#[derive(Args, Debug)]
+pub struct DecodeArgs {
+    /// Token IDs separated by spaces or commas, or a JSON array
+    /// (reads from stdin if not provided)
+    #[arg(long, value_name = "IDS")]
+    ids: Option<String>,
+
+    /// Leave special tokens (e.g. <|endoftext|>) out of the text
+    #[arg(long)]
+    skip_special_tokens: bool,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &DecodeArgs) -> Result<ExitCode> {
+    let input = match &args.ids {
+        Some(ids) => ids.clone(),
+        None => io::read_to_string(io::stdin().lock()).context("Failed to read stdin")?,
+    };
+    let ids = parse_ids(&input)?;
+
+    let tokenizer = args.tokenizer.load()?;
+    if let Some(&id) = ids
+        .iter()
+        .find(|&&id| id as usize >= tokenizer.get_vocab_size(true))
+    {
+        anyhow::bail!(
+            "token ID {} is outside the tokenizer's vocabulary of {}",
+            id,
+            tokenizer.get_vocab_size(true)
+        );
+    }
+
+    // The text is written exactly, so `tc --ids | tc decode` round-trips
+    let text = decode_ids(&ids, &tokenizer, args.skip_special_tokens)?;
+    io::stdout().write_all(text.as_bytes())?;
+
+    Ok(ExitCode::SUCCESS)
+}
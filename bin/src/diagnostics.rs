@@ -0,0 +1,75 @@
+//! Threshold diagnostics, printed to stderr and optionally as CI annotations
+
+use clap::ValueEnum;
+use std::fmt;
+
+/// CI systems whose annotation syntax `--ci` can emit
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiFormat {
+    /// GitHub Actions workflow commands (`::error file=...::`)
+    Github,
+}
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+/// The input a diagnostic refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input<'a> {
+    Stdin,
+    File(&'a str),
+    Total,
+}
+
+impl<'a> Input<'a> {
+    /// Name shown next to the counts (none for stdin, like `wc`)
+    pub fn name(&self) -> Option<&'a str> {
+        match self {
+            Input::Stdin => None,
+            Input::File(name) => Some(name),
+            Input::Total => Some("total"),
+        }
+    }
+}
+
+impl fmt::Display for Input<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name().unwrap_or("stdin"))
+    }
+}
+
+/// Reports a diagnostic on stderr, plus an annotation on stdout if `ci` is set
+pub fn report(ci: Option<CiFormat>, level: Level, input: Input, message: &str) {
+    eprintln!("tc: {}: {}", input, message);
+
+    if let Some(CiFormat::Github) = ci {
+        let command = match level {
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        let message = escape_data(&format!("{}: {}", input, message));
+        match input {
+            Input::File(file) => {
+                println!("::{} file={}::{}", command, escape_property(file), message)
+            }
+            _ => println!("::{}::{}", command, message),
+        }
+    }
+}
+
+/// Escapes a workflow command message
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command property value
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,76 @@
+//! `--duplicates`: byte-identical inputs and the tokens their copies waste
+
+use crate::output::outln;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Inputs grouped by a hash of their contents
+#[derive(Default)]
+pub struct Duplicates {
+    by_contents: HashMap<blake3::Hash, Vec<(PathBuf, usize)>>,
+}
+
+impl Duplicates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an input by the hash of its (decompressed) contents, with
+    /// its token count
+    pub fn add(&mut self, path: &Path, contents: blake3::Hash, tokens: usize) {
+        self.by_contents
+            .entry(contents)
+            .or_default()
+            .push((path.to_path_buf(), tokens));
+    }
+
+    /// Groups of two or more identical inputs, with the tokens counted for
+    /// all but the first of each, most wasteful first
+    fn groups(&self) -> Vec<(usize, &[(PathBuf, usize)])> {
+        let mut groups: Vec<_> = self
+            .by_contents
+            .values()
+            .filter(|files| files.len() > 1)
+            .map(|files| {
+                let wasted: usize = files[1..].iter().map(|(_, tokens)| tokens).sum();
+                (wasted, files.as_slice())
+            })
+            .collect();
+        groups.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1[0].0.cmp(&b.1[0].0)));
+        groups
+    }
+
+    /// Prints each group of duplicates and the tokens they waste in total
+    pub fn print(&self) {
+        let groups = self.groups();
+        outln!();
+        if groups.is_empty() {
+            outln!("no duplicate files");
+            return;
+        }
+        let mut wasted = 0;
+        let mut redundant = 0;
+        for (group_wasted, files) in &groups {
+            let paths: Vec<_> = files
+                .iter()
+                .map(|(path, _)| path.display().to_string())
+                .collect();
+            outln!(
+                "{:<10} {:>10} tokens x {}: {}",
+                "duplicate",
+                files[0].1,
+                files.len(),
+                paths.join(", ")
+            );
+            wasted += group_wasted;
+            redundant += files.len() - 1;
+        }
+        outln!(
+            "{:<10} {:>10} tokens in {} redundant file{}",
+            "wasted",
+            wasted,
+            redundant,
+            if redundant == 1 { "" } else { "s" }
+        );
+    }
+}
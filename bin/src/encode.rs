@@ -0,0 +1,104 @@
+//! `tc encode`: write the token IDs of inputs to a file
+
+use crate::inspect::IdsFormat;
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{encode_ids, read_document_text};
+
+/// Encode inputs and write their token IDs to a binary or JSON file
+#[derive(Args, Debug)]
+pub struct EncodeArgs {
+    /// Input files, encoded one after another (reads from stdin if not
+    /// provided)
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// File to write the token IDs to
+    #[arg(long, value_name = "PATH")]
+    out: PathBuf,
+
+    /// Output format (default: json for .json paths, bin otherwise)
+    #[arg(long, value_name = "FORMAT")]
+    format: Option<EncodeFormat>,
+
+    /// Integer width of each ID in bin output
+    #[arg(long, value_name = "TYPE", default_value = "u32")]
+    dtype: Dtype,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// How `tc encode` writes token IDs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum EncodeFormat {
+    /// Raw little-endian integers, one per ID
+    Bin,
+    /// A JSON array
+    Json,
+}
+
+/// Integer width of IDs in bin output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Dtype {
+    /// 2 bytes per ID, for vocabularies of up to 65,536 tokens
+    U16,
+    /// 4 bytes per ID
+    U32,
+}
+
+pub fn run(args: &EncodeArgs) -> Result<ExitCode> {
+    let format = args.format.unwrap_or_else(|| format_for(&args.out));
+    let tokenizer = args.tokenizer.load()?;
+    if format == EncodeFormat::Bin
+        && args.dtype == Dtype::U16
+        && tokenizer.get_vocab_size(true) > 1 << 16
+    {
+        anyhow::bail!(
+            "--dtype u16 cannot hold the IDs of a {}-token vocabulary; use u32",
+            tokenizer.get_vocab_size(true)
+        );
+    }
+
+    let mut ids = Vec::new();
+    if args.files.is_empty() {
+        let text = io::read_to_string(io::stdin().lock()).context("Failed to read stdin")?;
+        ids.extend(encode_ids(&text, &tokenizer)?);
+    }
+    for file in &args.files {
+        let text =
+            read_document_text(file).with_context(|| format!("Failed to read {:?}", file))?;
+        ids.extend(
+            encode_ids(&text, &tokenizer)
+                .with_context(|| format!("Failed to encode {:?}", file))?,
+        );
+    }
+
+    let bytes = match format {
+        EncodeFormat::Bin => match args.dtype {
+            Dtype::U16 => ids
+                .iter()
+                .flat_map(|&id| (id as u16).to_le_bytes())
+                .collect(),
+            Dtype::U32 => ids.iter().flat_map(|&id| id.to_le_bytes()).collect(),
+        },
+        EncodeFormat::Json => (IdsFormat::Json.format(&ids) + "\n").into_bytes(),
+    };
+    fs::write(&args.out, bytes).with_context(|| format!("Failed to write {:?}", args.out))?;
+    println!("{:8} {}", ids.len(), args.out.display());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The format implied by an output path's extension
+fn format_for(path: &Path) -> EncodeFormat {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => EncodeFormat::Json,
+        _ => EncodeFormat::Bin,
+    }
+}
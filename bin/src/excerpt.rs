@@ -0,0 +1,176 @@
+//! `tc head`, `tc tail`, and `tc truncate`: print the part of the input that
+//! fits in a token budget
+
+use crate::chat::{CLAUDE_TOKENIZER, OPENAI_TOKENIZER};
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{head_by_tokens, known_models, lookup_model, tail_by_tokens, ModelSpec};
+
+/// Print the longest prefix of a file that fits in N tokens
+#[derive(Args, Debug)]
+pub struct HeadArgs {
+    /// Input file (reads from stdin if not provided)
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Number of tokens to keep
+    #[arg(long, value_name = "N")]
+    tokens: usize,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// Print the longest suffix of a file that fits in N tokens
+#[derive(Args, Debug)]
+pub struct TailArgs {
+    /// Input file (reads from stdin if not provided)
+    #[arg(value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Number of tokens to keep
+    #[arg(long, value_name = "N")]
+    tokens: usize,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// Trim stdin so it fits a model's context window with room left for output
+#[derive(Args, Debug)]
+pub struct TruncateArgs {
+    /// Model whose context window to fit (e.g., "gpt-4o")
+    #[arg(short = 'm', long, value_name = "MODEL")]
+    model: String,
+
+    /// Tokens to leave free for the model's output
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    reserve: usize,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn head(args: &HeadArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let text = read_input(args.file.as_deref())?;
+
+    let head = head_by_tokens(&text, &tokenizer, args.tokens).context("Failed to cut input")?;
+    write_output(head)
+}
+
+pub fn tail(args: &TailArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let text = read_input(args.file.as_deref())?;
+
+    let tail = tail_by_tokens(&text, &tokenizer, args.tokens).context("Failed to cut input")?;
+    write_output(tail)
+}
+
+pub fn truncate(args: &TruncateArgs) -> Result<ExitCode> {
+    let Some(spec) = lookup_model(&args.model) else {
+        let names: Vec<_> = known_models().map(|spec| spec.name).collect();
+        anyhow::bail!(
+            "Unknown model '{}'. Known models:\n  {}",
+            args.model,
+            names.join("\n  ")
+        );
+    };
+    let budget = budget(spec, args.reserve)?;
+
+    let tokenizer = model_tokenizer_args(spec, &args.tokenizer).load()?;
+    let text = read_input(None)?;
+
+    let head = head_by_tokens(&text, &tokenizer, budget).context("Failed to cut input")?;
+    write_output(head)
+}
+
+/// Tokens of input that fit in `spec`'s context window with `reserve`
+/// left for the output and room for the request's own framing
+fn budget(spec: &ModelSpec, reserve: usize) -> Result<usize> {
+    spec.context_window
+        .checked_sub(reserve + spec.request_overhead)
+        .filter(|budget| *budget > 0)
+        .with_context(|| {
+            format!(
+                "--reserve ({}) and {} tokens of request framing leave no room in the {} token context window of {}",
+                reserve, spec.request_overhead, spec.context_window, spec.name
+            )
+        })
+}
+
+/// The tokenizer to fit `spec`'s window with: the one selected, or else
+/// the installed tokenizer the model's family counts with, as `--chat`
+/// picks them
+fn model_tokenizer_args(spec: &ModelSpec, selected: &TokenizerArgs) -> TokenizerArgs {
+    let purpose = format!("fitting {}", spec.name);
+    if spec.name.starts_with("claude") {
+        selected.or_preferred(CLAUDE_TOKENIZER, &purpose)
+    } else if ["gpt-", "o1", "o3"]
+        .iter()
+        .any(|prefix| spec.name.starts_with(prefix))
+    {
+        selected.or_preferred(OPENAI_TOKENIZER, &purpose)
+    } else {
+        if selected.is_default() {
+            eprintln!(
+                "tc: warning: no tokenizer is known for {}; {} tokens with {}",
+                spec.name,
+                purpose,
+                selected.label()
+            );
+        }
+        selected.clone()
+    }
+}
+
+/// Reads the whole input file, or stdin when no file is given
+fn read_input(file: Option<&Path>) -> Result<String> {
+    match file {
+        Some(path) => {
+            fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))
+        }
+        None => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .context("Failed to read stdin")?;
+            Ok(text)
+        }
+    }
+}
+
+fn write_output(text: &str) -> Result<ExitCode> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token_counter_lib::{count_tokens, load_tokenizer_from_bytes};
+
+    #[test]
+    fn test_truncate_fits_the_context_window() {
+        let tokenizer =
+            load_tokenizer_from_bytes(include_bytes!("../assets/gpt2-tokenizer.json")).unwrap();
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(2000);
+        let spec = lookup_model("gpt-4").unwrap();
+        for reserve in [0, 1000, 8000] {
+            let budget = budget(spec, reserve).unwrap();
+            let head = head_by_tokens(&text, &tokenizer, budget).unwrap();
+            let tokens = count_tokens(head, &tokenizer, false).unwrap();
+            assert!(tokens > 0);
+            assert!(tokens + spec.request_overhead <= spec.context_window - reserve);
+        }
+        assert!(budget(spec, spec.context_window - spec.request_overhead).is_err());
+        assert!(budget(spec, spec.context_window).is_err());
+    }
+}
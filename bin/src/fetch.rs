@@ -0,0 +1,65 @@
+//! `http(s)://` inputs: fetching remote documents to count them
+
+use anyhow::Result;
+use std::path::Path;
+
+/// Largest response body counted, in bytes
+#[cfg(feature = "http")]
+pub const MAX_FETCH_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Whether an input names a URL rather than a file
+pub fn is_url(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|input| input.starts_with("http://") || input.starts_with("https://"))
+}
+
+/// Fetches a URL's body, following redirects
+///
+/// Connecting times out after 10 seconds and the whole request after 60;
+/// bodies over [`MAX_FETCH_BYTES`] are refused rather than truncated.
+#[cfg(feature = "http")]
+pub fn fetch(url: &str) -> Result<Vec<u8>> {
+    use std::io::Read;
+    use std::time::Duration;
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .timeout(Duration::from_secs(60))
+        .redirects(10)
+        .user_agent(concat!("tc/", env!("CARGO_PKG_VERSION")))
+        .build();
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            anyhow::bail!("HTTP {} {}", code, response.status_text())
+        }
+        Err(ureq::Error::Transport(transport)) => {
+            // Inputs are reported with their URL already
+            let message = transport.to_string();
+            let url = transport.url().map(|url| format!("{}: ", url));
+            let message = url.and_then(|url| message.strip_prefix(&url).map(str::to_string));
+            anyhow::bail!("{}", message.unwrap_or_else(|| transport.to_string()))
+        }
+    };
+
+    let too_large = || anyhow::anyhow!("response is over {} MiB", MAX_FETCH_BYTES >> 20);
+    let length = response.header("Content-Length");
+    if length.and_then(|length| length.parse::<u64>().ok()) > Some(MAX_FETCH_BYTES) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_FETCH_BYTES + 1)
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > MAX_FETCH_BYTES {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+#[cfg(not(feature = "http"))]
+pub fn fetch(_url: &str) -> Result<Vec<u8>> {
+    anyhow::bail!("URL inputs are not enabled (build with the `http` feature)")
+}
@@ -0,0 +1,71 @@
+//! `--follow`: keep a running token count of a file as it grows
+
+use crate::diagnostics::Input;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use token_counter_lib::{count_stats, Report, TokenStats};
+use tokenizers::Tokenizer;
+
+/// How often the file is checked for new data
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Follows `path`, printing the running totals whenever complete lines are appended
+///
+/// Only complete lines are counted, so a token is never split across two
+/// appends. If the file is truncated, counting starts over. Runs until
+/// interrupted.
+pub fn follow(path: &Path, tokenizer: &Tokenizer, report: &Report) -> Result<()> {
+    let name = path.display().to_string();
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let live = io::stdout().is_terminal();
+
+    let mut total = TokenStats::new();
+    let mut offset = 0u64;
+    // Bytes read past the last newline, held until the line is complete
+    let mut pending = Vec::new();
+    let mut first = true;
+
+    loop {
+        let len = file.metadata()?.len();
+        if len < offset {
+            // Truncated (e.g. log rotation): start over
+            total = TokenStats::new();
+            offset = 0;
+            pending.clear();
+        }
+
+        let mut appended = Vec::new();
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_to_end(&mut appended)?;
+        offset += appended.len() as u64;
+        pending.extend_from_slice(&appended);
+
+        let changed = match pending.iter().rposition(|&b| b == b'\n') {
+            Some(last_newline) => {
+                let complete: Vec<u8> = pending.drain(..=last_newline).collect();
+                let text = String::from_utf8(complete)
+                    .with_context(|| format!("{:?} is not valid UTF-8", path))?;
+                total.add(&count_stats(&text, tokenizer)?);
+                true
+            }
+            None => false,
+        };
+
+        if changed || first {
+            let line = report.format_stats(&total, Input::File(&name).name());
+            if live {
+                print!("\r{}", line);
+                io::stdout().flush()?;
+            } else {
+                println!("{}", line);
+            }
+            first = false;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,56 @@
+//! `tc freq`: the most frequent tokens across inputs
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{read_document_text, VocabUsage};
+
+/// List the most frequent tokens with their counts and share of the total
+#[derive(Args, Debug)]
+pub struct FreqArgs {
+    /// Input files (reads from stdin if not provided)
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Number of tokens to list
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    top: usize,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &FreqArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let mut usage = VocabUsage::new();
+
+    if args.files.is_empty() {
+        let text = io::read_to_string(io::stdin().lock()).context("Failed to read stdin")?;
+        usage.add_text(&text, &tokenizer)?;
+    }
+    for file in &args.files {
+        let text =
+            read_document_text(file).with_context(|| format!("Failed to read {:?}", file))?;
+        usage.add_text(&text, &tokenizer)?;
+    }
+
+    let total = usage.tokens();
+    println!("{:>8} {:>7} {:>8}  token", "count", "share", "id");
+    for (id, count) in usage.most_common(args.top) {
+        // Decoded text shows byte-level pieces as the characters they stand for
+        let piece = tokenizer
+            .decode(&[id], false)
+            .ok()
+            .or_else(|| tokenizer.id_to_token(id))
+            .unwrap_or_default();
+        let share = count as f64 * 100.0 / total as f64;
+        println!("{:>8} {:>6.2}% {:>8}  {:?}", count, share, id, piece);
+    }
+    println!();
+    println!("{} tokens, {} distinct", total, usage.distinct());
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,276 @@
+//! `tc git`: count tokens in git changes and across git history
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Args, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, Stdio};
+use token_counter_lib::{count_tokens, parse_unified_diff};
+use tokenizers::Tokenizer;
+
+/// Count tokens added and removed in a git diff
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(group(ArgGroup::new("source")))]
+pub struct GitArgs {
+    #[command(subcommand)]
+    command: Option<GitCommand>,
+
+    /// Count staged changes (default: unstaged working tree changes)
+    #[arg(long, group = "source")]
+    staged: bool,
+
+    /// Count changes in a revision range (e.g., "main..HEAD")
+    #[arg(long, value_name = "REV..REV", group = "source")]
+    diff: Option<String>,
+
+    /// Limit counting to these paths
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum GitCommand {
+    /// Show the token count of a path at each commit that touched it
+    History(HistoryArgs),
+}
+
+#[derive(Args, Debug)]
+struct HistoryArgs {
+    /// File or directory to track
+    #[arg(value_name = "PATH")]
+    path: PathBuf,
+
+    /// Only show the most recent N commits
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = HistoryFormat::Table)]
+    format: HistoryFormat,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFormat {
+    Table,
+    Json,
+}
+
+/// Token count of a path at one commit
+#[derive(Serialize)]
+struct Revision {
+    commit: String,
+    date: String,
+    subject: String,
+    files: usize,
+    tokens: usize,
+}
+
+pub fn run(args: &GitArgs) -> Result<ExitCode> {
+    match &args.command {
+        Some(GitCommand::History(history_args)) => run_history(history_args),
+        None => run_diff(args),
+    }
+}
+
+fn run_diff(args: &GitArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+
+    let mut git_args = ["diff", "--no-color", "--no-ext-diff", "--unified=0"]
+        .map(String::from)
+        .to_vec();
+    if args.staged {
+        git_args.push("--cached".to_string());
+    }
+    if let Some(range) = &args.diff {
+        git_args.push(range.clone());
+    }
+    git_args.push("--".to_string());
+    git_args.extend(args.paths.iter().map(|p| p.display().to_string()));
+
+    let diff = git(&git_args)?;
+
+    let (mut total_added, mut total_removed) = (0, 0);
+    for file in parse_unified_diff(&diff) {
+        let added = count_tokens(&file.added, &tokenizer, false)
+            .with_context(|| format!("Failed to count tokens in {}", file.path))?;
+        let removed = count_tokens(&file.removed, &tokenizer, false)
+            .with_context(|| format!("Failed to count tokens in {}", file.path))?;
+        if added == 0 && removed == 0 {
+            continue;
+        }
+
+        println!("{}", format_diff_line(added, removed, &file.path));
+        total_added += added;
+        total_removed += removed;
+    }
+    println!("{}", format_diff_line(total_added, total_removed, "total"));
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_history(args: &HistoryArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let path = args.path.display().to_string();
+
+    let mut log_args = vec![
+        "log".to_string(),
+        "--reverse".to_string(),
+        "--date=short".to_string(),
+        "--format=%H%x09%ad%x09%s".to_string(),
+    ];
+    if let Some(limit) = args.limit {
+        log_args.push(format!("--max-count={}", limit));
+    }
+    log_args.extend(["--".to_string(), path.clone()]);
+
+    let mut revisions = Vec::new();
+    for line in git(&log_args)?.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(commit), Some(date), Some(subject)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        let (files, tokens) = count_at_revision(commit, &path, &tokenizer)?;
+        revisions.push(Revision {
+            commit: commit.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            files,
+            tokens,
+        });
+    }
+
+    match args.format {
+        HistoryFormat::Json => println!("{}", serde_json::to_string_pretty(&revisions)?),
+        HistoryFormat::Table => {
+            let mut previous = 0;
+            for revision in &revisions {
+                println!(
+                    "{} {} {:8} {:>+8} {}",
+                    &revision.commit[..revision.commit.len().min(8)],
+                    revision.date,
+                    revision.tokens,
+                    revision.tokens as i64 - previous as i64,
+                    revision.subject
+                );
+                previous = revision.tokens;
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Counts tokens in all text files under `path` at `commit`
+///
+/// Returns the number of files counted and their total tokens. Files that
+/// are not valid UTF-8 (e.g. images) are skipped.
+fn count_at_revision(commit: &str, path: &str, tokenizer: &Tokenizer) -> Result<(usize, usize)> {
+    let tree = git(&["ls-tree", "-r", commit, "--", path])?;
+    // Each line is "<mode> <type> <id>\t<path>"
+    let blobs: Vec<&str> = tree
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(_), Some("blob"), Some(id)) => Some(id),
+                _ => None,
+            }
+        })
+        .collect();
+    if blobs.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let contents = git_cat_blobs(&blobs)?;
+    let (mut files, mut tokens) = (0, 0);
+    for blob in contents {
+        if let Ok(text) = String::from_utf8(blob) {
+            tokens += count_tokens(&text, tokenizer, false)?;
+            files += 1;
+        }
+    }
+    Ok((files, tokens))
+}
+
+/// Reads blob contents with a single `git cat-file --batch` process
+pub fn git_cat_blobs(ids: &[&str]) -> Result<Vec<Vec<u8>>> {
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = ids.join("\n") + "\n";
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().context("Failed to run git")?;
+    writer
+        .join()
+        .expect("writer thread panicked")
+        .context("Failed to write to git cat-file")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git cat-file failed");
+    }
+
+    // Each entry is "<id> <type> <size>\n<content>\n"
+    let mut blobs = Vec::new();
+    let mut rest = output.stdout.as_slice();
+    while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&rest[..newline]);
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .with_context(|| format!("Unexpected git cat-file output: {}", header))?;
+        let start = newline + 1;
+        blobs.push(rest[start..start + size].to_vec());
+        rest = &rest[(start + size + 1).min(rest.len())..];
+    }
+    Ok(blobs)
+}
+
+/// Runs git with the given arguments and returns its output
+pub fn git<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Result<String> {
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first()
+                .map(|a| a.as_ref().to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("git output is not valid UTF-8")
+}
+
+/// Formats added, removed, and net token counts for one path
+fn format_diff_line(added: usize, removed: usize, name: &str) -> String {
+    let net = added as i64 - removed as i64;
+    format!(
+        "{:>8} {:>8} {:>8} {}",
+        format!("+{}", added),
+        format!("-{}", removed),
+        format!("{:+}", net),
+        name
+    )
+}
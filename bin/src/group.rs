@@ -0,0 +1,208 @@
+//! `--group-by`: roll up multi-file counts into groups of files
+
+use crate::config::{self, PathGroup};
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use token_counter_lib::TokenStats;
+
+/// Group files matching none of the `--group-by glob` patterns fall into
+const OTHER: &str = "(other)";
+
+/// How `--group-by` groups files
+#[derive(Clone, Debug)]
+pub enum GroupBy {
+    /// File extension (e.g. ".rs")
+    Ext,
+    /// Directory, with cumulative totals for every ancestor (like `du`)
+    Dir,
+    /// Named groups of path patterns, each file in the first that matches:
+    /// given inline, or `None` for the `[[groups]]` of `.tc.toml`
+    Glob(Option<Vec<PathGroup>>),
+}
+
+/// Parses `--group-by`: "ext", "dir", "glob", or
+/// "glob:PATTERN=NAME,PATTERN=NAME,..."
+pub fn parse_group_by(value: &str) -> Result<GroupBy, String> {
+    match value {
+        "ext" => return Ok(GroupBy::Ext),
+        "dir" => return Ok(GroupBy::Dir),
+        "glob" => return Ok(GroupBy::Glob(None)),
+        _ => {}
+    }
+    let Some(patterns) = value.strip_prefix("glob:") else {
+        return Err(format!(
+            "expected ext, dir, glob, or glob:PATTERN=NAME,..., got '{}'",
+            value
+        ));
+    };
+    split_patterns(patterns)
+        .into_iter()
+        .map(|pair| {
+            let (path, name) = pair
+                .rsplit_once('=')
+                .filter(|(path, name)| !path.is_empty() && !name.is_empty())
+                .ok_or_else(|| format!("expected PATTERN=NAME, got '{}'", pair))?;
+            PathGroup::new(name, path).map_err(|e| format!("invalid pattern '{}': {}", path, e))
+        })
+        .collect::<Result<_, _>>()
+        .map(|groups| GroupBy::Glob(Some(groups)))
+}
+
+/// Splits `patterns` at the commas outside `{...}` alternatives
+fn split_patterns(patterns: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in patterns.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&patterns[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&patterns[start..]);
+    parts
+}
+
+/// Token totals per group
+pub struct Groups {
+    by: GroupBy,
+    /// Directories given on the command line, which `--group-by dir` levels
+    /// are counted from
+    roots: Vec<PathBuf>,
+    /// Deepest directory level reported with `--group-by dir`
+    depth: Option<usize>,
+    totals: BTreeMap<String, TokenStats>,
+}
+
+impl Groups {
+    pub fn new(by: GroupBy, roots: &[PathBuf], depth: Option<usize>) -> Result<Self> {
+        if matches!(by, GroupBy::Glob(None)) && config::get().groups.is_empty() {
+            anyhow::bail!(
+                "--group-by glob needs [[groups]] in {}, or patterns as in \
+                 --group-by 'glob:docs/**=docs,src/**=code'",
+                config::PROJECT_FILE
+            );
+        }
+        Ok(Self {
+            by,
+            roots: roots.iter().filter(|root| root.is_dir()).cloned().collect(),
+            depth,
+            totals: BTreeMap::new(),
+        })
+    }
+
+    /// Adds a file's counts to every group it belongs to
+    pub fn add(&mut self, path: &Path, stats: &TokenStats) {
+        for key in self.keys(path) {
+            self.totals.entry(key).or_default().add(stats);
+        }
+    }
+
+    /// Groups with their totals: extensions largest first, directories in
+    /// path order so that subdirectories follow their parent, and globs in
+    /// the order they were given with files matching none last
+    pub fn rows(&self) -> Vec<(&str, &TokenStats)> {
+        let mut groups: Vec<_> = self
+            .totals
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+            .collect();
+        match &self.by {
+            GroupBy::Ext => groups.sort_by_key(|(_, stats)| Reverse(stats.tokens)),
+            GroupBy::Dir => {}
+            GroupBy::Glob(_) => {
+                let groups_given = self.path_groups();
+                groups.sort_by_key(|(name, _)| {
+                    groups_given
+                        .iter()
+                        .position(|group| group.name == *name)
+                        .unwrap_or(usize::MAX)
+                });
+            }
+        }
+        groups
+    }
+
+    /// The groups of `--group-by glob`, inline or from `.tc.toml`
+    fn path_groups(&self) -> &[PathGroup] {
+        match &self.by {
+            GroupBy::Glob(Some(groups)) => groups,
+            _ => &config::get().groups,
+        }
+    }
+
+    /// Names of the groups `path` belongs to
+    fn keys(&self, path: &Path) -> Vec<String> {
+        match &self.by {
+            GroupBy::Ext => vec![match path.extension() {
+                Some(ext) => format!(".{}", ext.to_string_lossy()),
+                None => "(none)".to_string(),
+            }],
+            GroupBy::Dir => {
+                // The directory given on the command line that `path` came
+                // from, then every directory below it down to the parent
+                let parent = path.parent().unwrap_or(Path::new(""));
+                let root = self
+                    .roots
+                    .iter()
+                    .filter(|root| parent.starts_with(root))
+                    .max_by_key(|root| root.components().count())
+                    .map_or(parent, PathBuf::as_path);
+                let below = parent.strip_prefix(root).unwrap_or(Path::new(""));
+
+                let mut dir = root.to_path_buf();
+                let mut keys = vec![display_dir(&dir)];
+                for component in below.components() {
+                    if self.depth.is_some_and(|depth| keys.len() > depth) {
+                        break;
+                    }
+                    dir.push(component);
+                    keys.push(display_dir(&dir));
+                }
+                keys
+            }
+            GroupBy::Glob(inline) => {
+                // Inline patterns are relative to the working directory,
+                // those of .tc.toml to the project root
+                let relative = match inline {
+                    Some(_) => Some(relative_to_cwd(path)),
+                    None => config::get().project_path(path),
+                };
+                let name = relative.and_then(|relative| {
+                    self.path_groups()
+                        .iter()
+                        .find(|group| group.matches(&relative))
+                });
+                vec![name.map_or(OTHER, |group| group.name.as_str()).to_string()]
+            }
+        }
+    }
+}
+
+/// `path` relative to the working directory, without a leading "./"
+fn relative_to_cwd(path: &Path) -> PathBuf {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    if path.is_absolute() {
+        if let Some(relative) = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(cwd).ok())
+        {
+            return relative.to_path_buf();
+        }
+    }
+    path.to_path_buf()
+}
+
+fn display_dir(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        dir.display().to_string()
+    }
+}
@@ -0,0 +1,99 @@
+//! `--timeout-per-file` and `--max-file-size`: skipping inputs that would
+//! hang or swamp a run, such as FIFOs that never end or enormous minified
+//! bundles
+
+use crate::sample::format_size;
+use anyhow::Result;
+use std::fmt;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokenizers::Tokenizer;
+
+/// Why an input was skipped; printed as a warning rather than an error
+#[derive(Debug)]
+pub struct Skipped(String);
+
+impl Skipped {
+    pub fn too_large(max: u64) -> Self {
+        Self(format!("larger than --max-file-size {}", format_size(max)))
+    }
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skipped: {}", self.0)
+    }
+}
+
+impl std::error::Error for Skipped {}
+
+/// Parses `--timeout-per-file` seconds, such as "30" or "2.5"
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.trim().parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!(
+            "expected a positive number of seconds, got '{}'",
+            value
+        )),
+    }
+}
+
+/// The `--timeout-per-file` limit, and the deadline of the file being
+/// counted
+pub struct FileTimeout {
+    timeout: Duration,
+    deadline: Option<Instant>,
+    /// Shared with the threads the work runs on, which may outlive a file
+    /// that timed out
+    tokenizer: Arc<Tokenizer>,
+}
+
+impl FileTimeout {
+    pub fn new(timeout: Duration, tokenizer: Arc<Tokenizer>) -> Self {
+        Self {
+            timeout,
+            deadline: None,
+            tokenizer,
+        }
+    }
+
+    /// Starts the clock for the next file
+    pub fn start(&mut self) {
+        self.deadline = Some(Instant::now() + self.timeout);
+    }
+
+    /// Runs `work` on a thread of its own and waits for it until the
+    /// current file's deadline, after which the file is skipped
+    ///
+    /// Reads and encodes cannot be interrupted, so work that times out is
+    /// left to finish on its own (or to block until `tc` exits) and its
+    /// result is dropped. Without a started clock, `work` runs here.
+    pub fn run<T, F>(&self, work: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Tokenizer) -> Result<T> + Send + 'static,
+    {
+        let Some(deadline) = self.deadline else {
+            return work(&self.tokenizer);
+        };
+        let tokenizer = Arc::clone(&self.tokenizer);
+        let (sender, receiver) = mpsc::channel();
+        // Builds without threads (such as WASI) fail here rather than panic
+        thread::Builder::new()
+            .spawn(move || {
+                // The receiver is gone if the file already timed out
+                let _ = sender.send(work(&tokenizer));
+            })
+            .map_err(|e| anyhow::anyhow!("--timeout-per-file needs a thread: {}", e))?;
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(Skipped(format!("not done after {:.1?}", self.timeout)).into())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("counting thread panicked")
+            }
+        }
+    }
+}
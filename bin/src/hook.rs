@@ -0,0 +1,219 @@
+//! `tc hook`: a git pre-commit hook that blocks commits of staged files
+//! over their token budgets
+//!
+//! `tc hook install` writes a `pre-commit` script that runs `tc hook run`,
+//! which counts the staged contents of each file (not the working tree) and
+//! checks them against the `[[budgets]]` of `.tc.toml` and `--max-tokens`.
+
+use crate::config;
+use crate::diagnostics::{self, Input, Level};
+use crate::git::{git, git_cat_blobs};
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::count_tokens;
+
+/// Line of the installed script that marks it as ours, so reinstalling
+/// replaces it without `--force`
+const MARKER: &str = "# Installed by `tc hook install`";
+
+/// Install or run a git pre-commit hook that checks token budgets
+#[derive(Args, Debug)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    command: HookCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum HookCommand {
+    /// Write a pre-commit hook that runs `tc hook run` to the repository
+    Install(InstallArgs),
+    /// Check staged files against their token budgets, failing if any
+    /// exceeds one
+    Run(RunArgs),
+}
+
+#[derive(Args, Debug)]
+struct InstallArgs {
+    /// Replace a pre-commit hook that `tc` did not install
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// Also fail any staged file over N tokens, on top of the budgets of
+    /// .tc.toml
+    #[arg(long, value_name = "N")]
+    max_tokens: Option<usize>,
+
+    /// Limit checking to staged files under these paths
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// A budget a staged file is checked against
+struct FileBudget {
+    /// " for PATTERN" if the budget is from .tc.toml, for messages
+    source: String,
+    max_tokens: Option<usize>,
+    min_tokens: Option<usize>,
+}
+
+pub fn run(args: &HookArgs) -> Result<ExitCode> {
+    match &args.command {
+        HookCommand::Install(install_args) => install(install_args),
+        HookCommand::Run(run_args) => run_hook(run_args),
+    }
+}
+
+fn install(args: &InstallArgs) -> Result<ExitCode> {
+    // Honors core.hooksPath
+    let hooks = PathBuf::from(git(&["rev-parse", "--git-path", "hooks"])?.trim_end());
+    let path = hooks.join("pre-commit");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !existing.contains(MARKER) && !args.force {
+            anyhow::bail!(
+                "{} already exists; add --force to replace it",
+                path.display()
+            );
+        }
+    }
+
+    let script = format!(
+        "#!/bin/sh\n{}: blocks commits of staged files over the\n\
+         # token budgets in .tc.toml\nexec tc hook run\n",
+        MARKER
+    );
+    fs::create_dir_all(&hooks).with_context(|| format!("Failed to create {:?}", hooks))?;
+    fs::write(&path, script).with_context(|| format!("Failed to write {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {:?} executable", path))?;
+    }
+
+    println!("installed {}", path.display());
+    if config::get().budgets.is_empty() {
+        eprintln!(
+            "tc: warning: no [[budgets]] in {}, so the hook checks nothing yet",
+            config::PROJECT_FILE
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_hook(args: &RunArgs) -> Result<ExitCode> {
+    let config = config::get();
+    if config.budgets.is_empty() && args.max_tokens.is_none() {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Staged files other than deletions, relative to the top of the
+    // repository
+    let mut diff_args = [
+        "diff",
+        "--cached",
+        "--name-only",
+        "--diff-filter=ACMR",
+        "-z",
+    ]
+    .map(String::from)
+    .to_vec();
+    diff_args.push("--".to_string());
+    diff_args.extend(args.paths.iter().map(|p| p.display().to_string()));
+    let staged = git(&diff_args)?;
+    let top = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim_end());
+    let top = top.canonicalize().unwrap_or(top);
+
+    // Each file with its budgets
+    let mut checked: Vec<(&str, Vec<FileBudget>)> = Vec::new();
+    for file in staged.split('\0').filter(|file| !file.is_empty()) {
+        let mut budgets = Vec::new();
+        if let Some(max) = args.max_tokens {
+            budgets.push(FileBudget {
+                source: String::new(),
+                max_tokens: Some(max),
+                min_tokens: None,
+            });
+        }
+        if let Some(path) = project_path(&top, file) {
+            for budget in config.budgets.iter().filter(|budget| budget.matches(&path)) {
+                budgets.push(FileBudget {
+                    source: format!(" for {}", budget.path),
+                    max_tokens: budget.max_tokens,
+                    min_tokens: budget.fail_under,
+                });
+            }
+        }
+        // Names with newlines cannot be asked of `git cat-file --batch`
+        if !budgets.is_empty() && !file.contains('\n') {
+            checked.push((file, budgets));
+        }
+    }
+    if checked.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let tokenizer = args.tokenizer.load()?;
+    // ":path" names a file's staged contents
+    let names: Vec<String> = checked
+        .iter()
+        .map(|(file, _)| format!(":{}", file))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let contents = git_cat_blobs(&names)?;
+
+    let mut violations = 0;
+    for ((file, budgets), blob) in checked.iter().zip(contents) {
+        // Binary files are not prompts or docs
+        let Ok(text) = String::from_utf8(blob) else {
+            continue;
+        };
+        let tokens = count_tokens(&text, &tokenizer, false)
+            .with_context(|| format!("Failed to count tokens in {}", file))?;
+        for budget in budgets {
+            let message = match (budget.max_tokens, budget.min_tokens) {
+                (Some(max), _) if tokens > max => format!(
+                    "exceeds token budget{} ({} > {} tokens)",
+                    budget.source, tokens, max
+                ),
+                (_, Some(min)) if tokens < min => format!(
+                    "below token minimum{} ({} < {} tokens)",
+                    budget.source, tokens, min
+                ),
+                _ => continue,
+            };
+            diagnostics::report(None, Level::Error, Input::File(file), &message);
+            violations += 1;
+        }
+    }
+
+    if violations > 0 {
+        eprintln!(
+            "tc: commit blocked by {} token budget violation{}; trim the files above, \
+             or commit with --no-verify to skip the check",
+            violations,
+            if violations == 1 { "" } else { "s" }
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `file`, relative to the repository at `top`, relative to the project
+/// root that budget patterns are matched from
+fn project_path(top: &Path, file: &str) -> Option<PathBuf> {
+    let root = config::get().project_root.as_ref()?;
+    top.join(file)
+        .strip_prefix(root)
+        .ok()
+        .map(Path::to_path_buf)
+}
@@ -0,0 +1,317 @@
+//! `--ids`, `--show-tokens`, `--highlight`, and `--per-line`: printing the
+//! tokens of inputs instead of their counts
+
+use crate::output::{self, outln};
+use crate::CountMode;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{stats_per_line, token_spans};
+use tokenizers::Tokenizer;
+
+/// How `--ids` prints token IDs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IdsFormat {
+    /// Separated by spaces
+    Space,
+    /// Separated by commas
+    Comma,
+    /// A JSON array
+    Json,
+}
+
+impl IdsFormat {
+    /// Formats a sequence of IDs
+    pub fn format(&self, ids: &[u32]) -> String {
+        let ids: Vec<_> = ids.iter().map(u32::to_string).collect();
+        match self {
+            IdsFormat::Space => ids.join(" "),
+            IdsFormat::Comma => ids.join(", "),
+            IdsFormat::Json => format!("[{}]", ids.join(",")),
+        }
+    }
+}
+
+/// `--ids`: prints each input's token IDs
+///
+/// With several files, each line is prefixed with the file name, or for
+/// JSON, the output is one object mapping file names to ID arrays.
+pub fn print_ids(
+    mode: &CountMode,
+    files: &[PathBuf],
+    format: IdsFormat,
+    tokenizer: &Tokenizer,
+) -> Result<ExitCode> {
+    if files.is_empty() {
+        let text = mode.read_stdin()?;
+        outln!(
+            "{}",
+            format.format(&mode.encode_ids(&text, None, tokenizer)?)
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut by_file = Vec::new();
+    for file in files {
+        let ids = mode
+            .read_file(file)
+            .and_then(|text| mode.encode_ids(&text, Some(file), tokenizer))
+            .with_context(|| format!("Failed to encode {:?}", file))?;
+        let name = file.display().to_string();
+        match format {
+            _ if files.len() == 1 => outln!("{}", format.format(&ids)),
+            IdsFormat::Json => by_file.push(format!(
+                "{}:{}",
+                serde_json::Value::from(name),
+                format.format(&ids)
+            )),
+            _ => outln!("{}: {}", name, format.format(&ids)),
+        }
+    }
+    if !by_file.is_empty() {
+        outln!("{{{}}}", by_file.join(","));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `--show-tokens`: prints a line per token with its byte offsets, ID, text,
+/// and vocabulary piece
+///
+/// With several files, each file's tokens follow a `==> name <==` header.
+pub fn print_tokens(
+    mode: &CountMode,
+    files: &[PathBuf],
+    tokenizer: &Tokenizer,
+) -> Result<ExitCode> {
+    if files.is_empty() {
+        show_tokens(mode, &mode.read_stdin()?, None, tokenizer)?;
+    }
+    for (index, file) in files.iter().enumerate() {
+        if files.len() > 1 {
+            if index > 0 {
+                outln!();
+            }
+            outln!("==> {} <==", file.display());
+        }
+        let text = mode
+            .read_file(file)
+            .with_context(|| format!("Failed to read {:?}", file))?;
+        show_tokens(mode, &text, Some(file), tokenizer)
+            .with_context(|| format!("Failed to tokenize {:?}", file))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show_tokens(
+    mode: &CountMode,
+    text: &str,
+    path: Option<&Path>,
+    tokenizer: &Tokenizer,
+) -> Result<()> {
+    outln!(
+        "{:>8} {:>8} {:>8}  {:<16} piece",
+        "start",
+        "end",
+        "id",
+        "text"
+    );
+    for text in mode.prepare(text, path)? {
+        for token in token_spans(&text, tokenizer)? {
+            let covered = text.get(token.span.clone()).unwrap_or_default();
+            outln!(
+                "{:>8} {:>8} {:>8}  {:<16} {}",
+                token.span.start,
+                token.span.end,
+                token.id,
+                format!("{:?}", covered),
+                token.piece
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `--per-line`: prints the tokens and bytes of each line
+///
+/// With several files, each line is labelled `name:line`, as grep does.
+pub fn print_per_line(
+    mode: &CountMode,
+    files: &[PathBuf],
+    tokenizer: &Tokenizer,
+) -> Result<ExitCode> {
+    if files.is_empty() {
+        per_line(mode, &mode.read_stdin()?, None, None, tokenizer)?;
+    }
+    for file in files {
+        let text = mode
+            .read_file(file)
+            .with_context(|| format!("Failed to read {:?}", file))?;
+        let name = file.display().to_string();
+        let label = (files.len() > 1).then_some(name.as_str());
+        per_line(mode, &text, Some(file), label, tokenizer)
+            .with_context(|| format!("Failed to tokenize {:?}", file))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn per_line(
+    mode: &CountMode,
+    text: &str,
+    path: Option<&Path>,
+    label: Option<&str>,
+    tokenizer: &Tokenizer,
+) -> Result<()> {
+    for text in mode.prepare(text, path)? {
+        for line in stats_per_line(&text, tokenizer) {
+            let line = line?;
+            match label {
+                Some(name) => outln!(
+                    "{:8} {:8} {}:{}",
+                    line.tokens,
+                    line.bytes,
+                    name,
+                    line.line_no
+                ),
+                None => outln!("{:8} {:8} {}", line.tokens, line.bytes, line.line_no),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Background colors `--highlight` cycles through (256-color palette
+/// pastels, legible under black text)
+pub const HIGHLIGHT_COLORS: [u8; 5] = [153, 194, 223, 218, 187];
+
+/// The part of `text` each token is highlighted over, with its color from
+/// [`HIGHLIGHT_COLORS`], in order; text between the parts is left plain
+///
+/// Tokens that split a multi-byte character all cover the whole character,
+/// so the character goes to the first of them and the rest are left out.
+pub fn highlight_spans(text: &str, tokenizer: &Tokenizer) -> Result<Vec<(Range<usize>, u8)>> {
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for (index, token) in token_spans(text, tokenizer)?.iter().enumerate() {
+        if token.span.end <= end {
+            continue;
+        }
+        let start = end.max(token.span.start);
+        spans.push((
+            start..token.span.end,
+            HIGHLIGHT_COLORS[index % HIGHLIGHT_COLORS.len()],
+        ));
+        end = token.span.end;
+    }
+    Ok(spans)
+}
+
+/// `--highlight`: echoes inputs with alternating background colors per token
+///
+/// Colors are used when `color` is set (by default, when stdout is a
+/// terminal and `NO_COLOR` is unset); otherwise token boundaries are marked
+/// with `|`. With several files, each
+/// file follows a `==> name <==` header.
+pub fn print_highlighted(
+    mode: &CountMode,
+    files: &[PathBuf],
+    tokenizer: &Tokenizer,
+    color: bool,
+) -> Result<ExitCode> {
+    if files.is_empty() {
+        highlight(mode, &mode.read_stdin()?, None, tokenizer, color)?;
+    }
+    for (index, file) in files.iter().enumerate() {
+        if files.len() > 1 {
+            if index > 0 {
+                outln!();
+            }
+            outln!("==> {} <==", file.display());
+        }
+        let text = mode
+            .read_file(file)
+            .with_context(|| format!("Failed to read {:?}", file))?;
+        highlight(mode, &text, Some(file), tokenizer, color)
+            .with_context(|| format!("Failed to tokenize {:?}", file))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn highlight(
+    mode: &CountMode,
+    text: &str,
+    path: Option<&Path>,
+    tokenizer: &Tokenizer,
+    color: bool,
+) -> Result<()> {
+    let mut out = String::new();
+    for text in mode.prepare(text, path)? {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        let mut end = 0;
+        for (span, background) in highlight_spans(&text, tokenizer)? {
+            out.push_str(&text[end..span.start]);
+            if color {
+                paint(&mut out, &text[span.clone()], background);
+            } else {
+                if end > 0 {
+                    out.push('|');
+                }
+                out.push_str(&text[span.clone()]);
+            }
+            end = span.end;
+        }
+        out.push_str(&text[end..]);
+    }
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    output::print(format_args!("{}", out));
+    Ok(())
+}
+
+/// Appends `text` on background `color`, leaving newlines unpainted so the
+/// color doesn't fill the rest of the line
+fn paint(out: &mut String, text: &str, color: u8) {
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        if !line.is_empty() {
+            out.push_str(&format!("\x1b[30;48;5;{}m{}\x1b[0m", color, line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token_counter_lib::load_tokenizer_from_bytes;
+
+    #[test]
+    fn test_highlight_spans() {
+        let tokenizer =
+            load_tokenizer_from_bytes(include_bytes!("../assets/gpt2-tokenizer.json")).unwrap();
+        let spans = highlight_spans("Hello, world", &tokenizer).unwrap();
+        assert_eq!(
+            spans,
+            [
+                (0..5, HIGHLIGHT_COLORS[0]),
+                (5..6, HIGHLIGHT_COLORS[1]),
+                (6..12, HIGHLIGHT_COLORS[2])
+            ]
+        );
+
+        // GPT-2 splits "🎉" into several byte tokens, all covering it whole
+        let text = "a🎉b";
+        let tokens = token_spans(text, &tokenizer).unwrap().len();
+        let spans = highlight_spans(text, &tokenizer).unwrap();
+        assert!(tokens > spans.len());
+        let ranges: Vec<_> = spans.into_iter().map(|(range, _)| range).collect();
+        assert_eq!(ranges.first().map(|range| range.start), Some(0));
+        assert_eq!(ranges.last().map(|range| range.end), Some(text.len()));
+        assert!(ranges.windows(2).all(|pair| pair[0].end <= pair[1].start));
+    }
+}
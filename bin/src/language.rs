@@ -0,0 +1,80 @@
+//! `--detect-lang`: token totals per natural language, for balancing
+//! multilingual corpora by tokens rather than bytes
+
+use anyhow::Result;
+#[cfg(not(feature = "lang"))]
+use token_counter_lib::TokenStats;
+
+#[cfg(feature = "lang")]
+mod breakdown {
+    use crate::output::outln;
+    use anyhow::Result;
+    use token_counter_lib::{LanguageBreakdown, TokenStats};
+
+    /// Inputs' stats by the language detected in their text
+    pub struct Languages(LanguageBreakdown);
+
+    impl Languages {
+        pub fn new() -> Result<Self> {
+            Ok(Self(LanguageBreakdown::new()))
+        }
+
+        /// Records the stats of one file or record's text
+        pub fn add(&mut self, text: &str, stats: &TokenStats) {
+            self.0.add(text, stats);
+        }
+
+        /// Prints a line per language with its share of the tokens and how
+        /// many bytes each of its tokens covers
+        pub fn print(&self) {
+            let total = self.0.tokens();
+            outln!();
+            outln!(
+                "{:<12} {:>10} {:>7} {:>10} {:>11}",
+                "language",
+                "tokens",
+                "share",
+                "bytes",
+                "bytes/token"
+            );
+            for (language, stats) in self.0.languages() {
+                let share = if total == 0 {
+                    0.0
+                } else {
+                    stats.tokens as f64 * 100.0 / total as f64
+                };
+                outln!(
+                    "{:<12} {:>10} {:>6.1}% {:>10} {:>11.2}",
+                    language.map_or("unknown", |language| language.name),
+                    stats.tokens,
+                    share,
+                    stats.bytes,
+                    stats.bytes_per_token()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "lang")]
+pub use breakdown::Languages;
+
+/// Stand-in when language detection is not compiled in: never created
+#[cfg(not(feature = "lang"))]
+pub struct Languages;
+
+#[cfg(not(feature = "lang"))]
+impl Languages {
+    pub fn new() -> Result<Self> {
+        anyhow::bail!("language detection is not enabled (build with the `lang` feature)")
+    }
+
+    pub fn add(&mut self, _text: &str, _stats: &TokenStats) {}
+
+    pub fn print(&self) {}
+}
+
+/// [`Languages::new`] if `--detect-lang` was given
+pub fn from_flag(detect: bool) -> Result<Option<Languages>> {
+    detect.then(Languages::new).transpose()
+}
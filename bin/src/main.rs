@@ -3,33 +3,134 @@
 //! A command-line utility for counting LLM tokens in files, similar to Unix `wc`.
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{ArgAction, ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::CompleteEnv;
+use serde::Deserialize;
+use std::cmp::Reverse;
 use std::env;
-use std::io::{self, IsTerminal};
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use token_counter_lib::{
-    count_tokens_from_reader, count_tokens_in_file, load_tokenizer, load_tokenizer_from_bytes,
-    TokenStats,
+    count_stats, count_stats_fast, decode_text, decompress, decompress_reader, encode_ids,
+    extract_text, for_each_member, lookup_model, read_decompressed, repair_utf8, select_text,
+    special_token_count, ArchiveFormat, ColoredFormatter, Columns, Compression, ContextFit,
+    DocumentFormat, JsonPath, ModelPricing, Report, ReportFormat, TextEncoding, Threshold,
+    Thresholds, TokenDistribution, TokenStats, VocabUsage,
 };
+use tokenizers::Tokenizer;
+use tracing::debug;
 
-/// Embedded GPT-2 tokenizer (default)
-const DEFAULT_TOKENIZER: &[u8] = include_bytes!("../assets/gpt2-tokenizer.json");
+mod bench;
+mod cache;
+mod chat;
+mod cloud;
+mod color;
+mod columnar;
+mod compare;
+mod completions;
+mod config;
+mod convert;
+mod daemon;
+mod dataset;
+mod decode;
+mod diagnostics;
+mod duplicates;
+mod encode;
+mod excerpt;
+mod fetch;
+mod follow;
+mod freq;
+mod git;
+mod group;
+mod guard;
+mod hook;
+mod inspect;
+mod language;
+mod mcp;
+mod memory;
+mod metrics;
+mod notebook;
+mod outline;
+mod output;
+mod preprocess;
+mod pricing;
+mod progress;
+mod runlog;
+mod sample;
+mod serve;
+mod service;
+mod similarity;
+mod snapshot;
+mod split;
+mod sqlite;
+mod stream;
+mod tokenizer;
+mod tui;
+mod verify;
+mod walk;
+
+use cache::CountCache;
+use chat::{ChatFormat, MessageCounts};
+use color::ColorWhen;
+use columnar::{ArrowStream, Table};
+use diagnostics::{CiFormat, Input, Level};
+use duplicates::Duplicates;
+use group::{GroupBy, Groups};
+use guard::{FileTimeout, Skipped};
+use inspect::IdsFormat;
+use language::Languages;
+use memory::MemoryLimit;
+use output::outln;
+use preprocess::StripKind;
+use runlog::{Run, Summary};
+use sample::SampleSize;
+use tokenizer::TokenizerArgs;
+use walk::{Symlinks, WalkOptions};
+
+/// Options that need every byte of the input, which `--sample` skips
+const SAMPLE_CONFLICTS: [&str; 20] = [
+    "chat",
+    "follow",
+    "stream",
+    "encoding",
+    "invalid_utf8",
+    "select",
+    "strip",
+    "ids",
+    "show_tokens",
+    "highlight",
+    "per_line",
+    "vocab_stats",
+    "summary",
+    "duplicates",
+    "detect_lang",
+    "group_by",
+    "top",
+    "budget",
+    "add_special_tokens",
+    "overhead",
+];
 
 /// Token counter - count LLM tokens in files (similar to wc for words)
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(group(ArgGroup::new("budget").multiple(true)))]
+#[command(group(ArgGroup::new("sampling")))]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input files (reads from stdin if not provided)
     #[arg(value_name = "FILE")]
     files: Vec<PathBuf>,
 
-    /// Path to custom tokenizer JSON file
-    #[arg(short = 't', long, value_name = "PATH", group = "tokenizer")]
-    tokenizer_path: Option<PathBuf>,
-
-    /// Named tokenizer to use (e.g., "gpt4", "bert")
-    #[arg(short = 'n', long, value_name = "NAME", group = "tokenizer")]
-    tokenizer_name: Option<String>,
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
 
     /// Show only token count
     #[arg(long)]
@@ -42,140 +143,1456 @@ struct Args {
     /// Show byte count
     #[arg(short = 'c', long)]
     bytes: bool,
-}
 
-struct OutputConfig {
+    /// Show tokens per line (average and maximum) and tokens per KiB
+    #[arg(long)]
+    density: bool,
+
+    /// Show bytes per token, how compactly the tokenizer encodes each input
+    #[arg(long)]
+    bytes_per_token: bool,
+
+    /// Output format for the counts [default: plain]
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Write the report to PATH instead of stdout, replacing it only once
+    /// the report is complete
+    #[arg(
+        short = 'o',
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["follow", "stream"]
+    )]
+    output: Option<PathBuf>,
+
+    /// Append a JSON line with this run's totals, tokenizer, arguments, and
+    /// duration to PATH
+    #[arg(long, value_name = "PATH", conflicts_with = "follow")]
+    append_log: Option<PathBuf>,
+
+    /// Add this run's per-file results and totals to the SQLite database at
+    /// PATH, creating it if needed
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["follow", "ids", "show_tokens", "highlight", "per_line", "sample", "sample_bytes"]
+    )]
+    output_sqlite: Option<PathBuf>,
+
+    /// Write each input's stats to PATH as Parquet
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["follow", "ids", "show_tokens", "highlight", "per_line", "sample", "sample_bytes"]
+    )]
+    output_parquet: Option<PathBuf>,
+
+    /// Stream each input's stats to PATH (stdout if "-") as Arrow IPC
+    /// batches while counting
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["follow", "ids", "show_tokens", "highlight", "per_line", "sample", "sample_bytes"]
+    )]
+    output_arrow: Option<PathBuf>,
+
+    /// When to color token counts and --highlight [default: auto]
+    #[arg(long, value_name = "WHEN")]
+    color: Option<ColorWhen>,
+
+    /// Color token counts yellow and red from these counts, or shares of
+    /// the --context window (e.g. "2000,8000", "50%,90%"; default with
+    /// --context: "75%,100%")
+    #[arg(long, value_name = "WARN,ALERT", value_parser = color::parse_thresholds)]
+    thresholds: Option<Thresholds>,
+
+    /// After the counts, report how many files were counted and the min,
+    /// max, mean, median, p90, and p99 of their tokens
+    #[arg(long, conflicts_with = "follow")]
+    summary: bool,
+
+    /// After the counts, report distinct token IDs, type/token ratio, and
+    /// vocabulary coverage across all inputs
+    #[arg(long, conflicts_with_all = ["chat", "follow"])]
+    vocab_stats: bool,
+
+    /// After the counts, list byte-identical inputs and the tokens their
+    /// extra copies add
+    #[arg(long, requires = "files", conflicts_with = "follow")]
+    duplicates: bool,
+
+    /// After the counts, break the totals down by the natural language
+    /// detected in each input (or each --select value)
+    #[arg(long, conflicts_with = "follow")]
+    detect_lang: bool,
+
+    /// Print token IDs instead of counts, separated by spaces or in the
+    /// given format (--ids=comma, --ids=json)
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "space",
+        conflicts_with_all = ["chat", "follow", "group_by", "vocab_stats", "summary", "top", "duplicates", "detect_lang"]
+    )]
+    ids: Option<IdsFormat>,
+
+    /// Print each token's byte offsets, ID, and text instead of counts
+    #[arg(long, conflicts_with_all = ["ids", "chat", "follow", "group_by", "vocab_stats", "summary", "top", "duplicates", "detect_lang"])]
     show_tokens: bool,
-    show_lines: bool,
-    show_bytes: bool,
+
+    /// Echo the input with alternating background colors per token (token
+    /// boundaries marked with `|` when not writing to a terminal)
+    #[arg(
+        long,
+        conflicts_with_all = ["ids", "show_tokens", "chat", "follow", "group_by", "vocab_stats", "summary", "top", "duplicates", "detect_lang"]
+    )]
+    highlight: bool,
+
+    /// Print the tokens and bytes of each line instead of counts
+    #[arg(
+        long,
+        conflicts_with_all = ["ids", "show_tokens", "highlight", "chat", "follow", "group_by", "vocab_stats", "summary", "top", "duplicates", "detect_lang"]
+    )]
+    per_line: bool,
+
+    /// Show estimated input cost in US dollars (requires --model, or a
+    /// model in a config file)
+    #[arg(long)]
+    cost: bool,
+
+    /// Model to estimate costs for (e.g., "gpt-4o", "claude-3-5-sonnet")
+    #[arg(short = 'm', long, value_name = "MODEL")]
+    model: Option<String>,
+
+    /// Tokens to add to the total for request framing or a system prompt,
+    /// given as a count or as a model whose framing to add (e.g., "1500",
+    /// "gpt-4o")
+    #[arg(long, value_name = "N|MODEL", conflicts_with = "follow")]
+    overhead: Option<String>,
+
+    /// Pricing file overriding built-in and ~/.config/tc/pricing.toml prices
+    /// (default: pricing-file from ~/.config/tc/config.toml)
+    #[arg(long, value_name = "PATH")]
+    pricing_file: Option<PathBuf>,
+
+    /// Check inputs against a context window, given in tokens or as a model name
+    /// (e.g., "128000", "gpt-4o")
+    #[arg(long, value_name = "LIMIT")]
+    context: Option<String>,
+
+    /// Exit with a non-zero status if the total exceeds N tokens
+    #[arg(long, value_name = "N", group = "budget")]
+    max_tokens: Option<usize>,
+
+    /// Exit with a non-zero status if the total is below N tokens
+    #[arg(long, value_name = "N", group = "budget")]
+    fail_under: Option<usize>,
+
+    /// Apply --max-tokens and --fail-under to each file instead of the total
+    #[arg(long, requires = "budget")]
+    per_file: bool,
+
+    /// Keep counting as the file grows, printing a running total (like `tail -f`)
+    #[arg(short = 'f', long)]
+    follow: bool,
+
+    /// Count stdin line by line as it arrives, printing each line's counts
+    /// and a running total instead of waiting for the end of input
+    #[arg(
+        long,
+        conflicts_with_all = ["files", "follow", "chat", "encoding", "add_special_tokens", "ids", "show_tokens", "highlight", "per_line"]
+    )]
+    stream: bool,
+
+    /// Parse inputs as chat payloads and count tokens as the API bills them
+    #[arg(long, value_name = "FORMAT", conflicts_with = "follow")]
+    chat: Option<ChatFormat>,
+
+    /// Count only the JSON values selected by a jq-like path
+    /// (e.g., ".messages[].content")
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["chat", "follow"])]
+    select: Option<JsonPath>,
+
+    /// Remove syntax before counting (repeatable)
+    #[arg(long, value_name = "SYNTAX", conflicts_with_all = ["chat", "follow"])]
+    strip: Vec<StripKind>,
+
+    /// Keep code fences when stripping Markdown
+    #[arg(long, requires = "strip")]
+    keep_fences: bool,
+
+    /// Count the special tokens the tokenizer wraps each input in (e.g.
+    /// BERT's [CLS] and [SEP]), as the model sees the sequence
+    #[arg(
+        long,
+        conflicts_with_all = ["chat", "follow", "ids", "show_tokens", "highlight"]
+    )]
+    add_special_tokens: bool,
+
+    /// Character encoding of plain-text inputs (auto: UTF-8, or the
+    /// encoding named by a byte order mark)
+    #[arg(long, value_name = "ENCODING", conflicts_with = "follow")]
+    encoding: Option<InputEncoding>,
+
+    /// What to do with bytes that are not valid UTF-8: replace them with
+    /// U+FFFD, skip (drop) them, or fail on the file; replace and skip warn
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "fail",
+        conflicts_with = "follow"
+    )]
+    invalid_utf8: InvalidUtf8,
+
+    /// Count the files in directories, recursively (skipping dotfiles and
+    /// dot-directories unless --hidden)
+    #[arg(short = 'r', long, conflicts_with = "follow")]
+    recursive: bool,
+
+    /// With -r, count dotfiles and dot-directories such as .github/ and
+    /// .cursorrules too (never .git)
+    #[arg(long, requires = "recursive")]
+    hidden: bool,
+
+    /// Tokenize every file instead of reusing counts cached by earlier
+    /// runs for unchanged contents
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Encode with at most N threads (default: one per CPU)
+    #[arg(
+        long,
+        value_name = "N",
+        global = true,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    threads: Option<u16>,
+
+    /// Show no progress bar and log only errors
+    #[arg(short = 'q', long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log which tokenizer and config are used and which files are skipped
+    /// (-vv adds per-file timing, cache hits, and tokenizer search paths)
+    #[arg(short = 'v', long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// With -r, skip files and directories matching a glob (repeatable,
+    /// e.g. "*.lock"; replaces the config file's exclude list)
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    exclude: Vec<String>,
+
+    /// With -r, follow symlinked directories too, counting each file once
+    /// and skipping links that loop back
+    #[arg(long, requires = "recursive", conflicts_with = "no_follow")]
+    follow_symlinks: bool,
+
+    /// With -r, skip symlinked files as well as symlinked directories
+    #[arg(long, requires = "recursive")]
+    no_follow: bool,
+
+    /// With -r, count only files at most N levels below each directory
+    /// given (1 for the files directly in it)
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "recursive",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    max_depth: Option<u16>,
+
+    /// Print totals per group of files instead of per file: ext, dir, or
+    /// glob (the [[groups]] of .tc.toml), or named patterns as in
+    /// 'glob:docs/**=docs,src/**=code'
+    #[arg(
+        long,
+        value_name = "GROUP",
+        value_parser = group::parse_group_by,
+        requires = "files",
+        conflicts_with = "follow"
+    )]
+    group_by: Option<GroupBy>,
+
+    /// With --group-by dir, report directories at most N levels deep
+    #[arg(long, value_name = "N", requires = "group_by")]
+    depth: Option<usize>,
+
+    /// Print only the N files (or groups) with the most tokens, once all
+    /// are counted; the total still covers every file
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "files",
+        conflicts_with_all = ["follow", "stream"],
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    top: Option<u32>,
+
+    /// Leave files with fewer than N tokens out of the listing; the total
+    /// still includes them
+    #[arg(long, value_name = "N", conflicts_with = "follow")]
+    min_tokens: Option<usize>,
+
+    /// Leave files with more than N tokens out of the listing; the total
+    /// still includes them
+    #[arg(long, value_name = "N", conflicts_with = "follow")]
+    max_tokens_filter: Option<usize>,
+
+    /// Estimate the total from a random sample of this share of the input
+    /// bytes (e.g., "5%")
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = sample::parse_percent,
+        group = "sampling",
+        requires = "files",
+        conflicts_with_all = SAMPLE_CONFLICTS
+    )]
+    sample: Option<f64>,
+
+    /// Estimate the total from a random sample of this many input bytes
+    /// (e.g., "50M", "1G")
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = sample::parse_size,
+        group = "sampling",
+        requires = "files",
+        conflicts_with_all = SAMPLE_CONFLICTS
+    )]
+    sample_bytes: Option<u64>,
+
+    /// Keep memory under SIZE (e.g., "512M") by streaming inputs too large
+    /// to count whole, and failing those whose options need the whole text
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = sample::parse_size,
+        conflicts_with_all = ["follow", "ids", "show_tokens", "highlight", "per_line"]
+    )]
+    max_memory: Option<u64>,
+
+    /// Skip a file (with a warning) if reading and counting it takes longer
+    /// than this many seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = guard::parse_seconds,
+        conflicts_with_all = ["follow", "stream", "ids", "show_tokens", "highlight", "per_line"]
+    )]
+    timeout_per_file: Option<Duration>,
+
+    /// Skip a file (with a warning) if it is larger than SIZE (e.g., "10M")
+    /// once decompressed
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = sample::parse_size,
+        conflicts_with_all = ["follow", "stream", "ids", "show_tokens", "highlight", "per_line"]
+    )]
+    max_file_size: Option<u64>,
+
+    /// Seed for drawing the sample, to repeat an estimate exactly
+    #[arg(long, value_name = "N", requires = "sampling")]
+    seed: Option<u64>,
+
+    /// Also emit threshold violations as CI annotations
+    #[arg(long, value_name = "CI")]
+    ci: Option<CiFormat>,
 }
 
-/// Find a tokenizer by name in standard directories
-fn find_tokenizer_by_name(name: &str) -> Result<PathBuf> {
-    let filename = format!("{}.json", name);
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Record per-file token counts, or check them against a baseline
+    Snapshot(snapshot::SnapshotArgs),
+    /// Count tokens added and removed in git changes
+    Git(git::GitArgs),
+    /// Install or run a git pre-commit hook that checks token budgets
+    Hook(hook::HookArgs),
+    /// Keep tokenizers warm and serve counts over a Unix domain socket
+    Daemon(daemon::DaemonArgs),
+    /// Serve token counts over HTTP
+    Serve(serve::ServeArgs),
+    /// Run a Model Context Protocol server on stdio with a count_tokens tool
+    Mcp(mcp::McpArgs),
+    /// Split a file into chunks of at most N tokens
+    Split(split::SplitArgs),
+    /// Print the longest prefix of a file that fits in N tokens
+    Head(excerpt::HeadArgs),
+    /// Print the longest suffix of a file that fits in N tokens
+    Tail(excerpt::TailArgs),
+    /// Trim stdin to fit a model's context window, leaving room for output
+    Truncate(excerpt::TruncateArgs),
+    /// Summarize token counts across the records of a JSONL dataset
+    Dataset(dataset::DatasetArgs),
+    /// Count tokens per cell of a Jupyter notebook
+    Notebook(notebook::NotebookArgs),
+    /// Count tokens per function, type, and block of a source file
+    Outline(outline::OutlineArgs),
+    /// List the most frequent tokens across inputs
+    Freq(freq::FreqArgs),
+    /// Convert token IDs back to text
+    Decode(decode::DecodeArgs),
+    /// Write the token IDs of inputs to a binary or JSON file
+    Encode(encode::EncodeArgs),
+    /// Count an input with several tokenizers side by side
+    Compare(compare::CompareArgs),
+    /// Measure how much two inputs overlap, token by token
+    Similarity(similarity::SimilarityArgs),
+    /// Measure tokenizer throughput in tokens and megabytes per second
+    Bench(bench::BenchArgs),
+    /// Manage the cache of token counts from earlier runs
+    Cache(cache::CacheArgs),
+    /// Check tokenizer files for corruption, or convert them to the
+    /// Hugging Face format
+    Tokenizers(tokenizer::TokenizersArgs),
+    /// Browse a directory's token counts interactively, like ncdu
+    Tui(tui::TuiArgs),
+    /// Print a completion script for bash, zsh, fish, or PowerShell
+    Completions(completions::CompletionsArgs),
+}
+
+/// Token budget enforced through the exit status
+struct Budget {
+    max_tokens: Option<usize>,
+    min_tokens: Option<usize>,
+    per_file: bool,
+    violated: bool,
+    ci: Option<CiFormat>,
+}
+
+impl Budget {
+    fn from_args(args: &Args) -> Self {
+        Self {
+            max_tokens: args.max_tokens,
+            min_tokens: args.fail_under,
+            per_file: args.per_file,
+            violated: false,
+            ci: args.ci,
+        }
+    }
+
+    /// Records whether `stats` stays within the budget bounds
+    fn check(&mut self, stats: &TokenStats, input: Input) {
+        self.check_bounds(self.max_tokens, self.min_tokens, stats, input);
+    }
+
+    /// Records whether a file stays within the budgets `.tc.toml` sets for
+    /// its path
+    fn check_path(&mut self, stats: &TokenStats, file: &Path, input: Input) {
+        let config = config::get();
+        let Some(path) = config.project_path(file) else {
+            return;
+        };
+        for budget in config.budgets.iter().filter(|budget| budget.matches(&path)) {
+            self.check_bounds(budget.max_tokens, budget.fail_under, stats, input);
+        }
+    }
+
+    fn check_bounds(
+        &mut self,
+        max_tokens: Option<usize>,
+        min_tokens: Option<usize>,
+        stats: &TokenStats,
+        input: Input,
+    ) {
+        if let Some(max) = max_tokens {
+            if stats.tokens > max {
+                let message = format!("exceeds token budget ({} > {} tokens)", stats.tokens, max);
+                diagnostics::report(self.ci, Level::Error, input, &message);
+                self.violated = true;
+            }
+        }
+        if let Some(min) = min_tokens {
+            if stats.tokens < min {
+                let message = format!("below token minimum ({} < {} tokens)", stats.tokens, min);
+                diagnostics::report(self.ci, Level::Error, input, &message);
+                self.violated = true;
+            }
+        }
+    }
+
+    fn exit_code(&self) -> ExitCode {
+        if self.violated {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// How input text is turned into token stats
+struct CountMode {
+    encoding: TextEncoding,
+    invalid_utf8: InvalidUtf8,
+    chat: Option<ChatFormat>,
+    /// Per-message counts of the last `--chat` payload counted, for the
+    /// reporting code to print with its stats
+    messages: Option<MessageCounts>,
+    select: Option<JsonPath>,
+    strip: Vec<StripKind>,
+    keep_fences: bool,
+    add_special_tokens: bool,
+    /// Whether to find the most tokens on one line, which needs token
+    /// offsets: for `--density` and the per-file tables that record it
+    line_maxima: bool,
+    /// Token IDs seen across all inputs, for `--vocab-stats`
+    vocab: Option<VocabUsage>,
+    /// Counts of files seen by earlier runs, unless `--no-cache`
+    cache: Option<CountCache>,
+    /// Inputs by contents, for `--duplicates`
+    duplicates: Option<Duplicates>,
+    /// Stats by detected language, for `--detect-lang`
+    languages: Option<Languages>,
+    /// Peak memory to stay under, for `--max-memory`
+    max_memory: Option<MemoryLimit>,
+    /// Bytes a file may have, for `--max-file-size`
+    max_file_size: Option<u64>,
+    /// Time each file may take, for `--timeout-per-file`
+    timeout: Option<FileTimeout>,
+}
+
+impl CountMode {
+    fn from_args(args: &Args) -> Result<Self> {
+        Ok(Self {
+            encoding: args.encoding.map_or(TextEncoding::Auto, TextEncoding::from),
+            invalid_utf8: args.invalid_utf8,
+            chat: args.chat,
+            messages: None,
+            select: args.select.clone(),
+            strip: args.strip.clone(),
+            keep_fences: args.keep_fences,
+            add_special_tokens: args.add_special_tokens,
+            line_maxima: args.density
+                || args.output_sqlite.is_some()
+                || args.output_parquet.is_some()
+                || args.output_arrow.is_some(),
+            vocab: args.vocab_stats.then(VocabUsage::new),
+            cache: None,
+            duplicates: args.duplicates.then(Duplicates::new),
+            languages: language::from_flag(args.detect_lang)?,
+            max_memory: args.max_memory.map(MemoryLimit::new).transpose()?,
+            max_file_size: args.max_file_size,
+            timeout: None,
+        })
+    }
+
+    /// Starts looking up and storing file counts in the cache
+    ///
+    /// Not with `--vocab-stats` or `--detect-lang`, which need every
+    /// file's text, or with `--chat`, whose per-message counts are not
+    /// cached, or with `--invalid-utf8 replace|skip`, whose warnings a
+    /// cached count would hide.
+    fn open_cache(&mut self, tokenizer: &Tokenizer) {
+        if self.vocab.is_some()
+            || self.languages.is_some()
+            || self.chat.is_some()
+            || self.invalid_utf8 != InvalidUtf8::Fail
+        {
+            return;
+        }
+        let options = format!(
+            "{:?}",
+            (
+                self.encoding,
+                self.chat,
+                &self.select,
+                &self.strip,
+                self.keep_fences,
+                self.add_special_tokens,
+                self.line_maxima
+            )
+        );
+        self.cache = CountCache::open(tokenizer, &options);
+    }
+
+    /// Reads a file's text in the `--encoding`, decompressing it and
+    /// extracting documents such as PDFs
+    fn read_file(&self, file: &Path) -> Result<String> {
+        self.text_from_bytes(read_input_bytes(file)?, file)
+    }
+
+    /// The text of a (decompressed) file's bytes, extracting documents
+    /// such as PDFs
+    fn text_from_bytes(&self, bytes: Vec<u8>, path: &Path) -> Result<String> {
+        match DocumentFormat::detect(&Compression::inner_path(path), &bytes) {
+            Some(format) => Ok(extract_text(format, &bytes)?),
+            None => self.decode(bytes, Some(path)),
+        }
+    }
+
+    /// Reads all of stdin as text in the `--encoding`
+    fn read_stdin(&self) -> Result<String> {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut bytes)
+            .context("Failed to read stdin")?;
+        self.decode(bytes, None)
+    }
+
+    /// Reads an input's (decompressed) bytes, within `--max-file-size`,
+    /// `--max-memory`, and `--timeout-per-file`
+    ///
+    /// Under `--max-memory`, an input too large to count whole comes back
+    /// as a reader to stream it from if `streamable` and
+    /// [`CountMode::can_stream`] allow; up to the most that can be counted
+    /// whole is read before deciding.
+    fn read_input(
+        &self,
+        file: &Path,
+        streamable: bool,
+        tokenizer: &Tokenizer,
+    ) -> Result<LimitedInput> {
+        if let Some(max) = self.max_file_size {
+            // Pipes and compressed files are measured as they are read
+            let size = fs::metadata(file)
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len());
+            if size.is_some_and(|size| size > max) {
+                return Err(Skipped::too_large(max).into());
+            }
+        }
+        let cap = [
+            self.max_file_size,
+            self.max_memory.map(MemoryLimit::max_input),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let path = file.to_path_buf();
+        let (bytes, rest) = self.timed(tokenizer, move |_| match cap {
+            Some(cap) => {
+                let mut reader = input_reader(&path)?;
+                let bytes = read_prefix(&mut reader, cap + 1)?;
+                Ok((bytes, Some(reader)))
+            }
+            None => Ok((read_input_bytes(&path)?, None)),
+        })?;
+        self.limit_input(bytes, rest, file, streamable)
+    }
+
+    /// Checks the bytes read of an input, and the `rest` of it if there may
+    /// be more, against `--max-file-size` and `--max-memory`
+    fn limit_input(
+        &self,
+        bytes: Vec<u8>,
+        rest: Option<Box<dyn Read + Send>>,
+        name: &Path,
+        streamable: bool,
+    ) -> Result<LimitedInput> {
+        let size = bytes.len() as u64;
+        if let Some(max) = self.max_file_size.filter(|&max| size > max) {
+            return Err(Skipped::too_large(max).into());
+        }
+        match (self.max_memory, rest) {
+            (Some(limit), Some(rest)) if size > limit.max_input() => {
+                if !streamable || !self.can_stream(&Compression::inner_path(name), &bytes) {
+                    return Err(limit.too_large(name));
+                }
+                let reader = io::Cursor::new(bytes).chain(rest);
+                Ok(LimitedInput::Stream(match self.max_file_size {
+                    Some(max) => Box::new(reader.take(max + 1)),
+                    None => Box::new(reader),
+                }))
+            }
+            _ => Ok(LimitedInput::Whole(bytes)),
+        }
+    }
+
+    /// Whether an input starting with `head` can be counted in pieces:
+    /// UTF-8 text, counted without options that need the whole text
+    fn can_stream(&self, path: &Path, head: &[u8]) -> bool {
+        let utf8 = match self.encoding {
+            TextEncoding::Auto => !head.starts_with(b"\xff\xfe") && !head.starts_with(b"\xfe\xff"),
+            TextEncoding::Utf8 => true,
+            _ => false,
+        };
+        utf8 && self.invalid_utf8 == InvalidUtf8::Fail
+            && self.chat.is_none()
+            && self.select.is_none()
+            && self.strip.is_empty()
+            && self.vocab.is_none()
+            && self.languages.is_none()
+            && DocumentFormat::detect(path, head).is_none()
+            && !head.starts_with(b"PK\x03\x04")
+    }
+
+    /// Counts an input in pieces (see [`memory::count_stream`]), adding
+    /// `--add-special-tokens` once
+    fn count_stream(
+        &mut self,
+        reader: Box<dyn Read + Send>,
+        name: &Path,
+        tokenizer: &Tokenizer,
+    ) -> Result<TokenStats> {
+        let start = Instant::now();
+        let hash = self.duplicates.is_some();
+        let (mut stats, contents) = self.timed(tokenizer, move |tokenizer| {
+            memory::count_stream(reader, hash, tokenizer)
+        })?;
+        if let Some(max) = self.max_file_size.filter(|&max| stats.bytes as u64 > max) {
+            return Err(Skipped::too_large(max).into());
+        }
+        if self.add_special_tokens {
+            stats.tokens += special_token_count(tokenizer);
+        }
+        if let (Some(duplicates), Some(contents)) = (&mut self.duplicates, contents) {
+            duplicates.add(name, contents, stats.tokens);
+        }
+        debug!(
+            "streamed {} in {:.1?}: {} tokens",
+            name.display(),
+            start.elapsed(),
+            stats.tokens
+        );
+        Ok(stats)
+    }
+
+    /// Decodes plain text in the `--encoding`, replacing or dropping
+    /// invalid UTF-8 (with a warning) as `--invalid-utf8` says
+    fn decode(&self, bytes: Vec<u8>, path: Option<&Path>) -> Result<String> {
+        let error = match decode_text(bytes, self.encoding) {
+            Ok(text) => return Ok(text),
+            Err(error) => error,
+        };
+        let offset = error.utf8_error().valid_up_to();
+        let (replacement, action) = match (self.invalid_utf8, path) {
+            (InvalidUtf8::Fail, Some(path)) => {
+                return Err(token_counter_lib::Error::InvalidUtf8 {
+                    path: path.to_path_buf(),
+                    offset,
+                }
+                .into())
+            }
+            (InvalidUtf8::Fail, None) => {
+                anyhow::bail!("stdin: invalid UTF-8 at byte {}", offset)
+            }
+            (InvalidUtf8::Replace, _) => ("\u{fffd}", "replaced with U+FFFD"),
+            (InvalidUtf8::Skip, _) => ("", "dropped"),
+        };
+        let (text, invalid) = repair_utf8(&error.into_bytes(), replacement);
+        eprintln!(
+            "tc: warning: {}: {} invalid UTF-8 sequence{} {} (first at byte {})",
+            path.map_or("stdin".into(), |path| path.display().to_string()),
+            invalid,
+            if invalid == 1 { "" } else { "s" },
+            action,
+            offset
+        );
+        Ok(text)
+    }
+
+    /// Counts a file's text (see [`CountMode::count_text`]), decompressing
+    /// it and extracting documents such as PDFs first
+    ///
+    /// Counts are looked up in and added to the cache, if it is open.
+    fn count_file(&mut self, file: &Path, tokenizer: &Tokenizer) -> Result<TokenStats> {
+        let start = Instant::now();
+        if let Some(timeout) = &mut self.timeout {
+            timeout.start();
+        }
+        let bytes = match self.read_input(file, true, tokenizer)? {
+            LimitedInput::Whole(bytes) => bytes,
+            LimitedInput::Stream(reader) => return self.count_stream(reader, file, tokenizer),
+        };
+        let path = Compression::inner_path(file);
+        let contents = self.duplicates.is_some().then(|| blake3::hash(&bytes));
+
+        // The extension picks the document format and comment syntax
+        let kind = path.extension().unwrap_or_default().to_string_lossy();
+        let key = self.cache.as_ref().map(|cache| cache.key(&kind, &bytes));
+        let cached = match (&self.cache, &key) {
+            (Some(cache), Some(key)) => cache.get(key),
+            _ => None,
+        };
+        let stats = match cached {
+            Some(stats) => {
+                debug!("cache hit for {}: {} tokens", file.display(), stats.tokens);
+                stats
+            }
+            None => {
+                let text = self.text_from_bytes(bytes, file)?;
+                let stats = self.count_text(&text, Some(&path), tokenizer)?;
+                if let (Some(cache), Some(key)) = (&self.cache, &key) {
+                    cache.put(key, &stats);
+                }
+                debug!(
+                    "counted {} in {:.1?}: {} tokens",
+                    file.display(),
+                    start.elapsed(),
+                    stats.tokens
+                );
+                stats
+            }
+        };
+
+        if let (Some(duplicates), Some(contents)) = (&mut self.duplicates, contents) {
+            duplicates.add(file, contents, stats.tokens);
+        }
+        Ok(stats)
+    }
+
+    /// Counts each regular file in an archive, naming it
+    /// `archive!member/path`
+    ///
+    /// A member that cannot be counted gets its error in place of stats; an
+    /// archive that cannot be read is an error.
+    fn count_archive(
+        &mut self,
+        file: &Path,
+        format: ArchiveFormat,
+        tokenizer: &Tokenizer,
+    ) -> Result<Vec<Counted>> {
+        if let Some(timeout) = &mut self.timeout {
+            timeout.start();
+        }
+        let LimitedInput::Whole(bytes) = self.read_input(file, false, tokenizer)? else {
+            unreachable!("archives are never streamed");
+        };
+        let mut members = Vec::new();
+        for_each_member(format, &bytes, |member, bytes| {
+            let name = PathBuf::from(format!("{}!{}", file.display(), member));
+            let result = self.count_member(bytes, &name, tokenizer);
+            members.push(Counted {
+                name,
+                result,
+                messages: self.take_messages(),
+            });
+        })?;
+        Ok(members)
+    }
+
+    /// The per-message counts of the chat payload just counted, if any
+    fn take_messages(&mut self) -> Option<MessageCounts> {
+        self.messages.take()
+    }
+
+    /// Counts an archive member like a file, decompressing it if needed
+    fn count_member(
+        &mut self,
+        bytes: Vec<u8>,
+        name: &Path,
+        tokenizer: &Tokenizer,
+    ) -> Result<TokenStats> {
+        let bytes = decompress_bytes(name, bytes)?;
+        let contents = self.duplicates.is_some().then(|| blake3::hash(&bytes));
+        let text = self.text_from_bytes(bytes, name)?;
+        let stats = self.count_text(&text, Some(&Compression::inner_path(name)), tokenizer)?;
+        if let (Some(duplicates), Some(contents)) = (&mut self.duplicates, contents) {
+            duplicates.add(name, contents, stats.tokens);
+        }
+        Ok(stats)
+    }
 
-    // Search paths in order:
-    // 1. Development: bin/assets/tokenizers/ relative to workspace root
-    // 2. Relative to executable (for installed packages)
-    //    - ../share/tc/tokenizers/
-    //    - ../share/tc/
-    // 3. User config directory (~/.config/tc/tokenizers/)
-    // 4. Homebrew share directory
-    //    - /opt/homebrew/share/tc/tokenizers/
-    //    - /opt/homebrew/share/tc/
-    // 5. Unix share directory
-    //    - /usr/local/share/tc/tokenizers/
-    //    - /usr/local/share/tc/
+    /// Counts an input's text, honoring `--chat`, `--select`, `--strip`,
+    /// and `--add-special-tokens`
+    ///
+    /// A chat payload's per-message counts are kept for
+    /// [`CountMode::take_messages`].
+    fn count_text(
+        &mut self,
+        text: &str,
+        path: Option<&Path>,
+        tokenizer: &Tokenizer,
+    ) -> Result<TokenStats> {
+        if let Some(format) = self.chat {
+            let payload = text.to_string();
+            let (stats, messages) = self.timed(tokenizer, move |tokenizer| {
+                chat::count(format, &payload, tokenizer)
+            })?;
+            if let Some(languages) = &mut self.languages {
+                languages.add(text, &stats);
+            }
+            self.messages = Some(messages);
+            return Ok(stats);
+        }
+        let mut stats = TokenStats::new();
+        for text in self.prepare(text, path)? {
+            let vocab_stats = self.vocab.is_some();
+            let line_maxima = self.line_maxima;
+            let (text, mut text_stats, text_vocab) = self.timed(tokenizer, move |tokenizer| {
+                let mut vocab = vocab_stats.then(VocabUsage::new);
+                if let Some(vocab) = &mut vocab {
+                    vocab.add_text(&text, tokenizer)?;
+                }
+                let stats = if line_maxima {
+                    count_stats(&text, tokenizer)?
+                } else {
+                    count_stats_fast(&text, tokenizer)?
+                };
+                Ok((text, stats, vocab))
+            })?;
+            if let (Some(vocab), Some(text_vocab)) = (&mut self.vocab, text_vocab) {
+                vocab.merge(&text_vocab);
+            }
+            if self.add_special_tokens {
+                text_stats.tokens += special_token_count(tokenizer);
+            }
+            if let Some(languages) = &mut self.languages {
+                languages.add(&text, &text_stats);
+            }
+            stats.add(&text_stats);
+        }
+        Ok(stats)
+    }
 
-    let mut search_paths = Vec::new();
+    /// The texts an input is counted as: the `--select`ed values (or the
+    /// whole input), each run through the `--strip` pipeline
+    fn prepare(&self, text: &str, path: Option<&Path>) -> Result<Vec<String>> {
+        let pipeline = preprocess::pipeline(&self.strip, self.keep_fences, path);
+        let texts = match &self.select {
+            Some(path) => select_text(text, path)?,
+            None => vec![text.to_string()],
+        };
+        texts
+            .iter()
+            .map(|text| Ok(pipeline.apply(text)?.into_owned()))
+            .collect()
+    }
 
-    // 1. Development path (relative to executable in target/release or target/debug)
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            // For cargo run/build: target/{debug,release} -> ../../bin/assets/tokenizers/
-            search_paths.push(exe_dir.join("../../bin/assets/tokenizers").join(&filename));
+    /// Runs `work` within the current file's `--timeout-per-file`, if any
+    fn timed<T, F>(&self, tokenizer: &Tokenizer, work: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Tokenizer) -> Result<T> + Send + 'static,
+    {
+        match &self.timeout {
+            Some(timeout) => timeout.run(work),
+            None => work(tokenizer),
         }
     }
 
-    // 2. Relative to executable (for installed packages)
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            // Try with tokenizers subdirectory
-            search_paths.push(exe_dir.join("../share/tc/tokenizers").join(&filename));
-            // Try without subdirectory (cargo-dist installs to share/tc/ directly)
-            search_paths.push(exe_dir.join("../share/tc").join(&filename));
+    /// Token IDs of an input, after `--select` and `--strip`
+    fn encode_ids(
+        &self,
+        text: &str,
+        path: Option<&Path>,
+        tokenizer: &Tokenizer,
+    ) -> Result<Vec<u32>> {
+        let mut ids = Vec::new();
+        for text in self.prepare(text, path)? {
+            ids.extend(encode_ids(&text, tokenizer)?);
         }
+        Ok(ids)
     }
+}
 
-    // 3. User config directory
-    if let Some(home) = env::var_os("HOME") {
-        search_paths.push(PathBuf::from(home).join(".config/tc/tokenizers").join(&filename));
+/// Character encodings `--encoding` reads inputs in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InputEncoding {
+    /// UTF-8, or UTF-16 when the input starts with a byte order mark
+    Auto,
+    #[value(name = "utf-8")]
+    Utf8,
+    #[value(name = "utf-16le")]
+    Utf16Le,
+    /// ISO-8859-1 (read as Windows-1252)
+    Latin1,
+    #[value(name = "shift-jis")]
+    ShiftJis,
+}
+
+impl From<InputEncoding> for TextEncoding {
+    fn from(encoding: InputEncoding) -> Self {
+        match encoding {
+            InputEncoding::Auto => TextEncoding::Auto,
+            InputEncoding::Utf8 => TextEncoding::Utf8,
+            InputEncoding::Utf16Le => TextEncoding::Utf16Le,
+            InputEncoding::Latin1 => TextEncoding::Latin1,
+            InputEncoding::ShiftJis => TextEncoding::ShiftJis,
+        }
     }
+}
 
-    // 4. Homebrew (try both with and without tokenizers subdirectory)
-    search_paths.push(PathBuf::from("/opt/homebrew/share/tc/tokenizers").join(&filename));
-    search_paths.push(PathBuf::from("/opt/homebrew/share/tc").join(&filename));
+/// An input counted by the default command
+struct Counted {
+    name: PathBuf,
+    /// Its stats, or why it could not be counted
+    result: Result<TokenStats>,
+    /// Tokens of each message, if it is a `--chat` payload
+    messages: Option<MessageCounts>,
+}
 
-    // 5. Unix standard (try both with and without tokenizers subdirectory)
-    search_paths.push(PathBuf::from("/usr/local/share/tc/tokenizers").join(&filename));
-    search_paths.push(PathBuf::from("/usr/local/share/tc").join(&filename));
+/// The (decompressed) bytes of an input file, fetching `http(s)://`,
+/// `s3://`, and `gs://` inputs
+fn read_input_bytes(file: &Path) -> Result<Vec<u8>> {
+    if fetch::is_url(file) {
+        let bytes = fetch::fetch(&file.to_string_lossy())?;
+        return decompress_bytes(file, bytes);
+    }
+    if cloud::is_object_url(file) {
+        let bytes = cloud::get(&file.to_string_lossy())?;
+        return decompress_bytes(file, bytes);
+    }
+    Ok(read_decompressed(file)?)
+}
+
+/// Reads a file's decompressed bytes as they are needed, or the bytes of a
+/// URL once fetched
+fn input_reader(file: &Path) -> Result<Box<dyn Read + Send>> {
+    if fetch::is_url(file) || cloud::is_object_url(file) {
+        return Ok(Box::new(io::Cursor::new(read_input_bytes(file)?)));
+    }
+    let file_error = |source| token_counter_lib::Error::File {
+        path: file.to_path_buf(),
+        source,
+    };
+    let mut reader = BufReader::new(File::open(file).map_err(file_error)?);
+    let head = reader.fill_buf().map_err(file_error)?;
+    Ok(match Compression::detect(file, head) {
+        Some(compression) => decompress_reader(compression, reader)?,
+        None => Box::new(reader),
+    })
+}
+
+/// An input read within `--max-memory`
+enum LimitedInput {
+    /// All of its bytes, few enough to count whole
+    Whole(Vec<u8>),
+    /// A reader of its bytes, to count in pieces
+    Stream(Box<dyn Read + Send>),
+}
+
+/// Reads up to `limit` bytes from `reader`
+fn read_prefix<R: Read>(reader: &mut R, limit: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(limit).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
 
-    // Search for the file
-    for path in &search_paths {
-        if path.exists() {
-            return Ok(path.clone());
+/// Decompresses bytes read from somewhere other than a file on disk, if
+/// they are compressed
+fn decompress_bytes(name: &Path, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(match Compression::detect(name, &bytes) {
+        Some(compression) => decompress(compression, &bytes[..])?,
+        None => bytes,
+    })
+}
+
+/// How `--invalid-utf8` handles invalid UTF-8
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InvalidUtf8 {
+    /// Replace each invalid sequence with U+FFFD
+    Replace,
+    /// Drop invalid sequences
+    Skip,
+    /// Fail on the input
+    Fail,
+}
+
+/// How `tc` prints stats rows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    /// Aligned columns followed by the name, like `wc`
+    Plain,
+    /// One JSON object per input
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl From<OutputFormat> for ReportFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Plain => ReportFormat::Plain,
+            OutputFormat::Json => ReportFormat::Json,
+            OutputFormat::Csv => ReportFormat::Csv,
         }
     }
+}
 
-    // Not found in any standard location
-    anyhow::bail!(
-        "Tokenizer '{}' not found. Searched in:\n  {}",
-        name,
-        search_paths.iter()
-            .map(|p| p.display().to_string())
-            .collect::<Vec<_>>()
-            .join("\n  ")
-    )
+struct OutputConfig {
+    report: Report,
+    context_limit: Option<usize>,
+    ci: Option<CiFormat>,
+    /// Token counts outside `--min-tokens` and `--max-tokens-filter` are
+    /// left out of the listing
+    min_tokens: Option<usize>,
+    max_tokens: Option<usize>,
+}
+
+/// Resolve `--context` to a window size, either a token count or a known model
+fn resolve_context_limit(args: &Args) -> Result<Option<usize>> {
+    let Some(limit) = args.context.as_deref() else {
+        return Ok(None);
+    };
+
+    if let Ok(tokens) = limit.parse::<usize>() {
+        return Ok(Some(tokens));
+    }
+    match lookup_model(limit) {
+        Some(spec) => Ok(Some(spec.context_window)),
+        None => anyhow::bail!(
+            "--context expects a token count or a known model, got '{}'",
+            limit
+        ),
+    }
+}
+
+/// Resolve `--overhead` to the tokens added to the total, either a token
+/// count or the request overhead of a known model
+///
+/// Chat payloads already include their framing, so a model's overhead does
+/// not apply to them.
+fn resolve_overhead(args: &Args) -> Result<usize> {
+    let Some(overhead) = args.overhead.as_deref() else {
+        return Ok(0);
+    };
+
+    if let Ok(tokens) = overhead.parse::<usize>() {
+        return Ok(tokens);
+    }
+    if args.chat.is_some() {
+        anyhow::bail!("--chat payloads include their framing; give --overhead as a token count");
+    }
+    match lookup_model(overhead) {
+        Some(spec) => Ok(spec.request_overhead),
+        None => anyhow::bail!(
+            "--overhead expects a token count or a known model, got '{}'",
+            overhead
+        ),
+    }
+}
+
+/// Whether `--color` (or the config file) turns color on; `auto` never
+/// colors a report written with `-o`
+fn color_enabled(args: &Args) -> bool {
+    match args.color.or(config::get().color).unwrap_or_default() {
+        ColorWhen::Auto if args.output.is_some() => false,
+        when => when.enabled(),
+    }
+}
+
+/// The thresholds to color token counts by: `--thresholds`, the config
+/// file's, or shares of the `--context` window
+///
+/// Configured percentages are skipped without `--context`, since they have
+/// nothing to be a share of.
+fn resolve_thresholds(args: &Args, context_limit: Option<usize>) -> Result<Option<Thresholds>> {
+    let uses_percent = |thresholds: &Thresholds| {
+        [thresholds.warn, thresholds.alert]
+            .iter()
+            .any(|threshold| matches!(threshold, Threshold::Percent(_)))
+    };
+
+    if let Some(thresholds) = args.thresholds {
+        if uses_percent(&thresholds) && context_limit.is_none() {
+            anyhow::bail!("--thresholds given as percentages need --context");
+        }
+        return Ok(Some(thresholds));
+    }
+    if let Some(configured) = &config::get().thresholds {
+        let thresholds = color::parse_thresholds(configured)
+            .map_err(|e| anyhow::anyhow!("Invalid thresholds in config: {}", e))?;
+        if !uses_percent(&thresholds) || context_limit.is_some() {
+            return Ok(Some(thresholds));
+        }
+    }
+    Ok(context_limit.map(|_| Thresholds::context_window()))
+}
+
+/// `--model`, or else the model set in a config file
+fn model(args: &Args) -> Option<&str> {
+    args.model.as_deref().or(config::get().model.as_deref())
+}
+
+/// Look up the pricing for `--model` when `--cost` is requested
+fn resolve_pricing(args: &Args) -> Result<Option<ModelPricing>> {
+    if !args.cost {
+        return Ok(None);
+    }
+    let Some(model) = model(args) else {
+        anyhow::bail!("--cost requires --model (or model in a config file)");
+    };
+
+    let pricing_file = args
+        .pricing_file
+        .as_deref()
+        .or(config::get().pricing_file.as_deref());
+    let table = pricing::load_pricing_table(pricing_file)?;
+    match table.get(model) {
+        Some(pricing) => Ok(Some(*pricing)),
+        None => anyhow::bail!(
+            "No pricing known for model '{}'. Known models:\n  {}",
+            model,
+            table.models().collect::<Vec<_>>().join("\n  ")
+        ),
+    }
+}
+
+/// User configuration directory (~/.config/tc)
+fn user_config_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/tc"))
 }
 
 impl OutputConfig {
-    fn from_args(args: &Args) -> Self {
+    fn from_args(args: &Args) -> Result<Self> {
         // If no specific flags are set, show all
         let nothing_specified = !args.tokens_only && !args.lines && !args.bytes;
 
-        Self {
-            show_tokens: args.tokens_only || nothing_specified,
-            show_lines: args.lines || nothing_specified,
-            show_bytes: args.bytes || nothing_specified,
-        }
+        let context_limit = resolve_context_limit(args)?;
+        let columns = Columns {
+            tokens: args.tokens_only || nothing_specified,
+            lines: args.lines || nothing_specified,
+            bytes: args.bytes || nothing_specified,
+            density: args.density,
+            bytes_per_token: args.bytes_per_token,
+            pricing: resolve_pricing(args)?,
+            context_limit,
+        };
+        let format = args
+            .format
+            .or(config::get().format)
+            .unwrap_or(OutputFormat::Plain);
+        let thresholds = resolve_thresholds(args, context_limit)?;
+        let report = match thresholds {
+            Some(thresholds) if format == OutputFormat::Plain && color_enabled(args) => {
+                Report::with_formatter(columns, ColoredFormatter::new(thresholds))
+            }
+            _ => Report::new(columns, format.into()),
+        };
+
+        Ok(Self {
+            report,
+            context_limit,
+            ci: args.ci,
+            min_tokens: args.min_tokens,
+            max_tokens: args.max_tokens_filter,
+        })
     }
 
-    fn format_stats(&self, stats: &TokenStats, name: Option<&str>) -> String {
-        let mut parts = Vec::new();
+    /// Whether an input or group with these stats is listed
+    fn lists(&self, stats: &TokenStats) -> bool {
+        self.min_tokens.is_none_or(|min| stats.tokens >= min)
+            && self.max_tokens.is_none_or(|max| stats.tokens <= max)
+    }
 
-        if self.show_tokens {
-            parts.push(format!("{:8}", stats.tokens));
+    /// Prints the header row of formats that have one
+    fn print_header(&self) {
+        if let Some(header) = self.report.header() {
+            outln!("{}", header);
         }
-        if self.show_lines {
-            parts.push(format!("{:8}", stats.lines));
-        }
-        if self.show_bytes {
-            parts.push(format!("{:8}", stats.bytes));
+    }
+
+    /// Prints a chat payload's per-message counts ahead of its stats line,
+    /// unless the payload is filtered out of the listing
+    fn report_messages(&self, messages: &MessageCounts, stats: &TokenStats) {
+        if self.lists(stats) {
+            messages.print();
         }
+    }
 
-        let counts = parts.join(" ");
+    /// Prints the stats line and flags inputs that overflow the context
+    /// window, unless the input is filtered out of the listing
+    fn report(&self, stats: &TokenStats, input: Input) {
+        if !matches!(input, Input::Total) && !self.lists(stats) {
+            return;
+        }
+        outln!("{}", self.report.format_stats(stats, input.name()));
 
-        if let Some(name) = name {
-            format!("{} {}", counts, name)
-        } else {
-            counts
+        if let Some(limit) = self.context_limit {
+            let fit = ContextFit::new(stats.tokens, limit);
+            if !fit.fits() {
+                let message = format!(
+                    "does not fit in context window ({} > {} tokens)",
+                    fit.tokens, fit.limit
+                );
+                diagnostics::report(self.ci, Level::Warning, input, &message);
+            }
         }
     }
 }
 
-fn main() -> Result<()> {
+/// Logs to stderr at the level chosen by `-q` and `-v`
+fn init_logging(args: &Args) {
+    use tracing::Level;
+
+    let level = match (args.quiet, args.verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::INFO,
+        (false, 2) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+fn main() -> Result<ExitCode> {
+    // Answer completion requests from the `tc completions` scripts
+    CompleteEnv::with_factory(Args::command)
+        .var(completions::COMPLETE_VAR)
+        .complete();
+
     let args = Args::parse();
-    let output_config = OutputConfig::from_args(&args);
+    init_logging(&args);
+    config::init(args.threads.map(usize::from))?;
 
-    // Load tokenizer based on user input
-    let tokenizer = if let Some(tokenizer_path) = &args.tokenizer_path {
-        // Explicit path provided
-        load_tokenizer(tokenizer_path)
-            .with_context(|| format!("Failed to load tokenizer from {:?}", tokenizer_path))?
-    } else if let Some(tokenizer_name) = &args.tokenizer_name {
-        // Named tokenizer (find in standard directories)
-        let path = find_tokenizer_by_name(tokenizer_name)
-            .with_context(|| format!("Failed to find tokenizer '{}'", tokenizer_name))?;
-        load_tokenizer(&path)
-            .with_context(|| format!("Failed to load tokenizer from {:?}", path))?
+    match &args.command {
+        Some(Command::Snapshot(snapshot_args)) => snapshot::run(snapshot_args),
+        Some(Command::Git(git_args)) => git::run(git_args),
+        Some(Command::Hook(hook_args)) => hook::run(hook_args),
+        Some(Command::Daemon(daemon_args)) => daemon::run(daemon_args),
+        Some(Command::Serve(serve_args)) => serve::run(serve_args),
+        Some(Command::Mcp(mcp_args)) => mcp::run(mcp_args),
+        Some(Command::Split(split_args)) => split::run(split_args),
+        Some(Command::Head(head_args)) => excerpt::head(head_args),
+        Some(Command::Tail(tail_args)) => excerpt::tail(tail_args),
+        Some(Command::Truncate(truncate_args)) => excerpt::truncate(truncate_args),
+        Some(Command::Dataset(dataset_args)) => dataset::run(dataset_args),
+        Some(Command::Notebook(notebook_args)) => notebook::run(notebook_args),
+        Some(Command::Outline(outline_args)) => outline::run(outline_args),
+        Some(Command::Freq(freq_args)) => freq::run(freq_args),
+        Some(Command::Decode(decode_args)) => decode::run(decode_args),
+        Some(Command::Encode(encode_args)) => encode::run(encode_args),
+        Some(Command::Compare(compare_args)) => compare::run(compare_args),
+        Some(Command::Bench(bench_args)) => bench::run(bench_args),
+        Some(Command::Cache(cache_args)) => cache::run(cache_args),
+        Some(Command::Tokenizers(tokenizers_args)) => tokenizer::run(tokenizers_args),
+        Some(Command::Tui(tui_args)) => tui::run(tui_args),
+        Some(Command::Similarity(similarity_args)) => similarity::run(similarity_args),
+        Some(Command::Completions(completions_args)) => completions::run(completions_args),
+        None => {
+            let run = Run::start();
+            if let Some(path) = &args.output {
+                output::to_file(path)?;
+            } else if args.output_arrow.as_deref() == Some(Path::new("-")) {
+                if args.ci.is_some() {
+                    anyhow::bail!("--output-arrow - leaves no room on stdout for --ci annotations");
+                }
+                output::discard();
+            }
+            let mut summary = Summary {
+                inputs: (args.summary
+                    || args.output_sqlite.is_some()
+                    || args.output_parquet.is_some())
+                .then(Vec::new),
+                ..Summary::default()
+            };
+            let result = count(&args, &mut summary);
+            let result = match summary.arrow.take() {
+                Some(stream) => stream.finish().and(result),
+                None => result,
+            };
+            let result = output::finish(result.is_ok()).and(result);
+            if let (Some(path), Ok(_)) = (&args.output_sqlite, &result) {
+                sqlite::write(path, &run, &summary)?;
+            }
+            if let (Some(path), Ok(_)) = (&args.output_parquet, &result) {
+                let table = Table::files(summary.inputs.as_deref().unwrap_or_default())
+                    .with_metadata("tokenizer", summary.tokenizer.clone().unwrap_or_default())
+                    .with_metadata("started", run.timestamp());
+                columnar::write_parquet(path, table)?;
+            }
+            if let Some(path) = &args.append_log {
+                runlog::append(path, &run, &summary, &result)?;
+            }
+            result
+        }
+    }
+}
+
+/// Default command: count tokens in files or stdin, like `wc`, noting what
+/// it counted in `summary`
+fn count(args: &Args, summary: &mut Summary) -> Result<ExitCode> {
+    let output_config = OutputConfig::from_args(args)?;
+    let mut budget = Budget::from_args(args);
+    let mut mode = CountMode::from_args(args)?;
+    let overhead = resolve_overhead(args)?;
+
+    if args.depth.is_some() && !matches!(args.group_by, Some(GroupBy::Dir)) {
+        anyhow::bail!("--depth only applies to --group-by dir");
+    }
+    let files = if args.recursive {
+        let exclude = match &config::get().exclude {
+            Some(exclude) if args.exclude.is_empty() => exclude,
+            _ => &args.exclude,
+        };
+        let symlinks = if args.follow_symlinks {
+            Symlinks::Follow
+        } else if args.no_follow {
+            Symlinks::Skip
+        } else {
+            Symlinks::Files
+        };
+        let options = WalkOptions {
+            exclude: walk::exclude_set(exclude)?,
+            symlinks,
+            max_depth: args.max_depth.map(usize::from),
+            hidden: args.hidden,
+        };
+        walk::expand(&args.files, &options)
     } else {
-        // Default: use embedded GPT-2 tokenizer
-        load_tokenizer_from_bytes(DEFAULT_TOKENIZER)
-            .context("Failed to load embedded GPT-2 tokenizer")?
+        args.files.clone()
+    };
+
+    // Load tokenizer based on user input
+    // Shared with the threads --timeout-per-file counts on
+    let tokenizer_args = chat::tokenizer_args(args.chat, &args.tokenizer);
+    let tokenizer = Arc::new(tokenizer_args.load()?);
+    summary.tokenizer = Some(tokenizer_args.label());
+    if let Some(path) = &args.output_arrow {
+        let table = Table::for_files().with_metadata("tokenizer", tokenizer_args.label());
+        summary.arrow = Some(ArrowStream::create(path, table)?);
+    }
+    summary.files = files.len().max(1);
+
+    if let Some(format) = args.ids {
+        return inspect::print_ids(&mode, &files, format, &tokenizer);
+    }
+    if args.show_tokens {
+        return inspect::print_tokens(&mode, &files, &tokenizer);
+    }
+    if args.highlight {
+        return inspect::print_highlighted(&mode, &files, &tokenizer, color_enabled(args));
+    }
+    if args.per_line {
+        return inspect::print_per_line(&mode, &files, &tokenizer);
+    }
+    let sample_size = match (args.sample, args.sample_bytes) {
+        (Some(fraction), _) => Some(SampleSize::Fraction(fraction)),
+        (_, Some(bytes)) => Some(SampleSize::Bytes(bytes)),
+        _ => None,
     };
+    if let Some(size) = sample_size {
+        return sample::print_estimate(&files, size, args.seed, &tokenizer);
+    }
+
+    // Fingerprinting the tokenizer for the cache costs more than counting
+    // a single file
+    if !args.no_cache && files.len() > 1 && !args.follow {
+        mode.open_cache(&tokenizer);
+    }
+    if let Some(timeout) = args.timeout_per_file {
+        mode.timeout = Some(FileTimeout::new(timeout, Arc::clone(&tokenizer)));
+    }
 
     // Process input
-    if args.files.is_empty() {
+    if args.follow {
+        let [file] = args.files.as_slice() else {
+            anyhow::bail!("--follow requires exactly one file");
+        };
+        output_config.print_header();
+        follow::follow(file, &tokenizer, &output_config.report)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    output_config.print_header();
+    if args.stream {
+        let total = stream::stream(&mut mode, overhead, &tokenizer, &output_config, summary)?;
+        budget.check(&total, Input::Stdin);
+        summary.total = Some(total);
+    } else if args.files.is_empty() {
         // Read from stdin
         let stdin = io::stdin();
 
@@ -183,44 +1600,239 @@ fn main() -> Result<()> {
         if stdin.is_terminal() {
             eprintln!("tc: reading from stdin (use --help for usage information)");
         }
+        if args.strip.contains(&StripKind::Comments) {
+            eprintln!(
+                "tc: warning: --strip comments needs a file's language; stdin keeps its comments"
+            );
+        }
 
-        let stats = count_tokens_from_reader(stdin.lock(), &tokenizer)
-            .context("Failed to count tokens from stdin")?;
+        let mut stats = match mode.max_memory {
+            Some(limit) => count_stdin_within(&mut mode, limit, &tokenizer),
+            None => mode
+                .read_stdin()
+                .and_then(|text| mode.count_text(&text, None, &tokenizer)),
+        }
+        .context("Failed to count tokens from stdin")?;
+        stats.tokens += overhead;
 
-        println!("{}", output_config.format_stats(&stats, None));
-    } else if args.files.len() == 1 {
+        if let Some(messages) = mode.take_messages() {
+            output_config.report_messages(&messages, &stats);
+        }
+        output_config.report(&stats, Input::Stdin);
+        budget.check(&stats, Input::Stdin);
+        summary.add_input("-", &stats);
+        summary.total = Some(stats);
+    } else if files.len() == 1
+        && !args.recursive
+        && args.group_by.is_none()
+        && ArchiveFormat::detect(&files[0]).is_none()
+    {
         // Single file
-        let file = &args.files[0];
-        let stats = count_tokens_in_file(file, &tokenizer)
-            .with_context(|| format!("Failed to count tokens in {:?}", file))?;
-
-        println!(
-            "{}",
-            output_config.format_stats(&stats, Some(&file.display().to_string()))
-        );
+        let file = &files[0];
+        match mode.count_file(file, &tokenizer) {
+            Err(e) if e.is::<Skipped>() => eprintln!("tc: warning: {}: {}", file.display(), e),
+            result => {
+                let stats =
+                    result.with_context(|| format!("Failed to count tokens in {:?}", file))?;
+                let name = file.display().to_string();
+                // The file is the whole request, so its row is the total
+                let total = TokenStats {
+                    tokens: stats.tokens + overhead,
+                    ..stats.clone()
+                };
+                if let Some(messages) = mode.take_messages() {
+                    output_config.report_messages(&messages, &total);
+                }
+                output_config.report(&total, Input::File(&name));
+                budget.check(&total, Input::File(&name));
+                budget.check_path(&stats, file, Input::File(&name));
+                summary.add_input(&name, &stats);
+                summary.total = Some(total);
+            }
+        }
     } else {
-        // Multiple files - show each file and a total
-        let mut total = TokenStats::new();
-
-        for file in &args.files {
-            match count_tokens_in_file(file, &tokenizer) {
-                Ok(stats) => {
-                    println!(
-                        "{}",
-                        output_config.format_stats(&stats, Some(&file.display().to_string()))
-                    );
-                    total.add(&stats);
+        // Multiple files - show each file (or group) and a total
+        let mut total = TokenStats {
+            tokens: overhead,
+            ..TokenStats::new()
+        };
+        let mut groups = args
+            .group_by
+            .clone()
+            .map(|by| Groups::new(by, &args.files, args.depth))
+            .transpose()?;
+        let mut progress = progress::CountProgress::new(&files, args.quiet);
+        // Files held back for --top until all are counted
+        let mut largest: Vec<(String, TokenStats)> = Vec::new();
+
+        for file in &files {
+            // An archive is counted member by member
+            let counts = match ArchiveFormat::detect(file) {
+                Some(format) => mode
+                    .count_archive(file, format, &tokenizer)
+                    .unwrap_or_else(|e| {
+                        vec![Counted {
+                            name: file.clone(),
+                            result: Err(e),
+                            messages: None,
+                        }]
+                    }),
+                None => {
+                    let result = mode.count_file(file, &tokenizer);
+                    vec![Counted {
+                        name: file.clone(),
+                        result,
+                        messages: mode.take_messages(),
+                    }]
                 }
-                Err(e) => {
-                    eprintln!("tc: {}: {}", file.display(), e);
-                    // Continue processing other files
+            };
+            let tokens = counts
+                .iter()
+                .filter_map(|counted| counted.result.as_ref().ok())
+                .map(|stats| stats.tokens)
+                .sum();
+            progress.inc(file, tokens);
+            progress.suspend(|| {
+                for Counted {
+                    name: file,
+                    result,
+                    messages,
+                } in counts
+                {
+                    match result {
+                        Ok(stats) => {
+                            let name = file.display().to_string();
+                            match &mut groups {
+                                Some(groups) => groups.add(&file, &stats),
+                                None if args.top.is_some() => {
+                                    if output_config.lists(&stats) {
+                                        largest.push((name.clone(), stats.clone()));
+                                    }
+                                }
+                                None => {
+                                    if let Some(messages) = &messages {
+                                        output_config.report_messages(messages, &stats);
+                                    }
+                                    output_config.report(&stats, Input::File(&name));
+                                }
+                            }
+                            if budget.per_file {
+                                budget.check(&stats, Input::File(&name));
+                            }
+                            budget.check_path(&stats, &file, Input::File(&name));
+                            summary.add_input(&name, &stats);
+                            total.add(&stats);
+                        }
+                        Err(e) if e.is::<Skipped>() => {
+                            eprintln!("tc: warning: {}: {}", file.display(), e);
+                        }
+                        Err(e) => {
+                            // Errors reading a file already name it
+                            let named = e
+                                .downcast_ref::<token_counter_lib::Error>()
+                                .is_some_and(|e| e.path().is_some());
+                            if named {
+                                eprintln!("tc: {}", e);
+                            } else {
+                                eprintln!("tc: {}: {}", file.display(), e);
+                            }
+                            // Continue processing other files
+                        }
+                    }
                 }
+            });
+        }
+        progress.finish();
+
+        let top = args.top.map(|n| n as usize);
+        if let Some(groups) = &groups {
+            let mut rows = groups.rows();
+            rows.retain(|(_, stats)| output_config.lists(stats));
+            if let Some(n) = top {
+                rows.sort_by_key(|(_, stats)| Reverse(stats.tokens));
+                rows.truncate(n);
+            }
+            for (name, stats) in rows {
+                outln!("{}", output_config.report.format_stats(stats, Some(name)));
+            }
+        }
+        if let Some(n) = top {
+            largest.sort_by_key(|(_, stats)| Reverse(stats.tokens));
+            for (name, stats) in largest.iter().take(n) {
+                output_config.report(stats, Input::File(name));
             }
         }
 
         // Print total
-        println!("{}", output_config.format_stats(&total, Some("total")));
+        output_config.report(&total, Input::Total);
+        if !budget.per_file {
+            budget.check(&total, Input::Total);
+        }
+        summary.total = Some(total);
+    }
+
+    if args.summary {
+        print_file_summary(summary.inputs.as_deref().unwrap_or_default());
+    }
+    if let Some(vocab) = &mode.vocab {
+        print_vocab_stats(vocab, &tokenizer);
+    }
+    if let Some(duplicates) = &mode.duplicates {
+        duplicates.print();
+    }
+    if let Some(languages) = &mode.languages {
+        languages.print();
     }
 
-    Ok(())
+    Ok(budget.exit_code())
+}
+
+/// Counts stdin within `--max-memory`, streaming it if it is too large to
+/// count whole
+fn count_stdin_within(
+    mode: &mut CountMode,
+    limit: MemoryLimit,
+    tokenizer: &Tokenizer,
+) -> Result<TokenStats> {
+    let name = Path::new("stdin");
+    let mut stdin = io::stdin();
+    let bytes = read_prefix(&mut stdin, limit.max_input() + 1)?;
+    match mode.limit_input(bytes, Some(Box::new(stdin)), name, true)? {
+        LimitedInput::Whole(bytes) => {
+            let text = mode.decode(bytes, None)?;
+            mode.count_text(&text, None, tokenizer)
+        }
+        LimitedInput::Stream(reader) => mode.count_stream(reader, name, tokenizer),
+    }
+}
+
+/// Prints the `--summary` of tokens per input
+fn print_file_summary(inputs: &[(String, TokenStats)]) {
+    outln!();
+    outln!("{:<10} {:>10}", "files", inputs.len());
+    let tokens = inputs.iter().map(|(_, stats)| stats.tokens).collect();
+    let Some(distribution) = TokenDistribution::new(tokens) else {
+        return;
+    };
+    outln!("{:<10} {:>10}", "min", distribution.min());
+    outln!("{:<10} {:>10}", "max", distribution.max());
+    outln!("{:<10} {:>10.1}", "mean", distribution.mean());
+    outln!("{:<10} {:>10}", "median", distribution.percentile(50.0));
+    outln!("{:<10} {:>10}", "p90", distribution.percentile(90.0));
+    outln!("{:<10} {:>10}", "p99", distribution.percentile(99.0));
+}
+
+/// Prints the `--vocab-stats` summary
+fn print_vocab_stats(vocab: &VocabUsage, tokenizer: &Tokenizer) {
+    let vocab_size = tokenizer.get_vocab_size(true);
+    outln!();
+    outln!("{:<10} {:>10}", "tokens", vocab.tokens());
+    outln!("{:<10} {:>10}", "distinct", vocab.distinct());
+    outln!("{:<10} {:>10.4}", "ttr", vocab.type_token_ratio());
+    outln!(
+        "{:<10} {:>9.2}% of {}",
+        "coverage",
+        vocab.coverage(vocab_size),
+        vocab_size
+    );
 }
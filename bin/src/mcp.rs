@@ -0,0 +1,153 @@
+//! `tc mcp`: Model Context Protocol server over stdio
+//!
+//! Speaks newline-delimited JSON-RPC 2.0 and exposes a single `count_tokens`
+//! tool, so coding assistants can measure the context they are assembling.
+
+use crate::service::{CountRequest, CountResponse, CountService};
+use crate::tokenizer::TokenizerArgs;
+use anyhow::Result;
+use clap::Args;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+/// Protocol revisions this server can speak, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Run a Model Context Protocol server on stdin/stdout
+#[derive(Args, Debug)]
+pub struct McpArgs {
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &McpArgs) -> Result<ExitCode> {
+    let service = CountService::new(args.tokenizer.clone())?;
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&message, &service),
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(response) = response {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Handles one JSON-RPC message; notifications get no response
+fn handle_message(message: &Value, service: &CountService) -> Option<Value> {
+    let method = message.get("method").and_then(Value::as_str);
+    let Some(id) = message.get("id").cloned() else {
+        // Notifications (e.g. notifications/initialized) need no reply
+        return None;
+    };
+    let Some(method) = method else {
+        return Some(error_response(id, INVALID_REQUEST, "missing method"));
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => initialize(&params),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": [count_tokens_tool()] }),
+        "tools/call" => match call_tool(&params, service) {
+            Ok(result) => result,
+            Err(message) => return Some(error_response(id, INVALID_PARAMS, &message)),
+        },
+        _ => {
+            let message = format!("method not found: {}", method);
+            return Some(error_response(id, METHOD_NOT_FOUND, &message));
+        }
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "tc", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn count_tokens_tool() -> Value {
+    json!({
+        "name": "count_tokens",
+        "description": "Count LLM tokens, lines, and bytes in a text or a file.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": "Text to count" },
+                "path": { "type": "string", "description": "Path of a file to count" },
+                "tokenizer": {
+                    "type": "string",
+                    "description": "Named tokenizer (e.g. \"gpt4\", \"bert\"); defaults to the server's tokenizer"
+                }
+            }
+        }
+    })
+}
+
+/// Runs a tool call; protocol-level problems are returned as `Err`
+fn call_tool(params: &Value, service: &CountService) -> Result<Value, String> {
+    let name = params.get("name").and_then(Value::as_str);
+    if name != Some("count_tokens") {
+        return Err(format!("unknown tool: {}", name.unwrap_or("<none>")));
+    }
+
+    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+    let string_arg = |key: &str| arguments.get(key).and_then(Value::as_str).map(String::from);
+    let request = CountRequest {
+        text: string_arg("text"),
+        path: string_arg("path").map(Into::into),
+        tokenizer: string_arg("tokenizer"),
+        tokenizer_path: None,
+    };
+
+    // Counting failures are tool errors the model can see and react to
+    Ok(match service.count(&request) {
+        Ok(stats) => {
+            let stats = serde_json::to_value(CountResponse::from(stats)).expect("serializable");
+            json!({
+                "content": [{ "type": "text", "text": stats.to_string() }],
+                "structuredContent": stats,
+                "isError": false,
+            })
+        }
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": format!("{:#}", e) }],
+            "isError": true,
+        }),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
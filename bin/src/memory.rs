@@ -0,0 +1,131 @@
+//! `--max-memory`: keeping a count's peak memory under a limit
+//!
+//! Counting an input whole holds its bytes and text, plus the encoder's
+//! working data for the text being encoded at once, which is many times
+//! the size of the text. Inputs too large for that under the limit are
+//! streamed through the lib's chunked counter, whose memory stays flat;
+//! those whose options need the whole text are refused instead.
+
+use crate::sample::format_size;
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use token_counter_lib::{count_tokens_from_reader_streaming, TokenStats, PARALLEL_CHUNK_SIZE};
+use tokenizers::utils::parallelism::get_parallelism;
+use tokenizers::Tokenizer;
+
+/// Memory the loaded tokenizer and its cache of encoded words take
+const BASELINE: u64 = 64 << 20;
+
+/// Peak encoder memory per byte of text encoded at once: pre-tokenized
+/// pieces, token IDs, offsets, and word indices
+const ENCODE_BYTES_PER_BYTE: u64 = 150;
+
+/// Copies of an input held while it is counted whole: its bytes and its text
+const INPUT_COPIES: u64 = 2;
+
+/// Smallest limit accepted, which leaves room for streaming's pieces next
+/// to the tokenizer
+pub const MIN_LIMIT: u64 = 128 << 20;
+
+/// The `--max-memory` limit, in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryLimit(u64);
+
+impl MemoryLimit {
+    pub fn new(bytes: u64) -> Result<Self> {
+        if bytes < MIN_LIMIT {
+            anyhow::bail!(
+                "--max-memory must be at least {}, the tokenizer alone takes about {}",
+                format_size(MIN_LIMIT),
+                format_size(BASELINE)
+            );
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Whether counting `size` bytes of text whole stays under the limit
+    pub fn fits(self, size: u64) -> bool {
+        estimate(size) <= self.0
+    }
+
+    /// The largest input that can be counted whole
+    pub fn max_input(self) -> u64 {
+        let (mut low, mut high) = (0, self.0);
+        while low < high {
+            let middle = low + (high - low).div_ceil(2);
+            if self.fits(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        low
+    }
+
+    /// The error for an input that needs its whole text but is too large
+    /// to count whole
+    pub fn too_large(self, name: &Path) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{}: over the {} that can be counted whole within --max-memory {}, \
+             and its format or the options given need the whole text",
+            name.display(),
+            format_size(self.max_input()),
+            format_size(self.0)
+        )
+    }
+}
+
+/// Estimated peak memory of counting `size` bytes of text whole
+///
+/// Texts of at least twice [`PARALLEL_CHUNK_SIZE`] are encoded a piece per
+/// thread at a time, so only that much is being encoded at once. The
+/// estimate still never falls below that of a text just under that size,
+/// which is encoded whole, so larger inputs never look cheaper.
+fn estimate(size: u64) -> u64 {
+    let piece = PARALLEL_CHUNK_SIZE as u64;
+    let threads = if get_parallelism() {
+        rayon::current_num_threads() as u64
+    } else {
+        1
+    };
+    let encoded = size.min((threads * piece).max(2 * piece));
+    BASELINE
+        .saturating_add(size.saturating_mul(INPUT_COPIES))
+        .saturating_add(encoded.saturating_mul(ENCODE_BYTES_PER_BYTE))
+}
+
+/// Counts UTF-8 text from `reader` in pieces, without a byte order mark,
+/// and hashes the bytes read for `--duplicates` if `hash` is set
+pub fn count_stream<R: Read>(
+    reader: R,
+    hash: bool,
+    tokenizer: &Tokenizer,
+) -> Result<(TokenStats, Option<blake3::Hash>)> {
+    let mut hasher = hash.then(blake3::Hasher::new);
+    let mut reader = BufReader::new(Hashing {
+        reader,
+        hasher: hasher.as_mut(),
+    });
+    if reader.fill_buf()?.starts_with(b"\xef\xbb\xbf") {
+        reader.consume(3);
+    }
+    let stats = count_tokens_from_reader_streaming(reader, tokenizer)?;
+    Ok((stats, hasher.map(|hasher| hasher.finalize())))
+}
+
+/// A reader that hashes what it reads
+struct Hashing<'a, R> {
+    reader: R,
+    hasher: Option<&'a mut blake3::Hasher>,
+}
+
+impl<R: Read> Read for Hashing<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
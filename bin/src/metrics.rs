@@ -0,0 +1,169 @@
+//! Metrics registry for the server modes, rendered in the Prometheus text format
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the latency histogram buckets
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Cumulative histogram of observed durations
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket (non-cumulative); the last slot is +Inf
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len() + 1];
+        }
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters and histograms shared by all request handlers
+#[derive(Default)]
+pub struct Metrics {
+    requests_ok: AtomicU64,
+    requests_error: AtomicU64,
+    tokens: AtomicU64,
+    bytes: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    /// Count latency per tokenizer label
+    latency: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl Metrics {
+    /// Records a successful count
+    pub fn record_count(&self, tokenizer: &str, tokens: usize, bytes: usize, elapsed: Duration) {
+        self.requests_ok.fetch_add(1, Ordering::Relaxed);
+        self.tokens.fetch_add(tokens as u64, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(tokenizer.to_string())
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records a failed request
+    pub fn record_error(&self) {
+        self.requests_error.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a tokenizer cache lookup
+    pub fn record_cache(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        out.push_str("# HELP tc_requests_total Count requests handled.\n");
+        out.push_str("# TYPE tc_requests_total counter\n");
+        let _ = writeln!(
+            out,
+            "tc_requests_total{{status=\"ok\"}} {}",
+            load(&self.requests_ok)
+        );
+        let _ = writeln!(
+            out,
+            "tc_requests_total{{status=\"error\"}} {}",
+            load(&self.requests_error)
+        );
+
+        counter(
+            &mut out,
+            "tc_tokens_counted_total",
+            "Tokens counted.",
+            load(&self.tokens),
+        );
+        counter(
+            &mut out,
+            "tc_bytes_counted_total",
+            "Bytes counted.",
+            load(&self.bytes),
+        );
+        counter(
+            &mut out,
+            "tc_tokenizer_cache_hits_total",
+            "Requests served by an already loaded tokenizer.",
+            load(&self.cache_hits),
+        );
+        counter(
+            &mut out,
+            "tc_tokenizer_cache_misses_total",
+            "Requests that had to load a tokenizer.",
+            load(&self.cache_misses),
+        );
+
+        out.push_str("# HELP tc_count_duration_seconds Time spent counting, per tokenizer.\n");
+        out.push_str("# TYPE tc_count_duration_seconds histogram\n");
+        let latency = self.latency.lock().unwrap_or_else(|e| e.into_inner());
+        for (tokenizer, histogram) in latency.iter() {
+            let label = escape_label(tokenizer);
+            let mut cumulative = 0;
+            for (bound, observed) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += observed;
+                let _ = writeln!(
+                    out,
+                    "tc_count_duration_seconds_bucket{{tokenizer=\"{}\",le=\"{}\"}} {}",
+                    label, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "tc_count_duration_seconds_bucket{{tokenizer=\"{}\",le=\"+Inf\"}} {}",
+                label, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "tc_count_duration_seconds_sum{{tokenizer=\"{}\"}} {}",
+                label, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "tc_count_duration_seconds_count{{tokenizer=\"{}\"}} {}",
+                label, histogram.count
+            );
+        }
+
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,74 @@
+//! `tc notebook`: per-cell token counts for Jupyter notebooks
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{count_tokens, parse_notebook};
+
+/// Count the tokens of each cell of a Jupyter notebook
+#[derive(Args, Debug)]
+pub struct NotebookArgs {
+    /// Notebook (.ipynb) file
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Also count the text of cell outputs
+    #[arg(long)]
+    outputs: bool,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &NotebookArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let json = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {:?}", args.file))?;
+    let cells = parse_notebook(&json).with_context(|| format!("{:?}", args.file))?;
+
+    let mut by_kind = BTreeMap::new();
+    let mut outputs_total = 0;
+
+    if args.outputs {
+        println!(
+            "{:>5}  {:<8} {:>8} {:>8}",
+            "cell", "kind", "source", "outputs"
+        );
+    } else {
+        println!("{:>5}  {:<8} {:>8}", "cell", "kind", "source");
+    }
+    for (index, cell) in cells.iter().enumerate() {
+        let source = count_tokens(&cell.source, &tokenizer, false)?;
+        *by_kind.entry(cell.kind).or_insert(0) += source;
+
+        if args.outputs {
+            let outputs = count_tokens(&cell.outputs, &tokenizer, false)?;
+            outputs_total += outputs;
+            println!(
+                "{:>5}  {:<8} {:>8} {:>8}",
+                index + 1,
+                cell.kind.name(),
+                source,
+                outputs
+            );
+        } else {
+            println!("{:>5}  {:<8} {:>8}", index + 1, cell.kind.name(), source);
+        }
+    }
+
+    println!();
+    for (kind, tokens) in &by_kind {
+        println!("{:<8} {:>10}", kind.name(), tokens);
+    }
+    if args.outputs {
+        println!("{:<8} {:>10}", "outputs", outputs_total);
+    }
+    let total: usize = by_kind.values().sum::<usize>() + outputs_total;
+    println!("{:<8} {:>10}", "total", total);
+
+    Ok(ExitCode::SUCCESS)
+}
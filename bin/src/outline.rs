@@ -0,0 +1,54 @@
+//! `tc outline`: token counts per function, type, and block of a source file
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{count_tokens, outline, OutlineLanguage};
+
+/// Count the tokens of each symbol in a source file
+#[derive(Args, Debug)]
+pub struct OutlineArgs {
+    /// Source file (Rust, Python, or TypeScript)
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &OutlineArgs) -> Result<ExitCode> {
+    let Some(language) = OutlineLanguage::from_path(&args.file) else {
+        anyhow::bail!(
+            "cannot outline {:?}: expected a Rust, Python, or TypeScript file",
+            args.file
+        );
+    };
+    let tokenizer = args.tokenizer.load()?;
+    let source = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {:?}", args.file))?;
+    let symbols = outline(&source, language).with_context(|| format!("{:?}", args.file))?;
+
+    println!("{:>8}  {:<11} symbol", "tokens", "lines");
+    for symbol in &symbols {
+        let tokens = count_tokens(&source[symbol.range.clone()], &tokenizer, false)?;
+        let lines = format!("{}-{}", symbol.lines.0, symbol.lines.1);
+        println!(
+            "{:>8}  {:<11} {}{} {}",
+            tokens,
+            lines,
+            "  ".repeat(symbol.depth),
+            symbol.kind.name(),
+            symbol.name
+        );
+    }
+    println!(
+        "{:>8}  {:<11} total",
+        count_tokens(&source, &tokenizer, false)?,
+        ""
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,115 @@
+//! `-o`: writing the default command's report to a file instead of stdout
+//!
+//! The report is printed with [`outln!`], which goes to stdout unless
+//! [`to_file`] redirected it or [`discard`] dropped it. The file is written
+//! under a temporary name beside its destination and only renamed into
+//! place by [`finish`], so it never holds half a report, and a failed run
+//! leaves any earlier one untouched.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Where the report goes, if not to stdout
+static OUTPUT: Mutex<Option<Destination>> = Mutex::new(None);
+
+enum Destination {
+    File(Pending),
+    /// Nowhere, as stdout carries something else
+    Discard,
+}
+
+/// A report being written to a file
+struct Pending {
+    file: BufWriter<File>,
+    temporary: PathBuf,
+    path: PathBuf,
+    /// The first write that failed, reported by [`finish`]
+    error: Option<io::Error>,
+}
+
+/// `println!` for the report, which `-o` sends to a file
+macro_rules! outln {
+    () => {
+        $crate::output::print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+pub(crate) use outln;
+
+/// Sends the report to `path` from now on
+pub fn to_file(path: &Path) -> Result<()> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("--output needs a file name, got {:?}", path))?;
+    let temporary = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let file =
+        File::create(&temporary).with_context(|| format!("Failed to create {:?}", temporary))?;
+    *OUTPUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Destination::File(Pending {
+        file: BufWriter::new(file),
+        temporary,
+        path: path.to_path_buf(),
+        error: None,
+    }));
+    Ok(())
+}
+
+/// Drops the report from now on, for when stdout carries other output
+/// (such as `--output-arrow -`)
+pub fn discard() {
+    *OUTPUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Destination::Discard);
+}
+
+/// Whether the report goes to stdout
+pub fn to_stdout() -> bool {
+    OUTPUT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_none()
+}
+
+/// Prints part of the report; used through [`outln!`]
+pub fn print(args: fmt::Arguments) {
+    let mut output = OUTPUT.lock().unwrap_or_else(PoisonError::into_inner);
+    match output.as_mut() {
+        Some(Destination::File(pending)) if pending.error.is_none() => {
+            if let Err(e) = pending.file.write_fmt(args) {
+                pending.error = Some(e);
+            }
+        }
+        Some(_) => {}
+        None => print!("{}", args),
+    }
+}
+
+/// Moves a report written to a file into place if `succeeded`, or else
+/// removes it
+pub fn finish(succeeded: bool) -> Result<()> {
+    let output = OUTPUT.lock().unwrap_or_else(PoisonError::into_inner).take();
+    let Some(Destination::File(mut pending)) = output else {
+        return Ok(());
+    };
+    let written = match pending.error.take() {
+        Some(e) => Err(e),
+        None => pending.file.flush(),
+    };
+    drop(pending.file);
+    if !succeeded || written.is_err() {
+        let _ = fs::remove_file(&pending.temporary);
+        return written.with_context(|| format!("Failed to write {:?}", pending.path));
+    }
+    fs::rename(&pending.temporary, &pending.path).with_context(|| {
+        let _ = fs::remove_file(&pending.temporary);
+        format!("Failed to write {:?}", pending.path)
+    })
+}
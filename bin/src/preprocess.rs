@@ -0,0 +1,36 @@
+//! `--strip`: preprocessing stages applied to inputs before counting
+
+use clap::ValueEnum;
+use std::path::Path;
+use token_counter_lib::{CommentSyntax, Pipeline, StripComments, StripHtml, StripMarkdown};
+
+/// Syntax that `--strip` can remove before counting
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StripKind {
+    /// Markdown syntax (headings, emphasis, links, fences, ...)
+    Markdown,
+    /// Code comments, in the syntax of each file's language
+    Comments,
+    /// HTML tags, scripts, and styles, keeping the visible text
+    Html,
+}
+
+/// Builds the preprocessing pipeline for the requested `--strip` stages
+///
+/// Comments are recognized by the language of `path`'s extension; inputs in
+/// an unrecognized language, and stdin, keep their comments.
+pub fn pipeline(strip: &[StripKind], keep_fences: bool, path: Option<&Path>) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    for kind in strip {
+        match kind {
+            StripKind::Markdown => pipeline.push(StripMarkdown { keep_fences }),
+            StripKind::Html => pipeline.push(StripHtml),
+            StripKind::Comments => {
+                if let Some(syntax) = path.and_then(CommentSyntax::for_path) {
+                    pipeline.push(StripComments { syntax });
+                }
+            }
+        }
+    }
+    pipeline
+}
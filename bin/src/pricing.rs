@@ -0,0 +1,81 @@
+//! Loading of user-supplied pricing tables (`pricing.toml`)
+//!
+//! A pricing file lists per-model prices in US dollars per million tokens:
+//!
+//! ```toml
+//! [models."gpt-4o"]
+//! input = 2.50
+//! output = 10.00
+//!
+//! [models.my-finetune]
+//! input = 3.00
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use token_counter_lib::{ModelPricing, PricingTable};
+
+/// Pricing file name looked up in the user config directory
+const PRICING_FILE: &str = "pricing.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricingFile {
+    #[serde(default)]
+    models: BTreeMap<String, ModelEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelEntry {
+    /// $ / 1M input tokens
+    input: f64,
+    /// $ / 1M output tokens
+    output: Option<f64>,
+}
+
+/// Builds the pricing table: built-in prices, overridden by
+/// `~/.config/tc/pricing.toml`, overridden by `--pricing-file`
+pub fn load_pricing_table(pricing_file: Option<&Path>) -> Result<PricingTable> {
+    let mut table = PricingTable::builtin();
+
+    if let Some(config_dir) = crate::user_config_dir() {
+        let path = config_dir.join(PRICING_FILE);
+        if path.is_file() {
+            table.extend(read_pricing_file(&path)?);
+        }
+    }
+
+    if let Some(path) = pricing_file {
+        table.extend(read_pricing_file(path)?);
+    }
+
+    Ok(table)
+}
+
+/// Parses a single pricing file
+fn read_pricing_file(path: &Path) -> Result<PricingTable> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pricing file {:?}", path))?;
+    let file: PricingFile = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse pricing file {:?}", path))?;
+
+    let mut table = PricingTable::new();
+    for (model, entry) in file.models {
+        let prices = [Some(entry.input), entry.output];
+        if prices.iter().flatten().any(|p| !p.is_finite() || *p < 0.0) {
+            anyhow::bail!("Invalid price for model '{}' in {:?}", model, path);
+        }
+        table.insert(
+            &model,
+            ModelPricing {
+                input_per_million: entry.input,
+                output_per_million: entry.output,
+            },
+        );
+    }
+    Ok(table)
+}
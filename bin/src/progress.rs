@@ -0,0 +1,84 @@
+//! Progress bar on stderr while counting many files
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Runs with at least this many files show a progress bar
+const MIN_FILES: usize = 20;
+
+/// Runs over at least this many bytes show a progress bar
+const MIN_BYTES: u64 = 16 << 20;
+
+/// Progress of a multi-file count: files done, bytes processed, ETA, and
+/// tokens per second
+///
+/// The bar only appears on an interactive stderr, for runs big enough to
+/// take a while, and not with `--quiet`; otherwise every method does
+/// nothing but run what it is given.
+pub struct CountProgress {
+    bar: Option<ProgressBar>,
+    files_total: usize,
+    files_done: usize,
+    tokens: usize,
+    started: Instant,
+}
+
+impl CountProgress {
+    pub fn new(files: &[PathBuf], quiet: bool) -> Self {
+        let bar = (!quiet && io::stderr().is_terminal())
+            .then(|| files.iter().map(|file| file_size(file)).sum::<u64>())
+            .filter(|&bytes| files.len() >= MIN_FILES || bytes >= MIN_BYTES)
+            .map(|bytes| {
+                let style = ProgressStyle::with_template(
+                    "{bar:20} {binary_bytes}/{binary_total_bytes} {msg} ETA {eta}",
+                )
+                .expect("valid progress template");
+                ProgressBar::new(bytes).with_style(style)
+            });
+
+        Self {
+            bar,
+            files_total: files.len(),
+            files_done: 0,
+            tokens: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Records a finished file and the tokens counted in it
+    pub fn inc(&mut self, file: &Path, tokens: usize) {
+        let Some(bar) = &self.bar else {
+            return;
+        };
+        self.files_done += 1;
+        self.tokens += tokens;
+        let rate = self.tokens as f64 / self.started.elapsed().as_secs_f64().max(1e-3);
+        bar.set_message(format!(
+            "{}/{} files {:.0} tokens/s",
+            self.files_done, self.files_total, rate
+        ));
+        bar.inc(file_size(file));
+    }
+
+    /// Runs `f`, which prints, with the bar cleared out of its way
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    /// Removes the bar before the summary is printed
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+fn file_size(file: &Path) -> u64 {
+    fs::metadata(file).map_or(0, |metadata| metadata.len())
+}
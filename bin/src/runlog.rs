@@ -0,0 +1,189 @@
+//! `--append-log`: a JSON line per run of the default command, for tracking
+//! how a corpus or prompt set grows over time
+//!
+//! Each line records when the run started, how long it took, the arguments
+//! and working directory it was given, the tokenizer, and the totals:
+//!
+//! ```text
+//! {"timestamp":"2026-03-02T09:15:04Z","duration_ms":412,"args":["-r","docs/","--append-log","runs.jsonl"],"cwd":"/home/me/project","tokenizer":"gpt2","status":"ok","files":42,"total":{"tokens":51230,"lines":4120,"bytes":201877,"words":30412,"chars":199803}}
+//! ```
+
+use crate::columnar::ArrowStream;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use token_counter_lib::TokenStats;
+
+/// What a run counted, filled in by the default command as it goes
+#[derive(Default)]
+pub struct Summary {
+    /// Label of the tokenizer used, once loaded
+    pub tokenizer: Option<String>,
+    /// Inputs counted (files, or 1 for stdin)
+    pub files: usize,
+    /// Totals across the inputs; `None` for modes that print tokens rather
+    /// than counts, such as `--ids`
+    pub total: Option<TokenStats>,
+    /// Each input's name and stats, kept only when a report needs them
+    /// (`Some` from the start)
+    pub inputs: Option<Vec<(String, TokenStats)>>,
+    /// Where each input's stats are sent as they are counted, for
+    /// `--output-arrow`
+    pub arrow: Option<ArrowStream>,
+}
+
+impl Summary {
+    /// Keeps `stats` as the result of the input called `name`, if inputs
+    /// are being kept, and sends it to the Arrow stream if there is one
+    pub fn add_input(&mut self, name: &str, stats: &TokenStats) {
+        if let Some(inputs) = &mut self.inputs {
+            inputs.push((name.to_string(), stats.clone()));
+        }
+        if let Some(arrow) = &mut self.arrow {
+            arrow.push_file(name, stats);
+        }
+    }
+}
+
+/// One line of the log
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    duration_ms: u128,
+    args: Vec<String>,
+    cwd: Option<String>,
+    tokenizer: Option<&'a str>,
+    /// "ok", "over budget" (a --max-tokens or --fail-under check failed), or
+    /// "error"
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    files: usize,
+    total: Option<Total>,
+}
+
+/// The counts of [`Summary::total`] worth tracking
+#[derive(Serialize)]
+struct Total {
+    tokens: usize,
+    lines: usize,
+    bytes: usize,
+    words: usize,
+    chars: usize,
+}
+
+/// When a run started, for the records of it
+pub struct Run {
+    started: SystemTime,
+    clock: Instant,
+}
+
+impl Run {
+    /// Starts timing the run
+    pub fn start() -> Self {
+        Self {
+            started: SystemTime::now(),
+            clock: Instant::now(),
+        }
+    }
+
+    /// The start of the run as an RFC 3339 UTC timestamp to the second,
+    /// such as "2026-03-02T09:15:04Z"
+    pub fn timestamp(&self) -> String {
+        format_timestamp(self.started)
+    }
+
+    /// Milliseconds since the run started
+    pub fn duration_ms(&self) -> u128 {
+        self.clock.elapsed().as_millis()
+    }
+}
+
+/// The arguments `tc` was run with, after the program name
+pub fn args() -> Vec<String> {
+    std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// The working directory, which relative arguments are resolved against
+pub fn cwd() -> Option<String> {
+    std::env::current_dir()
+        .ok()
+        .map(|dir| dir.display().to_string())
+}
+
+/// Appends the record of `run`, which ended with `result`, to the log at
+/// `path`
+///
+/// The line is written with a single call on a file opened for appending,
+/// so runs that finish at the same time do not interleave.
+pub fn append(path: &Path, run: &Run, summary: &Summary, result: &Result<ExitCode>) -> Result<()> {
+    let (status, error) = match result {
+        Ok(code) if *code == ExitCode::SUCCESS => ("ok", None),
+        Ok(_) => ("over budget", None),
+        Err(e) => ("error", Some(format!("{:#}", e))),
+    };
+    let record = Record {
+        timestamp: run.timestamp(),
+        duration_ms: run.duration_ms(),
+        args: args(),
+        cwd: cwd(),
+        tokenizer: summary.tokenizer.as_deref(),
+        status,
+        error,
+        files: summary.files,
+        total: summary.total.as_ref().map(|stats| Total {
+            tokens: stats.tokens,
+            lines: stats.lines,
+            bytes: stats.bytes,
+            words: stats.words,
+            chars: stats.chars,
+        }),
+    };
+    let line = serde_json::to_string(&record)? + "\n";
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Failed to append to {:?}", path))
+}
+
+/// Formats `time` as in [`Run::timestamp`]
+fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, of_day) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's
+    // `civil_from_days`, with eras of 400 years starting on March 1st
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60,
+        of_day % 60
+    )
+}
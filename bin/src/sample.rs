@@ -0,0 +1,110 @@
+//! `--sample`: estimating totals from a random sample of the input bytes
+
+use crate::output::outln;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+use token_counter_lib::{estimate_tokens, estimate_tokens_fraction};
+use tokenizers::Tokenizer;
+
+/// How much of the input `--sample` and `--sample-bytes` tokenize
+#[derive(Clone, Copy, Debug)]
+pub enum SampleSize {
+    /// A fraction of the input bytes
+    Fraction(f64),
+    /// A number of bytes
+    Bytes(u64),
+}
+
+/// Parses a percentage such as "5%" or "0.5" (percent sign optional)
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+    let number = value.strip_suffix('%').unwrap_or(value).trim();
+    match number.parse::<f64>() {
+        Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(percent / 100.0),
+        _ => Err(format!(
+            "expected a percentage between 0 and 100, got '{}'",
+            value
+        )),
+    }
+}
+
+/// Parses a size such as "50M", "1.5G", or "4096" (binary units)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper
+        .trim_end_matches('B')
+        .trim_end_matches('I')
+        .trim_end_matches(['K', 'M', 'G', 'T']);
+    let multiplier = match upper[number.len()..].chars().next() {
+        None | Some('B') => 1u64,
+        Some('K') => 1 << 10,
+        Some('M') => 1 << 20,
+        Some('G') => 1 << 30,
+        Some('T') => 1 << 40,
+        Some(_) => return Err(format!("unknown size unit in '{}'", value)),
+    };
+    match number.parse::<f64>() {
+        Ok(size) if size > 0.0 => Ok((size * multiplier as f64) as u64),
+        _ => Err(format!(
+            "expected a size such as 50M or 1G, got '{}'",
+            value
+        )),
+    }
+}
+
+/// Estimates the total tokens of `files` from a sample and prints it with
+/// its 95% confidence interval
+pub fn print_estimate(
+    files: &[PathBuf],
+    size: SampleSize,
+    seed: Option<u64>,
+    tokenizer: &Tokenizer,
+) -> Result<ExitCode> {
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+
+    let estimate = match size {
+        SampleSize::Bytes(bytes) => estimate_tokens(files, tokenizer, bytes, seed),
+        SampleSize::Fraction(fraction) => {
+            estimate_tokens_fraction(files, tokenizer, fraction, seed)
+        }
+    }
+    .context("Failed to sample inputs")?;
+    if estimate.is_exact() {
+        outln!(
+            "{:8} tokens (the sample covered every byte, so this is exact)",
+            estimate.sampled_tokens
+        );
+    } else {
+        outln!(
+            "{:8.0} tokens (95% CI {:.0} to {:.0}), estimated from {} of {}",
+            estimate.tokens,
+            estimate.low(),
+            estimate.high(),
+            format_size(estimate.sampled_bytes),
+            format_size(estimate.bytes)
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Formats a byte count with a binary unit, e.g. "1.5 GiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,179 @@
+//! `tc serve`: HTTP server for token counting
+//!
+//! Endpoints:
+//!
+//! - `POST /count[?tokenizer=NAME]` - counts the request body (UTF-8 text)
+//!   and returns `{"tokens":..,"lines":..,"bytes":..}`
+//! - `GET /health` - returns `ok`
+//! - `GET /metrics` - Prometheus metrics
+//!
+//! Only named tokenizers can be selected per request; the server never
+//! reads files on behalf of clients.
+
+use crate::service::{CountRequest, CountResponse, CountService, ErrorResponse};
+use crate::tokenizer::TokenizerArgs;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::io::Read;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Largest request body accepted, in bytes
+const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Serve token counts over HTTP
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to bind
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short = 'p', long, default_value_t = 8080)]
+    port: u16,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &ServeArgs) -> Result<ExitCode> {
+    let service = Arc::new(CountService::new(args.tokenizer.clone())?);
+    let address = format!("{}:{}", args.host, args.port);
+    let server = Arc::new(
+        Server::http(&address)
+            .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", address, e))?,
+    );
+    eprintln!("tc: listening on http://{}", address);
+
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let service = Arc::clone(&service);
+            thread::spawn(move || loop {
+                match server.recv() {
+                    Ok(request) => handle_request(request, &service),
+                    Err(e) => eprintln!("tc: failed to receive request: {}", e),
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn handle_request(mut request: Request, service: &CountService) {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+
+    let response = match (request.method(), path.as_str()) {
+        (Method::Post, "/count") => count(&mut request, &query, service),
+        (Method::Get, "/health") => Response::from_string("ok\n"),
+        (Method::Get, "/metrics") => metrics(service),
+        (_, "/count") | (_, "/health") | (_, "/metrics") => error(405, "method not allowed"),
+        _ => error(404, "not found"),
+    };
+
+    if let Err(e) = request.respond(response) {
+        eprintln!("tc: failed to send response: {}", e);
+    }
+}
+
+fn count(
+    request: &mut Request,
+    query: &str,
+    service: &CountService,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let mut tokenizer = None;
+    for (key, value) in query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+    {
+        match key {
+            "tokenizer" => tokenizer = Some(value.to_string()),
+            _ => return error(400, &format!("unknown query parameter '{}'", key)),
+        }
+    }
+
+    let mut body = Vec::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+    {
+        return error(400, &format!("failed to read body: {}", e));
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return error(413, "request body too large");
+    }
+    let Ok(text) = String::from_utf8(body) else {
+        return error(400, "request body is not valid UTF-8");
+    };
+
+    let request = CountRequest {
+        text: Some(text),
+        tokenizer,
+        ..CountRequest::default()
+    };
+    match service.count(&request) {
+        Ok(stats) => json(200, &CountResponse::from(stats)),
+        // Only the outermost context: the details may include server paths
+        Err(e) => error(400, &e.to_string()),
+    }
+}
+
+fn metrics(service: &CountService) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+        .expect("valid header");
+    Response::from_string(service.metrics().render()).with_header(content_type)
+}
+
+/// Serves only `GET /metrics` on `address`, from a background thread
+///
+/// Used by `tc daemon`, whose socket protocol has no room for metrics.
+#[cfg(unix)]
+pub fn spawn_metrics_server(address: &str, service: Arc<CountService>) -> Result<()> {
+    let server = Server::http(address)
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", address, e))?;
+    eprintln!("tc: serving metrics on http://{}/metrics", address);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match (request.method(), request.url()) {
+                (Method::Get, "/metrics") => metrics(&service),
+                _ => error(404, "not found"),
+            };
+            if let Err(e) = request.respond(response) {
+                eprintln!("tc: failed to send response: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn error(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json(
+        status,
+        &ErrorResponse {
+            error: message.to_string(),
+        },
+    )
+}
+
+fn json<T: Serialize>(status: u16, body: &T) -> Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_string(body).expect("response serializes") + "\n";
+    let content_type =
+        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("valid header");
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type)
+}
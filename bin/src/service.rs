@@ -0,0 +1,129 @@
+//! Shared request handling for the long-running server modes
+//!
+//! Tokenizers are loaded on first use and kept warm, so repeated requests
+//! only pay for encoding.
+
+use crate::metrics::Metrics;
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+use token_counter_lib::{
+    count_stats, count_tokens_in_file, SharedTokenizer, TokenStats, TokenizerPool,
+};
+use tracing::debug;
+
+/// A count request: either inline text or a file path
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CountRequest {
+    /// Text to count
+    pub text: Option<String>,
+    /// File to count (read by the server)
+    pub path: Option<PathBuf>,
+    /// Named tokenizer (defaults to the server's tokenizer)
+    pub tokenizer: Option<String>,
+    /// Tokenizer file or model directory
+    pub tokenizer_path: Option<PathBuf>,
+}
+
+/// Counts returned for a successful request
+#[derive(Serialize, Debug)]
+pub struct CountResponse {
+    pub tokens: usize,
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+impl From<TokenStats> for CountResponse {
+    fn from(stats: TokenStats) -> Self {
+        Self {
+            tokens: stats.tokens,
+            lines: stats.lines,
+            bytes: stats.bytes,
+        }
+    }
+}
+
+/// Error body for a failed request
+#[derive(Serialize, Debug)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Counting service with a cache of loaded tokenizers
+pub struct CountService {
+    default_tokenizer: TokenizerArgs,
+    tokenizers: TokenizerPool,
+    metrics: Metrics,
+}
+
+impl CountService {
+    /// Creates the service and loads the default tokenizer up front
+    pub fn new(default_tokenizer: TokenizerArgs) -> Result<Self> {
+        let service = Self {
+            default_tokenizer,
+            tokenizers: TokenizerPool::new(),
+            metrics: Metrics::default(),
+        };
+        service.tokenizer(&service.default_tokenizer)?;
+        Ok(service)
+    }
+
+    /// Handles one request, recording it in the service metrics
+    pub fn count(&self, request: &CountRequest) -> Result<TokenStats> {
+        let result = self.count_unrecorded(request);
+        if result.is_err() {
+            self.metrics.record_error();
+        }
+        result
+    }
+
+    /// Metrics for all requests handled so far
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    fn count_unrecorded(&self, request: &CountRequest) -> Result<TokenStats> {
+        let selection = match (&request.tokenizer, &request.tokenizer_path) {
+            (None, None) => self.default_tokenizer.clone(),
+            (Some(_), Some(_)) => {
+                anyhow::bail!("specify either 'tokenizer' or 'tokenizer_path', not both")
+            }
+            (name, path) => TokenizerArgs {
+                tokenizer_name: name.clone(),
+                tokenizer_path: path.clone(),
+            },
+        };
+        let tokenizer = self.tokenizer(&selection)?;
+
+        let start = Instant::now();
+        let stats = match (&request.text, &request.path) {
+            (Some(text), None) => count_stats(text, &tokenizer)?,
+            (None, Some(path)) => count_tokens_in_file(path, &tokenizer)
+                .with_context(|| format!("Failed to count tokens in {:?}", path))?,
+            _ => anyhow::bail!("specify exactly one of 'text' or 'path'"),
+        };
+        self.metrics.record_count(
+            &selection.label(),
+            stats.tokens,
+            stats.bytes,
+            start.elapsed(),
+        );
+        Ok(stats)
+    }
+
+    /// Returns a cached tokenizer, loading it on first use
+    fn tokenizer(&self, selection: &TokenizerArgs) -> Result<SharedTokenizer> {
+        let key = selection.label();
+        if let Some(tokenizer) = self.tokenizers.get(&key) {
+            debug!("tokenizer cache hit for {}", key);
+            self.metrics.record_cache(true);
+            return Ok(tokenizer);
+        }
+        debug!("tokenizer cache miss for {}", key);
+        self.metrics.record_cache(false);
+        self.tokenizers.get_or_load(&key, || selection.load())
+    }
+}
@@ -0,0 +1,119 @@
+//! `tc similarity`: how redundant two inputs are, measured over their tokens
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{encode_ids, read_document_text, shared_spans, token_similarity};
+
+/// Compare two inputs' tokens: Jaccard and cosine similarity, and the spans
+/// of tokens they share
+#[derive(Args, Debug)]
+pub struct SimilarityArgs {
+    /// First input
+    #[arg(value_name = "FILE_A")]
+    a: PathBuf,
+
+    /// Second input
+    #[arg(value_name = "FILE_B")]
+    b: PathBuf,
+
+    /// Shortest run of tokens counted as a shared span
+    #[arg(long, value_name = "N", default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    min_span: u16,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = SimilarityFormat::Table)]
+    format: SimilarityFormat,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SimilarityFormat {
+    Table,
+    Json,
+}
+
+/// Similarity of two inputs, as printed with `--format json`
+#[derive(Serialize)]
+struct Report {
+    a: Input,
+    b: Input,
+    jaccard: f64,
+    cosine: f64,
+    min_span: usize,
+    shared_spans: usize,
+}
+
+#[derive(Serialize)]
+struct Input {
+    path: String,
+    tokens: usize,
+    /// Tokens inside spans shared with the other input
+    shared_tokens: usize,
+}
+
+pub fn run(args: &SimilarityArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let encode = |file: &PathBuf| -> Result<Vec<u32>> {
+        let text =
+            read_document_text(file).with_context(|| format!("Failed to read {:?}", file))?;
+        encode_ids(&text, &tokenizer).with_context(|| format!("Failed to tokenize {:?}", file))
+    };
+    let (ids_a, ids_b) = (encode(&args.a)?, encode(&args.b)?);
+
+    let similarity = token_similarity(&ids_a, &ids_b);
+    let min_span = args.min_span as usize;
+    let shared = shared_spans(&ids_a, &ids_b, min_span);
+    let report = Report {
+        a: Input {
+            path: args.a.display().to_string(),
+            tokens: ids_a.len(),
+            shared_tokens: shared.tokens_a,
+        },
+        b: Input {
+            path: args.b.display().to_string(),
+            tokens: ids_b.len(),
+            shared_tokens: shared.tokens_b,
+        },
+        jaccard: similarity.jaccard,
+        cosine: similarity.cosine,
+        min_span,
+        shared_spans: shared.spans,
+    };
+
+    match args.format {
+        SimilarityFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        SimilarityFormat::Table => print_table(&report),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_table(report: &Report) {
+    for input in [&report.a, &report.b] {
+        println!(
+            "{:<10} {:>10} tokens, {:>5.1}% in shared spans  {}",
+            "input",
+            input.tokens,
+            percent(input.shared_tokens, input.tokens),
+            input.path
+        );
+    }
+    println!("{:<10} {:>10.4}", "jaccard", report.jaccard);
+    println!("{:<10} {:>10.4}", "cosine", report.cosine);
+    println!(
+        "{:<10} {:>10} of {}+ tokens",
+        "spans", report.shared_spans, report.min_span
+    );
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 * 100.0 / whole as f64
+}
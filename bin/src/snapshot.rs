@@ -0,0 +1,151 @@
+//! `tc snapshot`: record per-file token counts and check for regressions
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Args};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::{compare_counts, count_tokens_in_file, ChangeKind, GrowthTolerance};
+use tokenizers::Tokenizer;
+
+/// Record per-file token counts, or compare against a recorded baseline
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("mode").required(true)))]
+pub struct SnapshotArgs {
+    /// Input files
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Write a baseline snapshot to PATH
+    #[arg(long, value_name = "PATH", group = "mode")]
+    write: Option<PathBuf>,
+
+    /// Compare against the baseline snapshot at PATH
+    #[arg(long, value_name = "PATH", group = "mode")]
+    check: Option<PathBuf>,
+
+    /// Tokens a file may grow by before --check fails
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    max_growth: usize,
+
+    /// Percentage a file may grow by before --check fails
+    #[arg(long, value_name = "PCT", default_value_t = 0.0)]
+    max_growth_pct: f64,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// On-disk snapshot format
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    /// Tokenizer the counts were produced with
+    tokenizer: String,
+    /// Token count per file path
+    files: BTreeMap<String, usize>,
+}
+
+pub fn run(args: &SnapshotArgs) -> Result<ExitCode> {
+    let tokenizer = args.tokenizer.load()?;
+    let counts = count_files(&args.files, &tokenizer);
+
+    if let Some(path) = &args.write {
+        write_snapshot(path, &args.tokenizer.label(), counts)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let Some(path) = &args.check else {
+        unreachable!("clap requires --write or --check");
+    };
+    let baseline = read_snapshot(path)?;
+    if baseline.tokenizer != args.tokenizer.label() {
+        eprintln!(
+            "tc: warning: baseline was recorded with tokenizer '{}', checking with '{}'",
+            baseline.tokenizer,
+            args.tokenizer.label()
+        );
+    }
+
+    let tolerance = GrowthTolerance {
+        tokens: args.max_growth,
+        percent: args.max_growth_pct,
+    };
+    let mut regressed = false;
+
+    for change in compare_counts(&baseline.files, &counts) {
+        let kind = match change.kind() {
+            ChangeKind::Unchanged => continue,
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Grew => "grew",
+            ChangeKind::Shrank => "shrank",
+        };
+        let percent = change
+            .percent_change()
+            .map(|p| format!(" ({:+.1}%)", p))
+            .unwrap_or_default();
+
+        println!(
+            "{:<8} {:>8} -> {:>8} {:>+8}{} {}",
+            kind,
+            format_count(change.baseline),
+            format_count(change.current),
+            change.delta(),
+            percent,
+            change.path
+        );
+
+        if !tolerance.allows(&change) {
+            eprintln!(
+                "tc: {}: grew by {} tokens{}, beyond tolerance",
+                change.path,
+                change.delta(),
+                percent
+            );
+            regressed = true;
+        }
+    }
+
+    Ok(if regressed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Counts tokens per file, reporting and skipping unreadable files
+fn count_files(files: &[PathBuf], tokenizer: &Tokenizer) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for file in files {
+        match count_tokens_in_file(file, tokenizer) {
+            Ok(stats) => {
+                counts.insert(file.display().to_string(), stats.tokens);
+            }
+            Err(e) if e.path().is_some() => eprintln!("tc: {}", e),
+            Err(e) => eprintln!("tc: {}: {}", file.display(), e),
+        }
+    }
+    counts
+}
+
+fn write_snapshot(path: &Path, tokenizer: &str, files: BTreeMap<String, usize>) -> Result<()> {
+    let snapshot = SnapshotFile {
+        tokenizer: tokenizer.to_string(),
+        files,
+    };
+    let json = serde_json::to_string_pretty(&snapshot)?;
+    fs::write(path, json + "\n").with_context(|| format!("Failed to write snapshot {:?}", path))
+}
+
+fn read_snapshot(path: &Path) -> Result<SnapshotFile> {
+    let json =
+        fs::read_to_string(path).with_context(|| format!("Failed to read snapshot {:?}", path))?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse snapshot {:?}", path))
+}
+
+fn format_count(count: Option<usize>) -> String {
+    count.map_or_else(|| "-".to_string(), |n| n.to_string())
+}
@@ -0,0 +1,73 @@
+//! `tc split`: write a file out as chunks of bounded token count
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::split_by_tokens;
+
+/// Split a file into numbered chunk files of at most N tokens each
+#[derive(Args, Debug)]
+pub struct SplitArgs {
+    /// Input file
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Maximum number of tokens per chunk
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_tokens: u64,
+
+    /// Number of tokens consecutive chunks share
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    overlap: u64,
+
+    /// Directory to write the chunk files to (created if missing)
+    #[arg(long, value_name = "DIR")]
+    out_dir: PathBuf,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+pub fn run(args: &SplitArgs) -> Result<ExitCode> {
+    if args.overlap >= args.max_tokens {
+        anyhow::bail!(
+            "--overlap ({}) must be smaller than --max-tokens ({})",
+            args.overlap,
+            args.max_tokens
+        );
+    }
+
+    let tokenizer = args.tokenizer.load()?;
+    let text = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {:?}", args.file))?;
+
+    let max_tokens = args.max_tokens as usize;
+    let chunks = split_by_tokens(&text, &tokenizer, max_tokens, args.overlap as usize)
+        .with_context(|| format!("Failed to split {:?}", args.file))?;
+
+    fs::create_dir_all(&args.out_dir)
+        .with_context(|| format!("Failed to create {:?}", args.out_dir))?;
+
+    let width = chunks.len().to_string().len().max(4);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let path = args.out_dir.join(chunk_name(&args.file, index + 1, width));
+        fs::write(&path, chunk.text).with_context(|| format!("Failed to write {:?}", path))?;
+        println!("{:8} {}", chunk.tokens, path.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Names chunk `number` after the input, e.g. `notes-0001.md` for `notes.md`
+fn chunk_name(input: &Path, number: usize, width: usize) -> String {
+    let stem = input
+        .file_stem()
+        .map_or_else(|| "chunk".into(), |stem| stem.to_string_lossy());
+    let extension = input
+        .extension()
+        .map_or_else(|| "txt".into(), |ext| ext.to_string_lossy());
+    format!("{}-{:0width$}.{}", stem, number, extension, width = width)
+}
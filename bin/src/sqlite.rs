@@ -0,0 +1,122 @@
+//! `--output-sqlite`: per-file results in a SQLite database, one run after
+//! another, for ad-hoc SQL and for diffing repeated runs
+//!
+//! Each run adds a row to `runs`, a row per input to `files`, and its
+//! totals to `totals`; all three are keyed by the run's id. Earlier runs are
+//! kept, so a database can hold months of history.
+
+#[cfg(feature = "sqlite")]
+mod database {
+    use crate::runlog::{self, Run, Summary};
+    use anyhow::{Context, Result};
+    use rusqlite::{params, Connection};
+    use std::path::Path;
+    use std::time::Duration;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY,
+            started TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            tokenizer TEXT NOT NULL,
+            args TEXT NOT NULL,
+            cwd TEXT
+        );
+        CREATE TABLE IF NOT EXISTS files (
+            run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            tokens INTEGER NOT NULL,
+            lines INTEGER NOT NULL,
+            bytes INTEGER NOT NULL,
+            max_line_tokens INTEGER NOT NULL,
+            words INTEGER NOT NULL,
+            chars INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS files_by_run ON files (run_id, path);
+        CREATE TABLE IF NOT EXISTS totals (
+            run_id INTEGER PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+            files INTEGER NOT NULL,
+            tokens INTEGER NOT NULL,
+            lines INTEGER NOT NULL,
+            bytes INTEGER NOT NULL,
+            max_line_tokens INTEGER NOT NULL,
+            words INTEGER NOT NULL,
+            chars INTEGER NOT NULL
+        );";
+
+    /// Adds `run` and what it counted to the database at `path`, creating
+    /// the database and its tables if needed
+    pub fn write(path: &Path, run: &Run, summary: &Summary) -> Result<()> {
+        let mut connection =
+            Connection::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        // Runs writing to the same database wait their turn
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        connection.execute_batch(SCHEMA)?;
+
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO runs (started, duration_ms, tokenizer, args, cwd)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run.timestamp(),
+                u64::try_from(run.duration_ms()).unwrap_or(u64::MAX),
+                summary.tokenizer.as_deref().unwrap_or_default(),
+                serde_json::to_string(&runlog::args())?,
+                runlog::cwd(),
+            ],
+        )?;
+        let run_id = transaction.last_insert_rowid();
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO files
+                 (run_id, path, tokens, lines, bytes, max_line_tokens, words, chars)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for (name, stats) in summary.inputs.iter().flatten() {
+                insert.execute(params![
+                    run_id,
+                    name,
+                    stats.tokens,
+                    stats.lines,
+                    stats.bytes,
+                    stats.max_line_tokens,
+                    stats.words,
+                    stats.chars
+                ])?;
+            }
+        }
+        let files = summary.inputs.as_ref().map_or(0, Vec::len);
+        let total = summary.total.clone().unwrap_or_default();
+        transaction.execute(
+            "INSERT INTO totals
+             (run_id, files, tokens, lines, bytes, max_line_tokens, words, chars)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run_id,
+                files,
+                total.tokens,
+                total.lines,
+                total.bytes,
+                total.max_line_tokens,
+                total.words,
+                total.chars
+            ],
+        )?;
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use database::write;
+
+#[cfg(not(feature = "sqlite"))]
+pub fn write(
+    _path: &std::path::Path,
+    _run: &crate::runlog::Run,
+    _summary: &crate::runlog::Summary,
+) -> anyhow::Result<()> {
+    anyhow::bail!("SQLite output is not enabled (build with the `sqlite` feature)")
+}
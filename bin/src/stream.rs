@@ -0,0 +1,74 @@
+//! `--stream`: count stdin line by line as it arrives
+
+use crate::diagnostics::Input;
+use crate::output;
+use crate::runlog::Summary;
+use crate::{CountMode, OutputConfig};
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+use token_counter_lib::TokenStats;
+use tokenizers::Tokenizer;
+
+/// Counts stdin one line at a time, printing each line's counts as soon as
+/// the line is complete, and returns the total
+///
+/// Lines are named by number, and added to `summary` as inputs. On a
+/// terminal the running total is kept up to date below them; otherwise it
+/// is printed once stdin ends. A request's `overhead` is counted once, in
+/// the total, rather than on every line.
+pub fn stream(
+    mode: &mut CountMode,
+    overhead: usize,
+    tokenizer: &Tokenizer,
+    output_config: &OutputConfig,
+    summary: &mut Summary,
+) -> Result<TokenStats> {
+    let live = output::to_stdout() && io::stdout().is_terminal();
+    let mut total = TokenStats {
+        tokens: overhead,
+        ..TokenStats::new()
+    };
+    let mut stdin = io::stdin().lock();
+    let mut line = Vec::new();
+    let mut number = 0;
+
+    loop {
+        line.clear();
+        let read = stdin
+            .read_until(b'\n', &mut line)
+            .context("Failed to read stdin")?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+
+        let text = mode.decode(line.clone(), None)?;
+        let stats = mode
+            .count_text(&text, None, tokenizer)
+            .with_context(|| format!("Failed to count tokens in line {}", number))?;
+        total.add(&stats);
+
+        let name = format!("line {}", number);
+        summary.add_input(&name, &stats);
+        if live {
+            // Replace the running total with this line, then redraw it
+            print!("\r\x1b[K");
+            output_config.report(&stats, Input::File(&name));
+            print!(
+                "{}",
+                output_config
+                    .report
+                    .format_stats(&total, Input::Total.name())
+            );
+            io::stdout().flush()?;
+        } else {
+            output_config.report(&stats, Input::File(&name));
+        }
+    }
+
+    if live {
+        print!("\r\x1b[K");
+    }
+    output_config.report(&total, Input::Total);
+    Ok(total)
+}
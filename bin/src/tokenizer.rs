@@ -0,0 +1,291 @@
+//! Tokenizer selection shared by all commands, and `tc tokenizers` for
+//! looking after tokenizer files
+
+use crate::config;
+use crate::convert::{self, ConvertArgs};
+use crate::verify::{self, VerifyArgs};
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use token_counter_lib::{load_tokenizer, load_tokenizer_from_bytes};
+use tokenizers::Tokenizer;
+use tracing::{debug, info};
+
+/// Embedded GPT-2 tokenizer (default)
+const DEFAULT_TOKENIZER: &[u8] = include_bytes!("../assets/gpt2-tokenizer.json");
+
+/// Name the embedded tokenizer answers to when no file of that name is
+/// installed
+const DEFAULT_NAME: &str = "gpt2";
+
+/// Look after tokenizer files
+#[derive(Args, Debug)]
+pub struct TokenizersArgs {
+    #[command(subcommand)]
+    command: TokenizersCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenizersCommand {
+    /// Check a tokenizer for corruption: vocab and special-token
+    /// consistency, and round trips of sample text
+    Verify(VerifyArgs),
+    /// Convert a tiktoken or SentencePiece tokenizer to a Hugging Face
+    /// tokenizer.json
+    Convert(ConvertArgs),
+}
+
+pub fn run(args: &TokenizersArgs) -> Result<ExitCode> {
+    match &args.command {
+        TokenizersCommand::Verify(verify_args) => verify::run(verify_args),
+        TokenizersCommand::Convert(convert_args) => convert::run(convert_args),
+    }
+}
+
+/// Tokenizer selection flags (mutually exclusive)
+#[derive(Args, Debug, Clone, Default)]
+#[group(multiple = false)]
+pub struct TokenizerArgs {
+    /// Path to custom tokenizer JSON file or model directory
+    #[arg(short = 't', long, value_name = "PATH")]
+    pub tokenizer_path: Option<PathBuf>,
+
+    /// Named tokenizer to use (e.g., "gpt4", "bert")
+    #[arg(short = 'n', long, value_name = "NAME", add = name_completer())]
+    pub tokenizer_name: Option<String>,
+}
+
+impl TokenizerArgs {
+    /// Loads the selected tokenizer, defaulting to the config file's
+    /// tokenizer and then to the embedded GPT-2 tokenizer
+    pub fn load(&self) -> Result<Tokenizer> {
+        let selected = self.or_configured();
+        let source = if matches!(selected, Cow::Borrowed(_)) {
+            "flags"
+        } else {
+            "config or TC_TOKENIZER*"
+        };
+        if let Some(tokenizer_path) = &selected.tokenizer_path {
+            // Explicit path provided
+            info!(
+                "using tokenizer {} (from {})",
+                tokenizer_path.display(),
+                source
+            );
+            load_tokenizer(tokenizer_path)
+                .with_context(|| format!("Failed to load tokenizer from {:?}", tokenizer_path))
+        } else if let Some(tokenizer_name) = selected
+            .tokenizer_name
+            .as_ref()
+            .filter(|name| *name != DEFAULT_NAME || is_installed(name))
+        {
+            // Named tokenizer (find in standard directories)
+            let path = find_tokenizer_by_name(tokenizer_name)
+                .with_context(|| format!("Failed to find tokenizer '{}'", tokenizer_name))?;
+            info!(
+                "using tokenizer '{}' at {} (from {})",
+                tokenizer_name,
+                path.display(),
+                source
+            );
+            load_tokenizer(&path)
+                .with_context(|| format!("Failed to load tokenizer from {:?}", path))
+        } else {
+            // Default (or -n gpt2): use embedded GPT-2 tokenizer
+            if selected.tokenizer_name.is_some() {
+                info!("using the embedded GPT-2 tokenizer (no gpt2.json installed)");
+            } else {
+                info!("using the embedded GPT-2 tokenizer (no -n, -t, TC_TOKENIZER, or configured tokenizer)");
+            }
+            load_tokenizer_from_bytes(DEFAULT_TOKENIZER)
+                .context("Failed to load embedded GPT-2 tokenizer")
+        }
+    }
+
+    /// Whether no tokenizer was chosen, so the embedded default is used
+    pub fn is_default(&self) -> bool {
+        let selected = self.or_configured();
+        selected.tokenizer_path.is_none() && selected.tokenizer_name.is_none()
+    }
+
+    /// Short human-readable label for the selected tokenizer
+    pub fn label(&self) -> String {
+        let selected = self.or_configured();
+        if let Some(path) = &selected.tokenizer_path {
+            path.display().to_string()
+        } else if let Some(name) = &selected.tokenizer_name {
+            name.clone()
+        } else {
+            DEFAULT_NAME.to_string()
+        }
+    }
+
+    /// These flags, or the installed tokenizer `name` when no tokenizer
+    /// was chosen; without it, warns that `purpose` (e.g. "counting
+    /// OpenAI") goes on with the default
+    pub fn or_preferred(&self, name: &str, purpose: &str) -> TokenizerArgs {
+        if !self.is_default() {
+            return self.clone();
+        }
+        if is_installed(name) {
+            return TokenizerArgs {
+                tokenizer_name: Some(name.to_string()),
+                ..TokenizerArgs::default()
+            };
+        }
+        eprintln!(
+            "tc: warning: no '{}' tokenizer installed; {} tokens with {}",
+            name,
+            purpose,
+            self.label()
+        );
+        self.clone()
+    }
+
+    /// These flags, or the config file's tokenizer if neither is given
+    fn or_configured(&self) -> Cow<'_, Self> {
+        if self.tokenizer_path.is_some() || self.tokenizer_name.is_some() {
+            return Cow::Borrowed(self);
+        }
+        let config = config::get();
+        Cow::Owned(Self {
+            tokenizer_path: config.tokenizer_path.clone(),
+            tokenizer_name: config.tokenizer.clone(),
+        })
+    }
+}
+
+/// Whether a named tokenizer is installed in one of the standard directories
+pub fn is_installed(name: &str) -> bool {
+    find_tokenizer_by_name(name).is_ok()
+}
+
+/// Find a tokenizer by name in standard directories
+fn find_tokenizer_by_name(name: &str) -> Result<PathBuf> {
+    // Names are file stems, never paths (use --tokenizer-path for those)
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        anyhow::bail!("Invalid tokenizer name '{}'", name);
+    }
+    let filename = format!("{}.json", name);
+
+    let search_paths: Vec<PathBuf> = search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(&filename))
+        .collect();
+
+    // Search for the file
+    for path in &search_paths {
+        debug!("looking for tokenizer '{}' at {}", name, path.display());
+        if path.exists() {
+            return Ok(path.clone());
+        }
+    }
+
+    // Not found in any standard location
+    anyhow::bail!(
+        "Tokenizer '{}' not found. Searched in:\n  {}",
+        name,
+        search_paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n  ")
+    )
+}
+
+/// Directories searched for named tokenizers, in order
+fn search_dirs() -> Vec<PathBuf> {
+    // Search paths in order:
+    // 1. Development: bin/assets/tokenizers/ relative to workspace root
+    // 2. Relative to executable (for installed packages)
+    //    - ../share/tc/tokenizers/
+    //    - ../share/tc/
+    // 3. User config directory (~/.config/tc/tokenizers/)
+    // 4. Homebrew share directory
+    //    - /opt/homebrew/share/tc/tokenizers/
+    //    - /opt/homebrew/share/tc/
+    // 5. Unix share directory
+    //    - /usr/local/share/tc/tokenizers/
+    //    - /usr/local/share/tc/
+    //
+    // Under WASI there is no executable path or system share directory, only
+    // the directories the host maps in, so the search is instead:
+    // 1. tokenizers/ in the working directory
+    // 2. User config directory, if the host passes HOME
+
+    let mut dirs = Vec::new();
+
+    #[cfg(target_os = "wasi")]
+    dirs.push(PathBuf::from("tokenizers"));
+
+    #[cfg(not(target_os = "wasi"))]
+    if let Ok(exe_path) = std::env::current_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            // 1. Development path: target/{debug,release} -> ../../bin/assets/tokenizers/
+            dirs.push(exe_dir.join("../../bin/assets/tokenizers"));
+            // 2. Relative to executable, with and without the tokenizers
+            // subdirectory (cargo-dist installs to share/tc/ directly)
+            dirs.push(exe_dir.join("../share/tc/tokenizers"));
+            dirs.push(exe_dir.join("../share/tc"));
+        }
+    }
+
+    // 3. User config directory
+    if let Some(config_dir) = crate::user_config_dir() {
+        dirs.push(config_dir.join("tokenizers"));
+    }
+
+    #[cfg(not(target_os = "wasi"))]
+    {
+        // 4. Homebrew (try both with and without tokenizers subdirectory)
+        dirs.push(PathBuf::from("/opt/homebrew/share/tc/tokenizers"));
+        dirs.push(PathBuf::from("/opt/homebrew/share/tc"));
+
+        // 5. Unix standard (try both with and without tokenizers subdirectory)
+        dirs.push(PathBuf::from("/usr/local/share/tc/tokenizers"));
+        dirs.push(PathBuf::from("/usr/local/share/tc"));
+    }
+
+    dirs
+}
+
+/// Names of the tokenizers `-n` accepts: the embedded default plus every
+/// tokenizer installed in the standard directories, sorted
+pub fn installed_names() -> Vec<String> {
+    let mut names = vec![DEFAULT_NAME.to_string()];
+    for dir in search_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        names.extend(entries.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" || !path.is_file() {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?;
+            (!name.starts_with('.')).then(|| name.to_string())
+        }));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Completes `-n` with the names of installed tokenizers
+pub fn name_completer() -> ArgValueCompleter {
+    ArgValueCompleter::new(|current: &OsStr| {
+        let Some(current) = current.to_str() else {
+            return Vec::new();
+        };
+        installed_names()
+            .into_iter()
+            .filter(|name| name.starts_with(current))
+            .map(CompletionCandidate::new)
+            .collect()
+    })
+}
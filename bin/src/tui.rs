@@ -0,0 +1,503 @@
+//! `tc tui`: browse a directory's token counts interactively, like `ncdu`
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Browse token totals per directory and file, with a highlighted preview
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Directory to explore
+    #[arg(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[cfg(feature = "tui")]
+pub fn run(args: &TuiArgs) -> Result<ExitCode> {
+    if !args.dir.is_dir() {
+        anyhow::bail!("{:?} is not a directory", args.dir);
+    }
+    let tokenizer = args.tokenizer.load()?;
+    let root = explorer::scan(&args.dir, &tokenizer)?;
+    explorer::App::new(root, &tokenizer).run()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn run(_args: &TuiArgs) -> Result<ExitCode> {
+    anyhow::bail!("tc tui is not enabled (build with the `tui` feature)")
+}
+
+#[cfg(feature = "tui")]
+mod explorer {
+    use crate::config;
+    use crate::inspect::highlight_spans;
+    use crate::progress::CountProgress;
+    use crate::walk::{self, WalkOptions};
+    use anyhow::Result;
+    use indicatif::HumanBytes;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span, Text};
+    use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState, Wrap};
+    use ratatui::{DefaultTerminal, Frame};
+    use std::path::{Path, PathBuf};
+    use token_counter_lib::{count_stats, read_document_text, TokenStats};
+    use tokenizers::Tokenizer;
+
+    /// Only the start of a file is tokenized for the preview
+    const PREVIEW_BYTES: usize = 64 << 10;
+
+    /// Width of the share bar, in cells
+    const BAR_WIDTH: usize = 10;
+
+    /// A file, or a directory with the totals of the files beneath it
+    pub struct Entry {
+        name: String,
+        path: PathBuf,
+        stats: TokenStats,
+        /// Files counted (1 for a file that could be counted)
+        files: usize,
+        /// Contents of a directory; `None` for a file
+        children: Option<Vec<Entry>>,
+        /// Why a file could not be counted
+        error: Option<String>,
+    }
+
+    impl Entry {
+        fn dir(name: String, path: PathBuf) -> Self {
+            Self {
+                name,
+                path,
+                stats: TokenStats::new(),
+                files: 0,
+                children: Some(Vec::new()),
+                error: None,
+            }
+        }
+
+        /// Adds a file `components` below this directory, creating the
+        /// directories in between
+        fn insert(&mut self, components: &[String], path: PathBuf, counted: Result<TokenStats>) {
+            if let Ok(stats) = &counted {
+                self.stats.add(stats);
+                self.files += 1;
+            }
+            let children = self.children.get_or_insert_with(Vec::new);
+            let [name, rest @ ..] = components else {
+                return;
+            };
+            if rest.is_empty() {
+                let (stats, files, error) = match counted {
+                    Ok(stats) => (stats, 1, None),
+                    Err(e) => (TokenStats::new(), 0, Some(format!("{:#}", e))),
+                };
+                children.push(Entry {
+                    name: name.clone(),
+                    path,
+                    stats,
+                    files,
+                    children: None,
+                    error,
+                });
+                return;
+            }
+            // Files arrive in path order, so a directory's files are
+            // contiguous and it is the last entry if it exists
+            if !children
+                .last()
+                .is_some_and(|last| &last.name == name && last.children.is_some())
+            {
+                let dir = self.path.join(name);
+                children.push(Entry::dir(name.clone(), dir));
+            }
+            let dir = children
+                .last_mut()
+                .expect("directory was just found or added");
+            dir.insert(rest, path, counted);
+        }
+
+        fn is_dir(&self) -> bool {
+            self.children.is_some()
+        }
+
+        /// Sorts the entries beneath this one, recursively
+        fn sort(&mut self, by: SortBy, descending: bool) {
+            let Some(children) = &mut self.children else {
+                return;
+            };
+            children.sort_by(|a, b| {
+                let order = match by {
+                    SortBy::Tokens => a.stats.tokens.cmp(&b.stats.tokens),
+                    SortBy::Bytes => a.stats.bytes.cmp(&b.stats.bytes),
+                    SortBy::Name => a.name.cmp(&b.name),
+                };
+                let order = if descending { order.reverse() } else { order };
+                order.then_with(|| a.name.cmp(&b.name))
+            });
+            for child in children {
+                child.sort(by, descending);
+            }
+        }
+    }
+
+    /// Counts the files beneath `dir`, skipping what `tc -r` skips
+    pub fn scan(dir: &Path, tokenizer: &Tokenizer) -> Result<Entry> {
+        let exclude = config::get().exclude.clone().unwrap_or_default();
+        let options = WalkOptions {
+            exclude: walk::exclude_set(&exclude)?,
+            ..WalkOptions::default()
+        };
+        let files = walk::expand(&[dir.to_path_buf()], &options);
+        let mut progress = CountProgress::new(&files, false);
+
+        let mut root = Entry::dir(dir.display().to_string(), dir.to_path_buf());
+        for file in files {
+            let counted = read_document_text(&file)
+                .and_then(|text| count_stats(&text, tokenizer))
+                .map_err(anyhow::Error::from);
+            progress.inc(&file, counted.as_ref().map_or(0, |stats| stats.tokens));
+            let relative = file.strip_prefix(dir).unwrap_or(&file);
+            let components: Vec<_> = relative
+                .iter()
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            root.insert(&components, file, counted);
+        }
+        progress.finish();
+        Ok(root)
+    }
+
+    /// Column the entries are sorted by
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum SortBy {
+        Tokens,
+        Bytes,
+        Name,
+    }
+
+    /// Explorer state: the tree, the open directory, and the selection
+    pub struct App<'a> {
+        root: Entry,
+        tokenizer: &'a Tokenizer,
+        /// Names of the directories opened below the root
+        open: Vec<String>,
+        table: TableState,
+        sort: SortBy,
+        descending: bool,
+        /// The previewed file and its highlighted text
+        preview: Option<(PathBuf, Text<'static>)>,
+        scroll: u16,
+    }
+
+    impl<'a> App<'a> {
+        pub fn new(mut root: Entry, tokenizer: &'a Tokenizer) -> Self {
+            root.sort(SortBy::Tokens, true);
+            Self {
+                root,
+                tokenizer,
+                open: Vec::new(),
+                table: TableState::new().with_selected(0),
+                sort: SortBy::Tokens,
+                descending: true,
+                preview: None,
+                scroll: 0,
+            }
+        }
+
+        /// Takes over the terminal until the user quits
+        pub fn run(&mut self) -> Result<()> {
+            let mut terminal = ratatui::init();
+            let result = self.event_loop(&mut terminal);
+            ratatui::restore();
+            result
+        }
+
+        fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+            loop {
+                self.update_preview();
+                terminal.draw(|frame| self.draw(frame))?;
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Up | KeyCode::Char('k') => self.select_by(-1),
+                    KeyCode::Down | KeyCode::Char('j') => self.select_by(1),
+                    KeyCode::Home | KeyCode::Char('g') => self.select_by(isize::MIN),
+                    KeyCode::End | KeyCode::Char('G') => self.select_by(isize::MAX),
+                    KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open_selected(),
+                    KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => self.close(),
+                    KeyCode::Char('t') => self.sort_by(SortBy::Tokens),
+                    KeyCode::Char('b') => self.sort_by(SortBy::Bytes),
+                    KeyCode::Char('n') => self.sort_by(SortBy::Name),
+                    KeyCode::PageDown | KeyCode::Char('J') => {
+                        self.scroll = self.scroll.saturating_add(10)
+                    }
+                    KeyCode::PageUp | KeyCode::Char('K') => {
+                        self.scroll = self.scroll.saturating_sub(10)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// The open directory
+        fn current(&self) -> &Entry {
+            let mut dir = &self.root;
+            for name in &self.open {
+                let children = dir.children.as_deref().unwrap_or_default();
+                match children.iter().find(|child| &child.name == name) {
+                    Some(child) => dir = child,
+                    None => break,
+                }
+            }
+            dir
+        }
+
+        fn entries(&self) -> &[Entry] {
+            self.current().children.as_deref().unwrap_or_default()
+        }
+
+        fn selected(&self) -> Option<&Entry> {
+            self.entries().get(self.table.selected()?)
+        }
+
+        /// Moves the selection by `delta` rows, staying within the list
+        fn select_by(&mut self, delta: isize) {
+            let last = self.entries().len().saturating_sub(1);
+            let index = self.table.selected().unwrap_or(0);
+            let index = index.saturating_add_signed(delta).min(last);
+            self.table.select(Some(index));
+        }
+
+        fn open_selected(&mut self) {
+            if let Some(entry) = self.selected().filter(|entry| entry.is_dir()) {
+                self.open.push(entry.name.clone());
+                self.table.select(Some(0));
+            }
+        }
+
+        /// Goes up a directory, selecting the one that was open
+        fn close(&mut self) {
+            if let Some(name) = self.open.pop() {
+                self.select_named(&name);
+            }
+        }
+
+        /// Sorts by `by`, or reverses the order if already sorted by it
+        fn sort_by(&mut self, by: SortBy) {
+            self.descending = if self.sort == by {
+                !self.descending
+            } else {
+                // Biggest first, names A-Z
+                by != SortBy::Name
+            };
+            self.sort = by;
+            let selected = self.selected().map(|entry| entry.name.clone());
+            self.root.sort(self.sort, self.descending);
+            if let Some(name) = selected {
+                self.select_named(&name);
+            }
+        }
+
+        fn select_named(&mut self, name: &str) {
+            let index = self.entries().iter().position(|entry| entry.name == name);
+            self.table.select(Some(index.unwrap_or(0)));
+        }
+
+        /// Tokenizes the selected file for the preview, if not yet done
+        fn update_preview(&mut self) {
+            let Some(entry) = self.selected().filter(|entry| !entry.is_dir()) else {
+                return;
+            };
+            if self
+                .preview
+                .as_ref()
+                .is_some_and(|(path, _)| path == &entry.path)
+            {
+                return;
+            }
+            let text = match &entry.error {
+                Some(error) => Text::raw(error.clone()),
+                None => read_document_text(&entry.path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| highlighted(&text, self.tokenizer))
+                    .unwrap_or_else(|e| Text::raw(format!("{:#}", e))),
+            };
+            self.preview = Some((entry.path.clone(), text));
+            self.scroll = 0;
+        }
+
+        fn draw(&mut self, frame: &mut Frame) {
+            let [header, body, footer] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+            let [list, preview] =
+                Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                    .areas(body);
+
+            let current = self.current();
+            let title = format!(
+                " {}  {} tokens in {} files",
+                current.path.display(),
+                current.stats.tokens,
+                current.files
+            );
+            frame.render_widget(
+                Paragraph::new(title).style(Style::new().add_modifier(Modifier::REVERSED)),
+                header,
+            );
+
+            let arrow = if self.descending { "↓" } else { "↑" };
+            let heading = |label: &str, by: SortBy| match self.sort == by {
+                true => format!("{}{}", label, arrow),
+                false => label.to_string(),
+            };
+            let header_row = Row::new([
+                Cell::from(format!("{:>10}", heading("tokens", SortBy::Tokens))),
+                Cell::from(format!("{:>10}", heading("bytes", SortBy::Bytes))),
+                Cell::from("share"),
+                Cell::from(heading("name", SortBy::Name)),
+            ])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+            let total = current.stats.tokens.max(1);
+            let rows: Vec<Row> = self
+                .entries()
+                .iter()
+                .map(|entry| {
+                    let share = entry.stats.tokens as f64 / total as f64;
+                    let filled = (share * BAR_WIDTH as f64).round() as usize;
+                    let bar = format!(
+                        "{:>5.1}% {}{}",
+                        share * 100.0,
+                        "#".repeat(filled),
+                        " ".repeat(BAR_WIDTH - filled)
+                    );
+                    let tokens = match &entry.error {
+                        Some(_) => format!("{:>10}", "error"),
+                        None => format!("{:>10}", entry.stats.tokens),
+                    };
+                    let name = match entry.is_dir() {
+                        true => format!("{}/", entry.name),
+                        false => entry.name.clone(),
+                    };
+                    Row::new([
+                        Cell::from(tokens),
+                        Cell::from(format!(
+                            "{:>10}",
+                            HumanBytes(entry.stats.bytes as u64).to_string()
+                        )),
+                        Cell::from(bar),
+                        Cell::from(name),
+                    ])
+                })
+                .collect();
+            let table = Table::new(
+                rows,
+                [
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                    Constraint::Length(7 + BAR_WIDTH as u16),
+                    Constraint::Min(10),
+                ],
+            )
+            .header(header_row)
+            .block(Block::bordered())
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(table, list, &mut self.table);
+
+            let (title, text) = match self.selected() {
+                Some(entry) if entry.is_dir() => (
+                    format!(" {}/ ", entry.name),
+                    Text::raw(format!(
+                        "{} tokens\n{} lines\n{}\n{} files",
+                        entry.stats.tokens,
+                        entry.stats.lines,
+                        HumanBytes(entry.stats.bytes as u64),
+                        entry.files
+                    )),
+                ),
+                Some(entry) => {
+                    let text = match &self.preview {
+                        Some((path, text)) if path == &entry.path => text.clone(),
+                        _ => Text::default(),
+                    };
+                    let title = match entry.stats.bytes > PREVIEW_BYTES {
+                        true => format!(
+                            " {} (first {}) ",
+                            entry.name,
+                            HumanBytes(PREVIEW_BYTES as u64)
+                        ),
+                        false => format!(" {} ", entry.name),
+                    };
+                    (title, text)
+                }
+                None => (String::new(), Text::raw("(no files)")),
+            };
+            frame.render_widget(
+                Paragraph::new(text)
+                    .block(Block::bordered().title(title))
+                    .wrap(Wrap { trim: false })
+                    .scroll((self.scroll, 0)),
+                preview,
+            );
+
+            frame.render_widget(
+                Paragraph::new(
+                    " ↑↓ select  → open  ← back  t/b/n sort by tokens/bytes/name  PgUp/PgDn scroll  q quit",
+                )
+                .style(Style::new().add_modifier(Modifier::REVERSED)),
+                footer,
+            );
+        }
+    }
+
+    /// The start of a file's text with alternating background colors per
+    /// token, as `--highlight` prints it
+    fn highlighted(text: &str, tokenizer: &Tokenizer) -> Result<Text<'static>> {
+        let mut cut = text.len().min(PREVIEW_BYTES);
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        let text = &text[..cut];
+
+        let mut lines = vec![Line::default()];
+        let mut end = 0;
+        for (span, color) in highlight_spans(text, tokenizer)? {
+            push_text(&mut lines, &text[end..span.start], Style::new());
+            let style = Style::new().fg(Color::Black).bg(Color::Indexed(color));
+            push_text(&mut lines, &text[span.clone()], style);
+            end = span.end;
+        }
+        push_text(&mut lines, &text[end..], Style::new());
+        Ok(Text::from(lines))
+    }
+
+    /// Appends text to the last line, starting a new line at each newline
+    fn push_text(lines: &mut Vec<Line<'static>>, text: &str, style: Style) {
+        for (index, part) in text.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(Line::default());
+            }
+            // Tabs and carriage returns would throw off the layout
+            let part = part.replace('\t', "    ").replace('\r', "");
+            if !part.is_empty() {
+                let line = lines.last_mut().expect("lines start non-empty");
+                line.push_span(Span::styled(part, style));
+            }
+        }
+    }
+}
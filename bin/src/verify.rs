@@ -0,0 +1,633 @@
+//! `tc tokenizers verify`: checking that a tokenizer loads, is internally
+//! consistent, and gives text back unchanged
+//!
+//! A subtly corrupted tokenizer file often still loads and then counts
+//! wrong without complaint: a vocab entry moved onto another's ID, a lost
+//! byte token that makes byte-level BPE drop characters, or a special token
+//! that no longer maps to its ID. Each check reports pass, warn, or fail
+//! with what it found; any failure fails the command.
+
+use crate::tokenizer::TokenizerArgs;
+use anyhow::{Context, Result};
+use clap::Args;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use token_counter_lib::byte_level_chars;
+use tokenizers::{AddedToken, Model, ModelWrapper, Tokenizer};
+
+/// Text round-tripped by every verification: prose, code, whitespace,
+/// numbers, and scripts beyond ASCII
+const SAMPLES: &[&str] = &[
+    "The quick brown fox jumps over the lazy dog.",
+    "fn main() {\n    println!(\"{}\", 1 + 2);\n}\n",
+    "  leading and trailing spaces  \n\n\ttabs\r\nand CRLF\r\n",
+    "3.14159, 2,718; $1,000.00 (50% off!) #42 @user <tag/> a_b-c",
+    "naïve café, Øresund, Straße",
+    "日本語のテキストと中文文本",
+    "Привет, мир! مرحبا بالعالم",
+    "👋🏽 family: 👨‍👩‍👧",
+];
+
+/// Failures of a check listed before the rest are summed up
+const SHOWN: usize = 3;
+
+/// Check a tokenizer for corruption
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Tokenizer to check: a name as for -n, or a path as for -t
+    #[arg(value_name = "NAME|PATH")]
+    tokenizer: String,
+
+    /// Also round-trip the text of FILE (repeatable)
+    #[arg(long, value_name = "FILE")]
+    corpus: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        })
+    }
+}
+
+/// The outcome of one check
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+
+    /// Passes, or fails with `problems` (the first few, and how many more)
+    fn from_problems(name: &'static str, problems: &[String], passed: impl Into<String>) -> Self {
+        if problems.is_empty() {
+            return Self::new(name, Status::Pass, passed);
+        }
+        let mut detail = problems[..problems.len().min(SHOWN)].join("; ");
+        if problems.len() > SHOWN {
+            detail += &format!("; and {} more", problems.len() - SHOWN);
+        }
+        Self::new(name, Status::Fail, detail)
+    }
+}
+
+pub fn run(args: &VerifyArgs) -> Result<ExitCode> {
+    let selection = if Path::new(&args.tokenizer).exists() {
+        TokenizerArgs {
+            tokenizer_path: Some(PathBuf::from(&args.tokenizer)),
+            tokenizer_name: None,
+        }
+    } else {
+        TokenizerArgs {
+            tokenizer_path: None,
+            tokenizer_name: Some(args.tokenizer.clone()),
+        }
+    };
+
+    let mut samples: Vec<String> = SAMPLES.iter().map(|sample| sample.to_string()).collect();
+    for path in &args.corpus {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        samples.push(String::from_utf8_lossy(&bytes).into_owned());
+    }
+
+    let checks = checks(selection.load(), &samples)?;
+
+    for check in &checks {
+        println!("{}  {:<15} {}", check.status, check.name, check.detail);
+    }
+    let count = |status| checks.iter().filter(|check| check.status == status).count();
+    let (failed, warned) = (count(Status::Fail), count(Status::Warn));
+    if failed > 0 {
+        println!(
+            "{}: FAIL ({} of {} checks failed)",
+            args.tokenizer,
+            failed,
+            checks.len()
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+    match warned {
+        0 => println!("{}: ok", args.tokenizer),
+        _ => println!(
+            "{}: ok ({} warning{})",
+            args.tokenizer,
+            warned,
+            if warned == 1 { "" } else { "s" }
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The checks of a tokenizer, or of why it failed to load
+fn checks(loaded: Result<Tokenizer>, samples: &[String]) -> Result<Vec<Check>> {
+    match loaded {
+        Ok(tokenizer) => verify(&tokenizer, samples),
+        Err(e) => Ok(vec![Check::new("load", Status::Fail, format!("{:#}", e))]),
+    }
+}
+
+/// Runs every check on a loaded tokenizer
+fn verify(tokenizer: &Tokenizer, samples: &[String]) -> Result<Vec<Check>> {
+    let (model_type, unk) = match tokenizer.get_model() {
+        ModelWrapper::BPE(bpe) => ("BPE", bpe.unk_token.clone()),
+        ModelWrapper::WordPiece(wordpiece) => ("WordPiece", Some(wordpiece.unk_token.clone())),
+        ModelWrapper::WordLevel(wordlevel) => ("WordLevel", Some(wordlevel.unk_token.clone())),
+        ModelWrapper::Unigram(unigram) => (
+            "Unigram",
+            serde_json::to_value(unigram)?["unk_id"]
+                .as_u64()
+                .and_then(|id| unigram.id_to_token(u32::try_from(id).ok()?)),
+        ),
+    };
+    // The serialized pipeline shows what the API does not: whether text is
+    // split into bytes, and the special tokens the post-processor adds
+    let pre_tokenizer = serde_json::to_value(tokenizer.get_pre_tokenizer())?;
+    let decoder = serde_json::to_value(tokenizer.get_decoder())?;
+    let post_processor = serde_json::to_value(tokenizer.get_post_processor())?;
+    let vocab = tokenizer.get_vocab(false);
+    let added = tokenizer.get_added_tokens_decoder();
+
+    let mut checks = vec![Check::new(
+        "load",
+        Status::Pass,
+        format!(
+            "{} model, {} tokens, {} added",
+            model_type,
+            vocab.len(),
+            added.len()
+        ),
+    )];
+    checks.push(check_vocab(tokenizer, &vocab));
+    if is_byte_level(&pre_tokenizer) || is_byte_level(&decoder) {
+        checks.push(check_bytes(&vocab));
+    }
+    checks.push(check_special_tokens(
+        tokenizer,
+        &added,
+        &post_processor_tokens(&post_processor),
+        unk.as_deref(),
+    ));
+    let unk = unk.and_then(|unk| tokenizer.token_to_id(&unk));
+    checks.push(check_round_trip(
+        tokenizer,
+        samples,
+        is_lossless(&decoder),
+        unk,
+    )?);
+    Ok(checks)
+}
+
+/// Every vocab entry's ID maps back to it, and IDs run without gaps
+fn check_vocab(tokenizer: &Tokenizer, vocab: &HashMap<String, u32>) -> Check {
+    let model = tokenizer.get_model();
+    let mut problems = Vec::new();
+    let mut entries: Vec<(&String, &u32)> = vocab.iter().collect();
+    entries.sort_by_key(|(_, id)| **id);
+    for (token, id) in &entries {
+        match model.id_to_token(**id) {
+            Some(back) if back == **token => {}
+            Some(back) => problems.push(format!(
+                "{:?} and {:?} share ID {}",
+                token.as_str(),
+                back,
+                id
+            )),
+            None => problems.push(format!(
+                "{:?} has ID {}, which decodes to nothing",
+                token, id
+            )),
+        }
+    }
+    if !problems.is_empty() {
+        return Check::from_problems("vocab", &problems, "");
+    }
+
+    let ids: BTreeSet<u32> = vocab.values().copied().collect();
+    let highest = ids.last().copied().unwrap_or(0);
+    let gaps = (highest as usize + 1).saturating_sub(ids.len());
+    if gaps > 0 {
+        return Check::new(
+            "vocab",
+            Status::Warn,
+            format!(
+                "{} IDs up to {}, with {} unused below it",
+                ids.len(),
+                highest,
+                gaps
+            ),
+        );
+    }
+    Check::new(
+        "vocab",
+        Status::Pass,
+        format!("{} IDs, 0 to {}", ids.len(), highest),
+    )
+}
+
+/// Byte-level BPE has a token for each of the 256 bytes; text with a byte
+/// whose token is missing is silently dropped
+fn check_bytes(vocab: &HashMap<String, u32>) -> Check {
+    let missing: Vec<String> = byte_level_chars()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, c)| !vocab.contains_key(&c.to_string()))
+        .map(|(byte, c)| format!("byte 0x{:02x} ({:?}) has no token", byte, c))
+        .collect();
+    Check::from_problems("bytes", &missing, "all 256 bytes have tokens")
+}
+
+/// Added tokens, the post-processor's special tokens, and the unknown token
+/// map to the IDs they claim
+fn check_special_tokens(
+    tokenizer: &Tokenizer,
+    added: &HashMap<u32, AddedToken>,
+    specials: &[(String, u32)],
+    unk: Option<&str>,
+) -> Check {
+    let model = tokenizer.get_model();
+    let mut problems = Vec::new();
+    let mut ids: Vec<&u32> = added.keys().collect();
+    ids.sort();
+    for id in ids {
+        let token = &added[id].content;
+        if tokenizer.token_to_id(token) != Some(*id) {
+            problems.push(format!(
+                "added token {:?} has ID {}, but it {}",
+                token,
+                id,
+                maps_to(tokenizer, token)
+            ));
+        }
+        if let Some(vocab_token) = model.id_to_token(*id).filter(|vocab| vocab != token) {
+            problems.push(format!(
+                "ID {} is both {:?} in the vocab and added token {:?}",
+                id, vocab_token, token
+            ));
+        }
+        if added[id].special {
+            let encoded = tokenizer
+                .encode(token.as_str(), false)
+                .map(|encoding| encoding.get_ids().to_vec());
+            if !matches!(&encoded, Ok(encoded) if encoded == &[*id]) {
+                problems.push(format!(
+                    "special token {:?} does not encode to its ID {}",
+                    token, id
+                ));
+            }
+        }
+    }
+
+    for (token, id) in specials {
+        if tokenizer.token_to_id(token) != Some(*id) {
+            problems.push(format!(
+                "post-processor adds {:?} as ID {}, but it {}",
+                token,
+                id,
+                maps_to(tokenizer, token)
+            ));
+        }
+    }
+    if let Some(unk) = unk {
+        if tokenizer.token_to_id(unk).is_none() {
+            problems.push(format!("unknown token {:?} is not in the vocab", unk));
+        }
+    }
+
+    Check::from_problems(
+        "special tokens",
+        &problems,
+        format!(
+            "{} added, {} added by the post-processor",
+            added.len(),
+            specials.len()
+        ),
+    )
+}
+
+/// What `token` maps to, for messages about it mapping elsewhere
+fn maps_to(tokenizer: &Tokenizer, token: &str) -> String {
+    match tokenizer.token_to_id(token) {
+        Some(id) => format!("maps to {}", id),
+        None => "is not in the vocab".to_string(),
+    }
+}
+
+/// Each sample decodes back to its text
+///
+/// Tokenizers that normalize text (lowercasing, stripping accents) cannot
+/// give it back unchanged, so for them the decoded text need only encode to
+/// the same tokens again, except where the unknown token stood.
+fn check_round_trip(
+    tokenizer: &Tokenizer,
+    samples: &[String],
+    lossless: bool,
+    unk: Option<u32>,
+) -> Result<Check> {
+    let encode = |text: &str| -> Result<Vec<u32>> {
+        Ok(tokenizer
+            .encode(text, false)
+            .map_err(|e| anyhow::anyhow!("{}", e))?
+            .get_ids()
+            .to_vec())
+    };
+
+    let (mut exact, mut problems, mut unknown, mut lost) = (0, Vec::new(), 0, 0);
+    for sample in samples {
+        let ids = encode(sample)?;
+        let has_unknown = unk.is_some_and(|unk| ids.contains(&unk));
+        if sample.is_ascii() && has_unknown {
+            unknown += 1;
+        }
+        let decoded = tokenizer
+            .decode(&ids, false)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        if decoded == *sample {
+            exact += 1;
+            continue;
+        }
+        if !lossless {
+            let again = encode(&decoded)?;
+            let same = match unk {
+                Some(unk) if has_unknown => same_but_unknown(&ids, &again, unk),
+                _ => again == ids,
+            };
+            if same {
+                if has_unknown {
+                    lost += 1;
+                }
+                continue;
+            }
+        }
+        problems.push(format!(
+            "{:?} came back as {:?}",
+            excerpt(sample),
+            excerpt(&decoded)
+        ));
+    }
+
+    if !problems.is_empty() {
+        let mut check = Check::from_problems("round trip", &problems, "");
+        check.detail = format!(
+            "{} of {} samples differ: {}",
+            problems.len(),
+            samples.len(),
+            check.detail
+        );
+        return Ok(check);
+    }
+    if unknown > 0 {
+        return Ok(Check::new(
+            "round trip",
+            Status::Warn,
+            format!(
+                "{} of {} ASCII samples have unknown tokens",
+                unknown,
+                samples.iter().filter(|sample| sample.is_ascii()).count()
+            ),
+        ));
+    }
+    let mut detail = if exact + lost == samples.len() {
+        format!("{} samples decode unchanged", exact)
+    } else {
+        format!(
+            "{} samples decode unchanged, {} up to normalization",
+            exact,
+            samples.len() - exact - lost
+        )
+    };
+    if lost > 0 {
+        detail += &format!(", {} with unknown tokens", lost);
+    }
+    Ok(Check::new("round trip", Status::Pass, detail))
+}
+
+/// Whether `again` holds the tokens of `ids` in order, with anything in
+/// place of each `unk`, whose text cannot come back
+fn same_but_unknown(ids: &[u32], again: &[u32], unk: u32) -> bool {
+    let mut runs = ids.split(|&id| id == unk);
+    let first = runs.next().unwrap_or_default();
+    let Some(mut rest) = again.strip_prefix(first) else {
+        return false;
+    };
+    let mut runs = runs.peekable();
+    while let Some(run) = runs.next() {
+        if runs.peek().is_none() {
+            return rest.ends_with(run);
+        }
+        if run.is_empty() {
+            continue;
+        }
+        match rest.windows(run.len()).position(|window| window == run) {
+            Some(start) => rest = &rest[start + run.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// Whether `decoder` restores the exact bytes encoded, as byte-level and
+/// byte-fallback decoders do
+fn is_lossless(decoder: &Value) -> bool {
+    match decoder["type"].as_str() {
+        Some("ByteLevel" | "ByteFallback") => true,
+        Some("Sequence") => decoder["decoders"]
+            .as_array()
+            .is_some_and(|decoders| decoders.iter().any(is_lossless)),
+        _ => false,
+    }
+}
+
+/// Whether a serialized pre-tokenizer or decoder works on GPT-2 style byte
+/// characters
+fn is_byte_level(component: &Value) -> bool {
+    match component["type"].as_str() {
+        Some("ByteLevel") => true,
+        Some("Sequence") => ["pretokenizers", "decoders"].iter().any(|key| {
+            component[*key]
+                .as_array()
+                .is_some_and(|parts| parts.iter().any(is_byte_level))
+        }),
+        _ => false,
+    }
+}
+
+/// The special tokens a post-processor adds, with their IDs
+fn post_processor_tokens(processor: &Value) -> Vec<(String, u32)> {
+    let id = |value: &Value| value.as_u64().and_then(|id| u32::try_from(id).ok());
+    match processor["type"].as_str() {
+        Some("TemplateProcessing") => processor["special_tokens"]
+            .as_object()
+            .into_iter()
+            .flat_map(|tokens| tokens.values())
+            .flat_map(|special| {
+                let tokens = special["tokens"].as_array().cloned().unwrap_or_default();
+                let ids = special["ids"].as_array().cloned().unwrap_or_default();
+                tokens
+                    .into_iter()
+                    .zip(ids)
+                    .filter_map(|(token, value)| Some((token.as_str()?.to_string(), id(&value)?)))
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        Some("BertProcessing" | "RobertaProcessing") => ["cls", "sep"]
+            .iter()
+            .filter_map(|key| {
+                let pair = &processor[*key];
+                Some((pair[0].as_str()?.to_string(), id(&pair[1])?))
+            })
+            .collect(),
+        Some("Sequence") => processor["processors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(post_processor_tokens)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The start of `text`, for messages
+fn excerpt(text: &str) -> String {
+    const CHARS: usize = 40;
+    match text.char_indices().nth(CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token_counter_lib::load_tokenizer_from_bytes;
+
+    /// A WordPiece tokenizer whose decoder strips `prefix` from word pieces
+    fn wordpiece(vocab: &str, prefix: &str) -> String {
+        format!(
+            r###"{{
+                "version": "1.0",
+                "truncation": null,
+                "padding": null,
+                "added_tokens": [{{
+                    "id": 0, "content": "[UNK]", "single_word": false, "lstrip": false,
+                    "rstrip": false, "normalized": false, "special": true
+                }}],
+                "normalizer": null,
+                "pre_tokenizer": {{ "type": "BertPreTokenizer" }},
+                "post_processor": null,
+                "decoder": {{ "type": "WordPiece", "prefix": "{}", "cleanup": true }},
+                "model": {{
+                    "type": "WordPiece",
+                    "unk_token": "[UNK]",
+                    "continuing_subword_prefix": "##",
+                    "max_input_chars_per_word": 100,
+                    "vocab": {}
+                }}
+            }}"###,
+            prefix, vocab
+        )
+    }
+
+    const VOCAB: &str =
+        r###"{ "[UNK]": 0, "hel": 1, "##lo": 2, "world": 3, "#": 4, "lo": 5, "!": 6 }"###;
+
+    /// A BPE tokenizer of "a", "b", and their merge in `merge`'s order
+    fn bpe(merge: &str) -> String {
+        format!(
+            r#"{{
+                "version": "1.0",
+                "added_tokens": [],
+                "normalizer": null,
+                "pre_tokenizer": {{ "type": "Whitespace" }},
+                "post_processor": null,
+                "decoder": null,
+                "model": {{
+                    "type": "BPE",
+                    "vocab": {{ "a": 0, "b": 1, "ab": 2 }},
+                    "merges": ["{}"]
+                }}
+            }}"#,
+            merge
+        )
+    }
+
+    fn run_checks(json: &str, samples: &[&str]) -> Vec<Check> {
+        let samples: Vec<String> = samples.iter().map(|sample| sample.to_string()).collect();
+        let loaded = load_tokenizer_from_bytes(json.as_bytes()).map_err(anyhow::Error::from);
+        checks(loaded, &samples).unwrap()
+    }
+
+    fn failed(checks: &[Check]) -> Vec<&'static str> {
+        checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .map(|check| check.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_sound_tokenizers_pass() {
+        let checks = run_checks(&wordpiece(VOCAB, "##"), &["hello world!", "hello ☃"]);
+        assert_eq!(failed(&checks), Vec::<&str>::new());
+        let round_trip = checks.iter().find(|check| check.name == "round trip");
+        assert!(round_trip.unwrap().detail.contains("1 with unknown tokens"));
+
+        assert_eq!(
+            failed(&run_checks(&bpe("a b"), &["ab ba"])),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn test_duplicated_vocab_id_fails() {
+        let vocab = VOCAB.replace(r#""lo": 5"#, r#""lo": 3"#);
+        let checks = run_checks(&wordpiece(&vocab, "##"), &["hello world!"]);
+        assert!(failed(&checks).contains(&"vocab"));
+    }
+
+    #[test]
+    fn test_swapped_merge_fails() {
+        let checks = run_checks(&bpe("b a"), &["ab"]);
+        assert_eq!(failed(&checks), ["load"]);
+    }
+
+    #[test]
+    fn test_unknown_tokens_do_not_hide_corruption() {
+        // A decoder that no longer joins word pieces garbles the rest of a
+        // sample that also has an unknown token
+        let checks = run_checks(&wordpiece(VOCAB, "@@"), &["hello ☃"]);
+        assert_eq!(failed(&checks), ["round trip"]);
+    }
+
+    #[test]
+    fn test_same_but_unknown() {
+        assert!(same_but_unknown(&[1, 2], &[1, 2], 0));
+        assert!(!same_but_unknown(&[1, 2], &[1, 3], 0));
+        assert!(same_but_unknown(&[1, 0, 2], &[1, 7, 8, 2], 0));
+        assert!(same_but_unknown(&[0, 1, 0], &[5, 1], 0));
+        assert!(!same_but_unknown(&[1, 0, 2], &[1, 7, 3], 0));
+        assert!(!same_but_unknown(&[1, 2, 0], &[1, 4, 4, 5, 0], 0));
+        assert!(!same_but_unknown(&[1, 0, 2, 0, 3], &[1, 3, 2], 0));
+    }
+}
@@ -0,0 +1,402 @@
+//! `-r`: expanding directories into the files beneath them
+
+use crate::cloud;
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Compiles `--exclude` globs
+pub fn exclude_set(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob).with_context(|| format!("Invalid exclude glob '{}'", glob))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// How `-r` treats the symbolic links it finds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Count linked files but skip linked directories
+    #[default]
+    Files,
+    /// Follow linked directories too (`--follow-symlinks`)
+    Follow,
+    /// Skip every link (`--no-follow`)
+    Skip,
+}
+
+/// What `-r` keeps while walking
+#[derive(Debug, Default)]
+pub struct WalkOptions {
+    /// Entries to skip, matched by their path below the directory given or
+    /// by their name alone (`--exclude`)
+    pub exclude: GlobSet,
+    pub symlinks: Symlinks,
+    /// Most levels below a directory given to keep files from
+    /// (`--max-depth`; 1 for the files directly in it)
+    pub max_depth: Option<usize>,
+    /// Keep dotfiles and dot-directories other than `.git` (`--hidden`)
+    pub hidden: bool,
+}
+
+/// Expands the directories among `paths` into the files beneath them
+///
+/// Files come out in path order within each directory, and the files
+/// links lead to after all the others. Hidden entries (dotfiles and
+/// dot-directories) found while walking are skipped unless
+/// `options.hidden` is set, and so are the symbolic links
+/// `options.symlinks` says to skip; paths given explicitly are always
+/// kept. Each file or directory is counted once however many links lead to
+/// it, under its real path if the walk finds it, and a link back to a
+/// directory it is in is never followed. Entries
+/// matching `options.exclude` or deeper than `options.max_depth` are
+/// skipped too. Directories that cannot be read are reported and skipped,
+/// and skipped links are summed up on stderr.
+///
+/// `s3://` and `gs://` prefixes expand into the objects under them, in key
+/// order and filtered the same way; a URL naming a single object is kept.
+pub fn expand(paths: &[PathBuf], options: &WalkOptions) -> Vec<PathBuf> {
+    let mut walker = Walker::new(options);
+    let files = walker.expand(paths);
+    walker.report_skipped();
+    files
+}
+
+/// Whether an entry named `name` is hidden from the walk: a dotfile or
+/// dot-directory, unless `hidden` asks for those, and always `.git`
+fn is_hidden(name: &OsStr, hidden: bool) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with('.') && (!hidden || name == ".git")
+}
+
+fn list_objects(prefix: &Path, options: &WalkOptions, files: &mut Vec<PathBuf>) {
+    let prefix = prefix.to_string_lossy();
+    let objects = match cloud::list(&prefix) {
+        Ok(objects) => objects,
+        Err(e) => {
+            eprintln!("tc: {}: {}", prefix, e);
+            return;
+        }
+    };
+    if objects.is_empty() {
+        files.push(PathBuf::from(prefix.as_ref()));
+        return;
+    }
+
+    for object in objects {
+        let relative = object[prefix.len()..].trim_start_matches('/');
+        let relative = Path::new(relative);
+        let skip = relative.iter().find_map(|name| {
+            if is_hidden(name, options.hidden) {
+                Some("hidden")
+            } else if options.exclude.is_match(name) {
+                Some("excluded")
+            } else {
+                None
+            }
+        });
+        let too_deep = options
+            .max_depth
+            .is_some_and(|max_depth| relative.iter().count() > max_depth);
+        let skip = skip
+            .or_else(|| options.exclude.is_match(relative).then_some("excluded"))
+            .or_else(|| too_deep.then_some("deeper than --max-depth"));
+        match skip {
+            Some(reason) => info!("skipping {} ({})", object, reason),
+            None => files.push(PathBuf::from(object)),
+        }
+    }
+}
+
+/// A directory walk's settings, and what it has found so far
+struct Walker<'a> {
+    options: &'a WalkOptions,
+    /// Canonical paths of the files and directories found
+    seen: HashSet<PathBuf>,
+    /// Links skipped, by why
+    skipped: BTreeMap<LinkSkip, usize>,
+    /// Links found but not yet followed, which wait until every directory
+    /// has been walked so that what they lead to is found by its real path
+    links: VecDeque<Link>,
+}
+
+/// A link found while walking, to follow once the walk is done
+struct Link {
+    root: PathBuf,
+    path: PathBuf,
+    /// The directory the link is in, with links resolved
+    dir: PathBuf,
+    depth: usize,
+}
+
+/// Why a link was skipped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum LinkSkip {
+    Broken,
+    ToFile,
+    ToDirectory,
+    Loop,
+    /// Its target was already found
+    Duplicate,
+}
+
+impl LinkSkip {
+    /// Why a path was skipped, for `-v`
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Broken => "broken symlink",
+            Self::ToFile => "symlink to a file",
+            Self::ToDirectory => "symlink to a directory",
+            Self::Loop => "symlink loop",
+            Self::Duplicate => "already counted",
+        }
+    }
+
+    /// The links skipped for this reason, in the summary
+    fn plural(self) -> &'static str {
+        match self {
+            Self::Broken => "broken",
+            Self::ToFile => "to files",
+            Self::ToDirectory => "to directories",
+            Self::Loop => "loops",
+            Self::Duplicate => "already counted",
+        }
+    }
+}
+
+impl<'a> Walker<'a> {
+    fn new(options: &'a WalkOptions) -> Self {
+        Self {
+            options,
+            seen: HashSet::new(),
+            skipped: BTreeMap::new(),
+            links: VecDeque::new(),
+        }
+    }
+
+    /// [`expand`], without the summary of skipped links
+    fn expand(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for path in paths {
+            if cloud::is_object_url(path) {
+                list_objects(path, self.options, &mut files);
+            } else if path.is_dir() {
+                let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                self.seen.insert(canonical.clone());
+                self.walk(path, path, &canonical, 1, &mut files);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        while let Some(link) = self.links.pop_front() {
+            self.walk_link(&link.root, &link.path, &link.dir, link.depth, &mut files);
+        }
+        files
+    }
+
+    /// Walks `dir`, whose path with links resolved is `canonical` and whose
+    /// entries are `depth` levels below `root`
+    fn walk(
+        &mut self,
+        root: &Path,
+        dir: &Path,
+        canonical: &Path,
+        depth: usize,
+        files: &mut Vec<PathBuf>,
+    ) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("tc: {}: {}", dir.display(), e);
+                return;
+            }
+        };
+
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if is_hidden(&entry.file_name(), self.options.hidden) {
+                info!("skipping {} (hidden)", path.display());
+                continue;
+            }
+            let exclude = &self.options.exclude;
+            if exclude.is_match(relative) || exclude.is_match(entry.file_name()) {
+                info!("skipping {} (excluded)", path.display());
+                continue;
+            }
+            let target = canonical.join(entry.file_name());
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => self.links.push_back(Link {
+                    root: root.to_path_buf(),
+                    path,
+                    dir: canonical.to_path_buf(),
+                    depth,
+                }),
+                // Found already under another directory given; not a link
+                _ if !self.seen.insert(target.clone()) => {
+                    info!("skipping {} (already counted)", path.display());
+                }
+                Ok(file_type) if file_type.is_dir() => {
+                    self.descend(root, &path, &target, depth, files);
+                }
+                _ => files.push(path),
+            }
+        }
+    }
+
+    /// Counts or walks what the link at `path`, in the directory whose
+    /// path with links resolved is `dir`, leads to, as `symlinks` says
+    fn walk_link(
+        &mut self,
+        root: &Path,
+        path: &Path,
+        dir: &Path,
+        depth: usize,
+        files: &mut Vec<PathBuf>,
+    ) {
+        let Ok(target) = fs::canonicalize(path) else {
+            return self.skip(path, LinkSkip::Broken);
+        };
+        let is_dir = target.is_dir();
+        match self.options.symlinks {
+            Symlinks::Skip if is_dir => return self.skip(path, LinkSkip::ToDirectory),
+            Symlinks::Skip => return self.skip(path, LinkSkip::ToFile),
+            Symlinks::Files if is_dir => return self.skip(path, LinkSkip::ToDirectory),
+            _ => {}
+        }
+        if is_dir && dir.starts_with(&target) {
+            return self.skip(path, LinkSkip::Loop);
+        }
+        if !self.seen.insert(target.clone()) {
+            return self.skip(path, LinkSkip::Duplicate);
+        }
+        if is_dir {
+            self.descend(root, path, &target, depth, files);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    /// Walks the directory at `path`, found `depth` levels below `root`,
+    /// unless that is as deep as `--max-depth` goes
+    fn descend(
+        &mut self,
+        root: &Path,
+        path: &Path,
+        canonical: &Path,
+        depth: usize,
+        files: &mut Vec<PathBuf>,
+    ) {
+        if self
+            .options
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            info!("skipping {} (deeper than --max-depth)", path.display());
+            return;
+        }
+        self.walk(root, path, canonical, depth + 1, files);
+    }
+
+    fn skip(&mut self, path: &Path, reason: LinkSkip) {
+        info!("skipping {} ({})", path.display(), reason.describe());
+        *self.skipped.entry(reason).or_default() += 1;
+    }
+
+    /// Prints how many links were skipped and why, e.g. "skipped 3
+    /// symlinks (loops: 1, to directories: 2)"
+    fn report_skipped(&self) {
+        let total: usize = self.skipped.values().sum();
+        if total == 0 {
+            return;
+        }
+        let reasons: Vec<_> = self
+            .skipped
+            .iter()
+            .map(|(reason, count)| format!("{}: {}", reason.plural(), count))
+            .collect();
+        eprintln!(
+            "tc: skipped {} symlink{} ({}); -v lists them",
+            total,
+            if total == 1 { "" } else { "s" },
+            reasons.join(", ")
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_link_walked_before_its_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a_link_parent")).unwrap();
+        fs::create_dir_all(root.join("vendor")).unwrap();
+        fs::write(root.join("vendor/x.txt"), "x").unwrap();
+        fs::write(root.join("vendor/y.txt"), "y").unwrap();
+        symlink("../vendor", root.join("a_link_parent/v")).unwrap();
+        symlink("vendor/x.txt", root.join("b_link")).unwrap();
+
+        let options = WalkOptions {
+            symlinks: Symlinks::Follow,
+            ..WalkOptions::default()
+        };
+        let mut walker = Walker::new(&options);
+        let files = walker.expand(&[root.to_path_buf()]);
+        // The real paths are counted, and only the two links are skipped
+        assert_eq!(
+            files,
+            [root.join("vendor/x.txt"), root.join("vendor/y.txt")]
+        );
+        assert_eq!(
+            walker.skipped.into_iter().collect::<Vec<_>>(),
+            [(LinkSkip::Duplicate, 2)]
+        );
+    }
+
+    #[test]
+    fn test_link_to_unwalked_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let (walked, outside) = (dir.path().join("walked"), dir.path().join("outside"));
+        fs::create_dir_all(&walked).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(walked.join("a.txt"), "a").unwrap();
+        fs::write(outside.join("b.txt"), "b").unwrap();
+        symlink(&outside, walked.join("link")).unwrap();
+        symlink(&walked, walked.join("loop")).unwrap();
+
+        let options = WalkOptions {
+            symlinks: Symlinks::Follow,
+            ..WalkOptions::default()
+        };
+        let mut walker = Walker::new(&options);
+        let files = walker.expand(std::slice::from_ref(&walked));
+        assert_eq!(files, [walked.join("a.txt"), walked.join("link/b.txt")]);
+        assert_eq!(
+            walker.skipped.into_iter().collect::<Vec<_>>(),
+            [(LinkSkip::Loop, 1)]
+        );
+
+        let options = WalkOptions::default();
+        let mut walker = Walker::new(&options);
+        assert_eq!(
+            walker.expand(std::slice::from_ref(&walked)),
+            [walked.join("a.txt")]
+        );
+        assert_eq!(
+            walker.skipped.into_iter().collect::<Vec<_>>(),
+            [(LinkSkip::ToDirectory, 2)]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * tc - C interface to token counting
+ *
+ * Link against libtc_ffi (built by `cargo build --release -p token-counter-ffi`
+ * as a shared and a static library). Functions are thread-safe; a tokenizer
+ * can be shared between threads once loaded.
+ *
+ * Failing calls return NULL or -1, and tc_last_error() then describes the
+ * failure on the calling thread.
+ */
+
+#ifndef TC_H
+#define TC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A loaded tokenizer */
+typedef struct TcTokenizer tc_tokenizer;
+
+/*
+ * Loads a tokenizer from a tokenizer.json file or a model directory.
+ * Returns NULL on failure. Free it with tc_free().
+ */
+tc_tokenizer *tc_load_tokenizer(const char *path);
+
+/*
+ * Counts the tokens in `len` bytes of UTF-8 text (not necessarily
+ * NUL-terminated), without special tokens, as `tc` counts by default.
+ * Returns -1 on failure, e.g. if the text is not valid UTF-8.
+ */
+int64_t tc_count_utf8(const tc_tokenizer *tokenizer, const char *text, size_t len);
+
+/* Frees a tokenizer from tc_load_tokenizer(); NULL is ignored */
+void tc_free(tc_tokenizer *tokenizer);
+
+/*
+ * Describes the last failure on the calling thread, or returns NULL if
+ * there was none. The string is owned by the library and stays valid until
+ * the next failing call on the same thread.
+ */
+const char *tc_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TC_H */
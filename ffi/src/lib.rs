@@ -0,0 +1,152 @@
+//! C interface to token counting
+//!
+//! Exposes `token-counter-lib` to C, C++, Go, and anything else that can call
+//! C functions, so services count tokens exactly as `tc` does without
+//! shelling out. `include/tc.h` declares these functions.
+//!
+//! Functions report failure through their return value (NULL or -1); the
+//! reason is then available from [`tc_last_error`] on the same thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use token_counter_lib::{count_tokens, load_tokenizer};
+use tokenizers::Tokenizer;
+
+/// An opaque loaded tokenizer (`tc_tokenizer` in C)
+pub struct TcTokenizer(Tokenizer);
+
+thread_local! {
+    /// Message of the last error on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, turning errors and panics into `fallback` plus a last error
+fn guard<T>(fallback: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            fallback
+        }
+        Err(_) => {
+            set_last_error("internal error (panic) in tc");
+            fallback
+        }
+    }
+}
+
+/// Loads a tokenizer from a JSON file or model directory
+///
+/// Returns NULL on failure. Free the tokenizer with [`tc_free`].
+///
+/// # Safety
+///
+/// `path` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tc_load_tokenizer(path: *const c_char) -> *mut TcTokenizer {
+    guard(ptr::null_mut(), || {
+        if path.is_null() {
+            return Err("path is NULL".to_string());
+        }
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| "path is not valid UTF-8".to_string())?;
+        let tokenizer = load_tokenizer(path).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(TcTokenizer(tokenizer))))
+    })
+}
+
+/// Counts the tokens in `len` bytes of UTF-8 text
+///
+/// The text need not be NUL-terminated. Returns -1 on failure, e.g. if the
+/// text is not valid UTF-8.
+///
+/// # Safety
+///
+/// `tokenizer` must be NULL or a tokenizer from [`tc_load_tokenizer`] that
+/// has not been freed, and `text` must be NULL or point to `len` readable
+/// bytes (it may be NULL when `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn tc_count_utf8(
+    tokenizer: *const TcTokenizer,
+    text: *const c_char,
+    len: usize,
+) -> i64 {
+    guard(-1, || {
+        let Some(tokenizer) = tokenizer.as_ref() else {
+            return Err("tokenizer is NULL".to_string());
+        };
+        let bytes = match (text.is_null(), len) {
+            (_, 0) => &[][..],
+            (true, _) => return Err("text is NULL".to_string()),
+            (false, _) => std::slice::from_raw_parts(text.cast::<u8>(), len),
+        };
+        let text =
+            std::str::from_utf8(bytes).map_err(|e| format!("text is not valid UTF-8: {}", e))?;
+        let tokens = count_tokens(text, &tokenizer.0, false).map_err(|e| e.to_string())?;
+        i64::try_from(tokens).map_err(|_| "token count overflows int64_t".to_string())
+    })
+}
+
+/// Frees a tokenizer from [`tc_load_tokenizer`]; NULL is ignored
+///
+/// # Safety
+///
+/// `tokenizer` must be NULL or a tokenizer from [`tc_load_tokenizer`] that
+/// has not been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn tc_free(tokenizer: *mut TcTokenizer) {
+    if !tokenizer.is_null() {
+        drop(Box::from_raw(tokenizer));
+    }
+}
+
+/// Message of the last error on the calling thread, or NULL if there was
+/// none
+///
+/// The string belongs to the library and stays valid until the next failing
+/// call on the same thread.
+#[no_mangle]
+pub extern "C" fn tc_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPT2: &CStr = c"../bin/assets/gpt2-tokenizer.json";
+
+    #[test]
+    fn test_ffi_count() {
+        unsafe {
+            let tokenizer = tc_load_tokenizer(GPT2.as_ptr());
+            assert!(!tokenizer.is_null());
+
+            let text = "Hello, world";
+            assert_eq!(
+                tc_count_utf8(tokenizer, text.as_ptr().cast(), text.len()),
+                3
+            );
+            assert_eq!(tc_count_utf8(tokenizer, ptr::null(), 0), 0);
+
+            let invalid = [b'a', 0xff];
+            assert_eq!(tc_count_utf8(tokenizer, invalid.as_ptr().cast(), 2), -1);
+            let error = CStr::from_ptr(tc_last_error()).to_str().unwrap();
+            assert!(error.contains("UTF-8"), "{}", error);
+
+            tc_free(tokenizer);
+            tc_free(ptr::null_mut());
+
+            assert!(tc_load_tokenizer(c"missing.json".as_ptr()).is_null());
+            assert!(tc_load_tokenizer(ptr::null()).is_null());
+        }
+    }
+}
@@ -11,7 +11,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Count tokens in a simple string
     let text = "Hello, world! This is a token counting example.";
-    let count = count_tokens(text, &tokenizer)?;
+    let count = count_tokens(text, &tokenizer, false)?;
     println!("Text: \"{}\"", text);
     println!("Token count: {}\n", count);
 
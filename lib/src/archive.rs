@@ -0,0 +1,179 @@
+//! Reading the members of tar and zip archives without extracting them
+//!
+//! Archives are recognized by extension; compressed tarballs such as
+//! `.tar.gz` are decompressed first (see [`Compression`](crate::Compression)).
+//! Reading archives is behind the `archive` feature.
+
+use crate::{Error, Result};
+use std::path::Path;
+
+/// Archive formats whose members can be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// tar, possibly compressed (`.tar`, `.tar.gz`, `.tgz`, ...)
+    Tar,
+    /// zip (`.zip`)
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detects an archive format from a file name
+    ///
+    /// Only extensions are used: many document formats (DOCX, EPUB, JAR)
+    /// are zip files underneath and are counted as documents, not archives.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        let (stem, extension) = name.rsplit_once('.')?;
+        match extension {
+            "zip" => Some(Self::Zip),
+            "tar" | "tgz" | "tbz" | "tbz2" | "txz" | "tzst" => Some(Self::Tar),
+            "gz" | "zst" | "xz" | "bz2" if stem.ends_with(".tar") => Some(Self::Tar),
+            _ => None,
+        }
+    }
+
+    /// Short human-readable name of the format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+/// Calls `f` with the path and contents of each regular file in an archive,
+/// in archive order
+///
+/// Directories, links, and other special entries are skipped.
+///
+/// # Arguments
+///
+/// * `format` - The archive format
+/// * `bytes` - The (decompressed) archive
+/// * `f` - Called with each member's path inside the archive and its contents
+///
+/// # Errors
+///
+/// Returns an error if the archive is corrupt, or if support for archives
+/// was not compiled in
+#[cfg(feature = "archive")]
+pub fn for_each_member<F>(format: ArchiveFormat, bytes: &[u8], mut f: F) -> Result<()>
+where
+    F: FnMut(&str, Vec<u8>),
+{
+    use std::io::{Cursor, Read};
+
+    let failed = |e: &dyn std::fmt::Display| Error::Archive(format!("{}: {}", format.name(), e));
+    match format {
+        ArchiveFormat::Tar => {
+            let mut archive = tar::Archive::new(bytes);
+            for entry in archive.entries().map_err(|e| failed(&e))? {
+                let mut entry = entry.map_err(|e| failed(&e))?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path().map_err(|e| failed(&e))?;
+                // `tar -C dir .` names members "./path"
+                let name = name.strip_prefix(".").unwrap_or(&name);
+                let name = name.to_string_lossy().into_owned();
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).map_err(|e| failed(&e))?;
+                f(&name, contents);
+            }
+        }
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| failed(&e))?;
+            for index in 0..archive.len() {
+                let mut member = archive.by_index(index).map_err(|e| failed(&e))?;
+                if !member.is_file() {
+                    continue;
+                }
+                let name = member.name().to_string();
+                let mut contents = Vec::new();
+                member.read_to_end(&mut contents).map_err(|e| failed(&e))?;
+                f(&name, contents);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn for_each_member<F>(format: ArchiveFormat, _bytes: &[u8], _f: F) -> Result<()>
+where
+    F: FnMut(&str, Vec<u8>),
+{
+    Err(Error::Archive(format!(
+        "{} support is not enabled (build with the `archive` feature)",
+        format.name()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_archive_format() {
+        let detect = |name| ArchiveFormat::detect(Path::new(name));
+        assert_eq!(detect("data/shard.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(detect("shard.TAR.GZ"), Some(ArchiveFormat::Tar));
+        assert_eq!(detect("shard.tgz"), Some(ArchiveFormat::Tar));
+        assert_eq!(detect("shard.tar.zst"), Some(ArchiveFormat::Tar));
+        assert_eq!(detect("export.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(detect("corpus.jsonl.gz"), None);
+        assert_eq!(detect("report.docx"), None);
+        assert_eq!(detect("tar"), None);
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_for_each_member() {
+        use std::io::Write;
+
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        tar.append_data(&mut header, "docs/", &[][..]).unwrap();
+        for (name, contents) in [("docs/a.md", "# A"), ("./b.txt", "hello")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let tar = tar.into_inner().unwrap();
+
+        let mut members = Vec::new();
+        for_each_member(ArchiveFormat::Tar, &tar, |name, contents| {
+            members.push((name.to_string(), contents))
+        })
+        .unwrap();
+        assert_eq!(
+            members,
+            [
+                ("docs/a.md".to_string(), b"# A".to_vec()),
+                ("b.txt".to_string(), b"hello".to_vec())
+            ]
+        );
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("docs/", options).unwrap();
+        zip.start_file("docs/a.md", options).unwrap();
+        zip.write_all(b"# A").unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+
+        let mut members = Vec::new();
+        for_each_member(ArchiveFormat::Zip, &zip, |name, contents| {
+            members.push((name.to_string(), contents))
+        })
+        .unwrap();
+        assert_eq!(members, [("docs/a.md".to_string(), b"# A".to_vec())]);
+
+        assert!(matches!(
+            for_each_member(ArchiveFormat::Zip, b"not a zip", |_, _| {}),
+            Err(Error::Archive(_))
+        ));
+    }
+}
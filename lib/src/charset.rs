@@ -0,0 +1,171 @@
+//! Decoding text in encodings other than UTF-8
+//!
+//! Windows exports are often UTF-16 and older files Latin-1 or Shift JIS;
+//! they are transcoded to UTF-8 before counting so they tokenize as the text
+//! they hold rather than failing as invalid UTF-8.
+
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16LE, UTF_8, WINDOWS_1252};
+use std::string::FromUtf8Error;
+
+/// Character encoding of input text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Detected from a byte order mark (UTF-8, UTF-16LE, or UTF-16BE),
+    /// otherwise UTF-8
+    #[default]
+    Auto,
+    /// UTF-8
+    Utf8,
+    /// UTF-16, little-endian
+    Utf16Le,
+    /// ISO-8859-1, decoded as its superset Windows-1252 as browsers do
+    Latin1,
+    /// Shift JIS
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// Short human-readable name of the encoding
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Latin1 => "Latin-1",
+            Self::ShiftJis => "Shift JIS",
+        }
+    }
+}
+
+/// Decodes bytes in `encoding` to text, dropping any byte order mark
+///
+/// UTF-8 must be valid; in other encodings, malformed sequences become
+/// U+FFFD REPLACEMENT CHARACTER.
+///
+/// # Errors
+///
+/// Returns the UTF-8 error, which gives the offset of the first bad byte,
+/// if text read as UTF-8 is not valid UTF-8
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::{decode_text, TextEncoding};
+///
+/// let utf16 = vec![0xff, 0xfe, b'h', 0, b'i', 0];
+/// assert_eq!(decode_text(utf16, TextEncoding::Auto).unwrap(), "hi");
+/// assert_eq!(decode_text(vec![0xe9], TextEncoding::Latin1).unwrap(), "é");
+/// ```
+pub fn decode_text(bytes: Vec<u8>, encoding: TextEncoding) -> Result<String, FromUtf8Error> {
+    let charset = match encoding {
+        TextEncoding::Auto => match Encoding::for_bom(&bytes) {
+            Some((charset, _)) if charset != UTF_8 => charset,
+            _ => return decode_utf8(bytes),
+        },
+        TextEncoding::Utf8 => return decode_utf8(bytes),
+        TextEncoding::Utf16Le => UTF_16LE,
+        TextEncoding::Latin1 => WINDOWS_1252,
+        TextEncoding::ShiftJis => SHIFT_JIS,
+    };
+    Ok(charset.decode_with_bom_removal(&bytes).0.into_owned())
+}
+
+/// Decodes UTF-8 that may hold invalid sequences, putting `replacement` in
+/// place of each one
+///
+/// For text [`decode_text`] rejected, such as scraped pages with a few bad
+/// bytes. Returns the text, without any byte order mark, and the number of
+/// invalid sequences.
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::repair_utf8;
+///
+/// assert_eq!(repair_utf8(b"caf\xe9!", "\u{fffd}"), ("caf\u{fffd}!".to_string(), 1));
+/// assert_eq!(repair_utf8(b"caf\xe9!", ""), ("caf!".to_string(), 1));
+/// ```
+pub fn repair_utf8(bytes: &[u8], replacement: &str) -> (String, usize) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = 0;
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            text.push_str(replacement);
+            invalid += 1;
+        }
+    }
+    strip_bom(&mut text);
+    (text, invalid)
+}
+
+fn decode_utf8(bytes: Vec<u8>) -> Result<String, FromUtf8Error> {
+    let mut text = String::from_utf8(bytes)?;
+    strip_bom(&mut text);
+    Ok(text)
+}
+
+fn strip_bom(text: &mut String) {
+    if text.starts_with('\u{feff}') {
+        text.drain(..'\u{feff}'.len_utf8());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_auto_sniffs_bom() {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(utf16le("héllo"));
+        assert_eq!(decode_text(bytes, TextEncoding::Auto).unwrap(), "héllo");
+
+        let bytes = vec![0xfe, 0xff, 0, b'h', 0, b'i'];
+        assert_eq!(decode_text(bytes, TextEncoding::Auto).unwrap(), "hi");
+
+        let bytes = b"\xef\xbb\xbfhello".to_vec();
+        assert_eq!(decode_text(bytes, TextEncoding::Auto).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_auto_without_bom_is_utf8() {
+        assert_eq!(
+            decode_text(b"hello".to_vec(), TextEncoding::Auto).unwrap(),
+            "hello"
+        );
+        let err = decode_text(b"ok\xe9".to_vec(), TextEncoding::Auto).unwrap_err();
+        assert_eq!(err.utf8_error().valid_up_to(), 2);
+    }
+
+    #[test]
+    fn test_explicit_encodings() {
+        assert_eq!(
+            decode_text(utf16le("hi"), TextEncoding::Utf16Le).unwrap(),
+            "hi"
+        );
+        assert_eq!(
+            decode_text(b"caf\xe9".to_vec(), TextEncoding::Latin1).unwrap(),
+            "café"
+        );
+        // "日本" in Shift JIS
+        let bytes = vec![0x93, 0xfa, 0x96, 0x7b];
+        assert_eq!(decode_text(bytes, TextEncoding::ShiftJis).unwrap(), "日本");
+        assert!(decode_text(vec![0xff, 0xfe], TextEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn test_repair_utf8() {
+        let bytes = b"\xef\xbb\xbfa\xff\xfeb\xe6\x97c";
+        assert_eq!(
+            repair_utf8(bytes, "\u{fffd}"),
+            ("a\u{fffd}\u{fffd}b\u{fffd}c".to_string(), 3)
+        );
+        assert_eq!(repair_utf8(bytes, ""), ("abc".to_string(), 3));
+        assert_eq!(repair_utf8(b"ok", ""), ("ok".to_string(), 0));
+    }
+}
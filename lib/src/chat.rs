@@ -0,0 +1,352 @@
+//! Token counts for chat message payloads
+//!
+//! Chat APIs bill more than the text of each message: every message is
+//! wrapped in formatting tokens, and the reply is primed with a few more.
+//! This module parses chat payloads and adds that overhead back in.
+
+use crate::{count_tokens, Error, Result};
+use serde_json::Value;
+use tokenizers::Tokenizer;
+
+/// Formatting tokens wrapped around every OpenAI chat message
+const OPENAI_TOKENS_PER_MESSAGE: usize = 3;
+/// Extra token for a message that carries a `name`
+const OPENAI_TOKENS_PER_NAME: usize = 1;
+/// Tokens that prime the assistant reply
+const OPENAI_REPLY_TOKENS: usize = 3;
+
+/// Turn markers Claude models were trained on, used to estimate role framing
+const ANTHROPIC_USER_PREFIX: &str = "\n\nHuman: ";
+const ANTHROPIC_ASSISTANT_PREFIX: &str = "\n\nAssistant: ";
+/// Marker that primes the assistant reply
+const ANTHROPIC_REPLY_PREFIX: &str = "\n\nAssistant:";
+
+/// A single chat message, reduced to the text that reaches the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    /// Author role (e.g. "system", "user", "assistant")
+    pub role: String,
+    /// Optional participant name
+    pub name: Option<String>,
+    /// Text parts of the message content
+    pub content: Vec<String>,
+}
+
+/// Billed token counts for a conversation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatCount {
+    /// Tokens billed for each message, including its formatting overhead
+    pub messages: Vec<usize>,
+    /// Tokens billed for the whole request
+    pub total: usize,
+}
+
+/// Parses an OpenAI Chat Completions payload
+///
+/// Accepts either a JSON array of messages or a request object with a
+/// `messages` field. Content may be a string or an array of parts, of which
+/// only `text` parts are kept.
+///
+/// # Arguments
+///
+/// * `json` - The payload to parse
+///
+/// # Errors
+///
+/// Returns an error if the payload is not valid JSON or not shaped like a
+/// list of messages
+pub fn parse_openai_chat(json: &str) -> Result<Vec<ChatMessage>> {
+    let payload: Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidInput(format!("{}", e)))?;
+    let messages = match &payload {
+        Value::Array(messages) => messages,
+        Value::Object(request) => match request.get("messages") {
+            Some(Value::Array(messages)) => messages,
+            _ => return Err(invalid("expected a \"messages\" array")),
+        },
+        _ => return Err(invalid("expected an array of messages")),
+    };
+
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let role = message
+                .get("role")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid(&format!("message {} has no \"role\"", index)))?;
+            let name = message.get("name").and_then(Value::as_str);
+            Ok(ChatMessage {
+                role: role.to_string(),
+                name: name.map(str::to_string),
+                content: text_parts(message.get("content")),
+            })
+        })
+        .collect()
+}
+
+/// Counts tokens the way the OpenAI Chat Completions API bills them
+///
+/// Each message costs its role, name, and content tokens plus a fixed
+/// formatting overhead, and every request pays for priming the reply.
+///
+/// # Arguments
+///
+/// * `messages` - The conversation
+/// * `tokenizer` - The tokenizer to use (cl100k_base for GPT-4 class models)
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn count_openai_chat(messages: &[ChatMessage], tokenizer: &Tokenizer) -> Result<ChatCount> {
+    let mut counts = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut tokens = OPENAI_TOKENS_PER_MESSAGE + count_tokens(&message.role, tokenizer, false)?;
+        if let Some(name) = &message.name {
+            tokens += OPENAI_TOKENS_PER_NAME + count_tokens(name, tokenizer, false)?;
+        }
+        for part in &message.content {
+            tokens += count_tokens(part, tokenizer, false)?;
+        }
+        counts.push(tokens);
+    }
+
+    let total = counts.iter().sum::<usize>() + OPENAI_REPLY_TOKENS;
+    Ok(ChatCount {
+        messages: counts,
+        total,
+    })
+}
+
+/// Parses an Anthropic Messages API payload
+///
+/// Accepts a request object with `messages` and an optional `system` prompt,
+/// or a bare JSON array of messages. The system prompt, when present, becomes
+/// a leading message with the "system" role. Content may be a string or an
+/// array of blocks; text, tool use, tool result, and thinking blocks are
+/// kept, while images and documents are skipped.
+///
+/// # Arguments
+///
+/// * `json` - The payload to parse
+///
+/// # Errors
+///
+/// Returns an error if the payload is not valid JSON or not shaped like a
+/// Messages API request
+pub fn parse_anthropic_chat(json: &str) -> Result<Vec<ChatMessage>> {
+    let payload: Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidInput(format!("{}", e)))?;
+    let (system, messages) = match &payload {
+        Value::Array(messages) => (None, messages),
+        Value::Object(request) => match request.get("messages") {
+            Some(Value::Array(messages)) => (request.get("system"), messages),
+            _ => return Err(invalid("expected a \"messages\" array")),
+        },
+        _ => return Err(invalid("expected a Messages API request")),
+    };
+
+    let mut parsed = Vec::with_capacity(messages.len() + 1);
+    if let Some(system) = system {
+        parsed.push(ChatMessage {
+            role: "system".to_string(),
+            name: None,
+            content: anthropic_blocks(Some(system)),
+        });
+    }
+    for (index, message) in messages.iter().enumerate() {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(&format!("message {} has no \"role\"", index)))?;
+        parsed.push(ChatMessage {
+            role: role.to_string(),
+            name: None,
+            content: anthropic_blocks(message.get("content")),
+        });
+    }
+    Ok(parsed)
+}
+
+/// Estimates the tokens of an Anthropic Messages API request
+///
+/// Anthropic does not publish its message framing, so each turn is charged
+/// for the `Human:`/`Assistant:` marker it was historically rendered with,
+/// and the system prompt for its text alone. Counts are only as exact as the
+/// tokenizer is close to Claude's.
+///
+/// # Arguments
+///
+/// * `messages` - The conversation, as returned by [`parse_anthropic_chat`]
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn count_anthropic_chat(messages: &[ChatMessage], tokenizer: &Tokenizer) -> Result<ChatCount> {
+    let mut counts = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut tokens = match message.role.as_str() {
+            "system" => 0,
+            "assistant" => count_tokens(ANTHROPIC_ASSISTANT_PREFIX, tokenizer, false)?,
+            _ => count_tokens(ANTHROPIC_USER_PREFIX, tokenizer, false)?,
+        };
+        for part in &message.content {
+            tokens += count_tokens(part, tokenizer, false)?;
+        }
+        counts.push(tokens);
+    }
+
+    let total =
+        counts.iter().sum::<usize>() + count_tokens(ANTHROPIC_REPLY_PREFIX, tokenizer, false)?;
+    Ok(ChatCount {
+        messages: counts,
+        total,
+    })
+}
+
+/// Collects the model-visible text of Anthropic content blocks
+fn anthropic_blocks(content: Option<&Value>) -> Vec<String> {
+    let blocks = match content {
+        Some(Value::String(text)) => return vec![text.clone()],
+        Some(Value::Array(blocks)) => blocks,
+        _ => return Vec::new(),
+    };
+
+    let mut parts = Vec::new();
+    for block in blocks {
+        let field = |key: &str| block.get(key).and_then(Value::as_str).map(str::to_string);
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => parts.extend(field("text")),
+            Some("thinking") => parts.extend(field("thinking")),
+            Some("tool_use") => {
+                parts.extend(field("name"));
+                if let Some(input) = block.get("input") {
+                    parts.push(input.to_string());
+                }
+            }
+            Some("tool_result") => parts.extend(anthropic_blocks(block.get("content"))),
+            _ => {}
+        }
+    }
+    parts
+}
+
+/// Collects the text of a string or an array of content parts
+fn text_parts(content: Option<&Value>) -> Vec<String> {
+    match content {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidInput(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPT2_TOKENIZER: &[u8] = include_bytes!("../../bin/assets/gpt2-tokenizer.json");
+
+    #[test]
+    fn test_parse_openai_chat_shapes() {
+        let array = r#"[{"role": "user", "content": "Hi"}]"#;
+        let request = r#"{"model": "gpt-4o", "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "name": "ann", "content": [
+                {"type": "text", "text": "Describe"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+            ]},
+            {"role": "assistant", "content": null}
+        ]}"#;
+
+        assert_eq!(parse_openai_chat(array).unwrap()[0].content, ["Hi"]);
+        let messages = parse_openai_chat(request).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].name.as_deref(), Some("ann"));
+        assert_eq!(messages[1].content, ["Describe"]);
+        assert!(messages[2].content.is_empty());
+
+        assert!(parse_openai_chat("{}").is_err());
+        assert!(parse_openai_chat(r#"[{"content": "no role"}]"#).is_err());
+        assert!(parse_openai_chat("not json").is_err());
+    }
+
+    #[test]
+    fn test_count_openai_chat_adds_overhead() {
+        let tokenizer = Tokenizer::from_bytes(GPT2_TOKENIZER).unwrap();
+        let messages = parse_openai_chat(
+            r#"[{"role": "system", "content": "Be brief."},
+                {"role": "user", "name": "ann", "content": "Hello, world!"}]"#,
+        )
+        .unwrap();
+
+        let count = count_openai_chat(&messages, &tokenizer).unwrap();
+        let text = |s| count_tokens(s, &tokenizer, false).unwrap();
+        assert_eq!(count.messages[0], 3 + text("system") + text("Be brief."));
+        assert_eq!(
+            count.messages[1],
+            3 + text("user") + 1 + text("ann") + text("Hello, world!")
+        );
+        assert_eq!(count.total, count.messages[0] + count.messages[1] + 3);
+    }
+
+    #[test]
+    fn test_parse_anthropic_chat_blocks() {
+        let request = r#"{
+            "model": "claude-sonnet-4",
+            "system": [{"type": "text", "text": "Be brief."}],
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "Weather?"},
+                    {"type": "image", "source": {"type": "base64", "data": "AAAA"}}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "t1", "name": "weather", "input": {"city": "Oslo"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "Rain"}
+                ]}
+            ]
+        }"#;
+
+        let messages = parse_anthropic_chat(request).unwrap();
+        let roles: Vec<_> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert_eq!(messages[0].content, ["Be brief."]);
+        assert_eq!(messages[1].content, ["Weather?"]);
+        assert_eq!(messages[2].content, ["weather", r#"{"city":"Oslo"}"#]);
+        assert_eq!(messages[3].content, ["Rain"]);
+
+        let plain = parse_anthropic_chat(r#"{"system": "Hi", "messages": []}"#).unwrap();
+        assert_eq!(plain[0].content, ["Hi"]);
+        assert!(parse_anthropic_chat(r#"{"system": "Hi"}"#).is_err());
+    }
+
+    #[test]
+    fn test_count_anthropic_chat_adds_turn_markers() {
+        let tokenizer = Tokenizer::from_bytes(GPT2_TOKENIZER).unwrap();
+        let messages = parse_anthropic_chat(
+            r#"{"system": "Be brief.", "messages": [{"role": "user", "content": "Hi"}]}"#,
+        )
+        .unwrap();
+
+        let count = count_anthropic_chat(&messages, &tokenizer).unwrap();
+        let text = |s| count_tokens(s, &tokenizer, false).unwrap();
+        assert_eq!(
+            count.messages,
+            [text("Be brief."), text("\n\nHuman: ") + text("Hi")]
+        );
+        assert_eq!(
+            count.total,
+            count.messages.iter().sum::<usize>() + text("\n\nAssistant:")
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Cutting text into pieces at token boundaries
+//!
+//! Cuts are made in the original text, between two tokens, so no text is
+//! re-decoded and nothing is lost. Byte-level tokenizers can spend several
+//! tokens on one character; positions inside such a character are never used
+//! as cut points.
+
+use crate::{count_tokens, Error, Result};
+use tokenizers::Tokenizer;
+
+/// A slice of the input that holds a bounded number of tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    /// The chunk text, borrowed from the input
+    pub text: &'a str,
+    /// Byte offset of the chunk in the input
+    pub offset: usize,
+    /// Number of tokens in `text`
+    pub tokens: usize,
+}
+
+/// Byte positions where the text may be cut, indexed by token
+///
+/// `cuts[i]` is the position just before token `i`, or `None` when that
+/// position falls inside a character. `cuts[0]` is the start of the text and
+/// the last entry is its end.
+struct Cuts<'a> {
+    text: &'a str,
+    cuts: Vec<Option<usize>>,
+}
+
+impl<'a> Cuts<'a> {
+    fn new(text: &'a str, tokenizer: &Tokenizer) -> Result<Self> {
+        let encoding = tokenizer
+            .encode(text, false)
+            .map_err(|e| Error::Encoding(format!("{}", e)))?;
+        let offsets = encoding.get_offsets();
+
+        let mut cuts = Vec::with_capacity(offsets.len() + 1);
+        cuts.push(Some(0));
+        for pair in offsets.windows(2) {
+            let (previous, next) = (pair[0], pair[1]);
+            // Tokens of a single character share its offsets, so they overlap
+            let clean = next.0 >= previous.1 && text.is_char_boundary(next.0);
+            cuts.push(clean.then_some(next.0));
+        }
+        if !offsets.is_empty() {
+            cuts.push(Some(text.len()));
+        }
+
+        Ok(Self { text, cuts })
+    }
+
+    /// Number of tokens in the whole text
+    fn len(&self) -> usize {
+        self.cuts.len().saturating_sub(1)
+    }
+
+    /// Text between the cuts before tokens `start` and `end`
+    fn slice(&self, start: usize, end: usize) -> &'a str {
+        let from = self.cuts[start].expect("start is a valid cut");
+        let to = self.cuts[end].expect("end is a valid cut");
+        &self.text[from..to]
+    }
+
+    /// Largest cut `end` after `start` whose slice holds at most `max_tokens`
+    ///
+    /// Re-encoding a slice can differ from the tokens it covered in the full
+    /// text, so each candidate is counted again. Returns `None` when not even
+    /// the first character after `start` fits.
+    fn fit_end(
+        &self,
+        start: usize,
+        max_tokens: usize,
+        tokenizer: &Tokenizer,
+    ) -> Result<Option<(usize, usize)>> {
+        let limit = (start + max_tokens).min(self.len());
+        for end in (start + 1..=limit).rev() {
+            if self.cuts[end].is_none() {
+                continue;
+            }
+            let tokens = count_tokens(self.slice(start, end), tokenizer, false)?;
+            if tokens <= max_tokens {
+                return Ok(Some((end, tokens)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Smallest cut `start` before `end` whose slice holds at most `max_tokens`
+    ///
+    /// The mirror image of [`Cuts::fit_end`].
+    fn fit_start(
+        &self,
+        end: usize,
+        max_tokens: usize,
+        tokenizer: &Tokenizer,
+    ) -> Result<Option<(usize, usize)>> {
+        for start in end.saturating_sub(max_tokens)..end {
+            if self.cuts[start].is_none() {
+                continue;
+            }
+            let tokens = count_tokens(self.slice(start, end), tokenizer, false)?;
+            if tokens <= max_tokens {
+                return Ok(Some((start, tokens)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Nearest cut after `start`, with the token count of the slice up to it
+    fn next_cut(&self, start: usize, tokenizer: &Tokenizer) -> Result<(usize, usize)> {
+        let end = (start + 1..=self.len())
+            .find(|&end| self.cuts[end].is_some())
+            .unwrap_or(self.len());
+        Ok((end, count_tokens(self.slice(start, end), tokenizer, false)?))
+    }
+
+    /// Start of the chunk after `start..end`, `overlap` tokens before `end`
+    ///
+    /// Moves further back when that position falls inside a character, and
+    /// gives up the overlap when it would not move past `start`.
+    fn next_start(&self, start: usize, end: usize, overlap: usize) -> usize {
+        (start + 1..=end.saturating_sub(overlap))
+            .rev()
+            .find(|&cut| self.cuts[cut].is_some())
+            .unwrap_or(end)
+    }
+}
+
+/// Splits text into chunks of at most `max_tokens` tokens
+///
+/// Chunks are cut between tokens and never inside a multi-byte character.
+/// Each chunk starts `overlap` tokens (counted in the encoding of the whole
+/// text) before the end of the previous one; with no overlap, concatenating
+/// the chunks reproduces the input exactly. A chunk only exceeds `max_tokens`
+/// when a single character needs more tokens than that.
+///
+/// # Arguments
+///
+/// * `text` - The text to split
+/// * `tokenizer` - The tokenizer to use
+/// * `max_tokens` - Maximum number of tokens per chunk
+/// * `overlap` - Number of tokens consecutive chunks share
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+///
+/// # Panics
+///
+/// Panics if `max_tokens` is zero or `overlap` is not smaller than it
+pub fn split_by_tokens<'a>(
+    text: &'a str,
+    tokenizer: &Tokenizer,
+    max_tokens: usize,
+    overlap: usize,
+) -> Result<Vec<Chunk<'a>>> {
+    assert!(max_tokens > 0, "max_tokens must be positive");
+    assert!(
+        overlap < max_tokens,
+        "overlap must be smaller than max_tokens"
+    );
+
+    let cuts = Cuts::new(text, tokenizer)?;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < cuts.len() {
+        // Only a character needing more than max_tokens tokens fits nowhere
+        let (end, tokens) = match cuts.fit_end(start, max_tokens, tokenizer)? {
+            Some(fit) => fit,
+            None => cuts.next_cut(start, tokenizer)?,
+        };
+        chunks.push(Chunk {
+            text: cuts.slice(start, end),
+            offset: cuts.cuts[start].expect("start is a valid cut"),
+            tokens,
+        });
+        if end == cuts.len() {
+            break;
+        }
+        start = cuts.next_start(start, end, overlap);
+    }
+
+    Ok(chunks)
+}
+
+/// Returns the longest prefix of `text` that holds at most `max_tokens` tokens
+///
+/// The prefix ends at a token boundary, never inside a multi-byte character.
+///
+/// # Arguments
+///
+/// * `text` - The text to cut
+/// * `tokenizer` - The tokenizer to use
+/// * `max_tokens` - Maximum number of tokens in the prefix
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{head_by_tokens, load_tokenizer};
+///
+/// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
+/// let head = head_by_tokens("Hello, world!", &tokenizer, 2).unwrap();
+/// assert!("Hello, world!".starts_with(head));
+/// ```
+pub fn head_by_tokens<'a>(
+    text: &'a str,
+    tokenizer: &Tokenizer,
+    max_tokens: usize,
+) -> Result<&'a str> {
+    let cuts = Cuts::new(text, tokenizer)?;
+    Ok(match cuts.fit_end(0, max_tokens, tokenizer)? {
+        Some((end, _)) => cuts.slice(0, end),
+        None => "",
+    })
+}
+
+/// Returns the longest suffix of `text` that holds at most `max_tokens` tokens
+///
+/// The suffix starts at a token boundary, never inside a multi-byte character.
+///
+/// # Arguments
+///
+/// * `text` - The text to cut
+/// * `tokenizer` - The tokenizer to use
+/// * `max_tokens` - Maximum number of tokens in the suffix
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn tail_by_tokens<'a>(
+    text: &'a str,
+    tokenizer: &Tokenizer,
+    max_tokens: usize,
+) -> Result<&'a str> {
+    let cuts = Cuts::new(text, tokenizer)?;
+    let end = cuts.len();
+    Ok(match cuts.fit_start(end, max_tokens, tokenizer)? {
+        Some((start, _)) => cuts.slice(start, end),
+        None => "",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPT2_TOKENIZER: &[u8] = include_bytes!("../../bin/assets/gpt2-tokenizer.json");
+
+    fn gpt2() -> Tokenizer {
+        Tokenizer::from_bytes(GPT2_TOKENIZER).unwrap()
+    }
+
+    fn joined(chunks: &[Chunk]) -> String {
+        chunks.iter().map(|chunk| chunk.text).collect()
+    }
+
+    #[test]
+    fn test_split_respects_limit_and_round_trips() {
+        let tokenizer = gpt2();
+        let text = "The quick brown fox jumps over the lazy dog.\n".repeat(20);
+
+        let chunks = split_by_tokens(&text, &tokenizer, 16, 0).unwrap();
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.tokens <= 16);
+            assert_eq!(
+                chunk.tokens,
+                count_tokens(chunk.text, &tokenizer, false).unwrap()
+            );
+            assert_eq!(
+                &text[chunk.offset..chunk.offset + chunk.text.len()],
+                chunk.text
+            );
+        }
+        assert_eq!(joined(&chunks), text);
+    }
+
+    #[test]
+    fn test_split_never_breaks_characters() {
+        let tokenizer = gpt2();
+        // Each of these characters encodes to several byte-level tokens
+        let text = "日本語😀日本語😀";
+
+        for max_tokens in 1..=8 {
+            let chunks = split_by_tokens(text, &tokenizer, max_tokens, 0).unwrap();
+            assert_eq!(joined(&chunks), text);
+        }
+        assert!(split_by_tokens("", &tokenizer, 4, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_with_overlap() {
+        let tokenizer = gpt2();
+        let text = "one two three four five six seven eight nine ten eleven twelve";
+
+        let chunks = split_by_tokens(text, &tokenizer, 5, 2).unwrap();
+        let texts: Vec<_> = chunks.iter().map(|chunk| chunk.text).collect();
+        assert_eq!(
+            texts,
+            [
+                "one two three four five",
+                " four five six seven eight",
+                " seven eight nine ten eleven",
+                " ten eleven twelve",
+            ]
+        );
+
+        for pair in chunks.windows(2) {
+            let shared = &text[pair[1].offset..pair[0].offset + pair[0].text.len()];
+            assert_eq!(count_tokens(shared, &tokenizer, false).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_head_by_tokens() {
+        let tokenizer = gpt2();
+        let text = "Hello, world! How are you?";
+
+        assert_eq!(head_by_tokens(text, &tokenizer, 0).unwrap(), "");
+        assert_eq!(head_by_tokens(text, &tokenizer, 3).unwrap(), "Hello, world");
+        assert_eq!(head_by_tokens(text, &tokenizer, 100).unwrap(), text);
+        // 😀 is two byte-level tokens, so one token cannot hold it
+        assert_eq!(head_by_tokens("😀 hi", &tokenizer, 1).unwrap(), "");
+    }
+
+    #[test]
+    fn test_tail_by_tokens() {
+        let tokenizer = gpt2();
+        let text = "Hello, world! How are you?";
+
+        assert_eq!(tail_by_tokens(text, &tokenizer, 0).unwrap(), "");
+        assert_eq!(tail_by_tokens(text, &tokenizer, 3).unwrap(), " are you?");
+        assert_eq!(tail_by_tokens(text, &tokenizer, 100).unwrap(), text);
+        assert_eq!(tail_by_tokens("hi 😀", &tokenizer, 1).unwrap(), "");
+    }
+}
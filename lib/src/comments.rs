@@ -0,0 +1,258 @@
+//! Lightweight comment lexing for source code
+//!
+//! This is not a parser: each language is described by its comment markers
+//! and string quotes, which is enough to tell comments from code without
+//! mistaking a `//` or `#` inside a string literal for a comment.
+
+use std::path::Path;
+
+/// Comment and string syntax of a family of languages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentSyntax {
+    /// Marker starting a comment that runs to the end of the line
+    line: Option<&'static str>,
+    /// Markers opening and closing a block comment
+    block: Option<(&'static str, &'static str)>,
+    /// String delimiters, longest first
+    quotes: &'static [&'static str],
+    /// Whether `'x'` is a character literal (so `'a` alone is not a quote)
+    char_literals: bool,
+}
+
+impl CommentSyntax {
+    /// C, C++, Rust, Go, Java, C#, Kotlin, Swift, and similar
+    pub const C: Self = Self {
+        line: Some("//"),
+        block: Some(("/*", "*/")),
+        quotes: &["\"", "`"],
+        char_literals: true,
+    };
+
+    /// JavaScript and TypeScript
+    pub const JAVASCRIPT: Self = Self {
+        line: Some("//"),
+        block: Some(("/*", "*/")),
+        quotes: &["\"", "'", "`"],
+        char_literals: false,
+    };
+
+    /// CSS
+    pub const CSS: Self = Self {
+        line: None,
+        block: Some(("/*", "*/")),
+        quotes: &["\"", "'"],
+        char_literals: false,
+    };
+
+    /// Python, including triple-quoted strings
+    pub const PYTHON: Self = Self {
+        line: Some("#"),
+        block: None,
+        quotes: &["\"\"\"", "'''", "\"", "'"],
+        char_literals: false,
+    };
+
+    /// Shell, Ruby, Perl, R, TOML, YAML, and other `#`-comment languages
+    pub const HASH: Self = Self {
+        line: Some("#"),
+        block: None,
+        quotes: &["\"", "'"],
+        char_literals: false,
+    };
+
+    /// SQL
+    pub const SQL: Self = Self {
+        line: Some("--"),
+        block: Some(("/*", "*/")),
+        quotes: &["'", "\""],
+        char_literals: false,
+    };
+
+    /// Lua
+    pub const LUA: Self = Self {
+        line: Some("--"),
+        block: Some(("--[[", "]]")),
+        quotes: &["\"", "'"],
+        char_literals: false,
+    };
+
+    /// HTML and XML
+    pub const MARKUP: Self = Self {
+        line: None,
+        block: Some(("<!--", "-->")),
+        quotes: &[],
+        char_literals: false,
+    };
+
+    /// Looks up the syntax for a file extension (without the dot)
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let syntax = match extension.to_ascii_lowercase().as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "go" | "java" | "kt"
+            | "kts" | "scala" | "swift" | "cs" | "dart" | "zig" | "proto" => Self::C,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Self::JAVASCRIPT,
+            "css" | "scss" | "less" => Self::CSS,
+            "py" | "pyi" => Self::PYTHON,
+            "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "pm" | "r" | "toml" | "yaml" | "yml"
+            | "tf" | "nix" | "mk" | "cmake" => Self::HASH,
+            "sql" => Self::SQL,
+            "lua" => Self::LUA,
+            "html" | "htm" | "xml" | "svg" | "xhtml" => Self::MARKUP,
+            _ => return None,
+        };
+        Some(syntax)
+    }
+
+    /// Looks up the syntax for a file from its extension
+    pub fn for_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+/// Removes comments from source code
+///
+/// String literals are kept intact. Trailing whitespace before a removed
+/// comment is dropped, as are lines left blank by removing a comment.
+///
+/// # Arguments
+///
+/// * `code` - The source code
+/// * `syntax` - Comment syntax of the code's language
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::{strip_comments, CommentSyntax};
+///
+/// let code = "# setup\nurl = \"http://x#y\"  # the endpoint\n";
+/// assert_eq!(strip_comments(code, CommentSyntax::PYTHON), "url = \"http://x#y\"\n");
+/// ```
+pub fn strip_comments(code: &str, syntax: CommentSyntax) -> String {
+    let mut out = String::with_capacity(code.len());
+    // Start of the current output line, and whether a comment was removed from it
+    let mut line_start = 0;
+    let mut had_comment = false;
+    let mut rest = code;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '\n' {
+            if !end_line(&mut out, line_start, had_comment) {
+                out.push('\n');
+            }
+            line_start = out.len();
+            had_comment = false;
+            rest = &rest[1..];
+        } else if let Some((open, close)) = syntax.block.filter(|(open, _)| rest.starts_with(open))
+        {
+            had_comment = true;
+            rest = match rest[open.len()..].find(close) {
+                Some(end) => &rest[open.len() + end + close.len()..],
+                None => "",
+            };
+        } else if let Some(marker) = syntax.line.filter(|marker| rest.starts_with(marker)) {
+            had_comment = true;
+            rest = &rest[rest[marker.len()..]
+                .find('\n')
+                .map_or(rest.len(), |end| marker.len() + end)..];
+        } else if let Some(quote) = syntax.quotes.iter().find(|quote| rest.starts_with(*quote)) {
+            let len = string_len(rest, quote);
+            let literal = &rest[..len];
+            out.push_str(literal);
+            if let Some(newline) = literal.rfind('\n') {
+                line_start = out.len() - (literal.len() - newline - 1);
+                had_comment = false;
+            }
+            rest = &rest[len..];
+        } else if c == '\'' && syntax.char_literals {
+            // A character literal such as 'x' or '\n'; otherwise a lone
+            // quote, as in a Rust lifetime
+            let len = char_literal_len(rest).unwrap_or(1);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    end_line(&mut out, line_start, had_comment);
+    out
+}
+
+/// Length of the string literal at the start of `text`, including both
+/// quotes; an unterminated literal runs to the end of the text
+fn string_len(text: &str, quote: &str) -> usize {
+    let mut chars = text[quote.len()..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if text[quote.len() + i..].starts_with(quote) {
+            return quote.len() + i + quote.len();
+        }
+    }
+    text.len()
+}
+
+/// Length of the character literal at the start of `text`, if there is one
+fn char_literal_len(text: &str) -> Option<usize> {
+    let body = &text[1..];
+    let mut chars = body.char_indices();
+    // Index of the closing quote within `body`
+    let end = match chars.next()? {
+        // Escapes such as '\n', '\'', '\x7f', or '\u{1F600}'
+        (_, '\\') => {
+            chars.next()?;
+            chars.find(|&(_, c)| c == '\'' || c == '\n')?.0
+        }
+        (_, '\'' | '\n') => return None,
+        (_, c) => c.len_utf8(),
+    };
+    body[end..].starts_with('\'').then_some(end + 2)
+}
+
+/// Tidies the current output line once a comment was removed from it:
+/// trailing whitespace is dropped, and so is the whole line if nothing else
+/// is left. Returns whether the line was dropped.
+fn end_line(out: &mut String, line_start: usize, had_comment: bool) -> bool {
+    if !had_comment {
+        return false;
+    }
+    let kept = out[line_start..].trim_end_matches([' ', '\t']).len();
+    out.truncate(line_start + kept);
+    kept == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_c_style_comments() {
+        let code = "/// Adds one\n\
+            fn inc<'a>(x: &'a i32) -> i32 {\n    \
+                /* block */ x + 1 // trailing\n\
+            }\n\
+            let s = \"// not a comment\";\n\
+            let c = '\"';\n";
+        assert_eq!(
+            strip_comments(code, CommentSyntax::C),
+            "fn inc<'a>(x: &'a i32) -> i32 {\n     x + 1\n}\n\
+            let s = \"// not a comment\";\nlet c = '\"';\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_python_comments() {
+        let code = "def f():\n    \"\"\"Docs # kept\"\"\"\n    # gone\n    return '#'\n";
+        assert_eq!(
+            strip_comments(code, CommentSyntax::PYTHON),
+            "def f():\n    \"\"\"Docs # kept\"\"\"\n    return '#'\n"
+        );
+        assert_eq!(
+            CommentSyntax::for_path(Path::new("src/app.PY")),
+            Some(CommentSyntax::PYTHON)
+        );
+        assert_eq!(CommentSyntax::for_path(Path::new("notes.md")), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! Transparent decompression of gzip, zstd, xz, and bzip2 inputs
+//!
+//! Training corpora are usually stored compressed. Compressed files are
+//! detected by magic bytes or extension and decompressed as they are read,
+//! so they are counted under their own names instead of through a pipe.
+//! The decoders are pure Rust and behind the `compression` feature.
+
+#[cfg(feature = "fs")]
+use crate::file_error;
+use crate::{Error, Result};
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::Read;
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Compression formats that inputs are decompressed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip (`.gz`)
+    Gzip,
+    /// Zstandard (`.zst`)
+    Zstd,
+    /// xz (`.xz`)
+    Xz,
+    /// bzip2 (`.bz2`)
+    Bzip2,
+}
+
+impl Compression {
+    /// Detects a compression format from the magic bytes at the start of a
+    /// file, or failing that, from its extension
+    ///
+    /// Returns `None` for uncompressed input.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            return Some(Self::Gzip);
+        }
+        if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(Self::Zstd);
+        }
+        if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Some(Self::Xz);
+        }
+        // "BZh", a block size digit, then the magic of a block or of the
+        // end of the stream, so text that happens to start "BZh" is not taken
+        if bytes.len() >= 10
+            && bytes.starts_with(b"BZh")
+            && bytes[3].is_ascii_digit()
+            && (bytes[4..10] == [0x31, 0x41, 0x59, 0x26, 0x53, 0x59]
+                || bytes[4..10] == [0x17, 0x72, 0x45, 0x38, 0x50, 0x90])
+        {
+            return Some(Self::Bzip2);
+        }
+        Self::from_extension(path)
+    }
+
+    /// The compression format named by a file's extension
+    fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" | "gzip" => Some(Self::Gzip),
+            "zst" | "zstd" => Some(Self::Zstd),
+            "xz" => Some(Self::Xz),
+            "bz2" => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Short human-readable name of the format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Bzip2 => "bzip2",
+        }
+    }
+
+    /// The path of the file inside a compressed one: `path` without its
+    /// compression extension (`corpus.jsonl.zst` holds `corpus.jsonl`)
+    ///
+    /// Used to recognize the document format and language of the contents.
+    pub fn inner_path(path: &Path) -> Cow<'_, Path> {
+        match Self::from_extension(path) {
+            Some(_) => Cow::Owned(path.with_extension("")),
+            None => Cow::Borrowed(path),
+        }
+    }
+}
+
+/// Decompresses everything a reader yields
+///
+/// Concatenated gzip members, bzip2 streams, and zstd frames (as written by
+/// `pigz`, `pbzip2`, and `pzstd`) are decompressed one after another.
+///
+/// # Errors
+///
+/// Returns an error if the data is corrupt or the reader fails
+#[cfg(feature = "compression")]
+pub fn decompress<R: Read>(compression: Compression, reader: R) -> Result<Vec<u8>> {
+    use std::io::{BufRead, BufReader};
+
+    let failed =
+        |e: &dyn std::fmt::Display| Error::Decompression(format!("{}: {}", compression.name(), e));
+    let mut bytes = Vec::new();
+    match compression {
+        Compression::Gzip => {
+            flate2::read::MultiGzDecoder::new(reader)
+                .read_to_end(&mut bytes)
+                .map_err(|e| failed(&e))?;
+        }
+        Compression::Bzip2 => {
+            bzip2::read::MultiBzDecoder::new(reader)
+                .read_to_end(&mut bytes)
+                .map_err(|e| failed(&e))?;
+        }
+        Compression::Xz => {
+            lzma_rs::xz_decompress(&mut BufReader::new(reader), &mut bytes)
+                .map_err(|e| failed(&e))?;
+        }
+        Compression::Zstd => {
+            let mut reader = BufReader::new(reader);
+            while !reader.fill_buf().map_err(|e| failed(&e))?.is_empty() {
+                ruzstd::decoding::StreamingDecoder::new(&mut reader)
+                    .map_err(|e| failed(&e))?
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| failed(&e))?;
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn decompress<R: Read>(compression: Compression, _reader: R) -> Result<Vec<u8>> {
+    Err(Error::Decompression(format!(
+        "{} support is not enabled (build with the `compression` feature)",
+        compression.name()
+    )))
+}
+
+/// A reader that decompresses what `reader` yields as it is read, so the
+/// decompressed data never has to fit in memory
+///
+/// Like [`decompress`], it reads concatenated gzip members, bzip2 streams,
+/// and zstd frames one after another.
+///
+/// # Errors
+///
+/// Returns an error for xz, whose decoder cannot be read from
+/// incrementally, or if a zstd frame header is corrupt; other corrupt data
+/// surfaces as read errors
+#[cfg(feature = "compression")]
+pub fn decompress_reader<'a, R: Read + Send + 'a>(
+    compression: Compression,
+    reader: R,
+) -> Result<Box<dyn Read + Send + 'a>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Compression::Zstd => Box::new(ZstdFrames {
+            decoder: None,
+            reader: Some(std::io::BufReader::new(reader)),
+        }),
+        Compression::Xz => {
+            return Err(Error::Decompression(
+                "xz cannot be decompressed as a stream".to_string(),
+            ))
+        }
+    })
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn decompress_reader<'a, R: Read + Send + 'a>(
+    compression: Compression,
+    _reader: R,
+) -> Result<Box<dyn Read + Send + 'a>> {
+    Err(Error::Decompression(format!(
+        "{} support is not enabled (build with the `compression` feature)",
+        compression.name()
+    )))
+}
+
+/// Decodes consecutive zstd frames, as `pzstd` writes them
+#[cfg(feature = "compression")]
+struct ZstdFrames<R: Read> {
+    /// The frame being decoded
+    decoder: Option<ZstdFrame<R>>,
+    /// The input between frames
+    reader: Option<std::io::BufReader<R>>,
+}
+
+#[cfg(feature = "compression")]
+type ZstdFrame<R> =
+    ruzstd::decoding::StreamingDecoder<std::io::BufReader<R>, ruzstd::decoding::FrameDecoder>;
+
+#[cfg(feature = "compression")]
+impl<R: Read> Read for ZstdFrames<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(decoder) = &mut self.decoder {
+                let read = decoder.read(buf)?;
+                if read > 0 {
+                    return Ok(read);
+                }
+                self.reader = self.decoder.take().map(|decoder| decoder.into_inner());
+            }
+            // Nothing left after the last frame, or a frame header failed
+            let Some(reader) = &mut self.reader else {
+                return Ok(0);
+            };
+            if std::io::BufRead::fill_buf(reader)?.is_empty() {
+                return Ok(0);
+            }
+            let reader = self.reader.take().expect("checked above");
+            let decoder = ruzstd::decoding::StreamingDecoder::new(reader).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, format!("zstd: {}", e))
+            })?;
+            self.decoder = Some(decoder);
+        }
+    }
+}
+
+/// Reads a file, decompressing it if it is compressed
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or its compressed data is
+/// corrupt
+#[cfg(feature = "fs")]
+pub fn read_decompressed(path: &Path) -> Result<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(path).map_err(file_error(path))?);
+    let head = reader.fill_buf().map_err(file_error(path))?;
+    match Compression::detect(path, head) {
+        Some(compression) => decompress(compression, reader),
+        None => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(file_error(path))?;
+            Ok(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_compression() {
+        let plain = Path::new("a.txt");
+        assert_eq!(
+            Compression::detect(plain, &[0x1f, 0x8b, 8]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::detect(plain, &[0x28, 0xb5, 0x2f, 0xfd]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::detect(plain, b"\xfd7zXZ\x00"),
+            Some(Compression::Xz)
+        );
+        assert_eq!(
+            Compression::detect(plain, b"BZh91AY&SY"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::detect(plain, b"BZh9 is not bzip2"), None);
+        assert_eq!(
+            Compression::detect(Path::new("a.JSONL.ZST"), b""),
+            Some(Compression::Zstd)
+        );
+    }
+
+    #[test]
+    fn test_inner_path() {
+        assert_eq!(
+            Compression::inner_path(Path::new("data/corpus.jsonl.zst")),
+            Path::new("data/corpus.jsonl")
+        );
+        assert_eq!(
+            Compression::inner_path(Path::new("notes.txt")),
+            Path::new("notes.txt")
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_decompress_round_trip() {
+        use std::io::Write;
+
+        let text = "hello world\n".repeat(100);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(text.as_bytes()).unwrap();
+        let gzip = gzip.finish().unwrap();
+        // Two members, as pigz writes
+        let gzip = [gzip.clone(), gzip].concat();
+        let expected = text.repeat(2).into_bytes();
+        assert_eq!(decompress(Compression::Gzip, &gzip[..]).unwrap(), expected);
+
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(text.as_bytes()).unwrap();
+        let bzip2 = bzip2.finish().unwrap();
+        assert_eq!(
+            Compression::detect(Path::new("a"), &bzip2),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(
+            decompress(Compression::Bzip2, &bzip2[..]).unwrap(),
+            text.as_bytes()
+        );
+
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut text.as_bytes(), &mut xz).unwrap();
+        assert_eq!(
+            decompress(Compression::Xz, &xz[..]).unwrap(),
+            text.as_bytes()
+        );
+
+        let zstd = ruzstd::encoding::compress_to_vec(
+            text.as_bytes(),
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+        let zstd = [zstd.clone(), zstd].concat();
+        assert_eq!(decompress(Compression::Zstd, &zstd[..]).unwrap(), expected);
+
+        assert!(matches!(
+            decompress(Compression::Gzip, &b"\x1f\x8bgarbage"[..]),
+            Err(Error::Decompression(_))
+        ));
+
+        // Streamed, in small reads that end inside frames
+        for (compression, data) in [
+            (Compression::Gzip, &gzip),
+            (Compression::Bzip2, &bzip2),
+            (Compression::Zstd, &zstd),
+        ] {
+            let mut reader = decompress_reader(compression, &data[..]).unwrap();
+            let mut streamed = Vec::new();
+            let mut buffer = [0; 7];
+            loop {
+                let read = reader.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                streamed.extend_from_slice(&buffer[..read]);
+            }
+            let whole = decompress(compression, &data[..]).unwrap();
+            assert_eq!(streamed, whole, "{}", compression.name());
+        }
+        assert!(decompress_reader(Compression::Xz, &xz[..]).is_err());
+    }
+}
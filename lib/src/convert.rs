@@ -0,0 +1,889 @@
+//! Conversion of tiktoken and SentencePiece tokenizers to Hugging Face
+//! `tokenizer.json`, the format the tokenizers crate loads
+//!
+//! A tiktoken file lists each token's bytes (base64) with its rank, which
+//! is also its ID. It holds neither merges nor the pattern text is split
+//! by, so merges are rebuilt by running BPE over each token with only the
+//! ranks below it, and the pattern comes from [`TIKTOKEN_ENCODINGS`] or the
+//! caller. A SentencePiece `.model` is a protobuf of pieces, scores, and
+//! normalization rules; Unigram models carry over as they are, and BPE
+//! models get merges from the pieces that concatenate into other pieces,
+//! as the `transformers` converters do.
+
+use crate::{Error, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// An encoding tiktoken ships, whose file holds neither its pattern nor
+/// its special tokens
+#[derive(Debug, Clone, Copy)]
+pub struct TiktokenEncoding {
+    /// File stem, such as "cl100k_base"
+    pub name: &'static str,
+    /// Regex text is split with before merging
+    pub pattern: &'static str,
+    /// Special tokens and their IDs
+    pub special_tokens: &'static [(&'static str, u32)],
+}
+
+/// The encodings tiktoken ships
+pub const TIKTOKEN_ENCODINGS: &[TiktokenEncoding] = &[
+    TiktokenEncoding {
+        name: "r50k_base",
+        pattern: GPT2_PATTERN,
+        special_tokens: &[("<|endoftext|>", 50256)],
+    },
+    TiktokenEncoding {
+        name: "p50k_base",
+        pattern: GPT2_PATTERN,
+        special_tokens: &[("<|endoftext|>", 50256)],
+    },
+    TiktokenEncoding {
+        name: "cl100k_base",
+        pattern: CL100K_PATTERN,
+        special_tokens: &[
+            ("<|endoftext|>", 100257),
+            ("<|fim_prefix|>", 100258),
+            ("<|fim_middle|>", 100259),
+            ("<|fim_suffix|>", 100260),
+            ("<|endofprompt|>", 100276),
+        ],
+    },
+    TiktokenEncoding {
+        name: "o200k_base",
+        pattern: O200K_PATTERN,
+        special_tokens: &[("<|endoftext|>", 199999), ("<|endofprompt|>", 200018)],
+    },
+];
+
+const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+const O200K_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+
+/// SentencePiece's stand-in for a space
+const SPACE: &str = "\u{2581}";
+
+/// The known tiktoken encoding named `name`, such as "cl100k_base"
+pub fn tiktoken_encoding(name: &str) -> Option<&'static TiktokenEncoding> {
+    TIKTOKEN_ENCODINGS
+        .iter()
+        .find(|encoding| encoding.name == name)
+}
+
+/// The characters GPT-2 style byte-level BPE stands each byte in for:
+/// printable bytes as themselves, the rest shifted to U+0100 onward
+pub fn byte_level_chars() -> Vec<char> {
+    let printable = |byte: u8| matches!(byte, b'!'..=b'~' | 0xa1..=0xac | 0xae..=0xff);
+    let mut shifted = 0;
+    (0..=255u8)
+        .map(|byte| {
+            if printable(byte) {
+                char::from(byte)
+            } else {
+                shifted += 1;
+                char::from_u32(255 + shifted).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+        })
+        .collect()
+}
+
+/// Converts tiktoken BPE ranks to a byte-level BPE `tokenizer.json` that
+/// splits text with `pattern` and has `special_tokens` at their IDs
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] if `bytes` is not a tiktoken file, its
+/// ranks do not run from 0 without gaps, a token is listed twice or is not
+/// a merge of two lower-ranked tokens, or a special token's ID is taken.
+pub fn tiktoken_to_json(
+    bytes: &[u8],
+    pattern: &str,
+    special_tokens: &[(String, u32)],
+) -> Result<Value> {
+    let invalid = |reason: String| Error::InvalidInput(reason);
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| invalid("not a tiktoken file: not UTF-8".to_string()))?;
+    let mut ranks: HashMap<Vec<u8>, u32> = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed = line.split_once(' ').and_then(|(token, rank)| {
+            Some((
+                BASE64_STANDARD.decode(token).ok()?,
+                rank.trim().parse().ok()?,
+            ))
+        });
+        let Some((token, rank)) = parsed else {
+            return Err(invalid(format!(
+                "not a tiktoken file: line {} is not a base64 token and a rank",
+                number + 1
+            )));
+        };
+        if let Some(earlier) = ranks.insert(token, rank) {
+            return Err(invalid(format!(
+                "line {} lists the token of rank {} again",
+                number + 1,
+                earlier
+            )));
+        }
+    }
+    let mut by_rank: Vec<(&[u8], u32)> = ranks
+        .iter()
+        .map(|(token, rank)| (token.as_slice(), *rank))
+        .collect();
+    by_rank.sort_by_key(|(_, rank)| *rank);
+    if let Some((expected, (_, rank))) = by_rank
+        .iter()
+        .enumerate()
+        .find(|(expected, (_, rank))| *rank as usize != *expected)
+    {
+        return Err(invalid(format!(
+            "ranks must run from 0 without gaps or repeats; found {} where {} was expected",
+            rank, expected
+        )));
+    }
+
+    // Added tokens take the IDs after the vocab in the order they are
+    // listed, so any IDs between the special tokens get placeholders
+    let mut specials = special_tokens.to_vec();
+    specials.sort_by_key(|(_, id)| *id);
+    let mut added_tokens = Vec::new();
+    let mut next = by_rank.len() as u32;
+    for (token, id) in &specials {
+        if *id < next {
+            return Err(invalid(format!(
+                "special token {:?} has ID {}, which is already taken",
+                token, id
+            )));
+        }
+        for unused in next..*id {
+            added_tokens.push(added_token(&format!("<|unused_{}|>", unused), unused, true));
+        }
+        added_tokens.push(added_token(token, *id, true));
+        next = id + 1;
+    }
+
+    let chars = byte_level_chars();
+    let as_chars = |bytes: &[u8]| -> String { bytes.iter().map(|&b| chars[b as usize]).collect() };
+    let mut vocab = Map::new();
+    let mut merges = Vec::new();
+    for &(token, rank) in &by_rank {
+        vocab.insert(as_chars(token), json!(rank));
+        if token.len() < 2 {
+            continue;
+        }
+        let lower = |part: &[u8]| ranks.get(part).is_some_and(|part_rank| *part_rank < rank);
+        match merge_with_lower_ranks(&ranks, token, rank)[..] {
+            [left, right] if lower(left) && lower(right) => {
+                merges.push(json!([as_chars(left), as_chars(right)]))
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "token {} ({:?}) is not a merge of two lower-ranked tokens",
+                    rank,
+                    as_chars(token)
+                )))
+            }
+        }
+    }
+
+    Ok(json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": added_tokens,
+        "normalizer": null,
+        "pre_tokenizer": {
+            "type": "Sequence",
+            "pretokenizers": [
+                {
+                    "type": "Split",
+                    "pattern": { "Regex": pattern },
+                    "behavior": "Isolated",
+                    "invert": false
+                },
+                {
+                    "type": "ByteLevel",
+                    "add_prefix_space": false,
+                    "trim_offsets": true,
+                    "use_regex": false
+                }
+            ]
+        },
+        "post_processor": {
+            "type": "ByteLevel",
+            "add_prefix_space": true,
+            "trim_offsets": false,
+            "use_regex": true
+        },
+        "decoder": {
+            "type": "ByteLevel",
+            "add_prefix_space": true,
+            "trim_offsets": true,
+            "use_regex": true
+        },
+        "model": {
+            "type": "BPE",
+            "dropout": null,
+            "unk_token": null,
+            "continuing_subword_prefix": null,
+            "end_of_word_suffix": null,
+            "fuse_unk": false,
+            "byte_fallback": false,
+            // As tiktoken does, a piece of text that is a token whole is
+            // that token, whatever the merges would make of it
+            "ignore_merges": true,
+            "vocab": vocab,
+            "merges": merges
+        }
+    }))
+}
+
+/// Splits `token` into bytes and merges them as BPE would with only the
+/// tokens ranked below `rank`; a token that BPE can reach comes out in two
+fn merge_with_lower_ranks<'a>(
+    ranks: &HashMap<Vec<u8>, u32>,
+    token: &'a [u8],
+    rank: u32,
+) -> Vec<&'a [u8]> {
+    // Where each part starts, plus the end of the token
+    let mut bounds: Vec<usize> = (0..=token.len()).collect();
+    loop {
+        let lowest = (0..bounds.len() - 2)
+            .filter_map(|i| {
+                let merged = ranks.get(&token[bounds[i]..bounds[i + 2]])?;
+                (*merged < rank).then_some((*merged, i))
+            })
+            .min();
+        let Some((_, i)) = lowest else {
+            break;
+        };
+        bounds.remove(i + 1);
+    }
+    bounds
+        .windows(2)
+        .map(|part| &token[part[0]..part[1]])
+        .collect()
+}
+
+/// `SentencePiece.Type` values in a `.model`
+mod piece_type {
+    pub const UNKNOWN: u64 = 2;
+    pub const CONTROL: u64 = 3;
+    pub const USER_DEFINED: u64 = 4;
+    pub const BYTE: u64 = 6;
+}
+
+/// What a SentencePiece `.model` holds that conversion needs
+struct SentencePieceModel {
+    /// Each piece's text, score, and type, in ID order
+    pieces: Vec<(String, f32, u64)>,
+    /// `TrainerSpec.model_type`: 1 for Unigram, 2 for BPE
+    model_type: u64,
+    byte_fallback: bool,
+    precompiled_charsmap: Vec<u8>,
+    add_dummy_prefix: bool,
+    remove_extra_whitespaces: bool,
+}
+
+/// Converts a SentencePiece `.model` to a Unigram or BPE `tokenizer.json`,
+/// with the normalization SentencePiece applies
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] if `bytes` is not a SentencePiece model
+/// protobuf, or its model is neither Unigram nor BPE.
+pub fn sentencepiece_to_json(bytes: &[u8]) -> Result<Value> {
+    let model = parse_sentencepiece(bytes)
+        .map_err(|e| Error::InvalidInput(format!("not a SentencePiece model: {}", e)))?;
+    let unk_id = model
+        .pieces
+        .iter()
+        .position(|(_, _, kind)| *kind == piece_type::UNKNOWN);
+    let added_tokens: Vec<Value> = model
+        .pieces
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, kind))| {
+            [
+                piece_type::UNKNOWN,
+                piece_type::CONTROL,
+                piece_type::USER_DEFINED,
+            ]
+            .contains(kind)
+        })
+        .map(|(id, (piece, _, kind))| {
+            added_token(piece, id as u32, *kind != piece_type::USER_DEFINED)
+        })
+        .collect();
+
+    let mut normalizers = Vec::new();
+    if !model.precompiled_charsmap.is_empty() {
+        normalizers.push(json!({
+            "type": "Precompiled",
+            "precompiled_charsmap": BASE64_STANDARD.encode(&model.precompiled_charsmap)
+        }));
+    }
+    if model.remove_extra_whitespaces {
+        normalizers.push(json!({ "type": "Strip", "strip_left": true, "strip_right": true }));
+        normalizers.push(replace(json!({ "Regex": " {2,}" }), " "));
+    }
+
+    let (model_json, pre_tokenizer, decoder) = match model.model_type {
+        1 => {
+            let vocab: Vec<Value> = model
+                .pieces
+                .iter()
+                .map(|(piece, score, _)| json!([piece, score]))
+                .collect();
+            // Spaces become "▁" while splitting, and "▁" spaces again
+            let metaspace = json!({
+                "type": "Metaspace",
+                "replacement": SPACE,
+                "prepend_scheme": if model.add_dummy_prefix { "always" } else { "never" },
+                "split": true
+            });
+            let model_json = json!({
+                "type": "Unigram",
+                "unk_id": unk_id,
+                "vocab": vocab,
+                "byte_fallback": model.byte_fallback
+            });
+            (model_json, metaspace.clone(), metaspace)
+        }
+        2 => {
+            if model.add_dummy_prefix {
+                normalizers.push(json!({ "type": "Prepend", "prepend": SPACE }));
+            }
+            normalizers.push(replace(json!({ "String": " " }), SPACE));
+            let vocab: Map<String, Value> = model
+                .pieces
+                .iter()
+                .enumerate()
+                .map(|(id, (piece, _, _))| (piece.clone(), json!(id)))
+                .collect();
+            let model_json = json!({
+                "type": "BPE",
+                "dropout": null,
+                "unk_token": unk_id.map(|id| &model.pieces[id].0),
+                "continuing_subword_prefix": null,
+                "end_of_word_suffix": null,
+                "fuse_unk": true,
+                "byte_fallback": model.byte_fallback,
+                "ignore_merges": false,
+                "vocab": vocab,
+                "merges": sentencepiece_merges(&model.pieces)
+            });
+            let decoder = json!({
+                "type": "Sequence",
+                "decoders": [
+                    replace(json!({ "String": SPACE }), " "),
+                    { "type": "ByteFallback" },
+                    { "type": "Fuse" },
+                    {
+                        "type": "Strip",
+                        "content": " ",
+                        "start": usize::from(model.add_dummy_prefix),
+                        "stop": 0
+                    }
+                ]
+            });
+            (model_json, Value::Null, decoder)
+        }
+        3 => return Err(unsupported("Word")),
+        4 => return Err(unsupported("Char")),
+        other => {
+            return Err(Error::InvalidInput(format!(
+                "unknown SentencePiece model type {}",
+                other
+            )))
+        }
+    };
+
+    Ok(json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": added_tokens,
+        "normalizer": match normalizers.len() {
+            0 => Value::Null,
+            _ => json!({ "type": "Sequence", "normalizers": normalizers }),
+        },
+        "pre_tokenizer": pre_tokenizer,
+        "post_processor": null,
+        "decoder": decoder,
+        "model": model_json
+    }))
+}
+
+/// The error for a SentencePiece model type conversion does not handle
+fn unsupported(kind: &str) -> Error {
+    Error::InvalidInput(format!(
+        "{} models are not supported, only Unigram and BPE",
+        kind
+    ))
+}
+
+/// Merges of a SentencePiece BPE model: every split of a piece into two
+/// other pieces, ranked by the ID of the piece they make, then of the
+/// left and right pieces
+fn sentencepiece_merges(pieces: &[(String, f32, u64)]) -> Vec<Value> {
+    let mergeable =
+        |kind: u64| ![piece_type::CONTROL, piece_type::UNKNOWN, piece_type::BYTE].contains(&kind);
+    let ids: HashMap<&str, usize> = pieces
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, kind))| mergeable(*kind))
+        .map(|(id, (piece, _, _))| (piece.as_str(), id))
+        .collect();
+    let mut merges = Vec::new();
+    for (id, (piece, _, kind)) in pieces.iter().enumerate() {
+        if !mergeable(*kind) {
+            continue;
+        }
+        for (split, _) in piece.char_indices().skip(1) {
+            let (left, right) = piece.split_at(split);
+            if let (Some(left_id), Some(right_id)) = (ids.get(left), ids.get(right)) {
+                merges.push(((id, *left_id, *right_id), left, right));
+            }
+        }
+    }
+    merges.sort_by_key(|(order, _, _)| *order);
+    merges
+        .into_iter()
+        .map(|(_, left, right)| json!([left, right]))
+        .collect()
+}
+
+/// Reads the parts of a SentencePiece `ModelProto` that conversion needs;
+/// errors are reasons, without the "not a SentencePiece model" they go under
+fn parse_sentencepiece(bytes: &[u8]) -> std::result::Result<SentencePieceModel, String> {
+    let mut model = SentencePieceModel {
+        pieces: Vec::new(),
+        model_type: 1,
+        byte_fallback: false,
+        precompiled_charsmap: Vec::new(),
+        add_dummy_prefix: true,
+        remove_extra_whitespaces: true,
+    };
+    for field in Protobuf(bytes) {
+        match field? {
+            // ModelProto.pieces
+            (1, Field::Bytes(piece)) => {
+                let (mut text, mut score, mut kind) = (String::new(), 0.0, 1);
+                for field in Protobuf(piece) {
+                    match field? {
+                        (1, Field::Bytes(bytes)) => {
+                            text = String::from_utf8(bytes.to_vec())
+                                .map_err(|_| "a piece is not UTF-8".to_string())?;
+                        }
+                        (2, Field::Fixed32(bits)) => score = f32::from_bits(bits),
+                        (3, Field::Varint(value)) => kind = value,
+                        _ => {}
+                    }
+                }
+                model.pieces.push((text, score, kind));
+            }
+            // ModelProto.trainer_spec
+            (2, Field::Bytes(spec)) => {
+                for field in Protobuf(spec) {
+                    match field? {
+                        (3, Field::Varint(value)) => model.model_type = value,
+                        (35, Field::Varint(value)) => model.byte_fallback = value != 0,
+                        _ => {}
+                    }
+                }
+            }
+            // ModelProto.normalizer_spec
+            (3, Field::Bytes(spec)) => {
+                for field in Protobuf(spec) {
+                    match field? {
+                        (2, Field::Bytes(charsmap)) => {
+                            model.precompiled_charsmap = charsmap.to_vec();
+                        }
+                        (3, Field::Varint(value)) => model.add_dummy_prefix = value != 0,
+                        (4, Field::Varint(value)) => model.remove_extra_whitespaces = value != 0,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if model.pieces.is_empty() {
+        return Err("no pieces".to_string());
+    }
+    Ok(model)
+}
+
+/// A protobuf field's value, by wire type
+#[derive(Debug, PartialEq)]
+enum Field<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// The fields of a protobuf message, as (field number, value)
+struct Protobuf<'a>(&'a [u8]);
+
+impl<'a> Protobuf<'a> {
+    fn varint(&mut self) -> std::result::Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .0
+                .split_first()
+                .ok_or_else(|| "truncated varint".to_string())?;
+            self.0 = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint is too long".to_string())
+    }
+
+    fn take(&mut self, len: u64) -> std::result::Result<&'a [u8], String> {
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= self.0.len())
+            .ok_or_else(|| "truncated field".to_string())?;
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+}
+
+impl<'a> Iterator for Protobuf<'a> {
+    type Item = std::result::Result<(u64, Field<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = (|| {
+            let key = self.varint()?;
+            let value = match key & 7 {
+                0 => Field::Varint(self.varint()?),
+                1 => {
+                    self.take(8)?;
+                    Field::Fixed64
+                }
+                2 => {
+                    let len = self.varint()?;
+                    Field::Bytes(self.take(len)?)
+                }
+                5 => {
+                    let bytes = self.take(4)?;
+                    Field::Fixed32(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                }
+                wire_type => return Err(format!("unsupported wire type {}", wire_type)),
+            };
+            Ok((key >> 3, value))
+        })();
+        if field.is_err() {
+            // Nothing after a malformed field can be read
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+/// An `added_tokens` entry of a `tokenizer.json`
+fn added_token(content: &str, id: u32, special: bool) -> Value {
+    json!({
+        "id": id,
+        "content": content,
+        "single_word": false,
+        "lstrip": false,
+        "rstrip": false,
+        "normalized": !special,
+        "special": special
+    })
+}
+
+/// A `Replace` normalizer or decoder
+fn replace(pattern: Value, content: &str) -> Value {
+    json!({ "type": "Replace", "pattern": pattern, "content": content })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::load_tokenizer_from_bytes;
+    use tokenizers::Tokenizer;
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    fn varint_field(number: u64, value: u64) -> Vec<u8> {
+        [varint(number << 3), varint(value)].concat()
+    }
+
+    fn bytes_field(number: u64, bytes: &[u8]) -> Vec<u8> {
+        [
+            varint(number << 3 | 2),
+            varint(bytes.len() as u64),
+            bytes.to_vec(),
+        ]
+        .concat()
+    }
+
+    /// A SentencePiece `ModelProto` of `model_type` with `pieces` and the
+    /// default normalization
+    pub(crate) fn sentencepiece_model(model_type: u64, pieces: &[(&str, f32, u64)]) -> Vec<u8> {
+        let mut model = Vec::new();
+        for (text, score, kind) in pieces {
+            let piece = [
+                bytes_field(1, text.as_bytes()),
+                varint(2 << 3 | 5),
+                score.to_bits().to_le_bytes().to_vec(),
+                varint_field(3, *kind),
+            ]
+            .concat();
+            model.extend(bytes_field(1, &piece));
+        }
+        model.extend(bytes_field(2, &varint_field(3, model_type)));
+        model.extend(bytes_field(3, &varint_field(3, 1)));
+        model
+    }
+
+    fn load(json: &Value) -> Tokenizer {
+        load_tokenizer_from_bytes(json.to_string().as_bytes()).unwrap()
+    }
+
+    fn ids(tokenizer: &Tokenizer, text: &str) -> Vec<u32> {
+        tokenizer.encode(text, false).unwrap().get_ids().to_vec()
+    }
+
+    fn invalid_reason(result: Result<Value>) -> String {
+        match result {
+            Err(Error::InvalidInput(reason)) => reason,
+            other => panic!("expected invalid input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_protobuf_fields() {
+        let message = [
+            varint_field(1, 300),
+            varint(2 << 3 | 1),
+            vec![0; 8],
+            bytes_field(3, b"abc"),
+            varint(4 << 3 | 5),
+            vec![1, 0, 0, 0],
+        ]
+        .concat();
+        let fields: Vec<_> = Protobuf(&message).collect();
+        assert_eq!(
+            fields,
+            vec![
+                Ok((1, Field::Varint(300))),
+                Ok((2, Field::Fixed64)),
+                Ok((3, Field::Bytes(&b"abc"[..]))),
+                Ok((4, Field::Fixed32(1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_protobuf_malformed_fields() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0x08], "truncated varint"),
+            (&[0x08, 0x80], "truncated varint"),
+            (&[0x80], "truncated varint"),
+            (
+                &[
+                    0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01,
+                ],
+                "varint is too long",
+            ),
+            (&[0x0a, 0x05, b'a', b'b'], "truncated field"),
+            (
+                &[
+                    0x0a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+                ],
+                "truncated field",
+            ),
+            (&[0x0a], "truncated varint"),
+            (&[0x09, 0, 0, 0], "truncated field"),
+            (&[0x0d, 0, 0], "truncated field"),
+            (&[0x0b], "unsupported wire type 3"),
+        ];
+        for (bytes, reason) in cases {
+            // The error ends the message, with nothing read past it
+            let fields: Vec<_> = Protobuf(bytes).collect();
+            assert_eq!(fields, vec![Err(reason.to_string())], "{:?}", bytes);
+        }
+
+        let mut model = sentencepiece_model(1, &[("<unk>", 0.0, 2)]);
+        model.truncate(model.len() - 1);
+        assert_eq!(
+            invalid_reason(sentencepiece_to_json(&model)),
+            "not a SentencePiece model: truncated field"
+        );
+        assert_eq!(
+            invalid_reason(sentencepiece_to_json(b"")),
+            "not a SentencePiece model: no pieces"
+        );
+    }
+
+    #[test]
+    fn test_tiktoken_rejects_malformed_ranks() {
+        // "a", "b", and "ab" in base64
+        let cases = [
+            ("YQ== 0\nYg== 2\n", "found 2 where 1 was expected"),
+            ("YQ== 0\nYg== 0\n", "found 0 where 1 was expected"),
+            ("YQ== 1\nYg== 2\n", "found 1 where 0 was expected"),
+            (
+                "YQ== 0\nYg== 1\nYQ== 2\n",
+                "line 3 lists the token of rank 0 again",
+            ),
+            ("YQ== 0\nYWI= 1\n", "token 1 (\"ab\") is not a merge"),
+            (
+                "YQ== 0\nnot base64!\n",
+                "line 2 is not a base64 token and a rank",
+            ),
+            ("YQ== zero\n", "line 1 is not a base64 token and a rank"),
+        ];
+        for (file, reason) in cases {
+            let error = invalid_reason(tiktoken_to_json(file.as_bytes(), GPT2_PATTERN, &[]));
+            assert!(error.contains(reason), "{:?}: {}", file, error);
+        }
+
+        let taken = [("<|end|>".to_string(), 1)];
+        let error = invalid_reason(tiktoken_to_json(b"YQ== 0\nYg== 1\n", GPT2_PATTERN, &taken));
+        assert!(error.contains("already taken"), "{}", error);
+    }
+
+    #[test]
+    fn test_tiktoken_round_trip_matches_gpt2() {
+        // r50k_base is GPT-2's vocabulary, so writing GPT-2 out as a
+        // tiktoken file and converting it back must encode identically
+        let gpt2 =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let bytes: HashMap<char, u8> = byte_level_chars().into_iter().zip(0..=255).collect();
+        let mut vocab: Vec<(String, u32)> = gpt2.get_vocab(false).into_iter().collect();
+        vocab.retain(|(token, _)| token != "<|endoftext|>");
+        let file: String = vocab
+            .iter()
+            .map(|(token, id)| {
+                let token: Vec<u8> = token.chars().map(|c| bytes[&c]).collect();
+                format!("{} {}\n", BASE64_STANDARD.encode(token), id)
+            })
+            .collect();
+
+        let encoding = tiktoken_encoding("r50k_base").unwrap();
+        let specials: Vec<(String, u32)> = encoding
+            .special_tokens
+            .iter()
+            .map(|(token, id)| (token.to_string(), *id))
+            .collect();
+        let converted =
+            load(&tiktoken_to_json(file.as_bytes(), encoding.pattern, &specials).unwrap());
+        assert_eq!(converted.get_vocab_size(true), gpt2.get_vocab_size(true));
+        for text in [
+            "Hello, world! It's 2024 and we're counting tokens.",
+            "  leading spaces,\ttabs\n\nand blank lines   ",
+            "naïve café — 日本語のテキスト 🎉",
+            "fn main() { println!(\"{}\", 1_000_000); }",
+            "one<|endoftext|>two",
+        ] {
+            assert_eq!(ids(&converted, text), ids(&gpt2, text), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_sentencepiece_unigram() {
+        let model = sentencepiece_model(
+            1,
+            &[
+                ("<unk>", 0.0, 2),
+                ("<s>", 0.0, 3),
+                ("</s>", 0.0, 3),
+                ("\u{2581}hello", -1.0, 1),
+                ("\u{2581}world", -1.5, 1),
+                ("\u{2581}", -3.0, 1),
+                ("h", -5.0, 1),
+                ("e", -5.0, 1),
+                ("l", -5.0, 1),
+                ("o", -5.0, 1),
+            ],
+        );
+        let converted = sentencepiece_to_json(&model).unwrap();
+        assert_eq!(converted["model"]["type"], "Unigram");
+        let tokenizer = load(&converted);
+        assert_eq!(tokenizer.get_vocab_size(true), 10);
+        // Extra whitespace is removed, as SentencePiece does
+        assert_eq!(ids(&tokenizer, "  hello   world "), vec![3, 4]);
+        assert_eq!(ids(&tokenizer, "hello hole"), vec![3, 5, 6, 9, 8, 7]);
+        assert_eq!(ids(&tokenizer, "hello!"), vec![3, 0]);
+        assert_eq!(tokenizer.decode(&[3, 4], false).unwrap(), "hello world");
+    }
+
+    #[test]
+    fn test_sentencepiece_bpe() {
+        let model = sentencepiece_model(
+            2,
+            &[
+                ("<unk>", 0.0, 2),
+                ("<s>", 0.0, 3),
+                ("</s>", 0.0, 3),
+                ("he", -1.0, 1),
+                ("ll", -2.0, 1),
+                ("hell", -3.0, 1),
+                ("hello", -4.0, 1),
+                ("\u{2581}hello", -5.0, 1),
+                ("\u{2581}", -6.0, 1),
+                ("h", -7.0, 1),
+                ("e", -7.0, 1),
+                ("l", -7.0, 1),
+                ("o", -7.0, 1),
+            ],
+        );
+        let converted = sentencepiece_to_json(&model).unwrap();
+        assert_eq!(converted["model"]["type"], "BPE");
+        assert_eq!(
+            converted["model"]["merges"],
+            json!([
+                ["h", "e"],
+                ["l", "l"],
+                ["he", "ll"],
+                ["hell", "o"],
+                ["\u{2581}", "hello"]
+            ])
+        );
+        let tokenizer = load(&converted);
+        assert_eq!(ids(&tokenizer, "hello"), vec![7]);
+        assert_eq!(ids(&tokenizer, "hello hell"), vec![7, 8, 5]);
+        assert_eq!(ids(&tokenizer, "hello!"), vec![7, 0]);
+        assert_eq!(tokenizer.decode(&[7, 8, 5], false).unwrap(), "hello hell");
+    }
+
+    #[test]
+    fn test_sentencepiece_rejects_unsupported_models() {
+        let model = sentencepiece_model(3, &[("<unk>", 0.0, 2)]);
+        assert_eq!(
+            invalid_reason(sentencepiece_to_json(&model)),
+            "Word models are not supported, only Unigram and BPE"
+        );
+    }
+}
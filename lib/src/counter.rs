@@ -0,0 +1,228 @@
+//! A configured token counter
+//!
+//! [`TokenCounter`] bundles a tokenizer with the options that affect how
+//! text is counted, so new options can be added to the builder without
+//! changing the signature of every counting call.
+
+use crate::{
+    count_stats, count_tokens, encode_lengths, special_token_count, Error, Pipeline, Preprocessor,
+    Result, TokenStats,
+};
+#[cfg(feature = "fs")]
+use crate::{load_tokenizer, progress::count_each, read_document_text, Progress};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
+
+/// Counts tokens with a fixed tokenizer and set of options
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{StripMarkdown, TokenCounter};
+///
+/// let counter = TokenCounter::builder()
+///     .tokenizer_path("tokenizer.json")
+///     .add_special_tokens(true)
+///     .normalize(StripMarkdown::default())
+///     .build()
+///     .unwrap();
+/// let tokens = counter.count("# Hello, *world*").unwrap();
+/// assert!(tokens > 0);
+/// ```
+#[derive(Debug)]
+pub struct TokenCounter {
+    tokenizer: Tokenizer,
+    add_special_tokens: bool,
+    pipeline: Pipeline,
+}
+
+impl TokenCounter {
+    /// Starts configuring a counter
+    pub fn builder() -> TokenCounterBuilder {
+        TokenCounterBuilder::default()
+    }
+
+    /// Creates a counter with default options for `tokenizer`
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        Self {
+            tokenizer,
+            add_special_tokens: false,
+            pipeline: Pipeline::new(),
+        }
+    }
+
+    /// The tokenizer used for counting
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    /// Counts the tokens in `text`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a normalization stage or encoding fails
+    pub fn count(&self, text: &str) -> Result<usize> {
+        let text = self.pipeline.apply(text)?;
+        count_tokens(&text, &self.tokenizer, self.add_special_tokens)
+    }
+
+    /// Counts the tokens in each of `texts`, encoding them in parallel
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a normalization stage or encoding fails
+    pub fn count_batch(&self, texts: &[&str]) -> Result<Vec<usize>> {
+        let texts = texts
+            .iter()
+            .map(|text| self.pipeline.apply(text))
+            .collect::<Result<Vec<_>>>()?;
+        let texts: Vec<&str> = texts.iter().map(|text| text.as_ref()).collect();
+        encode_lengths(&texts, &self.tokenizer, self.add_special_tokens)
+    }
+
+    /// Counts the tokens, lines, and bytes of `text`
+    ///
+    /// Lines and bytes are those of the normalized text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a normalization stage or encoding fails
+    pub fn stats(&self, text: &str) -> Result<TokenStats> {
+        let text = self.pipeline.apply(text)?;
+        let mut stats = count_stats(&text, &self.tokenizer)?;
+        if self.add_special_tokens {
+            stats.tokens += special_token_count(&self.tokenizer);
+        }
+        Ok(stats)
+    }
+
+    /// Counts the tokens, lines, and bytes of a file, extracting the text of
+    /// documents such as PDFs first, and records its path in the stats
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or counting fails
+    #[cfg(feature = "fs")]
+    pub fn count_file<P: AsRef<Path>>(&self, path: P) -> Result<TokenStats> {
+        let path = path.as_ref();
+        Ok(TokenStats {
+            path: Some(path.to_path_buf()),
+            ..self.stats(&read_document_text(path)?)?
+        })
+    }
+
+    /// Counts each of `paths` as [`TokenCounter::count_file`] does, calling
+    /// `progress` after each file
+    ///
+    /// A file that cannot be read or counted fails on its own; the others
+    /// are still counted.
+    #[cfg(feature = "fs")]
+    pub fn count_files<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        progress: Option<&mut dyn FnMut(Progress)>,
+    ) -> Vec<Result<TokenStats>> {
+        count_each(paths, progress, |path| self.count_file(path))
+    }
+}
+
+/// Builder for [`TokenCounter`]
+#[derive(Debug, Default)]
+pub struct TokenCounterBuilder {
+    tokenizer: Option<Tokenizer>,
+    #[cfg(feature = "fs")]
+    tokenizer_path: Option<PathBuf>,
+    add_special_tokens: bool,
+    pipeline: Pipeline,
+}
+
+impl TokenCounterBuilder {
+    /// Counts with `tokenizer`
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = Some(tokenizer);
+        #[cfg(feature = "fs")]
+        {
+            self.tokenizer_path = None;
+        }
+        self
+    }
+
+    /// Counts with the tokenizer loaded from a JSON file or model directory
+    /// when the counter is built
+    #[cfg(feature = "fs")]
+    pub fn tokenizer_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.tokenizer_path = Some(path.into());
+        self.tokenizer = None;
+        self
+    }
+
+    /// Also counts the special tokens the tokenizer wraps a sequence in
+    /// (default: false)
+    pub fn add_special_tokens(mut self, add_special_tokens: bool) -> Self {
+        self.add_special_tokens = add_special_tokens;
+        self
+    }
+
+    /// Runs `stage` over text before it is counted, after any stages added
+    /// earlier
+    pub fn normalize<P: Preprocessor + 'static>(mut self, stage: P) -> Self {
+        self.pipeline.push(stage);
+        self
+    }
+
+    /// Builds the counter
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no tokenizer was given or it cannot be loaded
+    pub fn build(self) -> Result<TokenCounter> {
+        #[cfg(feature = "fs")]
+        let tokenizer = match (self.tokenizer, self.tokenizer_path) {
+            (None, Some(path)) => Some(load_tokenizer(path)?),
+            (tokenizer, _) => tokenizer,
+        };
+        #[cfg(not(feature = "fs"))]
+        let tokenizer = self.tokenizer;
+        let tokenizer = tokenizer
+            .ok_or_else(|| Error::InvalidInput("a token counter needs a tokenizer".to_string()))?;
+        Ok(TokenCounter {
+            tokenizer,
+            add_special_tokens: self.add_special_tokens,
+            pipeline: self.pipeline,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StripMarkdown;
+
+    #[test]
+    fn test_token_counter() {
+        let tokenizer = || {
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/tokenizers/bert.json")).unwrap()
+        };
+
+        let plain = TokenCounter::new(tokenizer());
+        assert_eq!(plain.count("Hello, world").unwrap(), 3);
+
+        let counter = TokenCounter::builder()
+            .tokenizer(tokenizer())
+            .add_special_tokens(true)
+            .normalize(StripMarkdown::default())
+            .build()
+            .unwrap();
+        assert_eq!(counter.count("**Hello**, world").unwrap(), 5);
+        assert_eq!(
+            counter.count_batch(&["**Hello**, world", "Hi"]).unwrap(),
+            [5, 3]
+        );
+        let stats = counter.stats("**Hello**, world\n").unwrap();
+        assert_eq!(stats.tokens, 5);
+        assert_eq!(stats.lines, 1);
+
+        assert!(TokenCounter::builder().build().is_err());
+    }
+}
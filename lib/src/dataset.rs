@@ -0,0 +1,121 @@
+//! Per-record token statistics for JSONL datasets
+
+use crate::{Error, Result};
+use serde_json::Value;
+
+/// Extracts the text of `field` from one JSONL record
+///
+/// String values are returned as-is; other values (arrays, objects, numbers)
+/// are returned as their JSON serialization. Returns `None` when the record
+/// has no such field.
+///
+/// # Arguments
+///
+/// * `line` - A single JSON object
+/// * `field` - Name of the top-level field to extract
+///
+/// # Errors
+///
+/// Returns an error if the line is not valid JSON
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::record_field;
+///
+/// let text = record_field(r#"{"id": 1, "text": "hello"}"#, "text").unwrap();
+/// assert_eq!(text.as_deref(), Some("hello"));
+/// ```
+pub fn record_field(line: &str, field: &str) -> Result<Option<String>> {
+    let record: Value =
+        serde_json::from_str(line).map_err(|e| Error::InvalidInput(format!("{}", e)))?;
+    Ok(record.get(field).map(|value| match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }))
+}
+
+/// Summary of how token counts are distributed across records
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenDistribution {
+    sorted: Vec<usize>,
+}
+
+impl TokenDistribution {
+    /// Builds a distribution from per-record counts, or `None` if there are none
+    pub fn new(mut counts: Vec<usize>) -> Option<Self> {
+        if counts.is_empty() {
+            return None;
+        }
+        counts.sort_unstable();
+        Some(Self { sorted: counts })
+    }
+
+    /// Number of records
+    pub fn count(&self) -> usize {
+        self.sorted.len()
+    }
+
+    /// Smallest record
+    pub fn min(&self) -> usize {
+        self.sorted[0]
+    }
+
+    /// Largest record
+    pub fn max(&self) -> usize {
+        self.sorted[self.sorted.len() - 1]
+    }
+
+    /// Tokens across all records
+    pub fn total(&self) -> usize {
+        self.sorted.iter().sum()
+    }
+
+    /// Mean tokens per record
+    pub fn mean(&self) -> f64 {
+        self.total() as f64 / self.count() as f64
+    }
+
+    /// Nearest-rank percentile, with `percent` between 0 and 100
+    pub fn percentile(&self, percent: f64) -> usize {
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * self.count() as f64).ceil() as usize;
+        self.sorted[rank.saturating_sub(1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_field() {
+        assert_eq!(
+            record_field(r#"{"text": "hi"}"#, "text")
+                .unwrap()
+                .as_deref(),
+            Some("hi")
+        );
+        assert_eq!(
+            record_field(r#"{"tags": ["a", "b"]}"#, "tags")
+                .unwrap()
+                .as_deref(),
+            Some(r#"["a","b"]"#)
+        );
+        assert_eq!(record_field(r#"{"id": 1}"#, "text").unwrap(), None);
+        assert!(record_field("{oops", "text").is_err());
+    }
+
+    #[test]
+    fn test_distribution() {
+        assert!(TokenDistribution::new(Vec::new()).is_none());
+
+        let dist = TokenDistribution::new((1..=100).rev().collect()).unwrap();
+        assert_eq!(dist.count(), 100);
+        assert_eq!((dist.min(), dist.max()), (1, 100));
+        assert_eq!(dist.mean(), 50.5);
+        assert_eq!(dist.percentile(50.0), 50);
+        assert_eq!(dist.percentile(95.0), 95);
+        assert_eq!(dist.percentile(0.0), 1);
+        assert_eq!(dist.percentile(100.0), 100);
+    }
+}
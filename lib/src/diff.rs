@@ -0,0 +1,131 @@
+//! Parsing of unified diffs (as produced by `git diff`) into added and removed text
+
+/// Text added and removed in one file of a diff
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileDiff {
+    /// Path of the file (the new path, or the old path for deletions)
+    pub path: String,
+    /// Added lines, joined with newlines
+    pub added: String,
+    /// Removed lines, joined with newlines
+    pub removed: String,
+}
+
+/// Parses a unified diff into per-file added and removed text
+///
+/// Binary files and files without content changes (e.g. pure renames or
+/// mode changes) are returned with empty `added` and `removed` text.
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::parse_unified_diff;
+///
+/// let diff = "diff --git a/notes.md b/notes.md\n\
+///             --- a/notes.md\n\
+///             +++ b/notes.md\n\
+///             @@ -1 +1 @@\n\
+///             -old line\n\
+///             +new line\n";
+/// let files = parse_unified_diff(diff);
+/// assert_eq!(files[0].path, "notes.md");
+/// assert_eq!(files[0].added, "new line\n");
+/// assert_eq!(files[0].removed, "old line\n");
+/// ```
+pub fn parse_unified_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut in_hunk = false;
+
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            files.push(FileDiff {
+                path: path_from_git_header(header),
+                ..FileDiff::default()
+            });
+            in_hunk = false;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk {
+            if let Some(text) = line.strip_prefix('+') {
+                file.added.push_str(text);
+                file.added.push('\n');
+            } else if let Some(text) = line.strip_prefix('-') {
+                file.removed.push_str(text);
+                file.removed.push('\n');
+            }
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if path != "/dev/null" {
+                file.path = strip_diff_prefix(path, "b/");
+            }
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            if path != "/dev/null" {
+                file.path = strip_diff_prefix(path, "a/");
+            }
+        }
+    }
+
+    files
+}
+
+/// Best-effort path from `a/old b/new` (used when no `---`/`+++` lines follow)
+fn path_from_git_header(header: &str) -> String {
+    match header.rfind(" b/") {
+        Some(index) => header[index + 3..].to_string(),
+        None => header.to_string(),
+    }
+}
+
+fn strip_diff_prefix(path: &str, prefix: &str) -> String {
+    let path = path.trim_matches('"');
+    path.strip_prefix(prefix).unwrap_or(path).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multiple_files() {
+        let diff = "\
+diff --git a/a.md b/a.md
+index 1111111..2222222 100644
+--- a/a.md
++++ b/a.md
+@@ -1,0 +2,2 @@
++first
++--second
+diff --git a/gone.md b/gone.md
+deleted file mode 100644
+--- a/gone.md
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+\\ No newline at end of file
+diff --git a/img.png b/img.png
+Binary files a/img.png and b/img.png differ
+";
+        let files = parse_unified_diff(diff);
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].path, "a.md");
+        assert_eq!(files[0].added, "first\n--second\n");
+        assert_eq!(files[0].removed, "");
+
+        assert_eq!(files[1].path, "gone.md");
+        assert_eq!(files[1].removed, "bye\n");
+
+        assert_eq!(files[2].path, "img.png");
+        assert!(files[2].added.is_empty() && files[2].removed.is_empty());
+    }
+
+    #[test]
+    fn test_parse_empty_diff() {
+        assert!(parse_unified_diff("").is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Text extraction from document formats
+//!
+//! Binary documents are detected by extension or magic bytes and reduced to
+//! their text, which then goes through the same counting (and preprocessing)
+//! as any plain-text input. Extractors that need extra dependencies are
+//! behind cargo features.
+
+#[cfg(feature = "fs")]
+use crate::{decode_text, read_decompressed, text_from_bytes, Compression, TextEncoding};
+use crate::{parse_notebook, Error, Result};
+use std::io;
+use std::path::Path;
+
+/// Main body of a Word document inside its zip archive
+const DOCX_DOCUMENT: &str = "word/document.xml";
+
+/// Document formats whose text can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// Portable Document Format (requires the `pdf` feature)
+    Pdf,
+    /// Word document (requires the `docx` feature)
+    Docx,
+    /// Jupyter notebook, reduced to its cell sources
+    Notebook,
+}
+
+impl DocumentFormat {
+    /// Detects a document format from the file extension or magic bytes
+    ///
+    /// Returns `None` for plain text.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        if extension.as_deref() == Some("pdf") || bytes.starts_with(b"%PDF-") {
+            return Some(Self::Pdf);
+        }
+        if extension.as_deref() == Some("ipynb") {
+            return Some(Self::Notebook);
+        }
+        // A docx is a zip archive whose entry names are stored uncompressed
+        if extension.as_deref() == Some("docx")
+            || (bytes.starts_with(b"PK\x03\x04") && contains(bytes, DOCX_DOCUMENT.as_bytes()))
+        {
+            return Some(Self::Docx);
+        }
+        None
+    }
+
+    /// Short human-readable name of the format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Docx => "DOCX",
+            Self::Notebook => "notebook",
+        }
+    }
+}
+
+/// Extracts the text of a document
+///
+/// # Arguments
+///
+/// * `format` - The document format
+/// * `bytes` - The document contents
+///
+/// # Errors
+///
+/// Returns an error if the document cannot be parsed, or if support for the
+/// format was not compiled in
+pub fn extract_text(format: DocumentFormat, bytes: &[u8]) -> Result<String> {
+    match format {
+        DocumentFormat::Pdf => extract_pdf(bytes),
+        DocumentFormat::Docx => extract_docx(bytes),
+        DocumentFormat::Notebook => extract_notebook(bytes),
+    }
+}
+
+/// Reads a file as text, extracting the text of recognized documents
+///
+/// Compressed files (see [`Compression`]) are decompressed first.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, a document cannot be
+/// extracted, or a plain-text file is not valid UTF-8
+#[cfg(feature = "fs")]
+pub fn read_document_text<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let bytes = read_decompressed(path)?;
+
+    match DocumentFormat::detect(&Compression::inner_path(path), &bytes) {
+        Some(format) => extract_text(format, &bytes),
+        None => text_from_bytes(path, bytes),
+    }
+}
+
+/// Reads a file as text in `encoding`, extracting the text of recognized
+/// documents
+///
+/// Like [`read_document_text`], but plain-text files are transcoded with
+/// [`decode_text`], so UTF-16, Latin-1, and Shift JIS files read as the text
+/// they hold.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, a document cannot be
+/// extracted, or a plain-text file read as UTF-8 is not valid UTF-8
+#[cfg(feature = "fs")]
+pub fn read_document_text_with_encoding<P: AsRef<Path>>(
+    path: P,
+    encoding: TextEncoding,
+) -> Result<String> {
+    let path = path.as_ref();
+    let bytes = read_decompressed(path)?;
+
+    match DocumentFormat::detect(&Compression::inner_path(path), &bytes) {
+        Some(format) => extract_text(format, &bytes),
+        None => decode_text(bytes, encoding).map_err(|e| Error::InvalidUtf8 {
+            path: path.to_path_buf(),
+            offset: e.utf8_error().valid_up_to(),
+        }),
+    }
+}
+
+/// Joins the cell sources of a notebook, leaving out outputs and metadata
+fn extract_notebook(bytes: &[u8]) -> Result<String> {
+    let json = std::str::from_utf8(bytes)
+        .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    let sources: Vec<_> = parse_notebook(json)?
+        .into_iter()
+        .map(|cell| cell.source)
+        .collect();
+    Ok(sources.join("\n\n"))
+}
+
+#[cfg(feature = "pdf")]
+fn extract_pdf(bytes: &[u8]) -> Result<String> {
+    pdf_extract::extract_text_from_mem(bytes).map_err(|e| Error::Extraction(format!("{}", e)))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn extract_pdf(_bytes: &[u8]) -> Result<String> {
+    Err(Error::Extraction(
+        "PDF support is not enabled (build with the `pdf` feature)".to_string(),
+    ))
+}
+
+#[cfg(feature = "docx")]
+fn extract_docx(bytes: &[u8]) -> Result<String> {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use std::io::{Cursor, Read};
+
+    let failed = |e: &dyn std::fmt::Display| Error::Extraction(format!("{}", e));
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| failed(&e))?;
+    let mut xml = String::new();
+    archive
+        .by_name(DOCX_DOCUMENT)
+        .map_err(|e| failed(&e))?
+        .read_to_string(&mut xml)?;
+
+    // Text lives in <w:t> runs; paragraphs, breaks, and tabs become whitespace
+    let mut reader = Reader::from_str(&xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event().map_err(|e| failed(&e))? {
+            Event::Start(e) if e.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(e) => match e.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"w:tab" => text.push('\t'),
+                b"w:br" | b"w:cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(t) if in_text => text.push_str(&t.unescape().map_err(|e| failed(&e))?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(not(feature = "docx"))]
+fn extract_docx(_bytes: &[u8]) -> Result<String> {
+    Err(Error::Extraction(
+        "DOCX support is not enabled (build with the `docx` feature)".to_string(),
+    ))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_document_format() {
+        let pdf = Some(DocumentFormat::Pdf);
+        assert_eq!(DocumentFormat::detect(Path::new("a.PDF"), b""), pdf);
+        assert_eq!(DocumentFormat::detect(Path::new("a"), b"%PDF-1.7\n"), pdf);
+        assert_eq!(DocumentFormat::detect(Path::new("a.txt"), b"hello"), None);
+
+        let docx = Some(DocumentFormat::Docx);
+        assert_eq!(DocumentFormat::detect(Path::new("a.docx"), b""), docx);
+        let zip = b"PK\x03\x04....word/document.xml....";
+        assert_eq!(DocumentFormat::detect(Path::new("a"), zip), docx);
+        assert_eq!(
+            DocumentFormat::detect(Path::new("a.zip"), b"PK\x03\x04"),
+            None
+        );
+    }
+
+    #[cfg(feature = "docx")]
+    #[test]
+    fn test_extract_docx() {
+        use std::io::{Cursor, Write};
+        use zip::write::SimpleFileOptions;
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+              <w:body>
+                <w:p><w:r><w:t>Hello,</w:t></w:r><w:r><w:t xml:space="preserve"> world</w:t></w:r></w:p>
+                <w:p><w:r><w:t>A</w:t><w:tab/><w:t>B &amp; C</w:t></w:r></w:p>
+              </w:body>
+            </w:document>"#;
+        let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        archive
+            .start_file(DOCX_DOCUMENT, SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(xml.as_bytes()).unwrap();
+        let bytes = archive.finish().unwrap().into_inner();
+
+        assert_eq!(
+            DocumentFormat::detect(Path::new("report"), &bytes),
+            Some(DocumentFormat::Docx)
+        );
+        let text = extract_text(DocumentFormat::Docx, &bytes).unwrap();
+        assert_eq!(text, "Hello, world\nA\tB & C\n");
+    }
+
+    #[test]
+    fn test_read_document_text_notebook() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("analysis.ipynb");
+        fs::write(
+            &path,
+            r##"{"cells": [
+                {"cell_type": "markdown", "source": ["# Title"]},
+                {"cell_type": "code", "source": ["x = 1"], "outputs": [
+                    {"output_type": "stream", "text": ["ignored"]}
+                ]}
+            ]}"##,
+        )
+        .unwrap();
+
+        assert_eq!(read_document_text(&path).unwrap(), "# Title\n\nx = 1");
+    }
+
+    #[test]
+    fn test_read_document_text_plain() {
+        let dir = TempDir::new().unwrap();
+        let text = dir.path().join("a.txt");
+        let binary = dir.path().join("b.bin");
+        fs::write(&text, "hello").unwrap();
+        fs::write(&binary, [0xff, 0xfe]).unwrap();
+
+        assert_eq!(read_document_text(&text).unwrap(), "hello");
+        assert!(matches!(
+            read_document_text(&binary),
+            Err(Error::InvalidUtf8 { offset: 0, .. })
+        ));
+        let missing = dir.path().join("c.txt");
+        let err = read_document_text(&missing).unwrap_err();
+        assert!(matches!(err, Error::File { .. }));
+        assert_eq!(err.path(), Some(missing.as_path()));
+    }
+
+    #[test]
+    fn test_read_document_text_with_encoding() {
+        let dir = TempDir::new().unwrap();
+        let utf16 = dir.path().join("a.txt");
+        let latin1 = dir.path().join("b.txt");
+        fs::write(&utf16, [0xff, 0xfe, b'h', 0, b'i', 0]).unwrap();
+        fs::write(&latin1, b"caf\xe9").unwrap();
+
+        let read = read_document_text_with_encoding;
+        assert_eq!(read(&utf16, TextEncoding::Auto).unwrap(), "hi");
+        assert_eq!(read(&latin1, TextEncoding::Latin1).unwrap(), "café");
+        assert!(matches!(
+            read(&latin1, TextEncoding::Auto),
+            Err(Error::InvalidUtf8 { offset: 3, .. })
+        ));
+    }
+
+    #[cfg(not(feature = "pdf"))]
+    #[test]
+    fn test_pdf_requires_feature() {
+        let err = extract_text(DocumentFormat::Pdf, b"%PDF-1.7").unwrap_err();
+        assert!(err.to_string().contains("pdf"));
+    }
+}
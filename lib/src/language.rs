@@ -0,0 +1,135 @@
+//! Natural-language detection, and token totals broken down by language
+//!
+//! Tokenizers encode some languages far more compactly than others, so a
+//! multilingual corpus balanced by bytes is rarely balanced by tokens.
+//! [`LanguageBreakdown`] detects the language of each text (with whatlang)
+//! and adds its stats to that language's total.
+
+use crate::TokenStats;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Confidence below which a detection counts as a guess; whatlang's own
+/// "reliable" cutoff of 0.9 rejects many single sentences, while code and
+/// short fragments stay well under this
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// A natural language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Language {
+    /// ISO 639-3 code (e.g. "eng")
+    pub code: &'static str,
+    /// English name (e.g. "English")
+    pub name: &'static str,
+}
+
+/// Detects the language `text` is written in
+///
+/// Returns `None` when the text is too short or too mixed (such as most
+/// source code) for a confident guess, or has no letters at all.
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::detect_language;
+///
+/// let language = detect_language("Ceci est une phrase en français sur le temps qu'il fait.");
+/// assert_eq!(language.map(|language| language.code), Some("fra"));
+/// assert_eq!(detect_language("12345"), None);
+/// ```
+pub fn detect_language(text: &str) -> Option<Language> {
+    let info = whatlang::detect(text).filter(|info| info.confidence() >= MIN_CONFIDENCE)?;
+    Some(Language {
+        code: info.lang().code(),
+        name: info.lang().eng_name(),
+    })
+}
+
+/// Token stats per detected language
+#[derive(Debug, Clone, Default)]
+pub struct LanguageBreakdown {
+    by_language: HashMap<Option<Language>, TokenStats>,
+}
+
+impl LanguageBreakdown {
+    /// Creates an empty breakdown
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Detects the language of `text` and adds `stats`, the counts of that
+    /// text, to its total
+    pub fn add(&mut self, text: &str, stats: &TokenStats) -> Option<Language> {
+        let language = detect_language(text);
+        self.add_to(language, stats);
+        language
+    }
+
+    /// Adds `stats` to the total of `language` (`None` for undetected
+    /// text)
+    pub fn add_to(&mut self, language: Option<Language>, stats: &TokenStats) {
+        self.by_language.entry(language).or_default().add(stats);
+    }
+
+    /// Adds the totals recorded in `other`
+    pub fn merge(&mut self, other: &LanguageBreakdown) {
+        for (&language, stats) in &other.by_language {
+            self.add_to(language, stats);
+        }
+    }
+
+    /// Tokens recorded across all languages
+    pub fn tokens(&self) -> usize {
+        self.by_language.values().map(|stats| stats.tokens).sum()
+    }
+
+    /// Each language with its stats, most tokens first; undetected text
+    /// comes last
+    pub fn languages(&self) -> Vec<(Option<Language>, &TokenStats)> {
+        let mut languages: Vec<_> = self
+            .by_language
+            .iter()
+            .map(|(&language, stats)| (language, stats))
+            .collect();
+        languages.sort_by_key(|&(language, stats)| {
+            (language.is_none(), Reverse(stats.tokens), language)
+        });
+        languages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_breakdown() {
+        let stats = |tokens| TokenStats {
+            tokens,
+            ..TokenStats::new()
+        };
+        let mut breakdown = LanguageBreakdown::new();
+        let english = breakdown.add(
+            "The quick brown fox jumps over the lazy dog and runs into the forest.",
+            &stats(15),
+        );
+        assert_eq!(english.map(|language| language.name), Some("English"));
+        breakdown.add(
+            "El rápido zorro marrón salta sobre el perro perezoso y corre hacia el bosque.",
+            &stats(20),
+        );
+        breakdown.add("", &stats(1));
+        breakdown.add(
+            "A second English sentence about the weather, which is rather pleasant today.",
+            &stats(10),
+        );
+
+        let languages = breakdown.languages();
+        let summary: Vec<_> = languages
+            .iter()
+            .map(|(language, stats)| (language.map(|language| language.code), stats.tokens))
+            .collect();
+        assert_eq!(summary, [(Some("eng"), 25), (Some("spa"), 20), (None, 1)]);
+        assert_eq!(breakdown.tokens(), 46);
+    }
+}
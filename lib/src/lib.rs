@@ -1,12 +1,109 @@
 //! # Token Counter Library
 //!
 //! This library provides token counting functionality for LLM tokenizers.
+//!
+//! [`TokenCounter`] holds a tokenizer together with its counting options and
+//! is the place new options are added; the free functions below count with
+//! default options.
+//!
+//! Reading files, directories, and other readers is behind the `fs` feature
+//! (on by default). Without it, and with `wasm` in place of `onig` for the
+//! tokenizer's regex engine, the library builds for
+//! `wasm32-unknown-unknown`.
 
+#[cfg(feature = "fs")]
 use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use std::io;
+#[cfg(feature = "fs")]
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
-use tokenizers::Tokenizer;
+use tokenizers::utils::parallelism::MaybeParallelIterator;
+use tokenizers::{PostProcessor, Tokenizer};
+
+pub mod archive;
+pub mod charset;
+pub mod chat;
+pub mod chunk;
+pub mod comments;
+pub mod compression;
+pub mod convert;
+pub mod counter;
+pub mod dataset;
+pub mod diff;
+pub mod extract;
+#[cfg(feature = "lang")]
+pub mod language;
+pub mod lines;
+#[cfg(feature = "fs")]
+pub mod model_dir;
+pub mod models;
+pub mod notebook;
+pub mod outline;
+pub mod parallel;
+pub mod pool;
+pub mod preprocess;
+pub mod pricing;
+#[cfg(feature = "fs")]
+pub mod progress;
+pub mod report;
+#[cfg(feature = "fs")]
+pub mod sample;
+pub mod select;
+pub mod similarity;
+pub mod snapshot;
+#[cfg(feature = "fs")]
+pub mod stream;
+pub mod vocab;
+
+pub use archive::{for_each_member, ArchiveFormat};
+pub use charset::{decode_text, repair_utf8, TextEncoding};
+pub use chat::{
+    count_anthropic_chat, count_openai_chat, parse_anthropic_chat, parse_openai_chat, ChatCount,
+    ChatMessage,
+};
+pub use chunk::{head_by_tokens, split_by_tokens, tail_by_tokens, Chunk};
+pub use comments::{strip_comments, CommentSyntax};
+#[cfg(feature = "fs")]
+pub use compression::read_decompressed;
+pub use compression::{decompress, decompress_reader, Compression};
+pub use convert::{
+    byte_level_chars, sentencepiece_to_json, tiktoken_encoding, tiktoken_to_json, TiktokenEncoding,
+    TIKTOKEN_ENCODINGS,
+};
+pub use counter::{TokenCounter, TokenCounterBuilder};
+pub use dataset::{record_field, TokenDistribution};
+pub use diff::{parse_unified_diff, FileDiff};
+pub use extract::{extract_text, DocumentFormat};
+#[cfg(feature = "fs")]
+pub use extract::{read_document_text, read_document_text_with_encoding};
+#[cfg(feature = "lang")]
+pub use language::{detect_language, Language, LanguageBreakdown};
+pub use lines::{stats_per_line, LineStats};
+#[cfg(feature = "fs")]
+pub use model_dir::{detect_tokenizer_format, load_tokenizer_from_dir, TokenizerFormat};
+pub use models::{known_models, lookup_model, ContextFit, ModelSpec};
+pub use notebook::{parse_notebook, CellKind, NotebookCell};
+pub use outline::{outline, OutlineLanguage, Symbol, SymbolKind};
+pub use parallel::{count_stats_parallel, PARALLEL_CHUNK_SIZE};
+pub use pool::{SharedTokenizer, TokenizerPool};
+pub use preprocess::{Pipeline, Preprocessor, StripComments, StripHtml, StripMarkdown};
+pub use pricing::{ModelPricing, PricingTable};
+#[cfg(feature = "fs")]
+pub use progress::{count_tokens_in_files, Progress};
+pub use report::{
+    csv_field, ColoredFormatter, Columns, CsvFormatter, Formatter, JsonFormatter, PlainFormatter,
+    Report, ReportFormat, Severity, Threshold, Thresholds,
+};
+#[cfg(feature = "fs")]
+pub use sample::{estimate_tokens, estimate_tokens_fraction, SampleEstimate, SAMPLE_BLOCK_SIZE};
+pub use select::{select_text, JsonPath};
+pub use similarity::{shared_spans, token_similarity, SharedSpans, Similarity};
+pub use snapshot::{compare_counts, ChangeKind, FileChange, GrowthTolerance};
+#[cfg(feature = "fs")]
+pub use stream::{count_tokens_from_reader_streaming, STREAM_CHUNK_SIZE};
+pub use vocab::VocabUsage;
 
 /// Custom error type for the library
 #[derive(Error, Debug)]
@@ -19,16 +116,90 @@ pub enum Error {
     #[error("io error: {0}")]
     Io(#[from] io::Error),
 
+    /// Error reading a file
+    #[error("{}: {source}", .path.display())]
+    File {
+        /// The file being read
+        path: PathBuf,
+        /// Why reading it failed
+        source: io::Error,
+    },
+
+    /// A text file that is not valid UTF-8
+    #[error("{}: invalid UTF-8 at byte {offset}", .path.display())]
+    InvalidUtf8 {
+        /// The file being read
+        path: PathBuf,
+        /// Offset of the first byte that is not part of valid UTF-8
+        offset: usize,
+    },
+
     /// Encoding error
     #[error("failed to encode text: {0}")]
     Encoding(String),
+
+    /// Decoding error
+    #[error("failed to decode token IDs: {0}")]
+    Decoding(String),
+
+    /// Archive reading error
+    #[error("failed to read archive: {0}")]
+    Archive(String),
+
+    /// Decompression error
+    #[error("failed to decompress: {0}")]
+    Decompression(String),
+
+    /// Document text extraction error
+    #[error("failed to extract text: {0}")]
+    Extraction(String),
+
+    /// Source code parsing error
+    #[error("failed to parse source: {0}")]
+    Parse(String),
+
+    /// Input that does not have the expected structure
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+}
+
+impl Error {
+    /// The file the error is about, for errors that name one
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::File { path, .. } | Error::InvalidUtf8 { path, .. } => Some(path),
+            _ => None,
+        }
+    }
 }
 
 /// Result type alias for the library
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Maps an error reading `path` to [`Error::File`]
+#[cfg(feature = "fs")]
+pub(crate) fn file_error(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+    move |source| Error::File {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Decodes the contents of `path` as UTF-8 text
+#[cfg(feature = "fs")]
+pub(crate) fn text_from_bytes(path: &Path, bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(|e| Error::InvalidUtf8 {
+        path: path.to_path_buf(),
+        offset: e.utf8_error().valid_up_to(),
+    })
+}
+
 /// Statistics for token counting
+///
+/// With the `serde` feature, the stats serialize as a flat object, leaving
+/// out `path` when it is not set.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TokenStats {
     /// Number of tokens
     pub tokens: usize,
@@ -36,6 +207,18 @@ pub struct TokenStats {
     pub lines: usize,
     /// Number of bytes
     pub bytes: usize,
+    /// Most tokens on a single line
+    pub max_line_tokens: usize,
+    /// Number of whitespace-separated words
+    pub words: usize,
+    /// Number of characters (Unicode scalar values)
+    pub chars: usize,
+    /// File the stats were counted from, if any
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub path: Option<PathBuf>,
 }
 
 impl TokenStats {
@@ -45,14 +228,52 @@ impl TokenStats {
             tokens: 0,
             lines: 0,
             bytes: 0,
+            max_line_tokens: 0,
+            words: 0,
+            chars: 0,
+            path: None,
         }
     }
 
-    /// Adds another TokenStats to this one
+    /// Adds another TokenStats to this one, keeping this one's path
     pub fn add(&mut self, other: &TokenStats) {
         self.tokens += other.tokens;
         self.lines += other.lines;
         self.bytes += other.bytes;
+        self.max_line_tokens = self.max_line_tokens.max(other.max_line_tokens);
+        self.words += other.words;
+        self.chars += other.chars;
+    }
+
+    /// Average number of tokens per line (0 when there are no lines)
+    pub fn tokens_per_line(&self) -> f64 {
+        if self.lines == 0 {
+            return 0.0;
+        }
+        self.tokens as f64 / self.lines as f64
+    }
+
+    /// Number of tokens per KiB of text (0 when there are no bytes)
+    ///
+    /// Prose and code usually land in the low hundreds; minified code and
+    /// base64 blobs run much higher.
+    pub fn tokens_per_kb(&self) -> f64 {
+        if self.bytes == 0 {
+            return 0.0;
+        }
+        self.tokens as f64 * 1024.0 / self.bytes as f64
+    }
+
+    /// Average number of bytes each token encodes (0 when there are no
+    /// tokens)
+    ///
+    /// Higher is more efficient: English prose usually runs around 4 bytes
+    /// per token, while code and non-Latin scripts often fare much worse.
+    pub fn bytes_per_token(&self) -> f64 {
+        if self.tokens == 0 {
+            return 0.0;
+        }
+        self.bytes as f64 / self.tokens as f64
     }
 }
 
@@ -62,17 +283,25 @@ impl Default for TokenStats {
     }
 }
 
-/// Loads a tokenizer from a JSON file
+/// Loads a tokenizer from a JSON file or a model directory
+///
+/// If `path` is a directory, the tokenizer files inside it are detected
+/// automatically (see [`load_tokenizer_from_dir`]).
 ///
 /// # Arguments
 ///
-/// * `path` - Path to the tokenizer JSON file
+/// * `path` - Path to the tokenizer JSON file or model directory
 ///
 /// # Errors
 ///
 /// Returns an error if the tokenizer file cannot be loaded or parsed
+#[cfg(feature = "fs")]
 pub fn load_tokenizer<P: AsRef<Path>>(path: P) -> Result<Tokenizer> {
-    Tokenizer::from_file(path.as_ref()).map_err(|e| Error::TokenizerLoad(format!("{}", e)))
+    let path = path.as_ref();
+    if path.is_dir() {
+        return load_tokenizer_from_dir(path);
+    }
+    Tokenizer::from_file(path).map_err(|e| Error::TokenizerLoad(format!("{}", e)))
 }
 
 /// Loads a tokenizer from bytes (e.g., embedded tokenizer data)
@@ -102,10 +331,16 @@ pub fn load_tokenizer_from_bytes(bytes: &[u8]) -> Result<Tokenizer> {
 
 /// Counts tokens in a string
 ///
+/// Only the length of the encoding is needed, so the text is encoded
+/// without tracking token offsets, which is measurably faster than a full
+/// encode (compare `tc bench` with and without `--offsets`).
+///
 /// # Arguments
 ///
 /// * `text` - The text to count tokens in
 /// * `tokenizer` - The tokenizer to use
+/// * `add_special_tokens` - Also count the special tokens the tokenizer
+///   wraps a sequence in (e.g. BERT's `[CLS]` and `[SEP]`)
 ///
 /// # Errors
 ///
@@ -117,18 +352,218 @@ pub fn load_tokenizer_from_bytes(bytes: &[u8]) -> Result<Tokenizer> {
 /// use token_counter_lib::{load_tokenizer, count_tokens};
 ///
 /// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
-/// let count = count_tokens("Hello, world!", &tokenizer).unwrap();
+/// let count = count_tokens("Hello, world!", &tokenizer, false).unwrap();
 /// assert!(count > 0);
 /// ```
-pub fn count_tokens(text: &str, tokenizer: &Tokenizer) -> Result<usize> {
+pub fn count_tokens(text: &str, tokenizer: &Tokenizer, add_special_tokens: bool) -> Result<usize> {
     let encoding = tokenizer
-        .encode(text, false)
+        .encode_fast(text, add_special_tokens)
         .map_err(|e| Error::Encoding(format!("{}", e)))?;
     Ok(encoding.len())
 }
 
+/// Counts tokens in many strings at once
+///
+/// Encodes the strings in parallel with the tokenizer's
+/// `encode_batch_fast`, which is much faster than calling [`count_tokens`]
+/// in a loop when there are many short strings.
+///
+/// # Arguments
+///
+/// * `texts` - The strings to count tokens in
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{load_tokenizer, count_tokens_batch};
+///
+/// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
+/// let counts = count_tokens_batch(&["Hello", "Hello, world!"], &tokenizer).unwrap();
+/// assert_eq!(counts.len(), 2);
+/// ```
+pub fn count_tokens_batch(texts: &[&str], tokenizer: &Tokenizer) -> Result<Vec<usize>> {
+    encode_lengths(texts, tokenizer, false)
+}
+
+/// Counts tokens in one text with each of several tokenizers
+///
+/// The text is read and prepared once and encoded with every tokenizer in
+/// parallel, which is faster than calling [`count_tokens`] per tokenizer
+/// when comparing several of them over a big input. Counts are in the
+/// order of `tokenizers`.
+///
+/// # Errors
+///
+/// Returns an error if encoding fails with any of the tokenizers
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{count_multi, load_tokenizer};
+///
+/// let gpt2 = load_tokenizer("gpt2.json").unwrap();
+/// let bert = load_tokenizer("bert.json").unwrap();
+/// let counts = count_multi("Hello, world!", &[&gpt2, &bert]).unwrap();
+/// assert_eq!(counts.len(), 2);
+/// ```
+pub fn count_multi(text: &str, tokenizers: &[&Tokenizer]) -> Result<Vec<usize>> {
+    tokenizers
+        .into_maybe_par_iter()
+        .map(|tokenizer| count_tokens(text, tokenizer, false))
+        .collect()
+}
+
+/// Token counts of `texts`, encoded in parallel without offsets
+pub(crate) fn encode_lengths(
+    texts: &[&str],
+    tokenizer: &Tokenizer,
+    add_special_tokens: bool,
+) -> Result<Vec<usize>> {
+    let encodings = tokenizer
+        .encode_batch_fast(texts.to_vec(), add_special_tokens)
+        .map_err(|e| Error::Encoding(format!("{}", e)))?;
+    Ok(encodings.iter().map(|encoding| encoding.len()).collect())
+}
+
+/// Number of special tokens the tokenizer adds around a single sequence
+///
+/// This is what `add_special_tokens` adds to [`count_tokens`]: 2 for BERT
+/// (`[CLS]` and `[SEP]`), 1 for tokenizers that prepend a BOS token, and 0
+/// for GPT-2.
+pub fn special_token_count(tokenizer: &Tokenizer) -> usize {
+    tokenizer
+        .get_post_processor()
+        .map_or(0, |processor| processor.added_tokens(false))
+}
+
+/// Encodes text into token IDs
+///
+/// # Arguments
+///
+/// * `text` - The text to encode
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{load_tokenizer, encode_ids};
+///
+/// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
+/// let ids = encode_ids("Hello, world!", &tokenizer).unwrap();
+/// assert!(!ids.is_empty());
+/// ```
+pub fn encode_ids(text: &str, tokenizer: &Tokenizer) -> Result<Vec<u32>> {
+    let encoding = tokenizer
+        .encode_fast(text, false)
+        .map_err(|e| Error::Encoding(format!("{}", e)))?;
+    Ok(encoding.get_ids().to_vec())
+}
+
+/// Decodes token IDs back into text
+///
+/// # Arguments
+///
+/// * `ids` - The token IDs
+/// * `tokenizer` - The tokenizer the IDs came from
+/// * `skip_special_tokens` - Leave special tokens (e.g. `<|endoftext|>`) out
+///
+/// # Errors
+///
+/// Returns an error if decoding fails
+pub fn decode_ids(ids: &[u32], tokenizer: &Tokenizer, skip_special_tokens: bool) -> Result<String> {
+    tokenizer
+        .decode(ids, skip_special_tokens)
+        .map_err(|e| Error::Decoding(format!("{}", e)))
+}
+
+/// Parses a list of token IDs
+///
+/// IDs may be separated by whitespace and/or commas, and the list may be
+/// wrapped in brackets, so JSON arrays and `--ids` output are both accepted.
+///
+/// # Errors
+///
+/// Returns an error if an item is not a non-negative integer
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::parse_ids;
+///
+/// assert_eq!(parse_ids("[15496, 11, 995]").unwrap(), [15496, 11, 995]);
+/// assert_eq!(parse_ids("15496 11\n995").unwrap(), [15496, 11, 995]);
+/// ```
+pub fn parse_ids(text: &str) -> Result<Vec<u32>> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .unwrap_or(text);
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .map_err(|_| Error::InvalidInput(format!("'{}' is not a token ID", item)))
+        })
+        .collect()
+}
+
+/// A token and the part of the text it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSpan {
+    /// Byte range of the token in the text
+    ///
+    /// Tokens that split a multi-byte character each cover the whole
+    /// character, so neighbouring spans can overlap.
+    pub span: Range<usize>,
+    /// Token ID
+    pub id: u32,
+    /// The token as written in the tokenizer's vocabulary (e.g. `Ġworld` for
+    /// byte-level BPE)
+    pub piece: String,
+}
+
+/// Splits text into tokens with their IDs and byte offsets
+///
+/// # Arguments
+///
+/// * `text` - The text to tokenize
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn token_spans(text: &str, tokenizer: &Tokenizer) -> Result<Vec<TokenSpan>> {
+    let encoding = tokenizer
+        .encode(text, false)
+        .map_err(|e| Error::Encoding(format!("{}", e)))?;
+    Ok(encoding
+        .get_offsets()
+        .iter()
+        .zip(encoding.get_ids())
+        .zip(encoding.get_tokens())
+        .map(|((&(start, end), &id), piece)| TokenSpan {
+            span: start..end,
+            id,
+            piece: piece.clone(),
+        })
+        .collect())
+}
+
 /// Counts tokens and other statistics for text
 ///
+/// Texts of at least twice [`PARALLEL_CHUNK_SIZE`] are cut at line breaks
+/// and counted on several threads (see [`count_stats_parallel`]).
+///
 /// # Arguments
 ///
 /// * `text` - The text to analyze
@@ -138,18 +573,69 @@ pub fn count_tokens(text: &str, tokenizer: &Tokenizer) -> Result<usize> {
 ///
 /// Returns an error if encoding fails
 pub fn count_stats(text: &str, tokenizer: &Tokenizer) -> Result<TokenStats> {
-    let tokens = count_tokens(text, tokenizer)?;
-    let lines = text.lines().count();
-    let bytes = text.len();
+    count_stats_with(text, tokenizer, true)
+}
+
+/// Counts tokens and other statistics for text, except the most tokens on
+/// one line, which is left at 0
+///
+/// Without `max_line_tokens` no token offsets are needed, so the text is
+/// encoded with `encode_fast`, as [`count_tokens`] does. Use it when the
+/// stats are only totals; it is what `tc` counts with unless `--density`
+/// or a per-file table asks for the per-line maximum.
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn count_stats_fast(text: &str, tokenizer: &Tokenizer) -> Result<TokenStats> {
+    count_stats_with(text, tokenizer, false)
+}
+
+/// [`count_stats`], finding `max_line_tokens` only if `line_maxima`
+fn count_stats_with(text: &str, tokenizer: &Tokenizer, line_maxima: bool) -> Result<TokenStats> {
+    if text.len() >= 2 * PARALLEL_CHUNK_SIZE {
+        return parallel::count_stats_in_chunks(text, tokenizer, PARALLEL_CHUNK_SIZE, line_maxima);
+    }
+    count_stats_serial(text, tokenizer, line_maxima)
+}
+
+/// [`count_stats`] on the calling thread, finding `max_line_tokens` from
+/// token offsets only if `line_maxima`
+pub(crate) fn count_stats_serial(
+    text: &str,
+    tokenizer: &Tokenizer,
+    line_maxima: bool,
+) -> Result<TokenStats> {
+    let encoding = if line_maxima {
+        tokenizer.encode(text, false)
+    } else {
+        tokenizer.encode_fast(text, false)
+    }
+    .map_err(|e| Error::Encoding(format!("{}", e)))?;
+
+    // Each token counts toward the line it starts on
+    let mut max_line_tokens = 0;
+    if line_maxima {
+        let line_ends: Vec<usize> = text.match_indices('\n').map(|(i, _)| i).collect();
+        let mut line_tokens = vec![0; line_ends.len() + 1];
+        for &(start, _) in encoding.get_offsets() {
+            line_tokens[line_ends.partition_point(|&end| end < start)] += 1;
+        }
+        max_line_tokens = line_tokens.into_iter().max().unwrap_or(0);
+    }
 
     Ok(TokenStats {
-        tokens,
-        lines,
-        bytes,
+        tokens: encoding.len(),
+        lines: text.lines().count(),
+        bytes: text.len(),
+        max_line_tokens,
+        words: text.split_whitespace().count(),
+        chars: text.chars().count(),
+        path: None,
     })
 }
 
-/// Counts tokens in a file
+/// Counts tokens in a file, recording its path in the stats
 ///
 /// # Arguments
 ///
@@ -170,13 +656,23 @@ pub fn count_stats(text: &str, tokenizer: &Tokenizer) -> Result<TokenStats> {
 /// let stats = count_tokens_in_file(Path::new("example.txt"), &tokenizer).unwrap();
 /// println!("Tokens: {}", stats.tokens);
 /// ```
+#[cfg(feature = "fs")]
 pub fn count_tokens_in_file<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> Result<TokenStats> {
-    let text = fs::read_to_string(path)?;
-    count_stats(&text, tokenizer)
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(file_error(path))?;
+    let text = text_from_bytes(path, bytes)?;
+    Ok(TokenStats {
+        path: Some(path.to_path_buf()),
+        ..count_stats(&text, tokenizer)?
+    })
 }
 
 /// Counts tokens from a reader (e.g., stdin)
 ///
+/// Reads everything into memory first; use
+/// [`count_tokens_from_reader_streaming`] for unbounded input such as
+/// sockets or long-running pipes.
+///
 /// # Arguments
 ///
 /// * `reader` - The reader to read from
@@ -184,13 +680,16 @@ pub fn count_tokens_in_file<P: AsRef<Path>>(path: P, tokenizer: &Tokenizer) -> R
 ///
 /// # Errors
 ///
-/// Returns an error if reading fails or encoding fails
+/// Returns an error if reading fails or encoding fails, and
+/// [`Error::InvalidUtf8`] with the path "-" if the input is not UTF-8
+#[cfg(feature = "fs")]
 pub fn count_tokens_from_reader<R: Read>(
     mut reader: R,
     tokenizer: &Tokenizer,
 ) -> Result<TokenStats> {
-    let mut text = String::new();
-    reader.read_to_string(&mut text)?;
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let text = text_from_bytes(Path::new("-"), bytes)?;
     count_stats(&text, tokenizer)
 }
 
@@ -215,15 +714,195 @@ mod tests {
             tokens: 10,
             lines: 2,
             bytes: 50,
+            max_line_tokens: 6,
+            words: 8,
+            chars: 50,
+            path: None,
         };
         let stats2 = TokenStats {
             tokens: 5,
             lines: 1,
             bytes: 25,
+            max_line_tokens: 5,
+            words: 4,
+            chars: 20,
+            path: None,
         };
         stats1.add(&stats2);
         assert_eq!(stats1.tokens, 15);
         assert_eq!(stats1.lines, 3);
         assert_eq!(stats1.bytes, 75);
+        assert_eq!(stats1.max_line_tokens, 6);
+        assert_eq!(stats1.words, 12);
+        assert_eq!(stats1.chars, 70);
+    }
+
+    #[test]
+    fn test_encode_ids() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        assert_eq!(
+            encode_ids("Hello, world", &tokenizer).unwrap(),
+            [15496, 11, 995]
+        );
+    }
+
+    #[test]
+    fn test_count_tokens_batch() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let texts = ["Hello, world", "", "a b c d"];
+        let counts = count_tokens_batch(&texts, &tokenizer).unwrap();
+        let looped: Vec<_> = texts
+            .iter()
+            .map(|text| count_tokens(text, &tokenizer, false).unwrap())
+            .collect();
+        assert_eq!(counts, looped);
+        assert_eq!(counts, [3, 0, 4]);
+    }
+
+    #[test]
+    fn test_count_tokens_matches_full_encode() {
+        let gpt2 =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let bert =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/tokenizers/bert.json")).unwrap();
+        let text = "fn main() {\n    println!(\"Grüße, 世界\");\n}\n<|endoftext|>";
+        for tokenizer in [&gpt2, &bert] {
+            for add_special_tokens in [false, true] {
+                let full = tokenizer.encode(text, add_special_tokens).unwrap();
+                assert_eq!(
+                    count_tokens(text, tokenizer, add_special_tokens).unwrap(),
+                    full.len()
+                );
+                if !add_special_tokens {
+                    assert_eq!(encode_ids(text, tokenizer).unwrap(), full.get_ids());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_multi() {
+        let gpt2 =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let bert =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/tokenizers/bert.json")).unwrap();
+        let text = "Hello, world! Tokenizers disagree about antidisestablishmentarianism.";
+        let counts = count_multi(text, &[&gpt2, &bert, &gpt2]).unwrap();
+        let gpt2_count = count_tokens(text, &gpt2, false).unwrap();
+        assert_eq!(
+            counts,
+            [
+                gpt2_count,
+                count_tokens(text, &bert, false).unwrap(),
+                gpt2_count
+            ]
+        );
+        assert!(count_multi(text, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_count_tokens_special() {
+        let gpt2 =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        assert_eq!(special_token_count(&gpt2), 0);
+        assert_eq!(count_tokens("Hello, world", &gpt2, true).unwrap(), 3);
+
+        let bert =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/tokenizers/bert.json")).unwrap();
+        assert_eq!(special_token_count(&bert), 2);
+        assert_eq!(count_tokens("Hello, world", &bert, false).unwrap(), 3);
+        assert_eq!(count_tokens("Hello, world", &bert, true).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_decode_ids() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let ids = parse_ids("15496, 11, 995, 50256").unwrap();
+        assert_eq!(
+            decode_ids(&ids, &tokenizer, false).unwrap(),
+            "Hello, world<|endoftext|>"
+        );
+        assert_eq!(decode_ids(&ids, &tokenizer, true).unwrap(), "Hello, world");
+        assert!(parse_ids("1, two").is_err());
+        assert!(parse_ids("-1").is_err());
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let spans = token_spans("Hello, world", &tokenizer).unwrap();
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[2].span, 6..12);
+        assert_eq!(spans[2].id, 995);
+        assert_eq!(spans[2].piece, "Ġworld");
+    }
+
+    #[test]
+    fn test_count_stats_density() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let stats = count_stats("a b\na b c d\n", &tokenizer).unwrap();
+        assert_eq!(stats.tokens, 8);
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.max_line_tokens, 5);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.chars, 12);
+        assert_eq!(stats.tokens_per_line(), 4.0);
+        assert_eq!(stats.tokens_per_kb(), 8.0 * 1024.0 / 12.0);
+        assert_eq!(TokenStats::new().tokens_per_kb(), 0.0);
+        assert_eq!(stats.bytes_per_token(), 12.0 / 8.0);
+        assert_eq!(TokenStats::new().bytes_per_token(), 0.0);
+
+        let fast = count_stats_fast("a b\na b c d\n", &tokenizer).unwrap();
+        assert_eq!(
+            fast,
+            TokenStats {
+                max_line_tokens: 0,
+                ..stats
+            }
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_count_tokens_from_reader_invalid_utf8() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let stats = count_tokens_from_reader(&b"Hello, world"[..], &tokenizer).unwrap();
+        assert_eq!(stats.tokens, 3);
+
+        let error = count_tokens_from_reader(&b"caf\xe9 au lait"[..], &tokenizer).unwrap_err();
+        assert!(
+            matches!(&error, Error::InvalidUtf8 { path, offset: 3 } if path == Path::new("-")),
+            "{:?}",
+            error
+        );
+        assert_eq!(error.to_string(), "-: invalid UTF-8 at byte 3");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_token_stats_serde() {
+        let mut stats = TokenStats {
+            tokens: 3,
+            lines: 1,
+            bytes: 12,
+            max_line_tokens: 3,
+            words: 2,
+            chars: 12,
+            path: None,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(!json.contains("path"));
+        assert_eq!(serde_json::from_str::<TokenStats>(&json).unwrap(), stats);
+
+        stats.path = Some(PathBuf::from("notes.md"));
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["path"], "notes.md");
+        assert_eq!(json["words"], 2);
     }
 }
@@ -0,0 +1,86 @@
+//! Token counts line by line
+
+use crate::{count_tokens, Result};
+use tokenizers::Tokenizer;
+
+/// Token and byte counts of one line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineStats {
+    /// Line number, starting at 1
+    pub line_no: usize,
+    /// Number of tokens, including the line's newline
+    pub tokens: usize,
+    /// Number of bytes, including the line's newline
+    pub bytes: usize,
+}
+
+/// Counts the tokens of each line of `text`, lazily
+///
+/// Each line is encoded on its own, together with its newline, so lines are
+/// only tokenized as the iterator is advanced and nothing is collected. A
+/// token spanning a line break (such as GPT-2's `"\n\n"`) is split in two,
+/// so the per-line counts can add up to slightly more than
+/// [`count_stats`](crate::count_stats) reports for the whole text.
+///
+/// # Arguments
+///
+/// * `text` - The text to count
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{load_tokenizer, stats_per_line};
+///
+/// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
+/// for line in stats_per_line("first line\nsecond line\n", &tokenizer) {
+///     let line = line.unwrap();
+///     println!("{}: {} tokens", line.line_no, line.tokens);
+/// }
+/// ```
+pub fn stats_per_line<'a>(
+    text: &'a str,
+    tokenizer: &'a Tokenizer,
+) -> impl Iterator<Item = Result<LineStats>> + 'a {
+    text.split_inclusive('\n')
+        .enumerate()
+        .map(move |(index, line)| {
+            Ok(LineStats {
+                line_no: index + 1,
+                tokens: count_tokens(line, tokenizer, false)?,
+                bytes: line.len(),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_per_line() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let text = "Hello, world\n\nlast";
+
+        let lines: Vec<_> = stats_per_line(text, &tokenizer)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            LineStats {
+                line_no: 1,
+                tokens: 4,
+                bytes: 13
+            }
+        );
+        assert_eq!(
+            (lines[1].line_no, lines[1].tokens, lines[1].bytes),
+            (2, 1, 1)
+        );
+        assert_eq!((lines[2].line_no, lines[2].bytes), (3, 4));
+        assert_eq!(stats_per_line("", &tokenizer).count(), 0);
+    }
+}
@@ -0,0 +1,262 @@
+//! Tokenizer discovery inside model directories
+//!
+//! Hugging Face model snapshots ship their tokenizer in one of several
+//! layouts. This module inspects a directory, figures out which layout it
+//! uses, and builds a [`Tokenizer`] with the matching loader.
+
+use crate::{file_error, sentencepiece_to_json, Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokenizers::decoders::byte_level::ByteLevel as ByteLevelDecoder;
+use tokenizers::decoders::wordpiece::WordPiece as WordPieceDecoder;
+use tokenizers::models::bpe::BPE;
+use tokenizers::models::wordpiece::WordPiece;
+use tokenizers::normalizers::bert::BertNormalizer;
+use tokenizers::pre_tokenizers::bert::BertPreTokenizer;
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::Tokenizer;
+
+/// Serialized Hugging Face tokenizer
+const TOKENIZER_JSON: &str = "tokenizer.json";
+/// Tokenizer settings shipped alongside the vocabulary files
+const TOKENIZER_CONFIG: &str = "tokenizer_config.json";
+/// SentencePiece model
+const SENTENCEPIECE_MODEL: &str = "tokenizer.model";
+/// GPT-2 style BPE vocabulary
+const BPE_VOCAB: &str = "vocab.json";
+/// GPT-2 style BPE merges
+const BPE_MERGES: &str = "merges.txt";
+/// BERT style WordPiece vocabulary
+const WORDPIECE_VOCAB: &str = "vocab.txt";
+
+/// Tokenizer layout found in a model directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizerFormat {
+    /// A serialized `tokenizer.json`
+    HuggingFace(PathBuf),
+    /// Byte-level BPE split into `vocab.json` and `merges.txt`
+    Bpe {
+        /// Path to `vocab.json`
+        vocab: PathBuf,
+        /// Path to `merges.txt`
+        merges: PathBuf,
+    },
+    /// WordPiece vocabulary in `vocab.txt`
+    WordPiece {
+        /// Path to `vocab.txt`
+        vocab: PathBuf,
+        /// Whether input is lowercased (from `tokenizer_config.json`)
+        lowercase: bool,
+    },
+    /// A SentencePiece `tokenizer.model`
+    SentencePiece(PathBuf),
+}
+
+/// Detects which tokenizer layout a model directory uses
+///
+/// Files are checked in order of preference: `tokenizer.json`, then
+/// `vocab.json` + `merges.txt`, then `vocab.txt`, then `tokenizer.model`.
+///
+/// # Arguments
+///
+/// * `dir` - Path to the model directory
+///
+/// # Errors
+///
+/// Returns an error if the directory contains no recognizable tokenizer files
+pub fn detect_tokenizer_format<P: AsRef<Path>>(dir: P) -> Result<TokenizerFormat> {
+    let dir = dir.as_ref();
+
+    let json = dir.join(TOKENIZER_JSON);
+    if json.is_file() {
+        return Ok(TokenizerFormat::HuggingFace(json));
+    }
+
+    let vocab = dir.join(BPE_VOCAB);
+    let merges = dir.join(BPE_MERGES);
+    if vocab.is_file() && merges.is_file() {
+        return Ok(TokenizerFormat::Bpe { vocab, merges });
+    }
+
+    let vocab = dir.join(WORDPIECE_VOCAB);
+    if vocab.is_file() {
+        let lowercase = read_do_lower_case(dir).unwrap_or(true);
+        return Ok(TokenizerFormat::WordPiece { vocab, lowercase });
+    }
+
+    let model = dir.join(SENTENCEPIECE_MODEL);
+    if model.is_file() {
+        return Ok(TokenizerFormat::SentencePiece(model));
+    }
+
+    Err(Error::TokenizerLoad(format!(
+        "no tokenizer found in {} (expected {}, {} + {}, {}, or {})",
+        dir.display(),
+        TOKENIZER_JSON,
+        BPE_VOCAB,
+        BPE_MERGES,
+        WORDPIECE_VOCAB,
+        SENTENCEPIECE_MODEL
+    )))
+}
+
+/// Loads a tokenizer from a model directory (e.g., a Hugging Face snapshot)
+///
+/// # Arguments
+///
+/// * `dir` - Path to the model directory
+///
+/// # Errors
+///
+/// Returns an error if no supported tokenizer is found or it cannot be loaded
+pub fn load_tokenizer_from_dir<P: AsRef<Path>>(dir: P) -> Result<Tokenizer> {
+    match detect_tokenizer_format(dir.as_ref())? {
+        TokenizerFormat::HuggingFace(path) => {
+            Tokenizer::from_file(&path).map_err(|e| Error::TokenizerLoad(format!("{}", e)))
+        }
+        TokenizerFormat::Bpe { vocab, merges } => load_bpe(&vocab, &merges),
+        TokenizerFormat::WordPiece { vocab, lowercase } => load_wordpiece(&vocab, lowercase),
+        TokenizerFormat::SentencePiece(path) => load_sentencepiece(&path),
+    }
+}
+
+/// Converts a SentencePiece `tokenizer.model` to a `tokenizer.json` and
+/// loads that
+fn load_sentencepiece(path: &Path) -> Result<Tokenizer> {
+    let bytes = fs::read(path).map_err(file_error(path))?;
+    let json = sentencepiece_to_json(&bytes).map_err(|e| {
+        let reason = match e {
+            Error::InvalidInput(reason) => reason,
+            other => other.to_string(),
+        };
+        Error::TokenizerLoad(format!("{}: {}", path.display(), reason))
+    })?;
+    Tokenizer::from_bytes(json.to_string()).map_err(|e| Error::TokenizerLoad(format!("{}", e)))
+}
+
+/// Builds a GPT-2 style byte-level BPE tokenizer
+fn load_bpe(vocab: &Path, merges: &Path) -> Result<Tokenizer> {
+    let model = BPE::from_file(&path_str(vocab)?, &path_str(merges)?)
+        .build()
+        .map_err(|e| Error::TokenizerLoad(format!("{}", e)))?;
+
+    let mut tokenizer = Tokenizer::new(model);
+    tokenizer
+        .with_pre_tokenizer(Some(ByteLevel::default()))
+        .with_decoder(Some(ByteLevelDecoder::default()));
+    Ok(tokenizer)
+}
+
+/// Builds a BERT style WordPiece tokenizer
+fn load_wordpiece(vocab: &Path, lowercase: bool) -> Result<Tokenizer> {
+    let model = WordPiece::from_file(&path_str(vocab)?)
+        .build()
+        .map_err(|e| Error::TokenizerLoad(format!("{}", e)))?;
+
+    let mut tokenizer = Tokenizer::new(model);
+    tokenizer
+        .with_normalizer(Some(BertNormalizer::new(true, true, None, lowercase)))
+        .with_pre_tokenizer(Some(BertPreTokenizer))
+        .with_decoder(Some(WordPieceDecoder::default()));
+    Ok(tokenizer)
+}
+
+/// Reads `do_lower_case` from `tokenizer_config.json`, if present
+fn read_do_lower_case(dir: &Path) -> Option<bool> {
+    let config = fs::read_to_string(dir.join(TOKENIZER_CONFIG)).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&config).ok()?;
+    value.get("do_lower_case")?.as_bool()
+}
+
+fn path_str(path: &Path) -> Result<String> {
+    path.to_str()
+        .map(str::to_owned)
+        .ok_or_else(|| Error::TokenizerLoad(format!("non UTF-8 path: {}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::tests::sentencepiece_model;
+    use tempfile::TempDir;
+
+    const GPT2_TOKENIZER: &[u8] = include_bytes!("../../bin/assets/gpt2-tokenizer.json");
+
+    #[test]
+    fn test_detect_tokenizer_json() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("tokenizer.json"), GPT2_TOKENIZER).unwrap();
+        fs::write(dir.path().join("tokenizer.model"), b"").unwrap();
+
+        let format = detect_tokenizer_format(dir.path()).unwrap();
+        assert_eq!(
+            format,
+            TokenizerFormat::HuggingFace(dir.path().join("tokenizer.json"))
+        );
+        assert!(load_tokenizer_from_dir(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_detect_wordpiece_with_config() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("vocab.txt"),
+            "[UNK]\nhello\nworld\n##s\n,\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("tokenizer_config.json"),
+            r#"{"do_lower_case": false}"#,
+        )
+        .unwrap();
+
+        let format = detect_tokenizer_format(dir.path()).unwrap();
+        assert_eq!(
+            format,
+            TokenizerFormat::WordPiece {
+                vocab: dir.path().join("vocab.txt"),
+                lowercase: false,
+            }
+        );
+
+        let tokenizer = load_tokenizer_from_dir(dir.path()).unwrap();
+        let encoding = tokenizer.encode("hello, worlds", false).unwrap();
+        assert_eq!(encoding.len(), 4);
+    }
+
+    #[test]
+    fn test_sentencepiece_dir() {
+        let dir = TempDir::new().unwrap();
+        let model = dir.path().join("tokenizer.model");
+        fs::write(&model, b"").unwrap();
+        let err = load_tokenizer_from_dir(dir.path()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "failed to load tokenizer: {}: not a SentencePiece model: no pieces",
+                model.display()
+            )
+        );
+
+        let pieces = [
+            ("<unk>", 0.0, 2),
+            ("\u{2581}hello", -1.0, 1),
+            ("\u{2581}", -2.0, 1),
+            ("h", -3.0, 1),
+        ];
+        fs::write(&model, sentencepiece_model(1, &pieces)).unwrap();
+        assert_eq!(
+            detect_tokenizer_format(dir.path()).unwrap(),
+            TokenizerFormat::SentencePiece(model)
+        );
+        let tokenizer = load_tokenizer_from_dir(dir.path()).unwrap();
+        let encoding = tokenizer.encode("hello h", false).unwrap();
+        assert_eq!(encoding.get_ids(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_empty_dir_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        assert!(detect_tokenizer_format(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Registry of known models, their context windows, and the tokens a
+//! request adds around its text
+
+/// Specification of a known model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelSpec {
+    /// Model name (lowercase)
+    pub name: &'static str,
+    /// Maximum number of tokens in the context window
+    pub context_window: usize,
+    /// Tokens a request adds around a single user message, beyond the
+    /// message text
+    pub request_overhead: usize,
+}
+
+/// OpenAI chat formatting: 3 tokens wrap the message, 1 names its role, and
+/// 3 prime the reply
+const OPENAI_OVERHEAD: usize = 7;
+/// Claude turn markers around the message and before the reply
+const ANTHROPIC_OVERHEAD: usize = 5;
+/// Gemini bills the message text only
+const GEMINI_OVERHEAD: usize = 0;
+
+/// Built-in model registry
+const MODELS: &[ModelSpec] = &[
+    spec("gpt-4o", 128_000, OPENAI_OVERHEAD),
+    spec("gpt-4o-mini", 128_000, OPENAI_OVERHEAD),
+    spec("gpt-4.1", 1_047_576, OPENAI_OVERHEAD),
+    spec("gpt-4.1-mini", 1_047_576, OPENAI_OVERHEAD),
+    spec("gpt-4.1-nano", 1_047_576, OPENAI_OVERHEAD),
+    spec("gpt-4-turbo", 128_000, OPENAI_OVERHEAD),
+    spec("gpt-4", 8_192, OPENAI_OVERHEAD),
+    spec("gpt-3.5-turbo", 16_385, OPENAI_OVERHEAD),
+    spec("o1", 200_000, OPENAI_OVERHEAD),
+    spec("o3-mini", 200_000, OPENAI_OVERHEAD),
+    spec("claude-opus-4", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-sonnet-4", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-3-7-sonnet", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-3-5-sonnet", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-3-5-haiku", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-3-opus", 200_000, ANTHROPIC_OVERHEAD),
+    spec("claude-3-haiku", 200_000, ANTHROPIC_OVERHEAD),
+    spec("gemini-1.5-pro", 2_097_152, GEMINI_OVERHEAD),
+    spec("gemini-1.5-flash", 1_048_576, GEMINI_OVERHEAD),
+];
+
+const fn spec(name: &'static str, context_window: usize, request_overhead: usize) -> ModelSpec {
+    ModelSpec {
+        name,
+        context_window,
+        request_overhead,
+    }
+}
+
+/// Looks up a known model by name (case-insensitive)
+pub fn lookup_model(name: &str) -> Option<&'static ModelSpec> {
+    MODELS
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+/// All models in the built-in registry
+pub fn known_models() -> impl Iterator<Item = &'static ModelSpec> {
+    MODELS.iter()
+}
+
+/// How much of a context window an input consumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextFit {
+    /// Tokens in the input
+    pub tokens: usize,
+    /// Size of the context window
+    pub limit: usize,
+}
+
+impl ContextFit {
+    /// Creates a new ContextFit for `tokens` against a window of `limit` tokens
+    pub fn new(tokens: usize, limit: usize) -> Self {
+        Self { tokens, limit }
+    }
+
+    /// Percentage of the window used (may exceed 100)
+    pub fn percent(&self) -> f64 {
+        if self.limit == 0 {
+            return if self.tokens == 0 { 0.0 } else { f64::INFINITY };
+        }
+        self.tokens as f64 * 100.0 / self.limit as f64
+    }
+
+    /// Whether the input fits in the window
+    pub fn fits(&self) -> bool {
+        self.tokens <= self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_model() {
+        assert_eq!(lookup_model("GPT-4o").unwrap().context_window, 128_000);
+        assert_eq!(lookup_model("gpt-4o").unwrap().request_overhead, 7);
+        assert!(lookup_model("unknown").is_none());
+    }
+
+    #[test]
+    fn test_context_fit() {
+        let fit = ContextFit::new(64_000, 128_000);
+        assert!(fit.fits());
+        assert!((fit.percent() - 50.0).abs() < 1e-9);
+
+        let over = ContextFit::new(130_000, 128_000);
+        assert!(!over.fits());
+        assert!(over.percent() > 100.0);
+
+        assert!(ContextFit::new(128_000, 128_000).fits());
+    }
+}
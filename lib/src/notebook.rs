@@ -0,0 +1,145 @@
+//! Jupyter notebook (`.ipynb`) parsing
+//!
+//! Notebooks are JSON documents whose escaping, metadata, and embedded
+//! output blobs inflate raw token counts. This module pulls out the text of
+//! each cell and of its textual outputs.
+
+use crate::{Error, Result};
+use serde_json::Value;
+
+/// Kind of a notebook cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CellKind {
+    /// Executable code
+    Code,
+    /// Markdown prose
+    Markdown,
+    /// Raw, unrendered content
+    Raw,
+}
+
+impl CellKind {
+    /// Name of the kind as written in the notebook
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::Markdown => "markdown",
+            Self::Raw => "raw",
+        }
+    }
+}
+
+/// Text of a single notebook cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotebookCell {
+    /// Kind of the cell
+    pub kind: CellKind,
+    /// Cell source
+    pub source: String,
+    /// Text of the cell's outputs (streams, plain-text results, tracebacks)
+    pub outputs: String,
+}
+
+/// Parses the cells of a Jupyter notebook
+///
+/// Only the plain-text form of outputs is kept; images, HTML, and other rich
+/// output data are skipped.
+///
+/// # Arguments
+///
+/// * `json` - The notebook document
+///
+/// # Errors
+///
+/// Returns an error if the document is not valid JSON or has no cell list
+pub fn parse_notebook(json: &str) -> Result<Vec<NotebookCell>> {
+    let notebook: Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidInput(format!("{}", e)))?;
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::InvalidInput("notebook has no \"cells\" array".to_string()))?;
+
+    Ok(cells
+        .iter()
+        .map(|cell| {
+            let kind = match cell.get("cell_type").and_then(Value::as_str) {
+                Some("code") => CellKind::Code,
+                Some("markdown") => CellKind::Markdown,
+                _ => CellKind::Raw,
+            };
+            let outputs = cell
+                .get("outputs")
+                .and_then(Value::as_array)
+                .map(|outputs| outputs.iter().map(output_text).collect())
+                .unwrap_or_default();
+            NotebookCell {
+                kind,
+                source: multiline(cell.get("source")),
+                outputs,
+            }
+        })
+        .collect())
+}
+
+/// Plain text of one output: a stream, a result's `text/plain`, or a traceback
+fn output_text(output: &Value) -> String {
+    match output.get("output_type").and_then(Value::as_str) {
+        Some("stream") => multiline(output.get("text")),
+        Some("execute_result" | "display_data") => {
+            multiline(output.get("data").and_then(|data| data.get("text/plain")))
+        }
+        Some("error") => output
+            .get("traceback")
+            .and_then(Value::as_array)
+            .map(|lines| {
+                let lines: Vec<_> = lines.iter().filter_map(Value::as_str).collect();
+                lines.join("\n") + "\n"
+            })
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Notebook text fields are either a string or a list of lines
+fn multiline(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notebook() {
+        let json = r##"{
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Title\n", "Intro"]},
+                {"cell_type": "code", "metadata": {}, "execution_count": 1,
+                 "source": "print(1)\n1 + 1",
+                 "outputs": [
+                    {"output_type": "stream", "name": "stdout", "text": ["1\n"]},
+                    {"output_type": "execute_result", "execution_count": 1,
+                     "data": {"text/plain": ["2"], "image/png": "iVBORw0KGgo="}}
+                 ]},
+                {"cell_type": "raw", "metadata": {}, "source": []}
+            ],
+            "metadata": {}, "nbformat": 4, "nbformat_minor": 5
+        }"##;
+
+        let cells = parse_notebook(json).unwrap();
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[0].kind, CellKind::Markdown);
+        assert_eq!(cells[0].source, "# Title\nIntro");
+        assert_eq!(cells[1].kind, CellKind::Code);
+        assert_eq!(cells[1].outputs, "1\n2");
+        assert_eq!(cells[2].kind, CellKind::Raw);
+        assert!(cells[2].source.is_empty());
+
+        assert!(parse_notebook(r#"{"nbformat": 4}"#).is_err());
+    }
+}
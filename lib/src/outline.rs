@@ -0,0 +1,433 @@
+//! Symbol outlines of source files via tree-sitter
+//!
+//! An outline lists the functions, types, and blocks (impls, classes,
+//! modules) of a file with their byte ranges, so each symbol's tokens can be
+//! counted on its own. Each grammar is behind a cargo feature:
+//! `outline-rust`, `outline-python`, and `outline-typescript`.
+
+use crate::{Error, Result};
+use std::ops::Range;
+use std::path::Path;
+
+/// Languages that can be outlined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineLanguage {
+    /// Rust (requires the `outline-rust` feature)
+    Rust,
+    /// Python (requires the `outline-python` feature)
+    Python,
+    /// TypeScript (requires the `outline-typescript` feature)
+    TypeScript,
+    /// TypeScript with JSX (requires the `outline-typescript` feature)
+    Tsx,
+}
+
+impl OutlineLanguage {
+    /// Detects the language of a file from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            _ => None,
+        }
+    }
+
+    /// Human-readable name of the language
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rust => "Rust",
+            Self::Python => "Python",
+            Self::TypeScript => "TypeScript",
+            Self::Tsx => "TSX",
+        }
+    }
+}
+
+/// Kind of an outlined symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// Function or method
+    Function,
+    /// Class
+    Class,
+    /// Struct
+    Struct,
+    /// Enum
+    Enum,
+    /// Union
+    Union,
+    /// Trait
+    Trait,
+    /// Interface
+    Interface,
+    /// Type alias
+    Type,
+    /// Impl block
+    Impl,
+    /// Module or namespace
+    Module,
+    /// Macro definition
+    Macro,
+}
+
+impl SymbolKind {
+    /// Short keyword-like name of the kind (e.g. "fn", "class")
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Function => "fn",
+            Self::Class => "class",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Union => "union",
+            Self::Trait => "trait",
+            Self::Interface => "interface",
+            Self::Type => "type",
+            Self::Impl => "impl",
+            Self::Module => "mod",
+            Self::Macro => "macro",
+        }
+    }
+}
+
+/// A symbol in a source file's outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Kind of the symbol
+    pub kind: SymbolKind,
+    /// Name of the symbol; impl blocks are named after their type (and trait)
+    pub name: String,
+    /// Byte range of the symbol, including leading doc comments, attributes,
+    /// and decorators
+    pub range: Range<usize>,
+    /// First and last line of the symbol (1-based)
+    pub lines: (usize, usize),
+    /// Nesting depth: 0 for top-level symbols, 1 for their members, and so on
+    pub depth: usize,
+}
+
+/// Outlines the symbols of a source file, in source order
+///
+/// Members of impls, traits, classes, and modules are listed after their
+/// container with a greater depth. Nested functions are not listed.
+///
+/// # Arguments
+///
+/// * `source` - The source code
+/// * `language` - The language of the source
+///
+/// # Errors
+///
+/// Returns an error if support for the language was not compiled in
+pub fn outline(source: &str, language: OutlineLanguage) -> Result<Vec<Symbol>> {
+    imp::outline(source, language)
+}
+
+#[cfg(any(
+    feature = "outline-rust",
+    feature = "outline-python",
+    feature = "outline-typescript"
+))]
+mod imp {
+    use super::{OutlineLanguage, Symbol, SymbolKind};
+    use crate::{Error, Result};
+    use tree_sitter::{Language, Node, Parser};
+
+    pub fn outline(source: &str, language: OutlineLanguage) -> Result<Vec<Symbol>> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&grammar(language)?)
+            .map_err(|e| Error::Parse(format!("{}", e)))?;
+        let tree = parser
+            .parse(source, None)
+            .ok_or_else(|| Error::Parse("parser returned no tree".to_string()))?;
+
+        let mut symbols = Vec::new();
+        walk(tree.root_node(), source, 0, &mut symbols);
+        Ok(symbols)
+    }
+
+    fn grammar(language: OutlineLanguage) -> Result<Language> {
+        match language {
+            #[cfg(feature = "outline-rust")]
+            OutlineLanguage::Rust => Ok(tree_sitter_rust::LANGUAGE.into()),
+            #[cfg(feature = "outline-python")]
+            OutlineLanguage::Python => Ok(tree_sitter_python::LANGUAGE.into()),
+            #[cfg(feature = "outline-typescript")]
+            OutlineLanguage::TypeScript => Ok(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+            #[cfg(feature = "outline-typescript")]
+            OutlineLanguage::Tsx => Ok(tree_sitter_typescript::LANGUAGE_TSX.into()),
+            #[allow(unreachable_patterns)]
+            _ => Err(super::not_enabled(language)),
+        }
+    }
+
+    /// Collects the symbols among `node`'s children
+    fn walk(node: Node, source: &str, depth: usize, out: &mut Vec<Symbol>) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            // Decorators and `export` wrap the declaration they apply to
+            let declaration = match child.kind() {
+                "decorated_definition" => child.child_by_field_name("definition"),
+                "export_statement" => child.child_by_field_name("declaration"),
+                _ => Some(child),
+            };
+            let Some(declaration) = declaration else {
+                continue;
+            };
+            let Some(kind) = symbol_kind(declaration) else {
+                continue;
+            };
+
+            let start = leading_start(child);
+            out.push(Symbol {
+                kind,
+                name: symbol_name(declaration, source),
+                range: start.start_byte()..child.end_byte(),
+                lines: (start.start_position().row + 1, child.end_position().row + 1),
+                depth,
+            });
+            if let Some(body) = member_body(declaration) {
+                walk(body, source, depth + 1, out);
+            }
+        }
+    }
+
+    fn symbol_kind(node: Node) -> Option<SymbolKind> {
+        let kind = match node.kind() {
+            "function_item"
+            | "function_signature_item"
+            | "function_definition"
+            | "function_declaration"
+            | "generator_function_declaration"
+            | "method_definition"
+            | "method_signature"
+            | "abstract_method_signature" => SymbolKind::Function,
+            "struct_item" => SymbolKind::Struct,
+            "enum_item" | "enum_declaration" => SymbolKind::Enum,
+            "union_item" => SymbolKind::Union,
+            "trait_item" => SymbolKind::Trait,
+            "impl_item" => SymbolKind::Impl,
+            "mod_item" | "internal_module" | "module" => SymbolKind::Module,
+            "macro_definition" => SymbolKind::Macro,
+            "class_definition" | "class_declaration" | "abstract_class_declaration" => {
+                SymbolKind::Class
+            }
+            "interface_declaration" => SymbolKind::Interface,
+            "type_item" | "type_alias_declaration" => SymbolKind::Type,
+            // `const f = () => ...` and `const f = function () ...`
+            "lexical_declaration" if function_declarator(node).is_some() => SymbolKind::Function,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn symbol_name(node: Node, source: &str) -> String {
+        let text = |node: Node| source[node.byte_range()].to_string();
+        match node.kind() {
+            "impl_item" => {
+                let ty = node
+                    .child_by_field_name("type")
+                    .map(text)
+                    .unwrap_or_default();
+                match node.child_by_field_name("trait") {
+                    Some(t) => format!("{} for {}", text(t), ty),
+                    None => ty,
+                }
+            }
+            "lexical_declaration" => function_declarator(node)
+                .and_then(|declarator| declarator.child_by_field_name("name"))
+                .map(text)
+                .unwrap_or_default(),
+            _ => node
+                .child_by_field_name("name")
+                .map(text)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The declarator of a `const`/`let` whose value is a function
+    fn function_declarator(node: Node) -> Option<Node> {
+        let declarator = node
+            .named_child(0)
+            .filter(|n| n.kind() == "variable_declarator")?;
+        let value = declarator.child_by_field_name("value")?;
+        matches!(value.kind(), "arrow_function" | "function_expression").then_some(declarator)
+    }
+
+    /// The block whose children are members worth outlining
+    fn member_body(node: Node) -> Option<Node> {
+        match node.kind() {
+            "impl_item"
+            | "trait_item"
+            | "mod_item"
+            | "class_definition"
+            | "class_declaration"
+            | "abstract_class_declaration"
+            | "interface_declaration"
+            | "internal_module"
+            | "module" => node.child_by_field_name("body"),
+            _ => None,
+        }
+    }
+
+    /// The first of the comments and attributes directly above `node`
+    fn leading_start(node: Node) -> Node {
+        let mut start = node;
+        while let Some(previous) = start.prev_named_sibling() {
+            let attached = matches!(
+                previous.kind(),
+                "line_comment" | "block_comment" | "comment" | "attribute_item"
+            );
+            if !attached || previous.end_position().row + 1 < start.start_position().row {
+                break;
+            }
+            start = previous;
+        }
+        start
+    }
+}
+
+#[cfg(not(any(
+    feature = "outline-rust",
+    feature = "outline-python",
+    feature = "outline-typescript"
+)))]
+mod imp {
+    use super::{OutlineLanguage, Symbol};
+    use crate::Result;
+
+    pub fn outline(_source: &str, language: OutlineLanguage) -> Result<Vec<Symbol>> {
+        Err(super::not_enabled(language))
+    }
+}
+
+fn not_enabled(language: OutlineLanguage) -> Error {
+    let feature = match language {
+        OutlineLanguage::Rust => "outline-rust",
+        OutlineLanguage::Python => "outline-python",
+        OutlineLanguage::TypeScript | OutlineLanguage::Tsx => "outline-typescript",
+    };
+    Error::Parse(format!(
+        "{} outlines are not enabled (build with the `{}` feature)",
+        language.name(),
+        feature
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(
+        feature = "outline-rust",
+        feature = "outline-python",
+        feature = "outline-typescript"
+    ))]
+    fn names(symbols: &[Symbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|s| format!("{}{} {}", "  ".repeat(s.depth), s.kind.name(), s.name))
+            .collect()
+    }
+
+    #[cfg(feature = "outline-rust")]
+    #[test]
+    fn test_outline_rust() {
+        let source = "use std::fmt;\n\
+            \n\
+            /// A point\n\
+            #[derive(Debug)]\n\
+            struct Point { x: i32 }\n\
+            \n\
+            impl fmt::Display for Point {\n    \
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        \
+                    fn nested() {}\n        \
+                    write!(f, \"{}\", self.x)\n    \
+                }\n\
+            }\n\
+            \n\
+            fn main() {}\n";
+
+        let symbols = outline(source, OutlineLanguage::Rust).unwrap();
+        assert_eq!(
+            names(&symbols),
+            [
+                "struct Point",
+                "impl fmt::Display for Point",
+                "  fn fmt",
+                "fn main"
+            ]
+        );
+        assert_eq!(symbols[0].lines, (3, 5));
+        assert!(source[symbols[0].range.clone()].starts_with("/// A point"));
+    }
+
+    #[cfg(feature = "outline-python")]
+    #[test]
+    fn test_outline_python() {
+        let source = "import os\n\
+            \n\
+            @dataclass\n\
+            class Config:\n    \
+                path: str\n\
+            \n    \
+                def load(self):\n        \
+                    return os.path.exists(self.path)\n\
+            \n\
+            def main():\n    \
+                pass\n";
+
+        let symbols = outline(source, OutlineLanguage::Python).unwrap();
+        assert_eq!(names(&symbols), ["class Config", "  fn load", "fn main"]);
+        assert!(source[symbols[0].range.clone()].starts_with("@dataclass"));
+    }
+
+    #[cfg(feature = "outline-typescript")]
+    #[test]
+    fn test_outline_typescript() {
+        let source = "export interface Shape { area(): number }\n\
+            \n\
+            export class Square implements Shape {\n  \
+                constructor(private side: number) {}\n  \
+                area() { return this.side ** 2; }\n\
+            }\n\
+            \n\
+            const double = (x: number) => x * 2;\n";
+
+        let symbols = outline(source, OutlineLanguage::TypeScript).unwrap();
+        assert_eq!(
+            names(&symbols),
+            [
+                "interface Shape",
+                "  fn area",
+                "class Square",
+                "  fn constructor",
+                "  fn area",
+                "fn double"
+            ]
+        );
+    }
+
+    #[cfg(not(feature = "outline-rust"))]
+    #[test]
+    fn test_outline_requires_feature() {
+        let err = outline("fn main() {}", OutlineLanguage::Rust).unwrap_err();
+        assert!(err.to_string().contains("outline-rust"));
+    }
+
+    #[test]
+    fn test_outline_language_from_path() {
+        assert_eq!(
+            OutlineLanguage::from_path(Path::new("src/lib.rs")),
+            Some(OutlineLanguage::Rust)
+        );
+        assert_eq!(
+            OutlineLanguage::from_path(Path::new("App.TSX")),
+            Some(OutlineLanguage::Tsx)
+        );
+        assert_eq!(OutlineLanguage::from_path(Path::new("README.md")), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Counting one large text on several threads
+//!
+//! The text is cut into pieces of about [`PARALLEL_CHUNK_SIZE`] bytes at
+//! line breaks, the pieces are encoded in parallel, and each cut is then
+//! corrected for the tokens it changed. Cuts are only made between a line
+//! that ends in a visible character and one that starts with one, where
+//! tokenizers split anyway; the correction covers the rest, such as a
+//! SentencePiece tokenizer's word-start marker on the first piece of a
+//! text.
+
+use crate::{count_stats_serial, Result, TokenStats};
+use tokenizers::utils::parallelism::MaybeParallelIterator;
+use tokenizers::Tokenizer;
+
+/// Target size of the pieces a large text is cut into
+///
+/// [`crate::count_stats`] counts texts of at least twice this size in
+/// parallel.
+pub const PARALLEL_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Counts a large text's tokens and stats, encoding pieces of it on several
+/// threads
+///
+/// Gives the same token count as encoding the text at once, except with
+/// tokenizers that truncate or pad, which are counted on one thread.
+/// `max_line_tokens` ignores the few tokens a cut can add or remove.
+///
+/// # Errors
+///
+/// Returns an error if encoding fails
+pub fn count_stats_parallel(text: &str, tokenizer: &Tokenizer) -> Result<TokenStats> {
+    count_stats_in_chunks(text, tokenizer, PARALLEL_CHUNK_SIZE, true)
+}
+
+/// [`count_stats_parallel`] with pieces of about `chunk_size` bytes,
+/// finding `max_line_tokens` only if `line_maxima`
+pub(crate) fn count_stats_in_chunks(
+    text: &str,
+    tokenizer: &Tokenizer,
+    chunk_size: usize,
+    line_maxima: bool,
+) -> Result<TokenStats> {
+    // Truncation and padding apply to each encode, so pieces would not add up
+    if tokenizer.get_truncation().is_some() || tokenizer.get_padding().is_some() {
+        return count_stats_serial(text, tokenizer, line_maxima);
+    }
+    let cuts = cut_points(text, chunk_size);
+    if cuts.is_empty() {
+        return count_stats_serial(text, tokenizer, line_maxima);
+    }
+
+    let bounds: Vec<usize> = std::iter::once(0)
+        .chain(cuts.iter().copied())
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let pieces: Vec<&str> = bounds
+        .windows(2)
+        .map(|pair| &text[pair[0]..pair[1]])
+        .collect();
+    let counts = pieces
+        .into_maybe_par_iter()
+        .map(|piece| count_stats_serial(piece, tokenizer, line_maxima))
+        .collect::<Result<Vec<_>>>()?;
+    let corrections = cuts
+        .into_maybe_par_iter()
+        .map(|cut| cut_correction(text, cut, tokenizer))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut stats = TokenStats::new();
+    for count in &counts {
+        stats.add(count);
+    }
+    stats.tokens = stats
+        .tokens
+        .checked_add_signed(corrections.iter().sum())
+        .expect("corrections never remove more tokens than the pieces have");
+    Ok(stats)
+}
+
+/// Byte positions to cut `text` at, about `chunk_size` bytes apart
+///
+/// Each cut follows a `\n` that comes right after a visible character and
+/// right before one.
+fn cut_points(text: &str, chunk_size: usize) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut cuts = Vec::new();
+    let mut target = chunk_size;
+    while target < text.len() {
+        let cut = bytes[target..]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &byte)| byte == b'\n')
+            .map(|(index, _)| target + index + 1)
+            .find(|&cut| {
+                let before = bytes[..cut - 1].last();
+                let after = bytes.get(cut);
+                before.is_some_and(|byte| !byte.is_ascii_whitespace())
+                    && after.is_some_and(|byte| !byte.is_ascii_whitespace())
+            });
+        let Some(cut) = cut else {
+            break;
+        };
+        cuts.push(cut);
+        target = cut + chunk_size;
+    }
+    cuts
+}
+
+/// Tokens that encoding the text at once has over encoding it in two
+/// pieces at `cut`, found by encoding the lines on either side of the cut
+/// together and apart
+fn cut_correction(text: &str, cut: usize, tokenizer: &Tokenizer) -> Result<isize> {
+    let start = text[..cut - 1].rfind('\n').map_or(0, |index| index + 1);
+    let end = text[cut..]
+        .find('\n')
+        .map_or(text.len(), |index| cut + index + 1);
+    let together = crate::count_tokens(&text[start..end], tokenizer, false)?;
+    let before = crate::count_tokens(&text[start..cut], tokenizer, false)?;
+    let after = crate::count_tokens(&text[cut..end], tokenizer, false)?;
+    Ok(together as isize - before as isize - after as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines that tempt tokenizers to merge across line breaks: indents,
+    /// blank lines, trailing spaces, and runs of punctuation and digits
+    fn sample_text() -> String {
+        let lines = [
+            "fn main() {",
+            "    let total = 1234 + 5678;",
+            "",
+            "    println!(\"Grüße, 世界: {}\", total);   ",
+            "}",
+            "The quick brown fox jumps over the lazy dog.",
+            "--- *** ---",
+            "\t\tindented with tabs",
+            "2024-01-01T00:00:00Z",
+        ];
+        let mut text = String::new();
+        for index in 0..300 {
+            text.push_str(lines[index % lines.len()]);
+            text.push('\n');
+            if index % 7 == 0 {
+                text.push_str(lines[(index * 3) % lines.len()]);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn test_count_stats_in_chunks() {
+        let text = sample_text();
+        for tokenizer in [
+            include_bytes!("../../bin/assets/gpt2-tokenizer.json").as_slice(),
+            include_bytes!("../../bin/assets/tokenizers/bert.json").as_slice(),
+        ] {
+            let tokenizer = Tokenizer::from_bytes(tokenizer).unwrap();
+            let serial = count_stats_serial(&text, &tokenizer, true).unwrap();
+            for (chunk_size, line_maxima) in [(1, true), (64, false), (500, true)] {
+                let chunked =
+                    count_stats_in_chunks(&text, &tokenizer, chunk_size, line_maxima).unwrap();
+                assert_eq!(chunked.tokens, serial.tokens, "chunks of {}", chunk_size);
+                assert_eq!(
+                    (chunked.lines, chunked.bytes, chunked.words, chunked.chars),
+                    (serial.lines, serial.bytes, serial.words, serial.chars)
+                );
+                assert_eq!(chunked.max_line_tokens > 0, line_maxima);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cut_points() {
+        let text = "one\ntwo\n\nthree \nfour\n  five\nsix";
+        let cuts = cut_points(text, 1);
+        // Never after a blank line, before an indent, or after a trailing space
+        assert_eq!(cuts, [4, 28]);
+        for cut in cuts {
+            assert_eq!(&text[cut - 1..cut], "\n");
+        }
+        assert!(cut_points(text, text.len()).is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Sharing loaded tokenizers between threads
+//!
+//! Loading a tokenizer takes on the order of 100 ms, while encoding with one
+//! only needs `&self`. [`SharedTokenizer`] is a cheap handle to a tokenizer
+//! loaded once, and [`TokenizerPool`] keeps such handles by name so that
+//! server threads load each tokenizer at most once.
+
+#[cfg(feature = "fs")]
+use crate::{load_tokenizer, Result};
+use std::collections::HashMap;
+use std::ops::Deref;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokenizers::Tokenizer;
+
+/// A handle to a loaded tokenizer that is cheap to clone
+///
+/// Handles are `Send` and `Sync`, and dereference to the [`Tokenizer`], so
+/// any number of threads can encode with one at the same time: encoding
+/// keeps its buffers per call rather than in the tokenizer, and needs no
+/// lock.
+#[derive(Debug, Clone)]
+pub struct SharedTokenizer(Arc<Tokenizer>);
+
+impl SharedTokenizer {
+    /// Shares an already loaded tokenizer
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        Self(Arc::new(tokenizer))
+    }
+
+    /// Loads a tokenizer from a JSON file or model directory to share
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokenizer cannot be loaded
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        load_tokenizer(path).map(Self::new)
+    }
+}
+
+impl Deref for SharedTokenizer {
+    type Target = Tokenizer;
+
+    fn deref(&self) -> &Tokenizer {
+        &self.0
+    }
+}
+
+impl From<Tokenizer> for SharedTokenizer {
+    fn from(tokenizer: Tokenizer) -> Self {
+        Self::new(tokenizer)
+    }
+}
+
+/// Tokenizers loaded on first use and kept by name
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{count_tokens, TokenizerPool};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let pool = Arc::new(TokenizerPool::new());
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let pool = Arc::clone(&pool);
+///         thread::spawn(move || {
+///             // Threads after the first reuse the loaded tokenizer
+///             let tokenizer = pool.get_or_load_path("tokenizer.json").unwrap();
+///             count_tokens("Hello, world", &tokenizer, false).unwrap()
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     println!("{}", worker.join().unwrap());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TokenizerPool {
+    tokenizers: Mutex<HashMap<String, SharedTokenizer>>,
+}
+
+impl TokenizerPool {
+    /// Creates an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tokenizer loaded under `name`, if any
+    pub fn get(&self, name: &str) -> Option<SharedTokenizer> {
+        self.lock().get(name).cloned()
+    }
+
+    /// The tokenizer loaded under `name`, loading it with `load` if there is
+    /// none yet
+    ///
+    /// Loading happens outside the pool's lock, so requests for other
+    /// tokenizers are not held up. If two threads load the same name at once,
+    /// both get the tokenizer stored first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `load`, leaving the pool unchanged
+    pub fn get_or_load<E>(
+        &self,
+        name: &str,
+        load: impl FnOnce() -> std::result::Result<Tokenizer, E>,
+    ) -> std::result::Result<SharedTokenizer, E> {
+        if let Some(tokenizer) = self.get(name) {
+            return Ok(tokenizer);
+        }
+        let tokenizer = SharedTokenizer::new(load()?);
+        Ok(self
+            .lock()
+            .entry(name.to_string())
+            .or_insert(tokenizer)
+            .clone())
+    }
+
+    /// The tokenizer at `path` (a JSON file or model directory), loading it
+    /// on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokenizer cannot be loaded
+    #[cfg(feature = "fs")]
+    pub fn get_or_load_path<P: AsRef<Path>>(&self, path: P) -> Result<SharedTokenizer> {
+        let path = path.as_ref();
+        self.get_or_load(&path.display().to_string(), || load_tokenizer(path))
+    }
+
+    /// Number of tokenizers loaded
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no tokenizer has been loaded yet
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, SharedTokenizer>> {
+        // A panic while holding the lock can't leave the map inconsistent
+        self.tokenizers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_tokens, load_tokenizer_from_bytes};
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_tokenizer_pool() {
+        assert_send_sync::<SharedTokenizer>();
+        assert_send_sync::<TokenizerPool>();
+
+        let pool = Arc::new(TokenizerPool::new());
+        let load =
+            || load_tokenizer_from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json"));
+        let counts: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || {
+                    let tokenizer = pool.get_or_load("gpt2", load).unwrap();
+                    count_tokens("Hello, world", &tokenizer, false).unwrap()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        assert_eq!(counts, [3; 4]);
+        assert_eq!(pool.len(), 1);
+
+        let first = pool.get("gpt2").unwrap();
+        let second = pool.get_or_load("gpt2", || -> crate::Result<Tokenizer> { unreachable!() });
+        assert!(Arc::ptr_eq(&first.0, &second.unwrap().0));
+        assert!(pool.get_or_load_path("missing.json").is_err());
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,389 @@
+//! Pluggable text preprocessing applied before counting
+//!
+//! A [`Pipeline`] runs a sequence of [`Preprocessor`] stages over the input,
+//! so callers can count only the part of a document that matters to them
+//! (e.g. prose without its Markdown syntax).
+
+use crate::{strip_comments, CommentSyntax, Result};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::borrow::Cow;
+
+/// A transformation applied to text before it is counted
+pub trait Preprocessor: Send + Sync {
+    /// Short name of the stage (e.g. "markdown")
+    fn name(&self) -> &'static str;
+
+    /// Transforms `text`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text cannot be processed
+    fn process(&self, text: &str) -> Result<String>;
+}
+
+/// An ordered sequence of preprocessing stages
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Preprocessor>>,
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline, which leaves text unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a stage to the pipeline
+    pub fn push<P: Preprocessor + 'static>(&mut self, stage: P) {
+        self.stages.push(Box::new(stage));
+    }
+
+    /// Whether the pipeline has no stages
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Names of the stages, in order
+    pub fn stage_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.stages.iter().map(|stage| stage.name())
+    }
+
+    /// Runs every stage over `text`, in order
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error raised by a stage
+    pub fn apply<'a>(&self, text: &'a str) -> Result<Cow<'a, str>> {
+        let mut text = Cow::Borrowed(text);
+        for stage in &self.stages {
+            text = Cow::Owned(stage.process(&text)?);
+        }
+        Ok(text)
+    }
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.stage_names()).finish()
+    }
+}
+
+/// Removes Markdown syntax, keeping the text a reader would see
+///
+/// Headings, emphasis, links, list markers, and tables are reduced to their
+/// text; link targets, image URLs, and HTML tags are dropped. Code block
+/// contents are kept, with their fences only when `keep_fences` is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripMarkdown {
+    /// Keep the ```` ``` ```` fences (and language tags) around code blocks
+    pub keep_fences: bool,
+}
+
+impl Preprocessor for StripMarkdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn process(&self, text: &str) -> Result<String> {
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS
+            | Options::ENABLE_FOOTNOTES;
+        let mut out = String::with_capacity(text.len());
+
+        for event in Parser::new_ext(text, options) {
+            match event {
+                Event::Text(text) | Event::Code(text) => out.push_str(&text),
+                Event::InlineMath(text) | Event::DisplayMath(text) => out.push_str(&text),
+                Event::SoftBreak | Event::HardBreak => out.push('\n'),
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    end_line(&mut out);
+                    if self.keep_fences {
+                        let language = match &kind {
+                            CodeBlockKind::Fenced(language) => language.as_ref(),
+                            CodeBlockKind::Indented => "",
+                        };
+                        out.push_str("```");
+                        out.push_str(language);
+                        out.push('\n');
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    end_line(&mut out);
+                    if self.keep_fences {
+                        out.push_str("```\n");
+                    }
+                }
+                Event::End(TagEnd::TableCell) => out.push(' '),
+                Event::End(
+                    TagEnd::Paragraph
+                    | TagEnd::Heading(_)
+                    | TagEnd::Item
+                    | TagEnd::TableHead
+                    | TagEnd::TableRow
+                    | TagEnd::FootnoteDefinition,
+                ) => end_line(&mut out),
+                _ => {}
+            }
+        }
+
+        let trimmed = out.trim_end().len();
+        out.truncate(trimmed);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Removes source code comments, keeping code and string literals
+///
+/// The comment syntax is fixed per stage, so build one per source language
+/// (see [`CommentSyntax::for_path`]).
+#[derive(Debug, Clone, Copy)]
+pub struct StripComments {
+    /// Comment syntax of the code's language
+    pub syntax: CommentSyntax,
+}
+
+impl Preprocessor for StripComments {
+    fn name(&self) -> &'static str {
+        "comments"
+    }
+
+    fn process(&self, text: &str) -> Result<String> {
+        Ok(strip_comments(text, self.syntax))
+    }
+}
+
+/// Elements whose contents are never shown as text
+const HIDDEN_ELEMENTS: [&str; 6] = ["script", "style", "noscript", "template", "svg", "head"];
+
+/// Elements that start and end their own lines
+const BLOCK_ELEMENTS: [&str; 31] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Reduces HTML to the text a browser would show
+///
+/// Tags, comments, and the contents of `<head>`, `<script>`, `<style>`, and
+/// similar elements are dropped. Block elements such as paragraphs and list
+/// items end lines, table cells are separated by spaces, runs of whitespace
+/// collapse to one space, and the common character references (`&amp;`,
+/// `&nbsp;`, `&#39;`, ...) are decoded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripHtml;
+
+impl Preprocessor for StripHtml {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn process(&self, text: &str) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find('<') {
+            push_html_text(&mut out, &rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+                continue;
+            }
+            // A '<' that does not open a tag is text, as in "a < b"
+            if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c)) {
+                push_html_text(&mut out, "<");
+                rest = &rest[1..];
+                continue;
+            }
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+
+            if !closing && !tag.ends_with('/') && HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                // Skip to the end of the closing tag
+                let close = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&close) {
+                    Some(at) => rest[at..].find('>').map_or("", |end| &rest[at + end + 1..]),
+                    None => "",
+                };
+            } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                end_html_line(&mut out);
+            } else if name == "td" || name == "th" {
+                push_html_text(&mut out, " ");
+            }
+        }
+        push_html_text(&mut out, rest);
+
+        let trimmed = out.trim_end().len();
+        out.truncate(trimmed);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Appends HTML text, decoding character references and collapsing
+/// whitespace
+fn push_html_text(out: &mut String, text: &str) {
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (c, len) = match rest.strip_prefix('&').and_then(decode_reference) {
+            Some((c, len)) => (c, len + 1),
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                (c, c.len_utf8())
+            }
+        };
+        rest = &rest[len..];
+
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with([' ', '\n']) {
+                out.push(' ');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Decodes the character reference at the start of `text` (just after the
+/// `&`), returning the character and the length of the reference
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    let end = text.get(..12).unwrap_or(text).find(';')?;
+    let name = &text[..end];
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = match name.strip_prefix('#')? {
+                hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+                decimal => decimal.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+/// Ends the current line of HTML text, dropping a trailing space
+fn end_html_line(out: &mut String) {
+    if out.ends_with(' ') {
+        out.pop();
+    }
+    end_line(out);
+}
+
+/// Ends the current line unless the output is already at a line start
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Title\n\
+        \n\
+        Some *emphasis*, `code`, and a [link](https://example.com).\n\
+        \n\
+        - one\n\
+        - **two**\n\
+        \n\
+        ```rust\n\
+        fn main() {}\n\
+        ```\n";
+
+    #[test]
+    fn test_strip_markdown() {
+        let stripped = StripMarkdown::default().process(DOC).unwrap();
+        assert_eq!(
+            stripped,
+            "Title\nSome emphasis, code, and a link.\none\ntwo\nfn main() {}\n"
+        );
+
+        let fenced = StripMarkdown { keep_fences: true }.process(DOC).unwrap();
+        assert!(fenced.ends_with("two\n```rust\nfn main() {}\n```\n"));
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let page = "<!DOCTYPE html><html><head><title>T</title>\
+            <style>p { color: red }</style></head>\
+            <body><h1>Hello</h1>\n  <p>A &amp; B&nbsp;&lt;3 &#x263A;\n  and a < b</p>\
+            <script>var x = '</p>';</script>\
+            <ul><li>one</li><li><b>two</b></li></ul><!-- <p>hidden</p> -->\
+            <table><tr><td>1</td><td>2</td></tr></table><br/>end</body></html>";
+        assert_eq!(
+            StripHtml.process(page).unwrap(),
+            "Hello\nA & B <3 \u{263a} and a < b\none\ntwo\n1 2\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::new();
+        assert!(pipeline.is_empty());
+        assert!(matches!(
+            pipeline.apply("**x**").unwrap(),
+            Cow::Borrowed("**x**")
+        ));
+
+        pipeline.push(StripMarkdown::default());
+        assert_eq!(pipeline.stage_names().collect::<Vec<_>>(), ["markdown"]);
+        assert_eq!(pipeline.apply("**x**").unwrap(), "x\n");
+
+        pipeline.push(StripComments {
+            syntax: CommentSyntax::MARKUP,
+        });
+        assert_eq!(pipeline.apply("**x** <!-- y -->").unwrap(), "x\n");
+    }
+}
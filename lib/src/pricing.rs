@@ -0,0 +1,153 @@
+//! Per-model token pricing for cost estimation
+
+use std::collections::BTreeMap;
+
+/// Built-in prices in US dollars per million tokens: (model, input, output)
+///
+/// Prices change; treat these as estimates.
+const BUILTIN_PRICING: &[(&str, f64, f64)] = &[
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o1", 15.00, 60.00),
+    ("o3-mini", 1.10, 4.40),
+    ("claude-opus-4", 15.00, 75.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+];
+
+/// Pricing for a single model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    /// US dollars per million input tokens
+    pub input_per_million: f64,
+    /// US dollars per million output tokens, if known
+    pub output_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    /// Estimated cost in US dollars of sending `tokens` input tokens
+    pub fn input_cost(&self, tokens: usize) -> f64 {
+        tokens as f64 * self.input_per_million / 1_000_000.0
+    }
+
+    /// Estimated cost in US dollars of generating `tokens` output tokens
+    pub fn output_cost(&self, tokens: usize) -> Option<f64> {
+        self.output_per_million
+            .map(|price| tokens as f64 * price / 1_000_000.0)
+    }
+}
+
+/// Table of model prices, keyed by lowercase model name
+#[derive(Debug, Clone, Default)]
+pub struct PricingTable {
+    models: BTreeMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    /// Creates an empty pricing table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table with the built-in model prices
+    pub fn builtin() -> Self {
+        let mut table = Self::new();
+        for (model, input_per_million, output_per_million) in BUILTIN_PRICING {
+            table.insert(
+                model,
+                ModelPricing {
+                    input_per_million: *input_per_million,
+                    output_per_million: Some(*output_per_million),
+                },
+            );
+        }
+        table
+    }
+
+    /// Adds or replaces the pricing for a model
+    pub fn insert(&mut self, model: &str, pricing: ModelPricing) {
+        self.models.insert(model.to_lowercase(), pricing);
+    }
+
+    /// Adds every entry of `other`, replacing existing models with the same name
+    pub fn extend(&mut self, other: PricingTable) {
+        self.models.extend(other.models);
+    }
+
+    /// Looks up the pricing for a model (case-insensitive)
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        self.models.get(&model.to_lowercase())
+    }
+
+    /// Names of all models in the table, sorted
+    pub fn models(&self) -> impl Iterator<Item = &str> {
+        self.models.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup_is_case_insensitive() {
+        let table = PricingTable::builtin();
+        let pricing = table.get("GPT-4o").unwrap();
+        assert_eq!(pricing.input_per_million, 2.50);
+        assert!(table.get("no-such-model").is_none());
+    }
+
+    #[test]
+    fn test_input_and_output_cost() {
+        let pricing = ModelPricing {
+            input_per_million: 3.0,
+            output_per_million: Some(15.0),
+        };
+        assert_eq!(pricing.input_cost(0), 0.0);
+        assert!((pricing.input_cost(500_000) - 1.5).abs() < 1e-9);
+        assert!((pricing.output_cost(100_000).unwrap() - 1.5).abs() < 1e-9);
+
+        let input_only = ModelPricing {
+            input_per_million: 3.0,
+            output_per_million: None,
+        };
+        assert_eq!(input_only.output_cost(100), None);
+    }
+
+    #[test]
+    fn test_extend_overrides_and_adds() {
+        let mut custom = PricingTable::new();
+        custom.insert(
+            "GPT-4o",
+            ModelPricing {
+                input_per_million: 1.0,
+                output_per_million: None,
+            },
+        );
+        custom.insert(
+            "my-finetune",
+            ModelPricing {
+                input_per_million: 4.0,
+                output_per_million: Some(16.0),
+            },
+        );
+
+        let mut table = PricingTable::builtin();
+        table.extend(custom);
+        assert_eq!(table.get("gpt-4o").unwrap().input_per_million, 1.0);
+        assert_eq!(table.get("my-finetune").unwrap().input_per_million, 4.0);
+        assert!(table.get("claude-3-haiku").is_some());
+    }
+}
@@ -0,0 +1,128 @@
+//! Counting many files with progress reports
+//!
+//! Frontends that render progress (a GUI, a TUI) pass a callback that is
+//! called after each file, instead of counting file by file themselves.
+
+use crate::{count_tokens_in_file, Result, TokenStats};
+use std::fs;
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+/// How far a count over several files has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of the files completed so far, as stored on disk
+    pub bytes_read: u64,
+    /// Files completed so far, including those that failed
+    pub files_completed: usize,
+    /// Files being counted
+    pub files_total: usize,
+}
+
+impl Progress {
+    /// Share of the files completed, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.files_total == 0 {
+            1.0
+        } else {
+            self.files_completed as f64 / self.files_total as f64
+        }
+    }
+}
+
+/// Counts tokens in each of `paths`, calling `progress` after each file
+///
+/// A file that cannot be read or encoded fails on its own; the others are
+/// still counted.
+///
+/// # Arguments
+///
+/// * `paths` - Paths of the files to count
+/// * `tokenizer` - The tokenizer to use
+/// * `progress` - Called with the progress so far after each file
+///
+/// # Examples
+///
+/// ```no_run
+/// use token_counter_lib::{count_tokens_in_files, load_tokenizer};
+///
+/// let tokenizer = load_tokenizer("tokenizer.json").unwrap();
+/// let results = count_tokens_in_files(&["a.txt", "b.txt"], &tokenizer, Some(&mut |progress| {
+///     eprint!("\r{:.0}%", progress.fraction() * 100.0);
+/// }));
+/// for stats in results.into_iter().flatten() {
+///     println!("{} {:?}", stats.tokens, stats.path);
+/// }
+/// ```
+pub fn count_tokens_in_files<P: AsRef<Path>>(
+    paths: &[P],
+    tokenizer: &Tokenizer,
+    progress: Option<&mut dyn FnMut(Progress)>,
+) -> Vec<Result<TokenStats>> {
+    count_each(paths, progress, |path| {
+        count_tokens_in_file(path, tokenizer)
+    })
+}
+
+/// Counts each of `paths` with `count`, reporting progress after each
+pub(crate) fn count_each<P: AsRef<Path>>(
+    paths: &[P],
+    mut progress: Option<&mut dyn FnMut(Progress)>,
+    count: impl Fn(&Path) -> Result<TokenStats>,
+) -> Vec<Result<TokenStats>> {
+    let mut state = Progress {
+        bytes_read: 0,
+        files_completed: 0,
+        files_total: paths.len(),
+    };
+    paths
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            let stats = count(path);
+            state.bytes_read += fs::metadata(path).map_or(0, |metadata| metadata.len());
+            state.files_completed += 1;
+            if let Some(progress) = progress.as_mut() {
+                progress(state);
+            }
+            stats
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_count_tokens_in_files_progress() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "Hello, world\n").unwrap();
+        let missing = dir.path().join("missing.txt");
+
+        let mut reports = Vec::new();
+        let results = count_tokens_in_files(
+            &[&file, &missing, &file],
+            &tokenizer,
+            Some(&mut |progress| reports.push(progress)),
+        );
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().tokens, 4);
+        assert!(results[1].is_err());
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                bytes_read: 26,
+                files_completed: 3,
+                files_total: 3
+            })
+        );
+        assert_eq!(reports[1].bytes_read, 13);
+        assert_eq!(reports[1].fraction(), 2.0 / 3.0);
+    }
+}
@@ -0,0 +1,475 @@
+//! Formatting token stats for display
+//!
+//! A [`Report`] pairs the [`Columns`] to show with a [`Formatter`] that
+//! renders each row, so every frontend (the CLI, the daemon, editor plugins)
+//! prints the same output for the same stats.
+
+use crate::{ContextFit, ModelPricing, TokenStats};
+use serde_json::{Map, Value};
+
+/// The columns a report shows
+#[derive(Debug, Clone, Default)]
+pub struct Columns {
+    /// Token count
+    pub tokens: bool,
+    /// Line count
+    pub lines: bool,
+    /// Byte count
+    pub bytes: bool,
+    /// Tokens per line (average and maximum) and tokens per KiB
+    pub density: bool,
+    /// Bytes per token, how efficiently the tokenizer encodes the text
+    pub bytes_per_token: bool,
+    /// Estimated input cost at this pricing
+    pub pricing: Option<ModelPricing>,
+    /// Share of a context window of this many tokens
+    pub context_limit: Option<usize>,
+}
+
+impl Columns {
+    /// Tokens, lines, and bytes, as `tc` shows by default
+    pub fn counts() -> Self {
+        Self {
+            tokens: true,
+            lines: true,
+            bytes: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Renders the rows of a report
+pub trait Formatter: Send + Sync {
+    /// Short name of the format (e.g. "json")
+    fn name(&self) -> &'static str;
+
+    /// Text printed once before the rows, for formats with a header
+    fn header(&self, _columns: &Columns) -> Option<String> {
+        None
+    }
+
+    /// Renders the stats of one input, named `name` (none for stdin)
+    fn row(&self, columns: &Columns, stats: &TokenStats, name: Option<&str>) -> String;
+}
+
+/// Space-separated, right-aligned columns followed by the name, like `wc`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainFormatter;
+
+impl Formatter for PlainFormatter {
+    fn name(&self) -> &'static str {
+        "plain"
+    }
+
+    fn row(&self, columns: &Columns, stats: &TokenStats, name: Option<&str>) -> String {
+        plain_row(plain_columns(columns, stats), name)
+    }
+}
+
+/// Plain rows with the token count in green, yellow, or red by
+/// [`Thresholds`], for terminals
+#[derive(Debug, Clone, Copy)]
+pub struct ColoredFormatter {
+    thresholds: Thresholds,
+}
+
+impl ColoredFormatter {
+    /// Colors token counts by `thresholds`
+    pub fn new(thresholds: Thresholds) -> Self {
+        Self { thresholds }
+    }
+}
+
+impl Formatter for ColoredFormatter {
+    fn name(&self) -> &'static str {
+        "plain"
+    }
+
+    fn row(&self, columns: &Columns, stats: &TokenStats, name: Option<&str>) -> String {
+        let mut parts = plain_columns(columns, stats);
+        if columns.tokens {
+            let color = match self
+                .thresholds
+                .severity(stats.tokens, columns.context_limit)
+            {
+                Severity::Ok => "\x1b[32m",
+                Severity::Warn => "\x1b[33m",
+                Severity::Alert => "\x1b[31m",
+            };
+            parts[0] = format!("{}{}\x1b[0m", color, parts[0]);
+        }
+        plain_row(parts, name)
+    }
+}
+
+/// A token count, or a share of the context window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    /// This many tokens
+    Tokens(usize),
+    /// This percentage of the context window
+    Percent(f64),
+}
+
+impl Threshold {
+    /// Whether `tokens` reaches the threshold; percentages are never
+    /// reached without a context window
+    fn reached(self, tokens: usize, context_limit: Option<usize>) -> bool {
+        match (self, context_limit) {
+            (Threshold::Tokens(threshold), _) => tokens >= threshold,
+            (Threshold::Percent(percent), Some(limit)) => {
+                ContextFit::new(tokens, limit).percent() >= percent
+            }
+            (Threshold::Percent(_), None) => false,
+        }
+    }
+}
+
+/// How close a token count is to its limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Below both thresholds
+    Ok,
+    /// At or above the warning threshold
+    Warn,
+    /// At or above the alert threshold
+    Alert,
+}
+
+/// The token counts at which a count is worth a warning and an alert
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::{Severity, Threshold, Thresholds};
+///
+/// let thresholds = Thresholds::context_window();
+/// assert_eq!(thresholds.severity(700, Some(1000)), Severity::Ok);
+/// assert_eq!(thresholds.severity(800, Some(1000)), Severity::Warn);
+/// assert_eq!(thresholds.severity(1200, Some(1000)), Severity::Alert);
+///
+/// let thresholds = Thresholds::new(Threshold::Tokens(2000), Threshold::Tokens(8000));
+/// assert_eq!(thresholds.severity(2000, None), Severity::Warn);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Threshold for [`Severity::Warn`]
+    pub warn: Threshold,
+    /// Threshold for [`Severity::Alert`]
+    pub alert: Threshold,
+}
+
+impl Thresholds {
+    /// Warn at `warn` and alert at `alert`
+    pub fn new(warn: Threshold, alert: Threshold) -> Self {
+        Self { warn, alert }
+    }
+
+    /// Warn at 75% of the context window and alert once it is full
+    pub fn context_window() -> Self {
+        Self::new(Threshold::Percent(75.0), Threshold::Percent(100.0))
+    }
+
+    /// How `tokens` compares to the thresholds, with percentages taken of
+    /// `context_limit`
+    pub fn severity(&self, tokens: usize, context_limit: Option<usize>) -> Severity {
+        if self.alert.reached(tokens, context_limit) {
+            Severity::Alert
+        } else if self.warn.reached(tokens, context_limit) {
+            Severity::Warn
+        } else {
+            Severity::Ok
+        }
+    }
+}
+
+/// The right-aligned columns of a plain row
+fn plain_columns(columns: &Columns, stats: &TokenStats) -> Vec<String> {
+    let mut parts = Vec::new();
+
+    if columns.tokens {
+        parts.push(format!("{:8}", stats.tokens));
+    }
+    if columns.lines {
+        parts.push(format!("{:8}", stats.lines));
+    }
+    if columns.bytes {
+        parts.push(format!("{:8}", stats.bytes));
+    }
+    if columns.density {
+        parts.push(format!(
+            "{:>30}",
+            format!(
+                "{:.1}/line (max {}) {:.0}/KB",
+                stats.tokens_per_line(),
+                stats.max_line_tokens,
+                stats.tokens_per_kb()
+            )
+        ));
+    }
+    if columns.bytes_per_token {
+        parts.push(format!("{:>8}", format!("{:.2}", stats.bytes_per_token())));
+    }
+    if let Some(pricing) = &columns.pricing {
+        parts.push(format!(
+            "{:>10}",
+            format!("${:.6}", pricing.input_cost(stats.tokens))
+        ));
+    }
+    if let Some(limit) = columns.context_limit {
+        let fit = ContextFit::new(stats.tokens, limit);
+        parts.push(format!(
+            "{:>24}",
+            format!("{} / {} ({:.1}%)", fit.tokens, fit.limit, fit.percent())
+        ));
+    }
+    parts
+}
+
+/// Joins plain columns and appends the name
+fn plain_row(parts: Vec<String>, name: Option<&str>) -> String {
+    let counts = parts.join(" ");
+    match name {
+        Some(name) => format!("{} {}", counts, name),
+        None => counts,
+    }
+}
+
+/// One JSON object per row (JSON Lines)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn row(&self, columns: &Columns, stats: &TokenStats, name: Option<&str>) -> String {
+        let mut object = Map::new();
+        if let Some(name) = name {
+            object.insert("name".to_string(), Value::from(name));
+        }
+        for (key, value) in fields(columns, stats) {
+            object.insert(key.to_string(), value);
+        }
+        Value::Object(object).to_string()
+    }
+}
+
+/// Comma-separated values under a header row, with the name last
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn header(&self, columns: &Columns) -> Option<String> {
+        let mut keys: Vec<_> = fields(columns, &TokenStats::new())
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.push("name");
+        Some(keys.join(","))
+    }
+
+    fn row(&self, columns: &Columns, stats: &TokenStats, name: Option<&str>) -> String {
+        let mut values: Vec<_> = fields(columns, stats)
+            .into_iter()
+            .map(|(_, value)| value.to_string())
+            .collect();
+        values.push(csv_field(name.unwrap_or_default()));
+        values.join(",")
+    }
+}
+
+/// The built-in formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// [`PlainFormatter`]
+    #[default]
+    Plain,
+    /// [`JsonFormatter`]
+    Json,
+    /// [`CsvFormatter`]
+    Csv,
+}
+
+impl ReportFormat {
+    /// The formatter rendering this format
+    pub fn formatter(self) -> Box<dyn Formatter> {
+        match self {
+            ReportFormat::Plain => Box::new(PlainFormatter),
+            ReportFormat::Json => Box::new(JsonFormatter),
+            ReportFormat::Csv => Box::new(CsvFormatter),
+        }
+    }
+}
+
+/// Formats token stats with a fixed set of columns and formatter
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::{Columns, Report, ReportFormat, TokenStats};
+///
+/// let report = Report::new(Columns::counts(), ReportFormat::Plain);
+/// let stats = TokenStats { tokens: 4, lines: 1, bytes: 13, ..TokenStats::new() };
+/// assert_eq!(report.format_stats(&stats, Some("a.txt")), "       4        1       13 a.txt");
+/// ```
+pub struct Report {
+    columns: Columns,
+    formatter: Box<dyn Formatter>,
+}
+
+impl Report {
+    /// Creates a report in one of the built-in formats
+    pub fn new(columns: Columns, format: ReportFormat) -> Self {
+        Self {
+            columns,
+            formatter: format.formatter(),
+        }
+    }
+
+    /// Creates a report rendered by a custom formatter
+    pub fn with_formatter<F: Formatter + 'static>(columns: Columns, formatter: F) -> Self {
+        Self {
+            columns,
+            formatter: Box::new(formatter),
+        }
+    }
+
+    /// The columns the report shows
+    pub fn columns(&self) -> &Columns {
+        &self.columns
+    }
+
+    /// Text to print once before the rows, for formats with a header
+    pub fn header(&self) -> Option<String> {
+        self.formatter.header(&self.columns)
+    }
+
+    /// Formats the stats of one input, named `name` (none for stdin)
+    pub fn format_stats(&self, stats: &TokenStats, name: Option<&str>) -> String {
+        self.formatter.row(&self.columns, stats, name)
+    }
+}
+
+impl std::fmt::Debug for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Report")
+            .field("columns", &self.columns)
+            .field("formatter", &self.formatter.name())
+            .finish()
+    }
+}
+
+/// The machine-readable fields of the selected columns, in column order
+fn fields(columns: &Columns, stats: &TokenStats) -> Vec<(&'static str, Value)> {
+    let mut fields = Vec::new();
+    if columns.tokens {
+        fields.push(("tokens", Value::from(stats.tokens)));
+    }
+    if columns.lines {
+        fields.push(("lines", Value::from(stats.lines)));
+    }
+    if columns.bytes {
+        fields.push(("bytes", Value::from(stats.bytes)));
+    }
+    if columns.density {
+        fields.push(("tokens_per_line", Value::from(stats.tokens_per_line())));
+        fields.push(("max_line_tokens", Value::from(stats.max_line_tokens)));
+        fields.push(("tokens_per_kb", Value::from(stats.tokens_per_kb())));
+    }
+    if columns.bytes_per_token {
+        fields.push(("bytes_per_token", Value::from(stats.bytes_per_token())));
+    }
+    if let Some(pricing) = &columns.pricing {
+        fields.push(("cost", Value::from(pricing.input_cost(stats.tokens))));
+    }
+    if let Some(limit) = columns.context_limit {
+        let fit = ContextFit::new(stats.tokens, limit);
+        fields.push(("context_limit", Value::from(fit.limit)));
+        fields.push(("context_percent", Value::from(fit.percent())));
+    }
+    fields
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote, or line break
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain.txt"), "plain.txt");
+        assert_eq!(csv_field("a,b.txt"), "\"a,b.txt\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let stats = TokenStats {
+            tokens: 4,
+            lines: 1,
+            bytes: 13,
+            ..TokenStats::new()
+        };
+        let columns = Columns {
+            context_limit: Some(8),
+            ..Columns::counts()
+        };
+
+        let plain = Report::new(columns.clone(), ReportFormat::Plain);
+        assert_eq!(plain.header(), None);
+        assert_eq!(
+            plain.format_stats(&stats, None),
+            "       4        1       13            4 / 8 (50.0%)"
+        );
+
+        let json = Report::new(columns.clone(), ReportFormat::Json);
+        let row: Value = serde_json::from_str(&json.format_stats(&stats, Some("a.txt"))).unwrap();
+        assert_eq!(row["name"], "a.txt");
+        assert_eq!(row["tokens"], 4);
+        assert_eq!(row["context_percent"], 50.0);
+
+        let csv = Report::new(columns, ReportFormat::Csv);
+        assert_eq!(
+            csv.header().unwrap(),
+            "tokens,lines,bytes,context_limit,context_percent,name"
+        );
+        assert_eq!(
+            csv.format_stats(&stats, Some("a,b.txt")),
+            "4,1,13,8,50.0,\"a,b.txt\""
+        );
+
+        let efficiency = Report::new(
+            Columns {
+                bytes_per_token: true,
+                ..Columns::counts()
+            },
+            ReportFormat::Plain,
+        );
+        assert_eq!(
+            efficiency.format_stats(&stats, None),
+            "       4        1       13     3.25"
+        );
+
+        let thresholds = Thresholds::new(Threshold::Tokens(2), Threshold::Percent(100.0));
+        let colored = Report::with_formatter(Columns::counts(), ColoredFormatter::new(thresholds));
+        assert_eq!(
+            colored.format_stats(&stats, Some("a.txt")),
+            "\x1b[33m       4\x1b[0m        1       13 a.txt"
+        );
+    }
+}
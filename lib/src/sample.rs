@@ -0,0 +1,394 @@
+//! Estimating the token count of large corpora from a random sample of
+//! their bytes
+
+use crate::{
+    count_tokens, decompress, decompress_reader, file_error, read_document_text, Compression,
+    DocumentFormat, Result,
+};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+/// Size of the blocks text files are sampled in
+pub const SAMPLE_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// Fewest blocks a sample is drawn from, so the spread between them says
+/// something about the confidence interval
+const MIN_SAMPLE_BLOCKS: u64 = 10;
+
+/// z-score of a two-sided 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// A token count extrapolated from a sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleEstimate {
+    /// Estimated total number of tokens
+    pub tokens: f64,
+    /// Half-width of the 95% confidence interval around `tokens`
+    pub margin: f64,
+    /// Bytes in all inputs
+    pub bytes: u64,
+    /// Bytes actually tokenized
+    pub sampled_bytes: u64,
+    /// Tokens counted in the sampled bytes
+    pub sampled_tokens: usize,
+}
+
+impl SampleEstimate {
+    /// Whether every byte was tokenized, so the count is exact
+    pub fn is_exact(&self) -> bool {
+        self.sampled_bytes == self.bytes
+    }
+
+    /// Lower bound of the 95% confidence interval
+    pub fn low(&self) -> f64 {
+        (self.tokens - self.margin).max(self.sampled_tokens as f64)
+    }
+
+    /// Upper bound of the 95% confidence interval
+    pub fn high(&self) -> f64 {
+        self.tokens + self.margin
+    }
+}
+
+/// A text file to draw blocks from
+struct TextInput<'a> {
+    path: &'a Path,
+    /// Bytes of text, after decompression
+    len: u64,
+    compression: Option<Compression>,
+}
+
+/// Estimates the tokens in `paths` by tokenizing about `sample_bytes` of
+/// them
+///
+/// Text files are cut into blocks of [`SAMPLE_BLOCK_SIZE`] bytes, and blocks
+/// are drawn at random (without replacement) until the sample is large
+/// enough (and at least 10 blocks). The total is extrapolated from the
+/// sample's tokens per byte, with a 95% confidence interval from the spread
+/// between blocks. Documents such as PDFs cannot be cut into blocks and are
+/// always counted in full, as is everything when `sample_bytes` covers all
+/// text bytes.
+///
+/// Compressed files are sampled from their decompressed text: they are
+/// decompressed once to measure them, and once more to read the blocks
+/// drawn from them.
+///
+/// # Arguments
+///
+/// * `paths` - Files to estimate
+/// * `tokenizer` - The tokenizer to use
+/// * `sample_bytes` - Number of text bytes to tokenize
+/// * `seed` - Seed for drawing blocks, so runs can be repeated
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or encoding fails
+pub fn estimate_tokens<P: AsRef<Path>>(
+    paths: &[P],
+    tokenizer: &Tokenizer,
+    sample_bytes: u64,
+    seed: u64,
+) -> Result<SampleEstimate> {
+    estimate(paths, tokenizer, |_| sample_bytes, seed)
+}
+
+/// Estimates the tokens in `paths` by tokenizing about `fraction` (0 to 1)
+/// of their bytes, as [`estimate_tokens`] does
+///
+/// The bytes of compressed files are counted after decompression.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or encoding fails
+pub fn estimate_tokens_fraction<P: AsRef<Path>>(
+    paths: &[P],
+    tokenizer: &Tokenizer,
+    fraction: f64,
+    seed: u64,
+) -> Result<SampleEstimate> {
+    estimate(
+        paths,
+        tokenizer,
+        |bytes| (bytes as f64 * fraction).ceil() as u64,
+        seed,
+    )
+}
+
+/// Estimates the tokens in `paths` from a sample of the size
+/// `sample_bytes` gives for the bytes of all inputs
+fn estimate<P: AsRef<Path>>(
+    paths: &[P],
+    tokenizer: &Tokenizer,
+    sample_bytes: impl FnOnce(u64) -> u64,
+    seed: u64,
+) -> Result<SampleEstimate> {
+    // Documents are counted in full; text files become the population
+    let mut documents = Vec::new();
+    let mut texts = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let (input, is_document) = measure(path)?;
+        if is_document {
+            documents.push(input);
+        } else {
+            texts.push(input);
+        }
+    }
+    let text_bytes: u64 = texts.iter().map(|text| text.len).sum();
+    let document_bytes: u64 = documents.iter().map(|document| document.len).sum();
+    let sample_bytes = sample_bytes(text_bytes + document_bytes);
+
+    let mut exact_tokens = 0;
+    let mut exact_bytes = 0;
+    for document in &documents {
+        exact_tokens += count_tokens(&read_document_text(document.path)?, tokenizer, false)?;
+        exact_bytes += document.len;
+    }
+
+    // Block `i` of the population is the `i`th block counting through the
+    // files in order
+    let mut first_block = Vec::with_capacity(texts.len());
+    let mut blocks = 0;
+    for text in &texts {
+        first_block.push(blocks);
+        blocks += text.len.div_ceil(SAMPLE_BLOCK_SIZE);
+    }
+    if sample_bytes >= text_bytes || blocks <= MIN_SAMPLE_BLOCKS {
+        for text in &texts {
+            exact_tokens += count_tokens(&read_document_text(text.path)?, tokenizer, false)?;
+            exact_bytes += text.len;
+        }
+        return Ok(SampleEstimate {
+            tokens: exact_tokens as f64,
+            margin: 0.0,
+            bytes: exact_bytes,
+            sampled_bytes: exact_bytes,
+            sampled_tokens: exact_tokens,
+        });
+    }
+
+    // Blocks are drawn first, so each compressed file is read through once
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut drawn = HashSet::new();
+    let mut draws = vec![Vec::new(); texts.len()];
+    let mut sampled_bytes = 0;
+    while sampled_bytes < sample_bytes || (drawn.len() as u64) < MIN_SAMPLE_BLOCKS {
+        let block = rng.u64(..blocks);
+        if !drawn.insert(block) {
+            continue;
+        }
+        let file = first_block.partition_point(|&first| first <= block) - 1;
+        let offset = (block - first_block[file]) * SAMPLE_BLOCK_SIZE;
+        let block_bytes = SAMPLE_BLOCK_SIZE.min(texts[file].len - offset);
+        draws[file].push((offset, block_bytes));
+        sampled_bytes += block_bytes;
+    }
+    let mut sample = Vec::new();
+    for (text, blocks) in texts.iter().zip(&mut draws) {
+        blocks.sort_unstable();
+        for (bytes, &(_, block_bytes)) in read_blocks(text, blocks)?.into_iter().zip(&*blocks) {
+            let tokens = count_tokens(&block_text(&bytes), tokenizer, false)?;
+            sample.push((tokens, block_bytes));
+        }
+    }
+
+    // Ratio estimator: tokens per byte across the sample, scaled to all text
+    // bytes, with variance from each block's deviation from that ratio
+    let sampled_tokens: usize = sample.iter().map(|&(tokens, _)| tokens).sum();
+    let ratio = sampled_tokens as f64 / sampled_bytes.max(1) as f64;
+    let n = sample.len() as f64;
+    let population = blocks as f64;
+    let deviation = if sample.len() > 1 {
+        sample
+            .iter()
+            .map(|&(tokens, bytes)| (tokens as f64 - ratio * bytes as f64).powi(2))
+            .sum::<f64>()
+            / (n - 1.0)
+    } else {
+        0.0
+    };
+    let variance = population * population * (1.0 - n / population) * deviation / n;
+
+    Ok(SampleEstimate {
+        tokens: exact_tokens as f64 + ratio * text_bytes as f64,
+        margin: Z_95 * variance.sqrt(),
+        bytes: exact_bytes + text_bytes,
+        sampled_bytes: exact_bytes + sampled_bytes,
+        sampled_tokens: exact_tokens + sampled_tokens,
+    })
+}
+
+/// Measures an input's (decompressed) bytes, and whether it is a document
+/// that is extracted rather than read as text
+fn measure(path: &Path) -> Result<(TextInput<'_>, bool)> {
+    let mut reader = BufReader::new(File::open(path).map_err(file_error(path))?);
+    let compression = Compression::detect(path, reader.fill_buf().map_err(file_error(path))?);
+    let mut head = Vec::new();
+    let len = match compression {
+        Some(compression) => {
+            let mut reader = open_decompressed(path, compression)?;
+            (&mut reader)
+                .take(SAMPLE_BLOCK_SIZE)
+                .read_to_end(&mut head)
+                .and_then(|_| io::copy(&mut reader, &mut io::sink()))
+                .map_err(file_error(path))?
+                + head.len() as u64
+        }
+        None => {
+            reader
+                .take(SAMPLE_BLOCK_SIZE)
+                .read_to_end(&mut head)
+                .map_err(file_error(path))?;
+            fs::metadata(path).map_err(file_error(path))?.len()
+        }
+    };
+    let is_document = DocumentFormat::detect(&Compression::inner_path(path), &head).is_some();
+    let input = TextInput {
+        path,
+        len,
+        compression,
+    };
+    Ok((input, is_document))
+}
+
+/// A reader of a compressed file's decompressed bytes
+///
+/// xz cannot be decompressed as a stream, so it is decompressed whole.
+fn open_decompressed(path: &Path, compression: Compression) -> Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(path).map_err(file_error(path))?);
+    match compression {
+        Compression::Xz => Ok(Box::new(io::Cursor::new(decompress(compression, file)?))),
+        _ => decompress_reader(compression, file),
+    }
+}
+
+/// Reads the `(offset, len)` blocks of a text input, in order of offset:
+/// seeking to each in a plain file, or skipping ahead to it in the
+/// decompressed bytes of a compressed one
+fn read_blocks(text: &TextInput, blocks: &[(u64, u64)]) -> Result<Vec<Vec<u8>>> {
+    let path = text.path;
+    let mut read = Vec::with_capacity(blocks.len());
+    match text.compression {
+        Some(compression) => {
+            let mut reader = open_decompressed(path, compression)?;
+            let mut position = 0;
+            for &(offset, len) in blocks {
+                let mut bytes = Vec::with_capacity(len as usize);
+                io::copy(&mut (&mut reader).take(offset - position), &mut io::sink())
+                    .and_then(|_| (&mut reader).take(len).read_to_end(&mut bytes))
+                    .map_err(file_error(path))?;
+                position = offset + len;
+                read.push(bytes);
+            }
+        }
+        None => {
+            let mut file = File::open(path).map_err(file_error(path))?;
+            for &(offset, len) in blocks {
+                let mut bytes = Vec::with_capacity(len as usize);
+                file.seek(SeekFrom::Start(offset))
+                    .and_then(|_| (&mut file).take(len).read_to_end(&mut bytes))
+                    .map_err(file_error(path))?;
+                read.push(bytes);
+            }
+        }
+    }
+    Ok(read)
+}
+
+/// A block's bytes as text, dropping characters cut by its edges
+fn block_text(bytes: &[u8]) -> String {
+    let start = bytes
+        .iter()
+        .take(3)
+        .take_while(|&&byte| is_continuation(byte))
+        .count();
+    let mut end = bytes.len();
+    if let Some(lead) = (start..end)
+        .rev()
+        .take(4)
+        .find(|&i| !is_continuation(bytes[i]))
+    {
+        let width = match bytes[lead] {
+            byte if byte >= 0xF0 => 4,
+            byte if byte >= 0xE0 => 3,
+            byte if byte >= 0xC0 => 2,
+            _ => 1,
+        };
+        if lead + width > end {
+            end = lead;
+        }
+    }
+    String::from_utf8_lossy(&bytes[start..end]).into_owned()
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_estimate_tokens() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let line = "The quick brown fox jumps over the lazy dog. Ünïcödé ✓\n";
+        for _ in 0..12_000 {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        let paths = [file.path()];
+        let exact = count_tokens(&line.repeat(12_000), &tokenizer, false).unwrap();
+
+        let all = estimate_tokens(&paths, &tokenizer, u64::MAX, 0).unwrap();
+        assert!(all.is_exact());
+        assert_eq!(all.tokens, exact as f64);
+        assert_eq!(all.margin, 0.0);
+
+        let sampled = estimate_tokens(&paths, &tokenizer, 100_000, 7).unwrap();
+        assert!(!sampled.is_exact());
+        assert!(sampled.sampled_bytes >= 100_000);
+        assert_eq!(sampled.bytes, (line.len() * 12_000) as u64);
+        let error = (sampled.tokens - exact as f64).abs() / exact as f64;
+        assert!(error < 0.01, "estimate off by {:.2}%", error * 100.0);
+        assert!(sampled.low() <= sampled.tokens && sampled.tokens <= sampled.high());
+
+        // The same seed draws the same blocks
+        assert_eq!(
+            estimate_tokens(&paths, &tokenizer, 100_000, 7).unwrap(),
+            sampled
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_estimate_tokens_of_compressed_files() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let text: String = (0..20_000)
+            .map(|i| format!("Line {} of the corpus, with a number: {}\n", i, i * 7919))
+            .collect();
+        let mut plain = tempfile::NamedTempFile::new().unwrap();
+        plain.write_all(text.as_bytes()).unwrap();
+        let mut gzip = tempfile::Builder::new().suffix(".gz").tempfile().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        gzip.write_all(&encoder.finish().unwrap()).unwrap();
+
+        // Blocks are drawn from the decompressed text, so a compressed file
+        // samples as the text it holds
+        let expected = estimate_tokens_fraction(&[plain.path()], &tokenizer, 0.1, 3).unwrap();
+        let sampled = estimate_tokens_fraction(&[gzip.path()], &tokenizer, 0.1, 3).unwrap();
+        assert!(!sampled.is_exact());
+        assert_eq!(sampled.bytes, text.len() as u64);
+        assert_eq!(sampled, expected);
+
+        let exact = count_tokens(&text, &tokenizer, false).unwrap() as f64;
+        assert!(sampled.low() <= exact && exact <= sampled.high());
+    }
+}
@@ -0,0 +1,248 @@
+//! jq-like field selection inside JSON documents
+//!
+//! Supports the subset of jq paths needed to pick text out of JSON:
+//! `.key`, `."quoted key"`, `.["key"]`, `.[N]`, and `.[]` to iterate arrays
+//! and object values, chained as in `.messages[].content`.
+
+use crate::{Error, Result};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Object field
+    Key(String),
+    /// Array element; negative indexes count from the end
+    Index(i64),
+    /// Every array element or object value
+    Iterate,
+}
+
+/// A parsed jq-like path such as `.messages[].content`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Parses a path
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not valid jq-like syntax
+    pub fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidInput(format!("path '{}': {}", path, reason));
+        let mut chars = path.chars().peekable();
+        let mut segments = Vec::new();
+
+        if chars.next() != Some('.') {
+            return Err(invalid("must start with '.'"));
+        }
+        // The leading dot may be followed directly by a key, as in `.key`
+        let mut expect_key = true;
+
+        while let Some(&c) = chars.peek() {
+            match c {
+                '[' => {
+                    chars.next();
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') if !inner.starts_with('"') || is_quoted(&inner) => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(invalid("unclosed '['")),
+                        }
+                    }
+                    let inner = inner.trim();
+                    segments.push(if inner.is_empty() {
+                        Segment::Iterate
+                    } else if is_quoted(inner) {
+                        Segment::Key(unquote(inner).map_err(|_| invalid("bad quoted key"))?)
+                    } else {
+                        let index = inner
+                            .parse()
+                            .map_err(|_| invalid(&format!("bad index '{}'", inner)))?;
+                        Segment::Index(index)
+                    });
+                    expect_key = false;
+                }
+                '.' if !expect_key => {
+                    chars.next();
+                    expect_key = true;
+                }
+                '"' if expect_key => {
+                    let mut quoted = String::new();
+                    while let Some(c) = chars.next() {
+                        quoted.push(c);
+                        if c == '\\' {
+                            quoted.extend(chars.next());
+                        } else if c == '"' && quoted.len() > 1 {
+                            break;
+                        }
+                    }
+                    segments.push(Segment::Key(
+                        unquote(&quoted).map_err(|_| invalid("bad quoted key"))?,
+                    ));
+                    expect_key = false;
+                }
+                _ if expect_key && is_key_char(c) => {
+                    let mut key = String::new();
+                    while let Some(&c) = chars.peek().filter(|&&c| is_key_char(c)) {
+                        key.push(c);
+                        chars.next();
+                    }
+                    segments.push(Segment::Key(key));
+                    expect_key = false;
+                }
+                _ => return Err(invalid(&format!("unexpected '{}'", c))),
+            }
+        }
+
+        if expect_key && !segments.is_empty() {
+            return Err(invalid("ends with '.'"));
+        }
+        Ok(Self {
+            source: path.to_string(),
+            segments,
+        })
+    }
+
+    /// Values the path selects in `root`; missing fields select nothing
+    fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&'a Value> {
+                    match (segment, value) {
+                        (Segment::Key(key), Value::Object(map)) => {
+                            map.get(key).into_iter().collect()
+                        }
+                        (Segment::Index(index), Value::Array(items)) => {
+                            let index = if *index < 0 {
+                                items.len().checked_sub(index.unsigned_abs() as usize)
+                            } else {
+                                Some(*index as usize)
+                            };
+                            index.and_then(|i| items.get(i)).into_iter().collect()
+                        }
+                        (Segment::Iterate, Value::Array(items)) => items.iter().collect(),
+                        (Segment::Iterate, Value::Object(map)) => map.values().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        current
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        Self::parse(path)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Selects text from a JSON document
+///
+/// Strings are returned as-is, `null` is skipped, and any other value is
+/// returned as its JSON serialization.
+///
+/// # Arguments
+///
+/// * `json` - The JSON document
+/// * `path` - The path selecting the values to return
+///
+/// # Errors
+///
+/// Returns an error if `json` is not valid JSON
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::{select_text, JsonPath};
+///
+/// let path: JsonPath = ".messages[].content".parse().unwrap();
+/// let json = r#"{"messages": [{"content": "Hi"}, {"content": "Hello"}]}"#;
+/// assert_eq!(select_text(json, &path).unwrap(), ["Hi", "Hello"]);
+/// ```
+pub fn select_text(json: &str, path: &JsonPath) -> Result<Vec<String>> {
+    let root: Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidInput(format!("{}", e)))?;
+    Ok(path
+        .select(&root)
+        .into_iter()
+        .filter(|value| !value.is_null())
+        .map(|value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+        .collect())
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn is_quoted(text: &str) -> bool {
+    text.len() >= 2 && text.starts_with('"') && text.ends_with('"') && !text.ends_with("\\\"")
+}
+
+/// Decodes a JSON string literal, escapes included
+fn unquote(quoted: &str) -> serde_json::Result<String> {
+    serde_json::from_str(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(json: &str, path: &str) -> Vec<String> {
+        select_text(json, &path.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_select_paths() {
+        let json = r#"{
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hi", "meta": {"tags": ["a"]}},
+                {"role": "assistant", "content": null}
+            ],
+            "odd key": "x"
+        }"#;
+
+        assert_eq!(select(json, ".messages[].content"), ["Be brief.", "Hi"]);
+        assert_eq!(select(json, ".messages[1].role"), ["user"]);
+        assert_eq!(select(json, ".messages[-1].role"), ["assistant"]);
+        assert_eq!(select(json, ".messages[].meta.tags"), [r#"["a"]"#]);
+        assert_eq!(select(json, r#"."odd key""#), ["x"]);
+        assert_eq!(select(json, r#".["odd key"]"#), ["x"]);
+        assert_eq!(select(json, ".missing.deeper"), Vec::<String>::new());
+        assert_eq!(select(r#"["a", "b"]"#, ".[]"), ["a", "b"]);
+        assert_eq!(select(r#""top""#, "."), ["top"]);
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        for path in ["", "messages", ".a.", ".a[", ".a[x]", ".a..b"] {
+            assert!(JsonPath::parse(path).is_err(), "{} should not parse", path);
+        }
+        assert_eq!(
+            JsonPath::parse(".a[]").unwrap().to_string(),
+            ".a[]".to_string()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! How much two texts overlap, measured over their tokens
+//!
+//! Before putting two documents in the same prompt it helps to know how
+//! redundant they are: [`token_similarity`] compares which tokens they use
+//! and how often, and [`shared_spans`] finds the stretches of tokens they
+//! have in common, such as copied paragraphs.
+
+use std::collections::{HashMap, HashSet};
+
+/// Similarity of two token sequences' token counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Similarity {
+    /// Multiset Jaccard index: tokens in common (counting repeats) divided
+    /// by tokens in either, from 0 to 1
+    pub jaccard: f64,
+    /// Cosine of the angle between the token count vectors, from 0 to 1
+    pub cosine: f64,
+}
+
+/// Compares the token IDs two texts use, ignoring their order
+///
+/// Two empty sequences are identical (1 by both measures); an empty and a
+/// non-empty one have nothing in common (0).
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::token_similarity;
+///
+/// let similarity = token_similarity(&[1, 2, 2, 3], &[2, 2, 3, 4]);
+/// assert_eq!(similarity.jaccard, 3.0 / 5.0);
+/// // Order does not matter
+/// assert_eq!(token_similarity(&[1, 2], &[2, 1]).jaccard, 1.0);
+/// ```
+pub fn token_similarity(a: &[u32], b: &[u32]) -> Similarity {
+    if a.is_empty() || b.is_empty() {
+        let same = if a.is_empty() && b.is_empty() {
+            1.0
+        } else {
+            0.0
+        };
+        return Similarity {
+            jaccard: same,
+            cosine: same,
+        };
+    }
+    let (counts_a, counts_b) = (counts(a), counts(b));
+
+    let mut shared = 0;
+    let mut dot = 0.0;
+    for (id, &count_a) in &counts_a {
+        if let Some(&count_b) = counts_b.get(id) {
+            shared += count_a.min(count_b);
+            dot += count_a as f64 * count_b as f64;
+        }
+    }
+    let norm = |counts: &HashMap<u32, usize>| {
+        counts
+            .values()
+            .map(|&count| (count * count) as f64)
+            .sum::<f64>()
+            .sqrt()
+    };
+    Similarity {
+        // For multisets, |A ∪ B| = |A| + |B| - |A ∩ B|
+        jaccard: shared as f64 / (a.len() + b.len() - shared) as f64,
+        cosine: (dot / (norm(&counts_a) * norm(&counts_b))).min(1.0),
+    }
+}
+
+fn counts(ids: &[u32]) -> HashMap<u32, usize> {
+    let mut counts = HashMap::new();
+    for &id in ids {
+        *counts.entry(id).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Stretches of tokens two sequences have in common
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SharedSpans {
+    /// Separate stretches of the first sequence that also occur in the
+    /// second
+    pub spans: usize,
+    /// Tokens of the first sequence inside those stretches
+    pub tokens_a: usize,
+    /// Tokens of the second sequence inside stretches shared with the first
+    pub tokens_b: usize,
+}
+
+/// Finds the runs of at least `min_tokens` tokens that occur in both `a`
+/// and `b`
+///
+/// A token is shared if some window of `min_tokens` tokens around it
+/// appears in the other sequence; adjacent and overlapping windows merge
+/// into one span. Short common phrases fall below `min_tokens`, so only
+/// copied text counts.
+///
+/// # Panics
+///
+/// Panics if `min_tokens` is 0
+///
+/// # Examples
+///
+/// ```
+/// use token_counter_lib::shared_spans;
+///
+/// let a = [1, 2, 3, 4, 5, 9, 9, 6, 7, 8];
+/// let b = [0, 1, 2, 3, 4, 5, 0, 6, 7, 8];
+/// let shared = shared_spans(&a, &b, 3);
+/// assert_eq!(shared.spans, 2);
+/// assert_eq!((shared.tokens_a, shared.tokens_b), (8, 8));
+/// ```
+pub fn shared_spans(a: &[u32], b: &[u32], min_tokens: usize) -> SharedSpans {
+    assert!(min_tokens > 0, "spans must be at least one token long");
+    let (spans, tokens_a) = covered(a, b, min_tokens);
+    let (_, tokens_b) = covered(b, a, min_tokens);
+    SharedSpans {
+        spans,
+        tokens_a,
+        tokens_b,
+    }
+}
+
+/// The number of runs of `ids` covered by windows that occur in `other`,
+/// and the tokens in them
+fn covered(ids: &[u32], other: &[u32], window: usize) -> (usize, usize) {
+    let windows: HashSet<&[u32]> = other.windows(window).collect();
+    let mut covered = vec![false; ids.len()];
+    for (start, ids) in ids.windows(window).enumerate() {
+        if windows.contains(ids) {
+            covered[start..start + window].fill(true);
+        }
+    }
+    let spans = covered
+        .iter()
+        .enumerate()
+        .filter(|&(index, &is_covered)| is_covered && (index == 0 || !covered[index - 1]))
+        .count();
+    (
+        spans,
+        covered.iter().filter(|&&is_covered| is_covered).count(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_similarity() {
+        let same = token_similarity(&[5, 6, 7], &[7, 6, 5]);
+        assert_eq!(same.jaccard, 1.0);
+        assert!((same.cosine - 1.0).abs() < 1e-12);
+
+        let disjoint = token_similarity(&[1, 2], &[3, 4]);
+        assert_eq!(
+            disjoint,
+            Similarity {
+                jaccard: 0.0,
+                cosine: 0.0
+            }
+        );
+
+        // Counts matter: {1, 1} and {1} share one token of two
+        let repeats = token_similarity(&[1, 1], &[1]);
+        assert_eq!(repeats.jaccard, 0.5);
+        assert!((repeats.cosine - 1.0).abs() < 1e-12);
+
+        assert_eq!(token_similarity(&[], &[]).jaccard, 1.0);
+        assert_eq!(token_similarity(&[1], &[]).cosine, 0.0);
+    }
+
+    #[test]
+    fn test_shared_spans() {
+        let a = [1, 2, 3, 4, 5, 6];
+        assert_eq!(
+            shared_spans(&a, &a, 4),
+            SharedSpans {
+                spans: 1,
+                tokens_a: 6,
+                tokens_b: 6
+            }
+        );
+        // A copied run shorter than the window is not shared
+        assert_eq!(
+            shared_spans(&[1, 2, 3], &[1, 2, 3], 4),
+            SharedSpans::default()
+        );
+        // The run [2, 3, 4] appears twice in b
+        let shared = shared_spans(&[2, 3, 4], &[2, 3, 4, 0, 2, 3, 4], 3);
+        assert_eq!((shared.spans, shared.tokens_a, shared.tokens_b), (1, 3, 6));
+    }
+}
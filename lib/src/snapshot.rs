@@ -0,0 +1,167 @@
+//! Comparison of per-file token counts against a recorded baseline
+
+use std::collections::BTreeMap;
+
+/// How a file's token count changed relative to the baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Present now, absent from the baseline
+    Added,
+    /// Present in the baseline, absent now
+    Removed,
+    /// More tokens than the baseline
+    Grew,
+    /// Fewer tokens than the baseline
+    Shrank,
+    /// Same number of tokens as the baseline
+    Unchanged,
+}
+
+/// Token count of one file in the baseline and now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// File path as recorded in the snapshot
+    pub path: String,
+    /// Tokens in the baseline, if the file was recorded
+    pub baseline: Option<usize>,
+    /// Tokens now, if the file is still present
+    pub current: Option<usize>,
+}
+
+impl FileChange {
+    /// Classifies the change
+    pub fn kind(&self) -> ChangeKind {
+        match (self.baseline, self.current) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            (Some(before), Some(after)) if after > before => ChangeKind::Grew,
+            (Some(before), Some(after)) if after < before => ChangeKind::Shrank,
+            _ => ChangeKind::Unchanged,
+        }
+    }
+
+    /// Difference in tokens (current minus baseline)
+    pub fn delta(&self) -> i64 {
+        self.current.unwrap_or(0) as i64 - self.baseline.unwrap_or(0) as i64
+    }
+
+    /// Difference as a percentage of the baseline, if the baseline is non-zero
+    pub fn percent_change(&self) -> Option<f64> {
+        match (self.baseline, self.current) {
+            (Some(before), Some(_)) if before > 0 => {
+                Some(self.delta() as f64 * 100.0 / before as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How much growth is accepted before a file counts as a regression
+///
+/// Growth is accepted if it is within either the absolute or the relative
+/// allowance. The default accepts no growth at all.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GrowthTolerance {
+    /// Tokens a file may grow by
+    pub tokens: usize,
+    /// Percentage a file may grow by
+    pub percent: f64,
+}
+
+impl GrowthTolerance {
+    /// Whether `change` is acceptable; only growth can be rejected
+    pub fn allows(&self, change: &FileChange) -> bool {
+        if change.kind() != ChangeKind::Grew {
+            return true;
+        }
+        let within_tokens = change.delta() <= self.tokens as i64;
+        let within_percent = change
+            .percent_change()
+            .is_some_and(|percent| percent <= self.percent);
+        within_tokens || within_percent
+    }
+}
+
+/// Compares current per-file token counts against a baseline
+///
+/// Returns one entry per path present in either map, sorted by path.
+pub fn compare_counts(
+    baseline: &BTreeMap<String, usize>,
+    current: &BTreeMap<String, usize>,
+) -> Vec<FileChange> {
+    let mut paths: Vec<&String> = baseline.keys().chain(current.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .map(|path| FileChange {
+            path: path.clone(),
+            baseline: baseline.get(path).copied(),
+            current: current.get(path).copied(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(entries: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        entries.iter().map(|(p, n)| (p.to_string(), *n)).collect()
+    }
+
+    #[test]
+    fn test_compare_counts_classifies_changes() {
+        let baseline = counts(&[("a.md", 100), ("b.md", 100), ("c.md", 100), ("d.md", 5)]);
+        let current = counts(&[("a.md", 120), ("b.md", 80), ("c.md", 100), ("e.md", 7)]);
+
+        let changes = compare_counts(&baseline, &current);
+        let kinds: Vec<_> = changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("a.md", ChangeKind::Grew),
+                ("b.md", ChangeKind::Shrank),
+                ("c.md", ChangeKind::Unchanged),
+                ("d.md", ChangeKind::Removed),
+                ("e.md", ChangeKind::Added),
+            ]
+        );
+        assert_eq!(changes[0].delta(), 20);
+        assert_eq!(changes[0].percent_change(), Some(20.0));
+        assert_eq!(changes[3].delta(), -5);
+        assert_eq!(changes[4].percent_change(), None);
+    }
+
+    #[test]
+    fn test_growth_tolerance() {
+        let grew = FileChange {
+            path: "a.md".to_string(),
+            baseline: Some(100),
+            current: Some(110),
+        };
+
+        assert!(!GrowthTolerance::default().allows(&grew));
+        let by_tokens = GrowthTolerance {
+            tokens: 10,
+            percent: 0.0,
+        };
+        assert!(by_tokens.allows(&grew));
+        let by_percent = GrowthTolerance {
+            tokens: 0,
+            percent: 5.0,
+        };
+        assert!(!by_percent.allows(&grew));
+
+        let added = FileChange {
+            path: "b.md".to_string(),
+            baseline: None,
+            current: Some(1000),
+        };
+        assert!(GrowthTolerance::default().allows(&added));
+    }
+}
@@ -0,0 +1,201 @@
+//! Counting a reader in fixed-size chunks, without holding all of its text
+//! in memory
+
+use crate::{Error, Result, TokenStats};
+use std::io::{self, Read};
+use tokenizers::Tokenizer;
+
+/// Bytes read from the reader at a time
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Most text carried over while looking for a safe place to cut, as a
+/// multiple of the chunk size
+const MAX_CARRY_CHUNKS: usize = 4;
+
+/// Counts tokens from a reader in fixed-size chunks
+///
+/// Unlike [`count_tokens_from_reader`](crate::count_tokens_from_reader),
+/// memory use stays bounded however much the reader yields, which suits
+/// sockets and pipes. Characters split across reads are reassembled, and
+/// text is only cut for encoding where a word meets the whitespace after
+/// it, which no token spans for tokenizers that split on whitespace before
+/// encoding (GPT-2, cl100k, BERT, and most others), so the stats match
+/// counting the whole text. Text with no such place in
+/// `4 * STREAM_CHUNK_SIZE` bytes (e.g. a long base64 blob) is cut anyway,
+/// which can shift the count by a token at the cut.
+///
+/// # Arguments
+///
+/// * `reader` - The reader to read from
+/// * `tokenizer` - The tokenizer to use
+///
+/// # Errors
+///
+/// Returns an error if reading fails, the input is not valid UTF-8, or
+/// encoding fails
+pub fn count_tokens_from_reader_streaming<R: Read>(
+    reader: R,
+    tokenizer: &Tokenizer,
+) -> Result<TokenStats> {
+    stream_stats(reader, tokenizer, STREAM_CHUNK_SIZE)
+}
+
+fn stream_stats<R: Read>(
+    mut reader: R,
+    tokenizer: &Tokenizer,
+    chunk_size: usize,
+) -> Result<TokenStats> {
+    let mut counter = StreamCounter::default();
+    let mut buffer = vec![0; chunk_size];
+    // Decoded text not yet encoded, and bytes of a character cut by a read
+    let mut pending = String::new();
+    let mut undecoded = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if read == 0 {
+            if !undecoded.is_empty() {
+                return Err(invalid_utf8("stream ends inside a character"));
+            }
+            counter.add(&pending, tokenizer)?;
+            return Ok(counter.finish());
+        }
+
+        undecoded.extend_from_slice(&buffer[..read]);
+        let valid = match std::str::from_utf8(&undecoded) {
+            Ok(_) => undecoded.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(invalid_utf8(&e.to_string())),
+        };
+        let text = std::str::from_utf8(&undecoded[..valid]).expect("validated above");
+        pending.push_str(text);
+        undecoded.drain(..valid);
+
+        if pending.len() < chunk_size {
+            continue;
+        }
+        let cut = match safe_cut(&pending) {
+            Some(cut) => cut,
+            None if pending.len() >= chunk_size * MAX_CARRY_CHUNKS => pending.len(),
+            None => continue,
+        };
+        counter.add(&pending[..cut], tokenizer)?;
+        pending.drain(..cut);
+    }
+}
+
+/// The last place in `text` where a word meets the whitespace after it
+fn safe_cut(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().rev().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c.is_whitespace()
+            && chars
+                .peek()
+                .is_some_and(|&(_, before)| before.is_alphanumeric())
+        {
+            return Some(index);
+        }
+    }
+    None
+}
+
+fn invalid_utf8(message: &str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+/// Stats accumulated over consecutive pieces of one text
+#[derive(Default)]
+struct StreamCounter {
+    stats: TokenStats,
+    /// Tokens so far on the line the last piece ended in
+    line_tokens: usize,
+    /// Whether any text has been counted
+    started: bool,
+    /// Whether the last piece ended with a newline
+    at_line_start: bool,
+    /// Whether the last piece ended inside a word, which a forced cut can
+    /// leave
+    in_word: bool,
+}
+
+impl StreamCounter {
+    /// Encodes the next piece of the text
+    fn add(&mut self, text: &str, tokenizer: &Tokenizer) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let encoding = tokenizer
+            .encode(text, false)
+            .map_err(|e| Error::Encoding(format!("{}", e)))?;
+
+        // Tokens count toward the line they start on; the piece's first line
+        // continues the line the previous piece ended in
+        let line_ends: Vec<usize> = text.match_indices('\n').map(|(i, _)| i).collect();
+        let mut line_tokens = vec![0; line_ends.len() + 1];
+        line_tokens[0] = self.line_tokens;
+        for &(start, _) in encoding.get_offsets() {
+            line_tokens[line_ends.partition_point(|&end| end < start)] += 1;
+        }
+        let current = line_tokens.pop().unwrap_or(0);
+        let finished = line_tokens.into_iter().max().unwrap_or(0);
+
+        self.stats.tokens += encoding.len();
+        self.stats.max_line_tokens = self.stats.max_line_tokens.max(finished);
+        self.line_tokens = current;
+        self.stats.lines += line_ends.len();
+        self.stats.bytes += text.len();
+        self.stats.words += text.split_whitespace().count();
+        if self.in_word && !text.starts_with(char::is_whitespace) {
+            self.stats.words -= 1;
+        }
+        self.stats.chars += text.chars().count();
+        self.at_line_start = text.ends_with('\n');
+        self.in_word = !text.ends_with(char::is_whitespace);
+        self.started = true;
+        Ok(())
+    }
+
+    fn finish(mut self) -> TokenStats {
+        self.stats.max_line_tokens = self.stats.max_line_tokens.max(self.line_tokens);
+        // Like `str::lines`, a final line without a newline still counts
+        if self.started && !self.at_line_start {
+            self.stats.lines += 1;
+        }
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_stats;
+
+    #[test]
+    fn test_streaming_matches_whole_text() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        let text = "fn main() {\n    println!(\"héllo, wörld 🌍\");\n}\n\n\
+                    Ünïcödé text, with   spaces\tand tabs.\nlast line without newline";
+
+        // Tiny chunks cut through characters, words, and lines
+        for chunk_size in [7, 16, 64, 4096] {
+            let stats = stream_stats(text.as_bytes(), &tokenizer, chunk_size).unwrap();
+            assert_eq!(
+                stats,
+                count_stats(text, &tokenizer).unwrap(),
+                "{}",
+                chunk_size
+            );
+        }
+        assert_eq!(
+            count_tokens_from_reader_streaming(&b""[..], &tokenizer).unwrap(),
+            TokenStats::new()
+        );
+        assert!(count_tokens_from_reader_streaming(&b"abc\xff"[..], &tokenizer).is_err());
+        assert!(count_tokens_from_reader_streaming(&b"abc\xc3"[..], &tokenizer).is_err());
+    }
+}
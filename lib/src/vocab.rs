@@ -0,0 +1,122 @@
+//! Vocabulary usage: which token IDs a corpus uses, and how often it repeats
+//! them
+
+use crate::{Error, Result};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use tokenizers::Tokenizer;
+
+/// Frequencies of the token IDs seen across one or more texts
+#[derive(Debug, Clone, Default)]
+pub struct VocabUsage {
+    counts: HashMap<u32, usize>,
+    tokens: usize,
+}
+
+impl VocabUsage {
+    /// Creates an empty usage record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the tokens of `text`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encoding fails
+    pub fn add_text(&mut self, text: &str, tokenizer: &Tokenizer) -> Result<()> {
+        let encoding = tokenizer
+            .encode_fast(text, false)
+            .map_err(|e| Error::Encoding(format!("{}", e)))?;
+        for &id in encoding.get_ids() {
+            *self.counts.entry(id).or_insert(0) += 1;
+        }
+        self.tokens += encoding.len();
+        Ok(())
+    }
+
+    /// Adds the tokens recorded in `other`
+    pub fn merge(&mut self, other: &VocabUsage) {
+        for (&id, &count) in &other.counts {
+            *self.counts.entry(id).or_insert(0) += count;
+        }
+        self.tokens += other.tokens;
+    }
+
+    /// Total number of tokens recorded
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Number of distinct token IDs recorded
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Number of times token `id` was recorded
+    pub fn count(&self, id: u32) -> usize {
+        self.counts.get(&id).copied().unwrap_or(0)
+    }
+
+    /// The `n` most frequent token IDs with their counts, most frequent
+    /// first (ties in ID order)
+    pub fn most_common(&self, n: usize) -> Vec<(u32, usize)> {
+        let mut counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(&id, &count)| (id, count))
+            .collect();
+        counts.sort_unstable_by_key(|&(id, count)| (Reverse(count), id));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Distinct tokens divided by total tokens (0 when empty)
+    ///
+    /// Lower ratios mean more repetitive text. The ratio falls as a corpus
+    /// grows, so compare corpora of similar size.
+    pub fn type_token_ratio(&self) -> f64 {
+        if self.tokens == 0 {
+            return 0.0;
+        }
+        self.distinct() as f64 / self.tokens as f64
+    }
+
+    /// Percentage of a vocabulary of `vocab_size` tokens that was used
+    pub fn coverage(&self, vocab_size: usize) -> f64 {
+        if vocab_size == 0 {
+            return 0.0;
+        }
+        self.distinct() as f64 * 100.0 / vocab_size as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vocab_usage() {
+        let tokenizer =
+            Tokenizer::from_bytes(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+
+        let mut usage = VocabUsage::new();
+        usage.add_text("a b a b", &tokenizer).unwrap();
+        assert_eq!(usage.tokens(), 4);
+        assert_eq!(usage.distinct(), 3);
+
+        let mut other = VocabUsage::new();
+        other.add_text(" b c", &tokenizer).unwrap();
+        usage.merge(&other);
+        assert_eq!(usage.tokens(), 6);
+        assert_eq!(usage.distinct(), 4);
+        assert_eq!(usage.type_token_ratio(), 4.0 / 6.0);
+        assert_eq!(usage.coverage(400), 1.0);
+        assert_eq!(VocabUsage::new().type_token_ratio(), 0.0);
+
+        let b = tokenizer.token_to_id("Ġb").unwrap();
+        assert_eq!(usage.count(b), 3);
+        assert_eq!(usage.most_common(1), [(b, 3)]);
+        assert_eq!(usage.most_common(10).len(), 4);
+    }
+}
@@ -11,7 +11,7 @@ const DEFAULT_TOKENIZER: &[u8] = include_bytes!("../../bin/assets/gpt2-tokenizer
 #[test]
 fn test_count_tokens_simple() {
     let tokenizer = load_tokenizer_from_bytes(DEFAULT_TOKENIZER).unwrap();
-    let count = count_tokens("Hello, world!", &tokenizer).unwrap();
+    let count = count_tokens("Hello, world!", &tokenizer, false).unwrap();
     assert!(count > 0, "Token count should be greater than 0");
     // GPT-2 typically encodes "Hello, world!" as 4 tokens
     assert!((3..=5).contains(&count), "Expected around 4 tokens");
@@ -52,11 +52,19 @@ fn test_token_stats_accumulation() {
         tokens: 10,
         lines: 2,
         bytes: 50,
+        max_line_tokens: 5,
+        words: 9,
+        chars: 50,
+        path: None,
     };
     let stats2 = TokenStats {
         tokens: 15,
         lines: 3,
         bytes: 75,
+        max_line_tokens: 5,
+        words: 14,
+        chars: 75,
+        path: None,
     };
 
     total.add(&stats1);
@@ -0,0 +1,113 @@
+//! Node.js bindings for token counting
+//!
+//! Exposes `token-counter-lib` to JavaScript and TypeScript through N-API, so
+//! Node tooling counts exactly as `tc` does instead of approximating with a
+//! JavaScript tokenizer port. Build with `napi build --platform --release`
+//! in this directory.
+//!
+//! ```js
+//! const { Tokenizer } = require("token-counter-node");
+//!
+//! const tokenizer = Tokenizer.fromFile("tokenizer.json");
+//! tokenizer.countTokens("Hello, world"); // 3
+//! tokenizer.countStats("Hello, world\n"); // { tokens: 4, lines: 1, ... }
+//! ```
+
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+use token_counter_lib::{
+    count_stats, count_tokens, count_tokens_batch, count_tokens_in_file, load_tokenizer,
+    load_tokenizer_from_bytes, SharedTokenizer,
+};
+
+/// Token, line, byte, word, and character counts of a text or file
+#[napi(object)]
+pub struct TokenStats {
+    pub tokens: i64,
+    pub lines: i64,
+    pub bytes: i64,
+    pub words: i64,
+    pub chars: i64,
+    /// Most tokens on a single line
+    pub max_line_tokens: i64,
+    /// The file counted, for `countFile`
+    pub path: Option<String>,
+}
+
+impl From<token_counter_lib::TokenStats> for TokenStats {
+    fn from(stats: token_counter_lib::TokenStats) -> Self {
+        Self {
+            tokens: stats.tokens as i64,
+            lines: stats.lines as i64,
+            bytes: stats.bytes as i64,
+            words: stats.words as i64,
+            chars: stats.chars as i64,
+            max_line_tokens: stats.max_line_tokens as i64,
+            path: stats.path.map(|path| path.display().to_string()),
+        }
+    }
+}
+
+/// A loaded tokenizer
+#[napi]
+pub struct Tokenizer {
+    inner: SharedTokenizer,
+}
+
+#[napi]
+impl Tokenizer {
+    /// Loads a tokenizer from a `tokenizer.json` file or model directory
+    #[napi(factory)]
+    pub fn from_file(path: String) -> napi::Result<Self> {
+        Ok(Self {
+            inner: load_tokenizer(path).map_err(to_napi)?.into(),
+        })
+    }
+
+    /// Loads a tokenizer from the contents of a `tokenizer.json`
+    #[napi(factory)]
+    pub fn from_buffer(bytes: Buffer) -> napi::Result<Self> {
+        Ok(Self {
+            inner: load_tokenizer_from_bytes(&bytes).map_err(to_napi)?.into(),
+        })
+    }
+
+    /// Counts the tokens in `text`, optionally with the special tokens the
+    /// tokenizer wraps a sequence in
+    #[napi]
+    pub fn count_tokens(
+        &self,
+        text: String,
+        add_special_tokens: Option<bool>,
+    ) -> napi::Result<i64> {
+        let tokens = count_tokens(&text, &self.inner, add_special_tokens.unwrap_or(false))
+            .map_err(to_napi)?;
+        Ok(tokens as i64)
+    }
+
+    /// Counts the tokens in each of `texts`, encoding them in parallel
+    #[napi]
+    pub fn count_tokens_batch(&self, texts: Vec<String>) -> napi::Result<Vec<i64>> {
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let counts = count_tokens_batch(&texts, &self.inner).map_err(to_napi)?;
+        Ok(counts.into_iter().map(|tokens| tokens as i64).collect())
+    }
+
+    /// Counts the tokens, lines, bytes, words, and characters of `text`
+    #[napi]
+    pub fn count_stats(&self, text: String) -> napi::Result<TokenStats> {
+        Ok(count_stats(&text, &self.inner).map_err(to_napi)?.into())
+    }
+
+    /// Counts the tokens, lines, bytes, words, and characters of a file
+    #[napi]
+    pub fn count_file(&self, path: String) -> napi::Result<TokenStats> {
+        Ok(count_tokens_in_file(path, &self.inner)
+            .map_err(to_napi)?
+            .into())
+    }
+}
+
+fn to_napi(error: token_counter_lib::Error) -> napi::Error {
+    napi::Error::from_reason(error.to_string())
+}
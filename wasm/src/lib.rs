@@ -0,0 +1,67 @@
+//! WebAssembly bindings for token counting
+//!
+//! Exposes `token-counter-lib` to JavaScript, so browser-based editors show
+//! the same counts as `tc`. Build with
+//! `wasm-pack build wasm --target web`; the library is built without its
+//! `fs` feature and with the pure-Rust regex engine.
+//!
+//! ```js
+//! import init, { loadTokenizer, countTokens } from "./pkg/token_counter_wasm.js";
+//!
+//! await init();
+//! const bytes = new Uint8Array(await (await fetch("tokenizer.json")).arrayBuffer());
+//! loadTokenizer(bytes);
+//! countTokens("Hello, world"); // 3
+//! ```
+
+use std::cell::RefCell;
+use token_counter_lib::{load_tokenizer_from_bytes, Error, Result};
+use tokenizers::Tokenizer;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// The tokenizer `countTokens` counts with
+    static TOKENIZER: RefCell<Option<Tokenizer>> = const { RefCell::new(None) };
+}
+
+/// Loads the tokenizer to count with from the bytes of a `tokenizer.json`,
+/// replacing any loaded before
+#[wasm_bindgen(js_name = loadTokenizer)]
+pub fn load_tokenizer(bytes: &[u8]) -> std::result::Result<(), JsError> {
+    Ok(load(bytes)?)
+}
+
+/// Counts the tokens in `text` with the loaded tokenizer
+#[wasm_bindgen(js_name = countTokens)]
+pub fn count_tokens(text: &str) -> std::result::Result<usize, JsError> {
+    Ok(count(text)?)
+}
+
+fn load(bytes: &[u8]) -> Result<()> {
+    let tokenizer = load_tokenizer_from_bytes(bytes)?;
+    TOKENIZER.with(|loaded| *loaded.borrow_mut() = Some(tokenizer));
+    Ok(())
+}
+
+fn count(text: &str) -> Result<usize> {
+    TOKENIZER.with(|loaded| match loaded.borrow().as_ref() {
+        Some(tokenizer) => token_counter_lib::count_tokens(text, tokenizer, false),
+        None => Err(Error::InvalidInput(
+            "no tokenizer loaded; call loadTokenizer first".to_string(),
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_count() {
+        assert!(count("Hello, world").is_err());
+        assert!(load(b"{}").is_err());
+
+        load(include_bytes!("../../bin/assets/gpt2-tokenizer.json")).unwrap();
+        assert_eq!(count("Hello, world").unwrap(), 3);
+    }
+}